@@ -0,0 +1,36 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+
+fn main() {
+  #[cfg(feature = "grpc")]
+  grpc();
+}
+
+/// Generates the server of `proto/pwdg.proto` for the messages defined in
+/// `src/cli/grpc.rs`, without requiring `protoc`.
+#[cfg(feature = "grpc")]
+fn grpc() {
+  use tonic_build::manual::{Builder, Method, Service};
+
+  println!("cargo:rerun-if-changed=proto/pwdg.proto");
+
+  let method = |name: &str, route: &str, message: &str| {
+    Method::builder()
+      .name(name)
+      .route_name(route)
+      .input_type(format!("super::{}Request", message))
+      .output_type(format!("super::{}Response", message))
+      .codec_path("tonic::codec::ProstCodec")
+      .build()
+  };
+  let service = Service::builder()
+    .name("Generator")
+    .package("pwdg.v1")
+    .method(method("generate", "Generate", "Generate"))
+    .method(method("validate", "Validate", "Validate"))
+    .build();
+
+  Builder::new().build_client(false).compile(&[service]);
+}
@@ -0,0 +1,81 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+//! Node.js bindings for pwdg, built with napi-rs.
+use std::borrow::Cow;
+
+use napi_derive::napi;
+
+/// Policy options, as given on the command line.
+#[napi(object)]
+pub struct Options {
+  pub length: u32,
+  pub min_upper: Option<u32>,
+  pub min_lower: Option<u32>,
+  pub min_digit: Option<u32>,
+  pub min_special: Option<u32>,
+  /// Characters to exclude, as for `--exclude`.
+  pub exclude: Option<String>,
+  /// Characters to use instead of the built-in classes.
+  pub charset: Option<String>,
+  pub min_entropy_bits: Option<u32>,
+}
+
+impl From<Options> for pwdg::Policy {
+  fn from(options: Options) -> Self {
+    let min = |n: Option<u32>| n.unwrap_or(0) as usize;
+    let pwdgen_options = pwdg::PwdGenOptions {
+      min_upper: min(options.min_upper),
+      min_lower: min(options.min_lower),
+      min_digit: min(options.min_digit),
+      min_special: min(options.min_special),
+      exclude: options.exclude.map(Cow::Owned),
+      charset: options.charset.map(Cow::Owned),
+      min_entropy_bits: min(options.min_entropy_bits),
+      ..Default::default()
+    };
+    pwdg::Policy::new(options.length as usize, pwdgen_options)
+  }
+}
+
+/// Whether a password is valid under a policy, and if not, why.
+#[napi(object)]
+pub struct Validation {
+  pub valid: bool,
+  pub errors: Vec<String>,
+}
+
+/// Generates a password satisfying `options`. Throws if the options are
+/// invalid.
+#[napi]
+pub fn generate(options: Options) -> napi::Result<String> {
+  let generator = pwdg::Policy::from(options)
+    .generator()
+    .map_err(|e| napi::Error::from_reason(e.to_string()))?;
+  Ok(generator.gen())
+}
+
+/// Checks `password` against the policy given by `options`. Throws if the
+/// options are invalid.
+#[napi]
+pub fn validate(
+  password: String,
+  options: Options,
+) -> napi::Result<Validation> {
+  let policy = pwdg::Policy::from(options);
+  let report = policy.validate();
+  if !report.is_valid() {
+    return Err(napi::Error::from_reason(report.to_string()));
+  }
+
+  let errors: Vec<String> = policy
+    .password_violations(&password)
+    .iter()
+    .map(ToString::to_string)
+    .collect();
+  Ok(Validation {
+    valid: errors.is_empty(),
+    errors,
+  })
+}
@@ -0,0 +1,27 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+const assert = require("node:assert");
+const test = require("node:test");
+
+const pwdg = require("..");
+
+test("generate", () => {
+  const password = pwdg.generate({ length: 16, minDigit: 2 });
+  assert.strictEqual(password.length, 16);
+  assert.ok(password.replace(/\D/g, "").length >= 2);
+  assert.throws(() => pwdg.generate({ length: 4 }), /\[Error::Length\]/);
+});
+
+test("validate", () => {
+  const options = { length: 12, minDigit: 1 };
+  assert.deepStrictEqual(pwdg.validate("abcdefgh1234", options), {
+    valid: true,
+    errors: [],
+  });
+
+  const validation = pwdg.validate("short", options);
+  assert.strictEqual(validation.valid, false);
+  assert.strictEqual(validation.errors.length, 2);
+});
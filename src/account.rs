@@ -0,0 +1,59 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+/// Shortest name, or display name token, that Windows forbids in passwords.
+const MIN_NAME_LENGTH: usize = 3;
+
+/// Characters at which Windows splits a display name into tokens.
+const DELIMITERS: &[char] = &[',', '.', '-', '_', '#'];
+
+/// Returns the lowercase names that the Windows complexity filter forbids in
+/// the passwords of an account: `account_name` and every token of
+/// `display_name`, split at commas, periods, dashes, underscores, number
+/// signs, and whitespace. Names shorter than three characters are allowed,
+/// so they are left out.
+pub fn windows_forbidden_names(
+  account_name: &str,
+  display_name: Option<&str>,
+) -> Vec<String> {
+  let tokens = display_name
+    .unwrap_or("")
+    .split(|c: char| c.is_whitespace() || DELIMITERS.contains(&c));
+  let mut names: Vec<String> = Vec::new();
+  for name in std::iter::once(account_name).chain(tokens) {
+    let name = name.to_lowercase();
+    if name.chars().count() >= MIN_NAME_LENGTH && !names.contains(&name) {
+      names.push(name);
+    }
+  }
+  names
+}
+
+/// Returns `true` if `password` contains any of `names`, as returned by
+/// `windows_forbidden_names`, ignoring case.
+pub fn contains_forbidden_name(password: &str, names: &[String]) -> bool {
+  let password = password.to_lowercase();
+  names.iter().any(|name| password.contains(name.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_windows_forbidden_names() {
+    assert_eq!(
+      windows_forbidden_names("JSmith", Some("Smith, Jo-Ann Q._Lee#Admin")),
+      ["jsmith", "smith", "ann", "lee", "admin"]
+    );
+    assert!(windows_forbidden_names("al", None).is_empty());
+  }
+
+  #[test]
+  fn test_contains_forbidden_name() {
+    let names = windows_forbidden_names("jsmith", Some("Jo Smith"));
+    assert!(contains_forbidden_name("x9SMITHq!", &names));
+    assert!(!contains_forbidden_name("x9SMIq!Jo", &names));
+  }
+}
@@ -0,0 +1,93 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+//! `proptest` strategies for policies, enabled by the `proptest` feature.
+use std::borrow::Cow;
+
+use proptest::prelude::*;
+
+use crate::{LengthBounds, Policy, PwdGenOptions, MIN_LENGTH};
+
+/// Largest minimum generated for each built-in class.
+const MAX_CLASS_MIN: usize = 2;
+
+/// Largest length generated.
+const MAX_LENGTH: usize = 64;
+
+impl Arbitrary for PwdGenOptions<'static> {
+  type Parameters = ();
+  type Strategy = BoxedStrategy<Self>;
+
+  /// Options with small minimums and a few excluded characters, which never
+  /// empty a built-in class.
+  fn arbitrary_with(_: ()) -> Self::Strategy {
+    let min = 0..=MAX_CLASS_MIN;
+    (
+      min.clone(),
+      min.clone(),
+      min.clone(),
+      min,
+      proptest::option::of("[A-Za-z0-9!@#$%^&*]{1,6}"),
+    )
+      .prop_map(|(min_upper, min_lower, min_digit, min_special, exclude)| {
+        PwdGenOptions {
+          min_upper,
+          min_lower,
+          min_digit,
+          min_special,
+          exclude: exclude.map(Cow::Owned),
+          ..Default::default()
+        }
+      })
+      .boxed()
+  }
+}
+
+impl Arbitrary for Policy {
+  type Parameters = ();
+  type Strategy = BoxedStrategy<Self>;
+
+  /// Valid policies of `MIN_LENGTH` to 64 characters.
+  fn arbitrary_with(_: ()) -> Self::Strategy {
+    (MIN_LENGTH..=MAX_LENGTH, any::<PwdGenOptions<'static>>())
+      .prop_map(|(length, options)| Policy::new(length, options))
+      .prop_filter("policy must be valid", |policy| {
+        policy.validate().is_valid()
+      })
+      .boxed()
+  }
+}
+
+impl Arbitrary for LengthBounds {
+  type Parameters = ();
+  type Strategy = BoxedStrategy<Self>;
+
+  /// Bounds from `MIN_LENGTH` to 64 characters, with or without a maximum.
+  fn arbitrary_with(_: ()) -> Self::Strategy {
+    (MIN_LENGTH..=MAX_LENGTH)
+      .prop_flat_map(|min| (Just(min), proptest::option::of(min..=MAX_LENGTH)))
+      .prop_map(|(min, max)| LengthBounds { min, max })
+      .boxed()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  proptest! {
+    #[test]
+    fn test_generated_passwords_are_allowed(policy in any::<Policy>()) {
+      let password = policy.generator().unwrap().gen();
+      let violations = policy.password_violations(&password);
+      prop_assert!(violations.is_empty(), "{:?}", violations);
+    }
+
+    #[test]
+    fn test_length_bounds_are_ordered(bounds in any::<LengthBounds>()) {
+      prop_assert!(bounds.min >= MIN_LENGTH);
+      prop_assert!(bounds.max.is_none_or(|max| max >= bounds.min));
+    }
+  }
+}
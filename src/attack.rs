@@ -0,0 +1,197 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+#[cfg(feature = "json")]
+use serde::Serialize;
+
+/// An attacker assumed to make guesses at a constant rate.
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct AttackerProfile {
+  /// Short name, e.g. `offline-fast-hash`.
+  pub name: &'static str,
+  pub description: &'static str,
+  pub guesses_per_second: f64,
+}
+
+/// Attackers assumed by `AttackEstimate::new`, from slowest to fastest. The
+/// rates follow those commonly used by strength estimators such as zxcvbn.
+pub const ATTACKER_PROFILES: &[AttackerProfile] = &[
+  AttackerProfile {
+    name: "online-throttled",
+    description: "online attack against a service that limits login attempts",
+    guesses_per_second: 100.0 / 3600.0,
+  },
+  AttackerProfile {
+    name: "online",
+    description: "online attack against a service without rate limiting",
+    guesses_per_second: 10.0,
+  },
+  AttackerProfile {
+    name: "offline-slow-hash",
+    description: "offline attack on a slow hash such as bcrypt or Argon2",
+    guesses_per_second: 1e4,
+  },
+  AttackerProfile {
+    name: "offline-fast-hash",
+    description: "offline attack on a fast hash such as SHA-256 or NTLM",
+    guesses_per_second: 1e10,
+  },
+  AttackerProfile {
+    name: "offline-fast-hash-cluster",
+    description: "offline attack on a fast hash by a large GPU cluster",
+    guesses_per_second: 1e14,
+  },
+];
+
+/// The average time an attacker needs to guess a password.
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct CrackTime {
+  pub attacker: AttackerProfile,
+  /// Average time to guess the password, in seconds.
+  pub seconds: f64,
+}
+
+/// How long attackers would take to guess a password with a given entropy.
+///
+/// Assumes the attacker knows the policy and searches its passwords at
+/// random, so that on average half of them are tried.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct AttackEstimate {
+  pub entropy_bits: f64,
+  /// Average number of guesses needed, `2^(entropy_bits - 1)`.
+  pub guesses: f64,
+  /// One crack time per attacker of `ATTACKER_PROFILES`.
+  pub crack_times: Vec<CrackTime>,
+}
+
+impl AttackEstimate {
+  pub fn new(entropy_bits: f64) -> Self {
+    let guesses = (entropy_bits - 1.0).exp2().max(1.0);
+    AttackEstimate {
+      entropy_bits,
+      guesses,
+      crack_times: ATTACKER_PROFILES
+        .iter()
+        .map(|&attacker| CrackTime {
+          attacker,
+          seconds: guesses / attacker.guesses_per_second,
+        })
+        .collect(),
+    }
+  }
+
+  /// Serializes the estimate as pretty-printed JSON.
+  #[cfg(feature = "json")]
+  pub fn to_json(&self) -> String {
+    serde_json::to_string_pretty(self).expect("Attack estimate is serializable")
+  }
+}
+
+impl std::fmt::Display for AttackEstimate {
+  /// Writes the entropy, then the crack time of each attacker on its own
+  /// line.
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    write!(
+      f,
+      "entropy: {:.1} bits, {:.3e} guesses on average",
+      self.entropy_bits, self.guesses
+    )?;
+    for crack_time in &self.crack_times {
+      write!(
+        f,
+        "\n{} ({:.3e} guesses/s): {}",
+        crack_time.attacker.name,
+        crack_time.attacker.guesses_per_second,
+        Duration(crack_time.seconds)
+      )?;
+    }
+    Ok(())
+  }
+}
+
+/// A number of seconds, written in the largest unit that fits.
+struct Duration(f64);
+
+impl std::fmt::Display for Duration {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    const UNITS: [(&str, f64); 6] = [
+      ("minutes", 60.0),
+      ("hours", 3600.0),
+      ("days", 86400.0),
+      ("years", 31_557_600.0),
+      ("centuries", 3_155_760_000.0),
+      ("million years", 31_557_600e6),
+    ];
+
+    let seconds = self.0;
+    if seconds < 1.0 {
+      return write!(f, "less than a second");
+    }
+    match UNITS.iter().rev().find(|(_, unit)| seconds >= *unit) {
+      Some((name, unit)) if seconds / unit >= 1e6 => {
+        write!(f, "{:.1e} {}", seconds / unit, name)
+      }
+      Some((name, unit)) => write!(f, "{:.0} {}", seconds / unit, name),
+      None => write!(f, "{:.0} seconds", seconds),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_attack_estimate() {
+    let estimate = AttackEstimate::new(41.0);
+    assert_eq!(estimate.guesses, 2f64.powi(40));
+    assert_eq!(estimate.crack_times.len(), ATTACKER_PROFILES.len());
+
+    let fast = &estimate.crack_times[3];
+    assert_eq!(fast.attacker.name, "offline-fast-hash");
+    assert_eq!(fast.seconds, 2f64.powi(40) / 1e10);
+    assert!(estimate
+      .crack_times
+      .windows(2)
+      .all(|pair| pair[0].seconds > pair[1].seconds));
+  }
+
+  #[test]
+  fn test_display() {
+    let display = AttackEstimate::new(41.0).to_string();
+    let lines: Vec<&str> = display.lines().collect();
+    assert_eq!(lines.len(), 1 + ATTACKER_PROFILES.len());
+    assert_eq!(
+      lines[4],
+      "offline-fast-hash (1.000e10 guesses/s): 2 minutes"
+    );
+    assert_eq!(
+      lines[5],
+      "offline-fast-hash-cluster (1.000e14 guesses/s): less than a second"
+    );
+  }
+
+  #[test]
+  fn test_duration() {
+    assert_eq!(Duration(59.0).to_string(), "59 seconds");
+    assert_eq!(Duration(7200.0).to_string(), "2 hours");
+    assert_eq!(Duration(31_557_600e13).to_string(), "1.0e7 million years");
+  }
+
+  #[cfg(feature = "json")]
+  #[test]
+  fn test_to_json() {
+    let json: serde_json::Value =
+      serde_json::from_str(&AttackEstimate::new(64.0).to_json()).unwrap();
+    assert_eq!(json["entropy_bits"], 64.0);
+    assert_eq!(
+      json["crack_times"][0]["attacker"]["name"],
+      "online-throttled"
+    );
+    assert!(json["crack_times"][2]["seconds"].is_number());
+  }
+}
@@ -0,0 +1,7 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+fn main() {
+  uniffi::uniffi_bindgen_main()
+}
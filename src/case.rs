@@ -0,0 +1,108 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use rand::Rng;
+
+/// Returns the lowercase and uppercase forms of `c`, if it has two distinct
+/// forms of one character each.
+fn case_pair(c: char) -> Option<(char, char)> {
+  let mut lower = c.to_lowercase();
+  let mut upper = c.to_uppercase();
+  match (lower.next(), lower.next(), upper.next(), upper.next()) {
+    (Some(lower), None, Some(upper), None) if lower != upper => {
+      Some((lower, upper))
+    }
+    _ => None,
+  }
+}
+
+/// Returns `true` if `c` is a letter whose case `randomize_case` chooses.
+pub fn has_case(c: char) -> bool {
+  case_pair(c).is_some()
+}
+
+/// Returns `text` with the case of every letter chosen independently and
+/// uniformly at random. Other characters, such as digits, are kept.
+pub fn randomize_case<R: Rng + ?Sized>(text: &str, rng: &mut R) -> String {
+  text
+    .chars()
+    .map(|c| match case_pair(c) {
+      Some((lower, upper)) => {
+        if rng.gen() {
+          upper
+        } else {
+          lower
+        }
+      }
+      None => c,
+    })
+    .collect()
+}
+
+/// Returns the entropy, in bits, that `randomize_case` adds to a character
+/// drawn uniformly from `charset`. Randomizing the case never removes
+/// entropy, and adds none if the charset has both cases of all its letters.
+pub fn random_case_gain(charset: &[char]) -> f64 {
+  let n = charset.len() as f64;
+  let mut probabilities: Vec<(char, f64)> = Vec::new();
+  let mut add = |c: char, p: f64| match probabilities
+    .iter_mut()
+    .find(|(other, _)| *other == c)
+  {
+    Some((_, total)) => *total += p,
+    None => probabilities.push((c, p)),
+  };
+  for &c in charset {
+    match case_pair(c) {
+      Some((lower, upper)) => {
+        add(lower, 0.5 / n);
+        add(upper, 0.5 / n);
+      }
+      None => add(c, 1.0 / n),
+    }
+  }
+
+  let after: f64 = probabilities.iter().map(|(_, p)| -p * p.log2()).sum();
+  (after - n.log2()).max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::{rngs::StdRng, SeedableRng};
+
+  #[test]
+  fn test_randomize_case() {
+    let mut rng = StdRng::seed_from_u64(7);
+    let texts: Vec<String> = (0..20)
+      .map(|_| randomize_case("correct-horse-42", &mut rng))
+      .collect();
+    for text in &texts {
+      assert_eq!(text.to_lowercase(), "correct-horse-42");
+    }
+    assert!(texts.iter().any(|text| text.contains(char::is_uppercase)));
+    assert!(texts.iter().any(|text| text.contains(char::is_lowercase)));
+  }
+
+  #[test]
+  fn test_random_case_gain() {
+    let lower: Vec<char> = ('a'..='z').collect();
+    assert!((random_case_gain(&lower) - 1.0).abs() < 1e-9);
+
+    let letters: Vec<char> = ('a'..='z').chain('A'..='Z').collect();
+    assert!(random_case_gain(&letters).abs() < 1e-9);
+
+    let half = ['a', 'b', '0', '1'];
+    assert!((random_case_gain(&half) - 0.5).abs() < 1e-9);
+  }
+
+  #[test]
+  fn test_has_case() {
+    assert!(has_case('a'));
+    assert!(has_case('\u{e9}'));
+    assert!(!has_case('7'));
+    // Uppercases to two characters, `SS`.
+    assert!(!has_case('\u{df}'));
+  }
+}
@@ -2,8 +2,164 @@
 Copyright 2024 Owain Davies
 SPDX-License-Identifier: Apache-2.0
 */
+#[cfg(feature = "builtin-charsets")]
 pub const SPECIAL_CHARS: &[char] = &[
   '!', '@', '#', '$', '%', '^', '&', '*', '(', ')', '_', '+', '-', '=', '{',
   '}', '[', ']', '|', ':', ';', '"', '\'', '<', '>', ',', '.', '?', '/', '~',
   '\\', '`',
 ];
+
+/// A class of characters that a password may be drawn from.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum Category {
+  /// Uppercase characters (`A` to `Z`).
+  Upper,
+  /// Lowercase characters (`a` to `z`).
+  Lower,
+  /// Digit characters (`0` to `9`).
+  Digit,
+  /// Special characters (`SPECIAL_CHARS`).
+  Special,
+  /// A caller-defined class, identified by its name.
+  Custom(String),
+}
+
+impl std::fmt::Display for Category {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      Category::Upper => write!(f, "upper"),
+      Category::Lower => write!(f, "lower"),
+      Category::Digit => write!(f, "digit"),
+      Category::Special => write!(f, "special"),
+      Category::Custom(name) => write!(f, "{}", name),
+    }
+  }
+}
+
+impl Category {
+  /// Returns the built-in class called `name`, e.g. `upper`, or otherwise
+  /// the custom class of that name.
+  pub fn from_name(name: &str) -> Category {
+    BUILTIN_CLASSES
+      .into_iter()
+      .find(|category| category.to_string() == name)
+      .unwrap_or_else(|| Category::Custom(name.to_string()))
+  }
+}
+
+/// The built-in character classes, in generation order.
+pub(crate) const BUILTIN_CLASSES: [Category; 4] = [
+  Category::Upper,
+  Category::Lower,
+  Category::Digit,
+  Category::Special,
+];
+
+/// Returns the characters of the built-in class `category`.
+///
+/// Always empty if the `builtin-charsets` feature is disabled, in which case
+/// only custom classes are available.
+#[cfg(feature = "builtin-charsets")]
+pub(crate) fn builtin_chars(category: &Category) -> Vec<char> {
+  match category {
+    Category::Upper => ('A'..='Z').collect(),
+    Category::Lower => ('a'..='z').collect(),
+    Category::Digit => ('0'..='9').collect(),
+    Category::Special => SPECIAL_CHARS.to_vec(),
+    Category::Custom(_) => Vec::new(),
+  }
+}
+
+#[cfg(not(feature = "builtin-charsets"))]
+pub(crate) fn builtin_chars(_category: &Category) -> Vec<char> {
+  Vec::new()
+}
+
+/// A named group of characters that are commonly excluded together, such as
+/// `quotes`, because they break a format or are hard to read.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ExclusionGroup {
+  pub name: &'static str,
+  /// One-line description of the group.
+  pub description: &'static str,
+  pub chars: &'static str,
+}
+
+/// Characters easily mistaken for one another, such as `0` and `O`, removed
+/// by `PwdGenOptions::avoid_ambiguous`.
+pub const AMBIGUOUS_CHARS: &str = "0OIl1|";
+
+/// Every exclusion group, in the order they are listed. New groups are added
+/// here.
+const EXCLUSION_GROUPS: &[ExclusionGroup] = &[
+  ExclusionGroup {
+    name: "quotes",
+    description: "Quotes and backticks, which end strings in most formats",
+    chars: "\"'`",
+  },
+  ExclusionGroup {
+    name: "slashes",
+    description: "Slashes and backslashes, used in paths and escapes",
+    chars: "/\\",
+  },
+  ExclusionGroup {
+    name: "brackets",
+    description: "Brackets, braces, parentheses, and angle brackets",
+    chars: "()[]{}<>",
+  },
+  ExclusionGroup {
+    name: "ambiguous",
+    description: "Characters easily mistaken for one another",
+    chars: AMBIGUOUS_CHARS,
+  },
+  ExclusionGroup {
+    name: "shell",
+    description: "Characters with a special meaning in POSIX shells",
+    chars: "$`\\\"'!&|;<>()*?[]{}~#",
+  },
+];
+
+impl ExclusionGroup {
+  /// Returns the group called `name`, if any.
+  pub fn lookup(name: &str) -> Option<&'static ExclusionGroup> {
+    EXCLUSION_GROUPS.iter().find(|group| group.name == name)
+  }
+
+  /// Returns every group.
+  pub fn all() -> &'static [ExclusionGroup] {
+    EXCLUSION_GROUPS
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_exclusion_groups() {
+    assert_eq!(ExclusionGroup::lookup("slashes").unwrap().chars, "/\\");
+    assert!(ExclusionGroup::lookup("nope").is_none());
+    let names =
+      crate::util::unique(ExclusionGroup::all().iter().map(|g| g.name));
+    assert_eq!(names.len(), ExclusionGroup::all().len());
+  }
+
+  #[test]
+  fn test_category_from_name() {
+    assert_eq!(Category::from_name("digit"), Category::Digit);
+    assert_eq!(
+      Category::from_name("accents"),
+      Category::Custom("accents".to_string())
+    );
+  }
+
+  #[test]
+  fn test_category_display() {
+    assert_eq!(Category::Upper.to_string(), "upper");
+    assert_eq!(Category::Special.to_string(), "special");
+    assert_eq!(
+      Category::Custom("accents".to_string()).to_string(),
+      "accents"
+    );
+  }
+}
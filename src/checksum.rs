@@ -0,0 +1,121 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+
+/// A check appended to passwords, so that typos made while entering a
+/// password by hand are caught.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Checksum {
+  /// One character computed with the Luhn mod N algorithm over an alphabet,
+  /// typically the password's charset, which also contains the character.
+  Luhn,
+  /// Two digits computed with ISO 7064 MOD 97-10 over the password's UTF-8
+  /// bytes, as in IBANs.
+  Mod97,
+}
+
+impl Checksum {
+  /// Returns `password` followed by its check characters, or `None` if a
+  /// character of `password` is not in `alphabet`. `alphabet` is only used
+  /// by `Checksum::Luhn`, and its order matters.
+  pub fn append(&self, password: &str, alphabet: &[char]) -> Option<String> {
+    let check = match self {
+      Checksum::Luhn => luhn_char(password, alphabet)?.to_string(),
+      Checksum::Mod97 => format!("{:02}", 98 - mod97(password, 100)),
+    };
+    Some(format!("{}{}", password, check))
+  }
+
+  /// Returns `true` if `password` ends with valid check characters for the
+  /// rest of it.
+  pub fn verify(&self, password: &str, alphabet: &[char]) -> bool {
+    match self {
+      Checksum::Luhn => {
+        let Some((check, body)) = password
+          .char_indices()
+          .last()
+          .map(|(i, c)| (c, &password[..i]))
+        else {
+          return false;
+        };
+        luhn_char(body, alphabet) == Some(check)
+      }
+      Checksum::Mod97 => {
+        let split = password.len().saturating_sub(2);
+        let Some(check) = password.get(split..) else {
+          return false;
+        };
+        check.len() == 2
+          && check.bytes().all(|b| b.is_ascii_digit())
+          && self.append(&password[..split], alphabet).as_deref()
+            == Some(password)
+      }
+    }
+  }
+}
+
+/// Computes the Luhn mod N check character of `input` over `alphabet`.
+fn luhn_char(input: &str, alphabet: &[char]) -> Option<char> {
+  let n = alphabet.len();
+  let mut factor = 2;
+  let mut sum = 0;
+
+  for c in input.chars().rev() {
+    let code = alphabet.iter().position(|&a| a == c)?;
+    let addend = factor * code;
+    sum += addend / n + addend % n;
+    factor = 3 - factor;
+  }
+
+  Some(alphabet[(n - sum % n) % n])
+}
+
+/// Returns `(input * multiplier) mod 97`, reading the UTF-8 bytes of `input`
+/// as a base-256 number.
+fn mod97(input: &str, multiplier: u32) -> u32 {
+  let remainder = input.bytes().fold(0, |r, b| (r * 256 + b as u32) % 97);
+  remainder * multiplier % 97
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const DIGITS: [char; 10] = ['0', '1', '2', '3', '4', '5', '6', '7', '8', '9'];
+
+  #[test]
+  fn test_luhn_matches_credit_card_digit() {
+    // The classic Luhn example: 7992739871 has check digit 3.
+    assert_eq!(
+      Checksum::Luhn.append("7992739871", &DIGITS).as_deref(),
+      Some("79927398713")
+    );
+    assert!(Checksum::Luhn.verify("79927398713", &DIGITS));
+    assert!(!Checksum::Luhn.verify("79927398710", &DIGITS));
+  }
+
+  #[test]
+  fn test_luhn_catches_single_substitution() {
+    let alphabet: Vec<char> = ('!'..='~').collect();
+    let password = Checksum::Luhn.append("Tr0ub4dor&3", &alphabet).unwrap();
+    assert!(Checksum::Luhn.verify(&password, &alphabet));
+    assert!(!Checksum::Luhn.verify(&password.replacen('b', "c", 1), &alphabet));
+  }
+
+  #[test]
+  fn test_luhn_character_outside_alphabet() {
+    assert_eq!(Checksum::Luhn.append("12a", &DIGITS), None);
+    assert!(!Checksum::Luhn.verify("", &DIGITS));
+  }
+
+  #[test]
+  fn test_mod97() {
+    let password = Checksum::Mod97.append("correct horse", &[]).unwrap();
+    assert_eq!(password.len(), "correct horse".len() + 2);
+    assert!(Checksum::Mod97.verify(&password, &[]));
+    assert!(!Checksum::Mod97.verify("correct hose", &[]));
+    assert!(!Checksum::Mod97.verify(&password.replacen('h', "j", 1), &[]));
+    assert!(!Checksum::Mod97.verify("1", &[]));
+  }
+}
@@ -0,0 +1,113 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+mod accounts;
+mod args;
+mod audit;
+#[cfg(feature = "aws")]
+mod aws;
+mod batch;
+mod bundle;
+mod case;
+mod charsets;
+mod checksum;
+#[cfg(feature = "clipboard")]
+mod clipboard;
+mod commit;
+mod config;
+#[cfg(unix)]
+mod daemon;
+#[cfg(feature = "dbus")]
+mod dbus;
+mod entropy;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod hash;
+mod infer;
+mod inject;
+#[cfg(feature = "keychain")]
+mod keychain;
+mod meta;
+#[cfg(any(unix, feature = "grpc"))]
+mod metrics;
+mod output;
+mod passphrase;
+mod pin;
+mod pinentry;
+mod policy;
+mod prompt;
+mod receipt;
+mod recipients;
+mod request;
+mod rotate;
+mod salt;
+mod secret_files;
+mod share;
+mod split;
+mod stats;
+#[cfg(any(
+  feature = "aws",
+  feature = "dbus",
+  feature = "keychain",
+  feature = "wincred"
+))]
+mod store;
+mod template;
+mod validity;
+#[cfg(feature = "wincred")]
+mod wincred;
+
+pub use accounts::run as run_accounts;
+pub use args::{Filter, PolicyArgs};
+pub use audit::run as run_audit;
+pub use batch::{BatchFormat, BatchOrder};
+pub use bundle::run as run_bundle;
+pub use bundle::BundleFormat;
+pub use case::randomize as randomize_case;
+pub use charsets::run as run_charsets;
+pub use checksum::ChecksumKind;
+pub use checksum::{append as append_checksum, verify as verify_checksum};
+#[cfg(feature = "clipboard")]
+pub use clipboard::{
+  clear_after as clear_clipboard_after, copy, run_clear as run_clear_clipboard,
+};
+pub use commit::{commit, CommitKind};
+pub use config::save_profile;
+#[cfg(unix)]
+pub use daemon::run as run_daemon;
+#[cfg(feature = "dbus")]
+pub use dbus::serve as serve_dbus;
+pub use entropy::{mixed_rng, read as read_extra_entropy};
+#[cfg(feature = "grpc")]
+pub use grpc::{serve as serve_grpc, RateLimit, TlsArgs};
+pub use hash::{hash as hash_password, Argon2Args, HashKind};
+pub use infer::run as run_infer_policy;
+pub use inject::inject;
+pub use meta::{record, run_due};
+pub use output::{format as format_password, OutputFormat};
+pub use passphrase::run as run_passphrase;
+pub use pin::run as run_pin;
+pub use pinentry::run as run_pinentry;
+pub use policy::{dry_run, PolicyCommand};
+pub use prompt::run as run_prompt_new;
+pub use receipt::{issue as issue_receipt, verify as verify_receipt};
+pub use request::run as run_options_json;
+pub use rotate::run as run_rotate;
+pub use salt::SaltEncoding;
+pub use share::{parse_ttl, run as run_share};
+pub use split::{run_combine, split};
+pub use stats::print as print_stats;
+#[cfg(any(
+  feature = "aws",
+  feature = "dbus",
+  feature = "keychain",
+  feature = "wincred"
+))]
+pub use store::{push as push_secret, Store};
+pub use template::render;
+pub use validity::Validity;
+
+pub use batch::run as run_batch;
+pub use policy::run as run_policy;
+pub use salt::run as run_salt;
@@ -0,0 +1,96 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use std::path::Path;
+
+use super::PolicyArgs;
+
+/// Reads the CSV file at `path` and writes it to standard output with a
+/// generated password in `column`, which is added if it does not exist.
+///
+/// Rows are streamed one at a time. If `overrides` is set, nonempty cells of
+/// columns named after an option, e.g. `length` or `min-digit`, override the
+/// options for their row.
+pub fn run(
+  path: &Path,
+  column: &str,
+  overrides: bool,
+  args: &PolicyArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let base = args.resolve()?;
+  let mut reader = csv::Reader::from_path(path)?;
+
+  let mut headers = reader.headers()?.clone();
+  let position = headers.iter().position(|header| header == column);
+  if position.is_none() {
+    headers.push_field(column);
+  }
+
+  let names = PolicyArgs::option_names();
+  let override_columns: Vec<(usize, &(String, bool))> = if overrides {
+    headers
+      .iter()
+      .enumerate()
+      .filter(|&(_, header)| header != column)
+      .filter_map(|(i, header)| {
+        names
+          .iter()
+          .find(|(name, _)| name == header)
+          .map(|name| (i, name))
+      })
+      .collect()
+  } else {
+    Vec::new()
+  };
+
+  let mut writer = csv::Writer::from_writer(std::io::stdout().lock());
+  writer.write_record(&headers)?;
+
+  let mut base_policy = None;
+  for (n, record) in reader.records().enumerate() {
+    let record = record?;
+    let row_error =
+      |e: Box<dyn std::error::Error>| format!("row {}: {}", n + 1, e);
+
+    // Flags are set by a cell of `true`, `yes`, or `1`.
+    let options: Vec<String> = override_columns
+      .iter()
+      .filter_map(|&(i, (name, takes_value))| {
+        let value = record.get(i).filter(|value| !value.is_empty())?;
+        match takes_value {
+          true => Some(format!("--{}={}", name, value)),
+          false => {
+            matches!(value, "true" | "yes" | "1").then(|| format!("--{}", name))
+          }
+        }
+      })
+      .collect();
+
+    let password = if options.is_empty() {
+      if base_policy.is_none() {
+        base_policy = Some(base.policy()?);
+      }
+      base.generate(base_policy.as_ref().expect("policy is loaded"))?
+    } else {
+      let row = PolicyArgs::try_parse_from(options)
+        .map_err(|e| {
+          let message = e.to_string();
+          row_error(message.lines().next().unwrap_or("").into())
+        })?
+        .or(&base);
+      let policy = row.policy().map_err(row_error)?;
+      row.generate(&policy).map_err(row_error)?
+    };
+
+    let mut fields: Vec<&str> = record.iter().collect();
+    match position {
+      Some(i) if i < fields.len() => fields[i] = &password,
+      _ => fields.push(&password),
+    }
+    writer.write_record(&fields)?;
+  }
+
+  writer.flush()?;
+  Ok(())
+}
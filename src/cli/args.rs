@@ -0,0 +1,593 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use clap::{Args, CommandFactory, Parser};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+/// Maximum number of passwords generated when looking for one that passes
+/// the `Filter`.
+const MAX_ATTEMPTS: usize = 1000;
+
+/// Rules beyond the policy that generated passwords must pass, or else are
+/// discarded and generated again.
+#[derive(Default)]
+pub struct Filter {
+  /// Substrings that passwords must not contain, from `--exclude-file`.
+  forbidden: Vec<String>,
+  /// Settings that libpwquality checks passwords against, from
+  /// `--pwquality`.
+  pwquality: Option<pwdg::PwQuality>,
+  /// Names that passwords must not contain, ignoring case, from
+  /// `--account-name` and `--display-name`.
+  names: Vec<String>,
+}
+
+impl Filter {
+  /// Returns `true` if `password` passes every rule.
+  pub fn allows(&self, password: &str) -> bool {
+    !self.forbidden.iter().any(|s| password.contains(s.as_str()))
+      && self
+        .pwquality
+        .as_ref()
+        .is_none_or(|pwq| pwq.allows(password))
+      && !pwdg::contains_forbidden_name(password, &self.names)
+  }
+}
+
+// Options that define a password policy. Options that are not given keep the
+// value from `--profile`, then `--policy-file`, `--preset`, or `--site-req`,
+// if any, or otherwise the library default. Profiles store these options as given.
+#[derive(Args, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct PolicyArgs {
+  /// Sets the length of the password. Must be at least 8 [default: 8].
+  #[clap(short, long)]
+  length: Option<usize>,
+
+  /// Minimum number of uppercase characters (A to Z) [default: 0].
+  #[clap(long)]
+  min_upper: Option<usize>,
+
+  /// Minimum number of lowercase characters (a to z) [default: 0].
+  #[clap(long)]
+  min_lower: Option<usize>,
+
+  /// Minimum number of digit characters (0 to 9) [default: 0].
+  #[clap(long)]
+  min_digit: Option<usize>,
+
+  /// Minimum number of special characters.
+  #[clap(long, help = &format!(
+    "Minimum number of special characters.\nSpecial characters: {} [default: 0]",
+    pwdg::SPECIAL_CHARS.iter().collect::<String>()
+  ))]
+  min_special: Option<usize>,
+
+  /// Minimum estimated entropy of the password, in bits. Fails if the other
+  /// options cannot reach it.
+  #[clap(long, value_name = "BITS")]
+  min_bits: Option<usize>,
+
+  /// Draws the characters beyond the minimums only from these classes, e.g.
+  /// upper,lower,digit for exactly --min-special special characters.
+  #[clap(long, value_name = "CLASSES", value_delimiter = ',')]
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  fill_classes: Vec<String>,
+
+  /// Class of the first character, e.g. alpha for systems that require
+  /// passwords to start with a letter.
+  #[clap(
+    long,
+    value_name = "CLASS",
+    value_parser = ["upper", "lower", "alpha", "digit"]
+  )]
+  first: Option<String>,
+
+  /// Regenerates passwords with a simple structure, such as palindromes,
+  /// repetitions like abcabc, or sequences like abcdefgh, which some
+  /// filters reject.
+  #[clap(long, action = clap::ArgAction::SetTrue)]
+  #[serde(skip_serializing_if = "std::ops::Not::not")]
+  reject_structures: bool,
+
+  /// Excludes characters easily mistaken for one another: 0, O, I, l, 1,
+  /// and |.
+  #[clap(long, action = clap::ArgAction::SetTrue)]
+  #[serde(skip_serializing_if = "std::ops::Not::not")]
+  avoid_ambiguous: bool,
+
+  /// Characters to exclude from the overall character set used for password
+  /// generation. Accepts ranges and classes, e.g. a-f,A-F, [0-9], or
+  /// class:special. Use - to read the characters from standard input.
+  #[clap(short, long)]
+  exclude: Option<String>,
+
+  /// Excludes named groups of characters, merged with --exclude, e.g.
+  /// quotes,slashes.
+  #[clap(
+    long,
+    value_name = "GROUPS",
+    value_delimiter = ',',
+    value_parser = clap::builder::PossibleValuesParser::new(
+      pwdg::ExclusionGroup::all().iter().map(|group| {
+        clap::builder::PossibleValue::new(group.name).help(group.description)
+      })
+    )
+  )]
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  exclude_group: Vec<String>,
+
+  /// Reads exclusions from a file, one per line, merged with --exclude. A
+  /// line of one character excludes that character, and a longer line forbids
+  /// the whole substring from appearing in generated passwords.
+  #[clap(long, value_name = "PATH")]
+  exclude_file: Option<std::path::PathBuf>,
+
+  /// Regenerates passwords until libpwquality, configured by this
+  /// pwquality.conf file, e.g. /etc/security/pwquality.conf, would accept
+  /// them. Dictionary lookups are not performed.
+  #[clap(long, value_name = "PATH")]
+  pwquality: Option<std::path::PathBuf>,
+
+  /// Regenerates passwords that contain this account name, as the Windows
+  /// complexity filter rejects them. Ignores case.
+  #[clap(long, value_name = "NAME")]
+  account_name: Option<String>,
+
+  /// Regenerates passwords that contain a word of this display name of at
+  /// least three characters, as the Windows complexity filter rejects them.
+  #[clap(long, value_name = "NAME", requires = "account_name")]
+  display_name: Option<String>,
+
+  /// Draws the password only from these characters instead of the
+  /// uppercase, lowercase, digit, and special classes, e.g. 0-9,A-F or
+  /// class:lower,[0-9]. Accepts the same ranges and classes as --exclude.
+  /// Duplicates are ignored, and exclusions are applied afterwards.
+  #[clap(long, value_name = "CHARS", conflicts_with = "charset_file")]
+  charset: Option<String>,
+
+  /// Reads the allowed characters from a UTF-8 file. Line breaks and
+  /// duplicates are ignored, and exclusions are applied afterwards.
+  #[clap(long, value_name = "PATH")]
+  charset_file: Option<std::path::PathBuf>,
+
+  /// Generates a password with at least 1 uppercase letter, 1 lowercase letter,
+  /// 1 digit, and 1 special character. This option overrides --min-upper,
+  /// --min-lower, --min-digit, and --min-special if they are also set.
+  #[clap(short, long, action = clap::ArgAction::SetTrue)]
+  #[serde(skip_serializing_if = "std::ops::Not::not")]
+  strong: bool,
+
+  /// Reads the policy from a file, either a JSON policy document (see `pwdg
+  /// policy export`) or an Active Directory-style password policy. Other
+  /// options override the values read from the file.
+  #[clap(long, value_name = "PATH")]
+  policy_file: Option<std::path::PathBuf>,
+
+  /// Starts from a named preset policy. Other options override the values of
+  /// the preset.
+  #[clap(
+    long,
+    value_name = "NAME",
+    conflicts_with = "policy_file",
+    value_parser = clap::builder::PossibleValuesParser::new(
+      pwdg::Preset::all().iter().map(|preset| preset.name)
+    )
+  )]
+  preset: Option<String>,
+
+  /// Starts from the requirements a site displays, in a forgiving syntax of
+  /// comma-separated terms, e.g. '8-20,no-spaces,symbols:!@#,must:Ul d': a
+  /// length range, generated at its maximum; must: U, l, d, and s for at
+  /// least one uppercase, lowercase, digit, and symbol; symbols: the only
+  /// symbols allowed; no-symbols; and no: characters to exclude. Other
+  /// options override the requirements.
+  #[clap(
+    long,
+    value_name = "REQUIREMENTS",
+    conflicts_with_all = ["policy_file", "preset"]
+  )]
+  site_req: Option<String>,
+
+  /// Starts from a profile saved with --save-profile. Other options override
+  /// the values of the profile.
+  #[clap(long, value_name = "NAME")]
+  #[serde(skip)]
+  profile: Option<String>,
+}
+
+/// Policy options parsed on their own, e.g. from a batch spec.
+#[derive(Parser)]
+#[command(no_binary_name = true)]
+struct OptionsOnly {
+  #[command(flatten)]
+  options: PolicyArgs,
+}
+
+impl PolicyArgs {
+  /// Parses policy options, without a binary name.
+  pub fn try_parse_from<I, T>(args: I) -> Result<Self, clap::Error>
+  where
+    I: IntoIterator<Item = T>,
+    T: Into<std::ffi::OsString> + Clone,
+  {
+    OptionsOnly::try_parse_from(args).map(|parsed| parsed.options)
+  }
+
+  /// Returns the long names of the options, e.g. `min-digit`, and whether
+  /// each takes a value.
+  pub fn option_names() -> Vec<(String, bool)> {
+    OptionsOnly::command()
+      .get_arguments()
+      .filter_map(|arg| {
+        let takes_value = arg.get_action().takes_values();
+        arg.get_long().map(|long| (long.to_string(), takes_value))
+      })
+      .collect()
+  }
+
+  /// Overrides the values of `policy` with the options that were given.
+  fn apply(
+    &self,
+    policy: &mut pwdg::Policy,
+  ) -> Result<(), Box<dyn std::error::Error>> {
+    let options = &mut policy.options;
+
+    if let Some(length) = self.length {
+      policy.length = length;
+    }
+
+    if self.strong {
+      options.min_upper = 1;
+      options.min_lower = 1;
+      options.min_digit = 1;
+      options.min_special = 1;
+    } else {
+      options.min_upper = self.min_upper.unwrap_or(options.min_upper);
+      options.min_lower = self.min_lower.unwrap_or(options.min_lower);
+      options.min_digit = self.min_digit.unwrap_or(options.min_digit);
+      options.min_special = self.min_special.unwrap_or(options.min_special);
+    }
+    if let Some(bits) = self.min_bits {
+      options.min_entropy_bits = bits;
+    }
+    if !self.fill_classes.is_empty() {
+      options.fill_classes = Some(
+        self
+          .fill_classes
+          .iter()
+          .map(|name| pwdg::Category::from_name(name))
+          .collect(),
+      );
+    }
+    if let Some(first) = &self.first {
+      if first == "alpha" {
+        // Letters, as a class with no minimum that only the first
+        // character is drawn from.
+        options.classes.to_mut().push(pwdg::CharClass {
+          name: "alpha".into(),
+          chars: ('A'..='Z').chain('a'..='z').collect::<String>().into(),
+          min: 0,
+        });
+      }
+      options.first_char_class = Some(pwdg::Category::from_name(first));
+    }
+    options.reject_structures |= self.reject_structures;
+    options.avoid_ambiguous |= self.avoid_ambiguous;
+
+    if let Some(charset) = &self.charset {
+      options.charset = Some(pwdg::parse_exclusions(charset)?.into());
+    }
+    if let Some(path) = &self.charset_file {
+      let charset = std::fs::read_to_string(path)?;
+      options.charset = Some(
+        charset
+          .chars()
+          .filter(|&c| c != '\n' && c != '\r')
+          .collect(),
+      );
+    }
+
+    let mut exclude = self
+      .exclude()?
+      .map(|e| pwdg::parse_exclusions(&e))
+      .transpose()?;
+    for name in &self.exclude_group {
+      let group = pwdg::ExclusionGroup::lookup(name)
+        .ok_or_else(|| format!("Unknown exclusion group '{}'.", name))?;
+      exclude
+        .get_or_insert_with(String::new)
+        .push_str(group.chars);
+    }
+    if let Some((chars, _)) = self.exclude_file()? {
+      exclude.get_or_insert_with(String::new).push_str(&chars);
+    }
+    if let Some(exclude) = exclude {
+      options.exclude = Some(exclude.into());
+    }
+
+    Ok(())
+  }
+
+  /// Returns `true` if exclusions are read from standard input.
+  pub fn reads_stdin(&self) -> bool {
+    self.exclude.as_deref() == Some("-")
+  }
+
+  /// Returns the characters given by `--exclude`, reading them from standard
+  /// input if the value is `-`. Line breaks read from standard input are
+  /// ignored.
+  fn exclude(&self) -> std::io::Result<Option<String>> {
+    match self.exclude.as_deref() {
+      Some("-") => {
+        let input = std::io::read_to_string(std::io::stdin())?;
+        Ok(Some(
+          input.chars().filter(|&c| c != '\n' && c != '\r').collect(),
+        ))
+      }
+      exclude => Ok(exclude.map(String::from)),
+    }
+  }
+
+  /// Reads the characters and forbidden substrings of `--exclude-file`, if
+  /// given. Blank lines are ignored.
+  fn exclude_file(&self) -> std::io::Result<Option<(String, Vec<String>)>> {
+    let Some(path) = &self.exclude_file else {
+      return Ok(None);
+    };
+
+    let mut chars = String::new();
+    let mut substrings = Vec::new();
+    for line in std::fs::read_to_string(path)?.lines() {
+      match line.chars().count() {
+        0 => {}
+        1 => chars.push_str(line),
+        _ => substrings.push(line.to_string()),
+      }
+    }
+
+    Ok(Some((chars, substrings)))
+  }
+
+  /// Returns the options that were given, falling back to `base` for options
+  /// that were not.
+  pub fn or(&self, base: &PolicyArgs) -> PolicyArgs {
+    let (charset, charset_file) =
+      if self.charset.is_some() || self.charset_file.is_some() {
+        (self.charset.clone(), self.charset_file.clone())
+      } else {
+        (base.charset.clone(), base.charset_file.clone())
+      };
+    let (policy_file, preset, site_req) = if self.policy_file.is_some()
+      || self.preset.is_some()
+      || self.site_req.is_some()
+    {
+      (
+        self.policy_file.clone(),
+        self.preset.clone(),
+        self.site_req.clone(),
+      )
+    } else {
+      (
+        base.policy_file.clone(),
+        base.preset.clone(),
+        base.site_req.clone(),
+      )
+    };
+    PolicyArgs {
+      length: self.length.or(base.length),
+      min_upper: self.min_upper.or(base.min_upper),
+      min_lower: self.min_lower.or(base.min_lower),
+      min_digit: self.min_digit.or(base.min_digit),
+      min_special: self.min_special.or(base.min_special),
+      min_bits: self.min_bits.or(base.min_bits),
+      fill_classes: if self.fill_classes.is_empty() {
+        base.fill_classes.clone()
+      } else {
+        self.fill_classes.clone()
+      },
+      first: self.first.clone().or(base.first.clone()),
+      reject_structures: self.reject_structures || base.reject_structures,
+      avoid_ambiguous: self.avoid_ambiguous || base.avoid_ambiguous,
+      exclude: self.exclude.clone().or(base.exclude.clone()),
+      exclude_group: if self.exclude_group.is_empty() {
+        base.exclude_group.clone()
+      } else {
+        self.exclude_group.clone()
+      },
+      exclude_file: self.exclude_file.clone().or(base.exclude_file.clone()),
+      pwquality: self.pwquality.clone().or(base.pwquality.clone()),
+      account_name: self.account_name.clone().or(base.account_name.clone()),
+      display_name: self.display_name.clone().or(base.display_name.clone()),
+      charset,
+      charset_file,
+      strong: self.strong || base.strong,
+      policy_file,
+      preset,
+      site_req,
+      profile: self.profile.clone().or(base.profile.clone()),
+    }
+  }
+
+  /// Returns the options of `--profile`, if given, overridden by the options
+  /// that were given.
+  fn with_profile(&self) -> Result<Self, Box<dyn std::error::Error>> {
+    let Some(name) = &self.profile else {
+      return Ok(self.clone());
+    };
+    let config = super::config::Config::load()?;
+    let merged = self.or(config.profile(name)?);
+    Ok(PolicyArgs {
+      profile: None,
+      ..merged
+    })
+  }
+
+  /// Returns the name given to `--profile`.
+  pub fn profile_name(&self) -> Option<&str> {
+    self.profile.as_deref()
+  }
+
+  /// Sets `--profile` to `name`.
+  pub fn set_profile(&mut self, name: Option<String>) {
+    self.profile = name;
+  }
+
+  /// Returns the options merged with `--profile`, with exclusions read from
+  /// standard input, so that policies can be loaded from them repeatedly.
+  pub fn resolve(&self) -> Result<Self, Box<dyn std::error::Error>> {
+    let mut resolved = self.with_profile()?;
+    resolved.exclude = resolved.exclude()?;
+    Ok(resolved)
+  }
+
+  /// Returns the options to save as a profile: the resolved options, with
+  /// paths made absolute.
+  pub fn to_profile(&self) -> Result<Self, Box<dyn std::error::Error>> {
+    let mut profile = self.resolve()?;
+    for path in [
+      &mut profile.exclude_file,
+      &mut profile.pwquality,
+      &mut profile.charset_file,
+      &mut profile.policy_file,
+    ]
+    .into_iter()
+    .flatten()
+    {
+      *path = std::fs::canonicalize(&*path)?;
+    }
+    Ok(profile)
+  }
+
+  /// Returns the rules beyond the policy that generated passwords must pass.
+  pub fn filter(&self) -> Result<Filter, Box<dyn std::error::Error>> {
+    let args = self.with_profile()?;
+    let pwquality = match &args.pwquality {
+      Some(path) => {
+        Some(pwdg::PwQuality::parse(&std::fs::read_to_string(path)?)?)
+      }
+      None => None,
+    };
+    Ok(Filter {
+      forbidden: args
+        .exclude_file()?
+        .map(|(_, substrings)| substrings)
+        .unwrap_or_default(),
+      pwquality,
+      names: args.account_name.as_deref().map_or_else(Vec::new, |name| {
+        pwdg::windows_forbidden_names(name, args.display_name.as_deref())
+      }),
+    })
+  }
+
+  /// Generates a password for `policy`, as loaded from these options, that
+  /// passes the `filter`.
+  pub fn generate(
+    &self,
+    policy: &pwdg::Policy,
+  ) -> Result<String, Box<dyn std::error::Error>> {
+    self.generate_using(policy, &mut OsRng)
+  }
+
+  /// Generates a password like `generate`, drawing randomness from `rng`.
+  pub fn generate_using(
+    &self,
+    policy: &pwdg::Policy,
+    rng: &mut dyn RngCore,
+  ) -> Result<String, Box<dyn std::error::Error>> {
+    Self::generate_with(&policy.generator()?, &self.filter()?, rng)
+  }
+
+  /// Generates a password with `generator` and `rng` that passes `filter`.
+  pub fn generate_with(
+    generator: &pwdg::PwdGen,
+    filter: &Filter,
+    rng: &mut dyn RngCore,
+  ) -> Result<String, Box<dyn std::error::Error>> {
+    Self::generate_counting(generator, filter, rng)
+      .map(|(password, _)| password)
+  }
+
+  /// Generates a password like `generate_with`, also returning the number
+  /// of passwords discarded for failing `filter`.
+  pub fn generate_counting(
+    generator: &pwdg::PwdGen,
+    filter: &Filter,
+    rng: &mut dyn RngCore,
+  ) -> Result<(String, usize), Box<dyn std::error::Error>> {
+    Self::retry(filter, || Ok(generator.gen_with_rng(rng)))
+  }
+
+  /// Generates a password with `generator` and `rng` in the structure of
+  /// `pattern` that passes `filter`. See `PwdGen::gen_from_pattern`.
+  pub fn generate_pattern_with(
+    generator: &pwdg::PwdGen,
+    pattern: &str,
+    filter: &Filter,
+    rng: &mut dyn RngCore,
+  ) -> Result<String, Box<dyn std::error::Error>> {
+    Self::retry(filter, || generator.gen_from_pattern_with_rng(pattern, rng))
+      .map(|(password, _)| password)
+  }
+
+  /// Calls `generate` until it returns a password that passes `filter`, and
+  /// returns it with the number of passwords discarded.
+  fn retry(
+    filter: &Filter,
+    mut generate: impl FnMut() -> Result<String, pwdg::Error>,
+  ) -> Result<(String, usize), Box<dyn std::error::Error>> {
+    for retries in 0..MAX_ATTEMPTS {
+      let password = generate()?;
+      if filter.allows(&password) {
+        return Ok((password, retries));
+      }
+    }
+    Err(
+      "Could not generate a password without forbidden substrings or names \
+       that libpwquality accepts."
+        .into(),
+    )
+  }
+
+  /// Loads the policy given by the options, without validating it.
+  pub fn load(&self) -> Result<pwdg::Policy, Box<dyn std::error::Error>> {
+    self.with_profile()?.load_without_profile()
+  }
+
+  fn load_without_profile(
+    &self,
+  ) -> Result<pwdg::Policy, Box<dyn std::error::Error>> {
+    let preset = self.preset.as_deref().and_then(pwdg::Preset::lookup);
+    let mut policy = match (&self.policy_file, preset) {
+      (Some(path), _) => pwdg::Policy::parse(&std::fs::read_to_string(path)?)?,
+      (None, Some(preset)) => preset.policy(),
+      (None, None) => match &self.site_req {
+        Some(requirements) => {
+          pwdg::Policy::from_site_requirements(requirements)?
+        }
+        None => {
+          pwdg::Policy::new(pwdg::MIN_LENGTH, pwdg::DEFAULT_PWDGEN_OPTIONS)
+        }
+      },
+    };
+
+    self.apply(&mut policy)?;
+
+    Ok(policy)
+  }
+
+  /// Loads the policy given by the options, reporting every violated
+  /// constraint.
+  pub fn policy(&self) -> Result<pwdg::Policy, Box<dyn std::error::Error>> {
+    let policy = self.load()?;
+
+    let report = policy.validate();
+    if !report.is_valid() {
+      return Err(report.into());
+    }
+
+    Ok(policy)
+  }
+}
@@ -0,0 +1,412 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use pwdg::EffectiveConfig;
+
+use super::PolicyArgs;
+
+/// Chance that unbiased output is reported as biased, across every test of
+/// an audit.
+const ALPHA: f64 = 0.001;
+
+/// A frequency whose confidence interval excludes the frequency expected of
+/// unbiased output.
+#[derive(Debug, PartialEq)]
+struct Anomaly {
+  /// What was counted, e.g. `character 'a'` or `position 3, upper`.
+  subject: String,
+  count: usize,
+  trials: usize,
+  expected: f64,
+  interval: (f64, f64),
+}
+
+impl std::fmt::Display for Anomaly {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    write!(
+      f,
+      "{}: {:.3}% ({} of {}, CI {:.3}% to {:.3}%), expected {:.3}%",
+      self.subject,
+      100.0 * self.count as f64 / self.trials as f64,
+      self.count,
+      self.trials,
+      100.0 * self.interval.0,
+      100.0 * self.interval.1,
+      100.0 * self.expected
+    )
+  }
+}
+
+/// The results of an audit of the distribution of passwords.
+#[derive(Debug, Default)]
+struct Audit {
+  passwords: usize,
+  /// Characters that no class of the policy contains.
+  outside: Vec<char>,
+  characters: Vec<Anomaly>,
+  character_tests: usize,
+  /// Length of the passwords whose positions were tested.
+  length: usize,
+  positions: Vec<Anomaly>,
+  position_tests: usize,
+}
+
+/// Audits passwords generated under the policy of `args`: `samples` of them,
+/// or those read from `input`, one per line, or from standard input if it
+/// is `-`. Prints characters and positions whose frequencies are anomalous,
+/// with confidence intervals, and fails if there are any.
+pub fn run(
+  input: Option<&Path>,
+  samples: usize,
+  args: &PolicyArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+  if input.is_some_and(|path| path == Path::new("-")) && args.reads_stdin() {
+    return Err(
+      "--input and --exclude cannot both read standard input.".into(),
+    );
+  }
+  let args = args.resolve()?;
+  let generator = args.policy()?.generator()?;
+  let passwords = match input {
+    Some(path) => {
+      let text = if path == Path::new("-") {
+        std::io::read_to_string(std::io::stdin())?
+      } else {
+        std::fs::read_to_string(path)?
+      };
+      text
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+    }
+    None => generator.gen_n(samples),
+  };
+  if passwords.is_empty() {
+    return Err("No passwords to audit.".into());
+  }
+
+  let result = audit(generator.config(), &passwords);
+  println!("passwords: {}", result.passwords);
+  if !result.outside.is_empty() {
+    println!(
+      "characters outside the charset: {}",
+      result.outside.iter().collect::<String>()
+    );
+  }
+  println!(
+    "character anomalies: {} of {} tested",
+    result.characters.len(),
+    result.character_tests
+  );
+  for anomaly in &result.characters {
+    println!("  {}", anomaly);
+  }
+  println!(
+    "position anomalies: {} of {} tested, at length {}",
+    result.positions.len(),
+    result.position_tests,
+    result.length
+  );
+  for anomaly in &result.positions {
+    println!("  {}", anomaly);
+  }
+  println!(
+    "confidence intervals are Wilson score intervals at a family-wise \
+     significance of {}%",
+    100.0 * ALPHA
+  );
+
+  let anomalies =
+    result.outside.len() + result.characters.len() + result.positions.len();
+  if anomalies > 0 {
+    return Err(format!("Found {} anomalies.", anomalies).into());
+  }
+  println!("No bias detected.");
+  Ok(())
+}
+
+/// Tests the frequencies of `passwords` against those expected of `config`.
+///
+/// Characters that the same classes, fill, and first characters contain are
+/// drawn with the same probability, so each is tested against the others of
+/// its group. Every position but the first, if the first character is
+/// restricted, holds each group with the same probability, so each group at
+/// each position is tested against its frequency at all of them. Positions
+/// are tested over the passwords of the most common length.
+fn audit(config: &EffectiveConfig, passwords: &[String]) -> Audit {
+  let mut groups: BTreeMap<Vec<usize>, Vec<char>> = BTreeMap::new();
+  for &c in &config.charset {
+    groups.entry(group_key(config, c)).or_default().push(c);
+  }
+  let group_of: HashMap<char, usize> = groups
+    .values()
+    .enumerate()
+    .flat_map(|(i, chars)| chars.iter().map(move |&c| (c, i)))
+    .collect();
+  let names: Vec<String> =
+    groups.keys().map(|key| group_name(config, key)).collect();
+
+  let mut audit = Audit {
+    passwords: passwords.len(),
+    ..Default::default()
+  };
+  let mut counts: HashMap<char, usize> = HashMap::new();
+  for c in passwords.iter().flat_map(|p| p.chars()) {
+    *counts.entry(c).or_default() += 1;
+  }
+  audit.outside = counts
+    .keys()
+    .filter(|c| !group_of.contains_key(c))
+    .cloned()
+    .collect();
+  audit.outside.sort_unstable();
+
+  // Characters, against the others of their group.
+  audit.character_tests = groups
+    .values()
+    .filter(|chars| chars.len() > 1)
+    .map(Vec::len)
+    .sum();
+  let z = critical_z(audit.character_tests);
+  for chars in groups.values().filter(|chars| chars.len() > 1) {
+    let trials: usize = chars.iter().map(|c| counts.get(c).unwrap_or(&0)).sum();
+    let expected = 1.0 / chars.len() as f64;
+    for &c in chars {
+      let count = counts.get(&c).cloned().unwrap_or(0);
+      if let Some(anomaly) =
+        test(format!("character {:?}", c), count, trials, expected, z)
+      {
+        audit.characters.push(anomaly);
+      }
+    }
+  }
+
+  // Groups at each position, against their frequency at every position.
+  let mut lengths: HashMap<usize, usize> = HashMap::new();
+  for password in passwords {
+    *lengths.entry(password.chars().count()).or_default() += 1;
+  }
+  audit.length = lengths
+    .iter()
+    .max_by_key(|&(length, count)| (count, length))
+    .map_or(0, |(&length, _)| length);
+  let first = usize::from(config.first.is_some());
+  let positions = audit.length.saturating_sub(first);
+  let mut at: Vec<Vec<usize>> = vec![vec![0; groups.len()]; positions];
+  let mut trials = 0;
+  for password in passwords {
+    let chars: Vec<char> = password.chars().collect();
+    if chars.len() != audit.length {
+      continue;
+    }
+    trials += 1;
+    for (position, c) in chars.iter().skip(first).enumerate() {
+      if let Some(&group) = group_of.get(c) {
+        at[position][group] += 1;
+      }
+    }
+  }
+  if positions > 1 && groups.len() > 1 {
+    audit.position_tests = positions * groups.len();
+    let z = critical_z(audit.position_tests);
+    let total = (trials * positions) as f64;
+    for group in 0..groups.len() {
+      let expected =
+        at.iter().map(|counts| counts[group]).sum::<usize>() as f64 / total;
+      for (position, counts) in at.iter().enumerate() {
+        let subject =
+          format!("position {}, {}", position + first, names[group]);
+        if let Some(anomaly) = test(subject, counts[group], trials, expected, z)
+        {
+          audit.positions.push(anomaly);
+        }
+      }
+    }
+  }
+
+  audit
+}
+
+/// Returns what determines the probability of `c`: the indices of the
+/// classes that contain it, then whether the fill and the first characters
+/// do, if restricted.
+fn group_key(config: &EffectiveConfig, c: char) -> Vec<usize> {
+  let n = config.classes.len();
+  let mut key: Vec<usize> = (0..n)
+    .filter(|&i| config.classes[i].chars.contains(&c))
+    .collect();
+  if config.fill.as_ref().is_some_and(|fill| fill.contains(&c)) {
+    key.push(n);
+  }
+  if config
+    .first
+    .as_ref()
+    .is_some_and(|first| first.contains(&c))
+  {
+    key.push(n + 1);
+  }
+  key
+}
+
+fn group_name(config: &EffectiveConfig, key: &[usize]) -> String {
+  key
+    .iter()
+    .filter_map(|&i| config.classes.get(i))
+    .map(|class| class.category.to_string())
+    .collect::<Vec<_>>()
+    .join("+")
+}
+
+/// Returns an anomaly if the Wilson score interval at `z` of `count` of
+/// `trials` excludes `expected`.
+fn test(
+  subject: String,
+  count: usize,
+  trials: usize,
+  expected: f64,
+  z: f64,
+) -> Option<Anomaly> {
+  if trials == 0 {
+    return None;
+  }
+  let interval = wilson_interval(count, trials, z);
+  if interval.0 <= expected && expected <= interval.1 {
+    return None;
+  }
+  Some(Anomaly {
+    subject,
+    count,
+    trials,
+    expected,
+    interval,
+  })
+}
+
+/// Returns the Wilson score interval of a proportion of `count` of `trials`,
+/// for the standard normal quantile `z`.
+fn wilson_interval(count: usize, trials: usize, z: f64) -> (f64, f64) {
+  let n = trials as f64;
+  let p = count as f64 / n;
+  let z2 = z * z;
+  let center = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+  let half =
+    z / (1.0 + z2 / n) * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+  ((center - half).max(0.0), (center + half).min(1.0))
+}
+
+/// Returns the two-sided critical value of the standard normal distribution
+/// for `tests` tests at a family-wise significance of `ALPHA`, by the
+/// Bonferroni correction.
+fn critical_z(tests: usize) -> f64 {
+  normal_quantile(1.0 - ALPHA / (2.0 * tests.max(1) as f64))
+}
+
+/// Returns the quantile of the standard normal distribution at `p`, by the
+/// rational approximation of Peter Acklam, with a relative error below
+/// 1.15e-9.
+fn normal_quantile(p: f64) -> f64 {
+  const A: [f64; 6] = [
+    -3.969683028665376e1,
+    2.209460984245205e2,
+    -2.759285104469687e2,
+    1.38357751867269e2,
+    -3.066479806614716e1,
+    2.506628277459239,
+  ];
+  const B: [f64; 5] = [
+    -5.447609879822406e1,
+    1.615858368580409e2,
+    -1.556989798598866e2,
+    6.680131188771972e1,
+    -1.328068155288572e1,
+  ];
+  const C: [f64; 6] = [
+    -7.784894002430293e-3,
+    -3.223964580411365e-1,
+    -2.400758277161838,
+    -2.549732539343734,
+    4.374664141464968,
+    2.938163982698783,
+  ];
+  const D: [f64; 4] = [
+    7.784695709041462e-3,
+    3.224671290700398e-1,
+    2.445134137142996,
+    3.754408661907416,
+  ];
+  const P_LOW: f64 = 0.02425;
+
+  let tail = |q: f64| {
+    (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+      / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+  };
+  if p < P_LOW {
+    tail((-2.0 * p.ln()).sqrt())
+  } else if p > 1.0 - P_LOW {
+    -tail((-2.0 * (1.0 - p).ln()).sqrt())
+  } else {
+    let q = p - 0.5;
+    let r = q * q;
+    (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+      / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use pwdg::{PwdGen, PwdGenOptions};
+
+  #[test]
+  fn test_normal_quantile() {
+    assert!((normal_quantile(0.975) - 1.959964).abs() < 1e-6);
+    assert!((normal_quantile(0.995) - 2.575829).abs() < 1e-6);
+    assert!((normal_quantile(0.01) + 2.326348).abs() < 1e-6);
+    assert!(normal_quantile(0.5).abs() < 1e-12);
+  }
+
+  #[test]
+  fn test_wilson_interval() {
+    let (low, high) = wilson_interval(50, 100, 1.959964);
+    assert!((low - 0.4038).abs() < 1e-4);
+    assert!((high - 0.5962).abs() < 1e-4);
+    assert_eq!(wilson_interval(0, 10, 1.96).0, 0.0);
+  }
+
+  #[test]
+  fn test_audit() {
+    let options = PwdGenOptions {
+      charset: Some("ABCDabcd0123".into()),
+      min_digit: 2,
+      ..Default::default()
+    };
+    let generator = PwdGen::new(8, Some(options)).unwrap();
+    let passwords = generator.gen_n(2000);
+    let result = audit(generator.config(), &passwords);
+    assert!(result.outside.is_empty());
+    assert_eq!(result.character_tests, 12);
+    assert_eq!(result.position_tests, 8 * 3);
+    assert_eq!(result.length, 8);
+
+    // Every password starts with A.
+    let biased: Vec<String> =
+      passwords.iter().map(|p| format!("A{}", &p[1..])).collect();
+    let result = audit(generator.config(), &biased);
+    assert!(result
+      .characters
+      .iter()
+      .any(|a| a.subject == "character 'A'"));
+    assert!(result
+      .positions
+      .iter()
+      .any(|a| a.subject == "position 0, upper"));
+
+    let result = audit(generator.config(), &["ABCDabc!".to_string()]);
+    assert_eq!(result.outside, vec!['!']);
+  }
+}
@@ -0,0 +1,110 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Creates or rotates the secret `name` in AWS Secrets Manager with the `aws`
+/// CLI, and prints only its ARN and version.
+///
+/// The secret is passed to the CLI on standard input, so that it never
+/// appears on screen or in the process list.
+pub fn secrets_manager(
+  name: &str,
+  secret: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let put = [
+    "secretsmanager",
+    "put-secret-value",
+    "--secret-id",
+    name,
+    "--secret-string",
+    STDIN,
+  ];
+  let response = match aws(&put, secret) {
+    Err(e) if e.contains("ResourceNotFoundException") => {
+      let create = [
+        "secretsmanager",
+        "create-secret",
+        "--name",
+        name,
+        "--secret-string",
+        STDIN,
+      ];
+      aws(&create, secret)?
+    }
+    response => response?,
+  };
+  println!(
+    "{} {}",
+    field(&response, "ARN")?,
+    field(&response, "VersionId")?
+  );
+  Ok(())
+}
+
+/// Creates or rotates the SecureString parameter `name` in AWS Systems
+/// Manager Parameter Store with the `aws` CLI, as for `secrets_manager`, and
+/// prints only its name and version.
+pub fn parameter_store(
+  name: &str,
+  secret: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let put = [
+    "ssm",
+    "put-parameter",
+    "--name",
+    name,
+    "--type",
+    "SecureString",
+    "--overwrite",
+    "--value",
+    STDIN,
+  ];
+  let response = aws(&put, secret)?;
+  println!("{} {}", name, field(&response, "Version")?);
+  Ok(())
+}
+
+/// Parameter value making the `aws` CLI read the value from standard input.
+const STDIN: &str = "file:///dev/stdin";
+
+/// Runs the `aws` CLI with `secret` on standard input, returning its JSON
+/// response, or its error output if it fails.
+fn aws(args: &[&str], secret: &str) -> Result<serde_json::Value, String> {
+  if cfg!(not(unix)) {
+    return Err("Storing secrets in AWS requires /dev/stdin.".to_string());
+  }
+
+  let mut child = Command::new("aws")
+    .args(args)
+    .args(["--output", "json"])
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .map_err(|e| format!("Could not run the aws CLI: {}", e))?;
+
+  child
+    .stdin
+    .take()
+    .expect("stdin is piped")
+    .write_all(secret.as_bytes())
+    .map_err(|e| e.to_string())?;
+  let output = child.wait_with_output().map_err(|e| e.to_string())?;
+
+  if !output.status.success() {
+    return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+  }
+  serde_json::from_slice(&output.stdout)
+    .map_err(|e| format!("Unexpected response from the aws CLI: {}", e))
+}
+
+fn field(response: &serde_json::Value, name: &str) -> Result<String, String> {
+  match &response[name] {
+    serde_json::Value::String(value) => Ok(value.clone()),
+    serde_json::Value::Number(value) => Ok(value.to_string()),
+    _ => Err(format!("No {} in the response of the aws CLI.", name)),
+  }
+}
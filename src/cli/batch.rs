@@ -0,0 +1,302 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use clap::{Parser, ValueEnum};
+use rand::{rngs::OsRng, seq::SliceRandom, Rng};
+use std::path::Path;
+
+use super::secret_files;
+use super::template::render;
+use super::validity::Validity;
+use super::PolicyArgs;
+
+/// A label and the options to generate its passwords with.
+struct Spec {
+  label: String,
+  options: PolicyArgs,
+  /// Number of passwords. Each is labelled `<label>-<n>` if more than one.
+  count: usize,
+}
+
+/// The options of a line spec: policy options and `--count`.
+#[derive(Parser)]
+#[command(no_binary_name = true)]
+struct LineOptions {
+  /// Number of passwords to generate for the spec.
+  #[clap(long, default_value_t = 1)]
+  count: usize,
+
+  #[command(flatten)]
+  options: PolicyArgs,
+}
+
+/// A generated password, its label, and the entropy and fingerprint of its
+/// policy.
+pub struct Secret {
+  /// Position of the password in generation order, from 0.
+  pub index: usize,
+  pub label: String,
+  pub password: String,
+  pub entropy_bits: f64,
+  pub policy_hash: String,
+}
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum BatchFormat {
+  /// One `label: password` line per spec.
+  #[default]
+  Text,
+  /// A JSON array of `{"label": ..., "password": ...}` objects, each with
+  /// the `index` of its password in generation order, its `length`, its
+  /// `entropy_bits`, the `policy_hash` fingerprint of its policy, and with
+  /// --valid-days its `created_at` and `expires_at`.
+  Json,
+  /// One file per spec, named by its label, under --dir.
+  DockerSecret,
+}
+
+/// Order of the passwords printed by `pwdg batch`.
+#[derive(Clone, Copy)]
+pub enum BatchOrder {
+  /// The order of the specs.
+  Spec,
+  /// Sorted by label, so that outputs can be diffed.
+  Sort,
+  /// Shuffled, so that the order reveals nothing of the generation sequence.
+  Shuffle,
+}
+
+/// Generates the passwords of every spec of `file`, in order, and prints them
+/// in `order` and `format`, or by rendering `template` once per password if
+/// given. `dir` is where `BatchFormat::DockerSecret` writes files, and where
+/// passwords are encrypted to the keys of the `recipients` file instead, if
+/// given. See `generate` for `vary_length`. `validity` is stamped on every
+/// password of JSON and template output.
+#[allow(clippy::too_many_arguments)]
+pub fn run(
+  file: &Path,
+  format: BatchFormat,
+  order: BatchOrder,
+  dir: Option<&Path>,
+  template: Option<&str>,
+  vary_length: usize,
+  validity: Option<Validity>,
+  recipients: Option<&Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let mut secrets = generate(file, vary_length)?;
+  match order {
+    BatchOrder::Spec => {}
+    BatchOrder::Sort => secrets.sort_by(|a, b| a.label.cmp(&b.label)),
+    BatchOrder::Shuffle => secrets.shuffle(&mut OsRng),
+  }
+
+  if let Some(recipients) = recipients {
+    let dir = dir.ok_or("No directory given.")?;
+    return super::recipients::write(dir, &secrets, recipients);
+  }
+
+  if let Some(template) = template {
+    let validity: Vec<_> = validity.iter().flat_map(Validity::fields).collect();
+    for secret in &secrets {
+      let index = secret.index.to_string();
+      let mut variables = vec![
+        ("label", secret.label.as_str()),
+        ("password", secret.password.as_str()),
+        ("index", index.as_str()),
+      ];
+      variables.extend(validity.iter().map(|(name, value)| (*name, &**value)));
+      println!("{}", render(template, &variables)?);
+    }
+    return Ok(());
+  }
+
+  match format {
+    BatchFormat::Text => {
+      for secret in &secrets {
+        println!("{}: {}", secret.label, secret.password);
+      }
+    }
+    BatchFormat::Json => {
+      let entries: Vec<serde_json::Value> = secrets
+        .iter()
+        .map(|secret| {
+          let mut entry = serde_json::json!({
+            "index": secret.index,
+            "label": secret.label,
+            "password": secret.password,
+            "length": secret.password.chars().count(),
+            "entropy_bits": secret.entropy_bits,
+            "policy_hash": secret.policy_hash,
+          });
+          if let Some(validity) = validity {
+            entry["created_at"] = validity.created_at.into();
+            entry["expires_at"] = validity.expires_at.into();
+          }
+          entry
+        })
+        .collect();
+      println!("{}", serde_json::to_string_pretty(&entries)?);
+    }
+    BatchFormat::DockerSecret => {
+      secret_files::write(dir.ok_or("No directory given.")?, &pairs(secrets))?;
+    }
+  }
+
+  Ok(())
+}
+
+/// Returns the label and password of each of `secrets`.
+pub fn pairs(secrets: Vec<Secret>) -> Vec<(String, String)> {
+  secrets
+    .into_iter()
+    .map(|secret| (secret.label, secret.password))
+    .collect()
+}
+
+/// Generates the passwords of every spec of `file`, in order. Fails without
+/// output if any spec is invalid. Each password is up to `vary_length`
+/// characters longer than its spec's length, at random.
+///
+/// A `.toml` file holds `[[spec]]` tables and a `.json` file an array of
+/// objects, each with a `label`, an optional `count`, and the options in
+/// their long form, e.g. `min-digit`. Any other file holds one
+/// `label: options` line per spec, e.g. `db: --length 20 --count 3`, where
+/// options are separated by whitespace. Blank lines and lines starting with
+/// `#` are ignored.
+pub fn generate(
+  file: &Path,
+  vary_length: usize,
+) -> Result<Vec<Secret>, Box<dyn std::error::Error>> {
+  let text = std::fs::read_to_string(file)?;
+  let specs = match file.extension().and_then(|e| e.to_str()) {
+    Some("toml") => parse_toml(&text)?,
+    Some("json") => parse_json(&text)?,
+    _ => parse_lines(&text)?,
+  };
+
+  let mut secrets = Vec::new();
+  for spec in specs {
+    let label_error =
+      |e: Box<dyn std::error::Error>| -> Box<dyn std::error::Error> {
+        format!("{}: {}", spec.label, e).into()
+      };
+    if spec.count == 0 {
+      return Err(label_error("count must be at least 1".into()));
+    }
+    let base = spec.options.policy().map_err(label_error)?;
+
+    for n in 1..=spec.count {
+      let mut policy = base.clone();
+      policy.length += OsRng.gen_range(0..=vary_length);
+      let entropy_bits = policy
+        .options
+        .validate(policy.length)
+        .map_err(|e| label_error(e.into()))?
+        .entropy_bits();
+      let password = spec.options.generate(&policy).map_err(label_error)?;
+      let label = if spec.count == 1 {
+        spec.label.clone()
+      } else {
+        format!("{}-{}", spec.label, n)
+      };
+      secrets.push(Secret {
+        index: secrets.len(),
+        label,
+        password,
+        entropy_bits,
+        policy_hash: policy.fingerprint(),
+      });
+    }
+  }
+
+  Ok(secrets)
+}
+
+fn parse_lines(text: &str) -> Result<Vec<Spec>, Box<dyn std::error::Error>> {
+  let mut specs = Vec::new();
+  for (n, line) in text.lines().enumerate() {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+
+    let (label, options) = line.split_once(':').unwrap_or((line, ""));
+    let LineOptions { count, options } =
+      LineOptions::try_parse_from(options.split_whitespace()).map_err(|e| {
+        let message = e.to_string();
+        format!("line {}: {}", n + 1, message.lines().next().unwrap_or(""))
+      })?;
+    specs.push(Spec {
+      label: label.trim().to_string(),
+      options,
+      count,
+    });
+  }
+  Ok(specs)
+}
+
+fn parse_toml(text: &str) -> Result<Vec<Spec>, Box<dyn std::error::Error>> {
+  let mut table: toml::Table = toml::from_str(text)?;
+  let Some(toml::Value::Array(entries)) = table.remove("spec") else {
+    return Err("Expected an array of [[spec]] tables.".into());
+  };
+
+  entries
+    .into_iter()
+    .map(|entry| {
+      let toml::Value::Table(mut entry) = entry else {
+        return Err("Each spec must be a table.".into());
+      };
+      let label = match entry.remove("label") {
+        Some(toml::Value::String(label)) => label,
+        _ => return Err("Each spec must have a string label.".into()),
+      };
+      let count = match entry.remove("count") {
+        None => 1,
+        Some(toml::Value::Integer(count)) => usize::try_from(count)
+          .map_err(|_| format!("{}: count must not be negative", label))?,
+        Some(_) => {
+          return Err(format!("{}: count must be an integer", label).into())
+        }
+      };
+      let options = entry
+        .try_into()
+        .map_err(|e| format!("{}: {}", label, e.message()))?;
+      Ok(Spec {
+        label,
+        options,
+        count,
+      })
+    })
+    .collect()
+}
+
+fn parse_json(text: &str) -> Result<Vec<Spec>, Box<dyn std::error::Error>> {
+  let entries: Vec<serde_json::Map<String, serde_json::Value>> =
+    serde_json::from_str(text)?;
+
+  entries
+    .into_iter()
+    .map(|mut entry| {
+      let label = match entry.remove("label") {
+        Some(serde_json::Value::String(label)) => label,
+        _ => return Err("Each spec must have a string label.".into()),
+      };
+      let count = match entry.remove("count") {
+        None => 1,
+        Some(count) => count
+          .as_u64()
+          .and_then(|count| usize::try_from(count).ok())
+          .ok_or_else(|| format!("{}: count must be an integer", label))?,
+      };
+      let options = serde_json::from_value(entry.into())
+        .map_err(|e| format!("{}: {}", label, e))?;
+      Ok(Spec {
+        label,
+        options,
+        count,
+      })
+    })
+    .collect()
+}
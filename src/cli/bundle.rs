@@ -0,0 +1,92 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use clap::ValueEnum;
+use std::path::Path;
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum BundleFormat {
+  /// A JSON object mapping each name to its secret.
+  #[default]
+  Json,
+  /// A `.env` file of `NAME='secret'` lines.
+  Env,
+  /// One file per secret, named by its name, under --dir.
+  DockerSecret,
+}
+
+/// Generates every secret of the spec file `spec` (see `pwdg batch`) and
+/// prints them together in `format`. Nothing is printed unless every secret
+/// could be generated. `dir` is where `BundleFormat::DockerSecret` writes
+/// files, and where secrets are encrypted to the keys of the `recipients`
+/// file instead, if given. See `batch::generate` for `vary_length`.
+pub fn run(
+  spec: &Path,
+  format: BundleFormat,
+  dir: Option<&Path>,
+  vary_length: usize,
+  recipients: Option<&Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let generated = super::batch::generate(spec, vary_length)?;
+  if let Some(recipients) = recipients {
+    let dir = dir.ok_or("No directory given.")?;
+    return super::recipients::write(dir, &generated, recipients);
+  }
+  let secrets = super::batch::pairs(generated);
+
+  let names: Vec<&str> =
+    secrets.iter().map(|(name, _)| name.as_str()).collect();
+  if let Some(name) = names
+    .iter()
+    .enumerate()
+    .find_map(|(i, name)| names[..i].contains(name).then_some(name))
+  {
+    return Err(format!("Duplicate secret name '{}'.", name).into());
+  }
+
+  match format {
+    BundleFormat::Json => {
+      let bundle: serde_json::Map<String, serde_json::Value> = secrets
+        .into_iter()
+        .map(|(name, secret)| (name, secret.into()))
+        .collect();
+      println!("{}", serde_json::to_string_pretty(&bundle)?);
+    }
+    BundleFormat::Env => {
+      for (name, secret) in &secrets {
+        println!("{}={}", env_name(name), env_quote(secret));
+      }
+    }
+    BundleFormat::DockerSecret => {
+      super::secret_files::write(dir.ok_or("No directory given.")?, &secrets)?;
+    }
+  }
+
+  Ok(())
+}
+
+/// Converts `name` to an environment variable name, e.g. `db-password` to
+/// `DB_PASSWORD`.
+fn env_name(name: &str) -> String {
+  let name: String = name
+    .chars()
+    .map(|c| {
+      if c.is_ascii_alphanumeric() {
+        c.to_ascii_uppercase()
+      } else {
+        '_'
+      }
+    })
+    .collect();
+  if name.starts_with(|c: char| c.is_ascii_digit()) {
+    format!("_{}", name)
+  } else {
+    name
+  }
+}
+
+/// Single-quotes `value` for a `.env` file or POSIX shell.
+pub fn env_quote(value: &str) -> String {
+  format!("'{}'", value.replace('\'', "'\\''"))
+}
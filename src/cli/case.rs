@@ -0,0 +1,31 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use rand::rngs::OsRng;
+
+/// Returns `password`, generated under `policy`, with the case of every
+/// letter chosen at random, and the estimated entropy this adds, in bits.
+/// Fails if a class with a minimum has letters, as their new case could
+/// break the minimum.
+pub fn randomize(
+  password: &str,
+  policy: &pwdg::Policy,
+) -> Result<(String, f64), Box<dyn std::error::Error>> {
+  let config = policy.options.validate(policy.length)?;
+  let cased = config.classes.iter().find(|class| {
+    class.min > 0 && class.chars.iter().any(|&c| pwdg::has_case(c))
+  });
+  if let Some(class) = cased {
+    return Err(
+      format!(
+        "--random-case could break the minimum of the {} class.",
+        class.category
+      )
+      .into(),
+    );
+  }
+
+  let gain = config.length as f64 * pwdg::random_case_gain(&config.charset);
+  Ok((pwdg::randomize_case(password, &mut OsRng), gain))
+}
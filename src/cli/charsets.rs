@@ -0,0 +1,69 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use super::PolicyArgs;
+
+/// Prints the characters of each class of the policy given by `args` after
+/// presets and exclusions, with their number, the entropy of a character
+/// drawn from the class, and the characters exclusions removed. Ends with
+/// the size and per-character entropy of the whole charset. The policy need
+/// not be valid.
+pub fn run(args: &PolicyArgs) -> Result<(), Box<dyn std::error::Error>> {
+  let options = args.load()?.options;
+  let classes = options.effective_classes();
+  let unfiltered = pwdg::PwdGenOptions {
+    exclude: None,
+    charset: None,
+    ..options
+  }
+  .effective_classes();
+
+  let mut charset: Vec<char> = Vec::new();
+  for (i, class) in classes.iter().enumerate() {
+    println!("{}: {}", class.category, size(class.chars.len()));
+    if !class.chars.is_empty() {
+      println!("  {}", class.chars.iter().collect::<String>());
+    }
+    // The charset of --charset has no unfiltered counterpart.
+    if let Some(all) = unfiltered.get(i) {
+      let removed: String = all
+        .chars
+        .iter()
+        .filter(|c| !class.chars.contains(c))
+        .collect();
+      if !removed.is_empty() {
+        println!("  removed: {}", removed);
+      }
+    }
+    for &c in &class.chars {
+      if !charset.contains(&c) {
+        charset.push(c);
+      }
+    }
+  }
+  println!("total: {}", size(charset.len()));
+
+  Ok(())
+}
+
+/// Describes a set of `n` characters and the entropy of one drawn from it.
+fn size(n: usize) -> String {
+  match n {
+    0 => "0 characters".to_string(),
+    1 => "1 character, 0.00 bits each".to_string(),
+    n => format!("{} characters, {:.2} bits each", n, (n as f64).log2()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_size() {
+    assert_eq!(size(0), "0 characters");
+    assert_eq!(size(1), "1 character, 0.00 bits each");
+    assert_eq!(size(32), "32 characters, 5.00 bits each");
+  }
+}
@@ -0,0 +1,71 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use clap::ValueEnum;
+
+use super::PolicyArgs;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ChecksumKind {
+  /// One Luhn mod N character from the password's charset.
+  Luhn,
+  /// Two ISO 7064 MOD 97-10 digits.
+  Mod97,
+}
+
+impl From<ChecksumKind> for pwdg::Checksum {
+  fn from(kind: ChecksumKind) -> Self {
+    match kind {
+      ChecksumKind::Luhn => pwdg::Checksum::Luhn,
+      ChecksumKind::Mod97 => pwdg::Checksum::Mod97,
+    }
+  }
+}
+
+/// Returns the alphabet of the Luhn checksum: the policy's charset, sorted so
+/// that it does not depend on the order of the classes.
+pub fn alphabet(
+  policy: &pwdg::Policy,
+) -> Result<Vec<char>, Box<dyn std::error::Error>> {
+  let mut alphabet = policy.options.validate(policy.length)?.charset;
+  alphabet.sort_unstable();
+  Ok(alphabet)
+}
+
+/// Appends the check characters of `kind` to `password`, generated for
+/// `policy`.
+pub fn append(
+  kind: ChecksumKind,
+  password: &str,
+  policy: &pwdg::Policy,
+) -> Result<String, Box<dyn std::error::Error>> {
+  pwdg::Checksum::from(kind)
+    .append(password, &alphabet(policy)?)
+    .ok_or_else(|| "Password contains characters outside its charset.".into())
+}
+
+/// Checks the check characters of `password`, read from standard input if it
+/// is `-`, against the policy given by `args`.
+pub fn verify(
+  kind: ChecksumKind,
+  password: &str,
+  args: &PolicyArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let password = match password {
+    "-" => {
+      let mut line = String::new();
+      std::io::stdin().read_line(&mut line)?;
+      line.trim_end_matches(['\n', '\r']).to_string()
+    }
+    password => password.to_string(),
+  };
+
+  let policy = args.policy()?;
+  if !pwdg::Checksum::from(kind).verify(&password, &alphabet(&policy)?) {
+    return Err("Invalid checksum.".into());
+  }
+
+  println!("Valid checksum.");
+  Ok(())
+}
@@ -0,0 +1,216 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Environment variable naming a command that reads the text to copy from
+/// standard input, overriding the platform clipboard commands.
+const COPY_COMMAND_ENV: &str = "PWDG_COPY_COMMAND";
+
+/// Environment variable naming a command that writes the text on the
+/// clipboard to standard output, overriding the platform paste commands.
+const PASTE_COMMAND_ENV: &str = "PWDG_PASTE_COMMAND";
+
+/// Clipboard commands, tried in order until one is found.
+const COMMANDS: &[&[&str]] = &[
+  &["pbcopy"],
+  &["wl-copy"],
+  &["xclip", "-selection", "clipboard"],
+  &["xsel", "--clipboard", "--input"],
+  &["clip.exe"],
+];
+
+/// Commands that print the clipboard, tried in order until one is found.
+const PASTE_COMMANDS: &[&[&str]] = &[
+  &["pbpaste"],
+  &["wl-paste", "--no-newline"],
+  &["xclip", "-selection", "clipboard", "-o"],
+  &["xsel", "--clipboard", "--output"],
+  &["powershell.exe", "-NoProfile", "-Command", "Get-Clipboard"],
+];
+
+/// Copies `text` to the clipboard with the first available clipboard command,
+/// or with `$PWDG_COPY_COMMAND` if set.
+pub fn copy(text: &str) -> Result<(), Box<dyn std::error::Error>> {
+  if cfg!(target_os = "wasi") {
+    return Err("Copying to the clipboard is not supported on WASI.".into());
+  }
+  if let Ok(command) = std::env::var(COPY_COMMAND_ENV) {
+    let command: Vec<&str> = command.split_whitespace().collect();
+    return match pipe(&command, text)? {
+      true => Ok(()),
+      false => Err(format!("Copy command '{}' not found.", command[0]).into()),
+    };
+  }
+
+  for command in COMMANDS {
+    if pipe(command, text)? {
+      return Ok(());
+    }
+  }
+
+  Err(
+    format!(
+      "No clipboard command found. Set {} to a command reading from standard \
+       input.",
+      COPY_COMMAND_ENV
+    )
+    .into(),
+  )
+}
+
+/// Starts a background pwdg process that clears the clipboard once `after`
+/// has passed, unless it no longer holds `text`. Only the SHA-256 digest of
+/// `text` is passed to the process, on its standard input, so that neither
+/// the text nor its digest, from which a weak password could be recovered,
+/// appears in the process list.
+pub fn clear_after(
+  text: &str,
+  after: Duration,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let mut child = Command::new(std::env::current_exe()?)
+    .arg("clear-clipboard")
+    .arg("--after")
+    .arg(format!("{}s", after.as_secs()))
+    .stdin(Stdio::piped())
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .spawn()?;
+  // Dropping the handle closes the pipe, so the process stops reading.
+  child
+    .stdin
+    .take()
+    .expect("Standard input is piped")
+    .write_all(digest(text).as_bytes())?;
+  Ok(())
+}
+
+/// Reads the SHA-256 digest of the text to clear, in hexadecimal, from
+/// standard input, waits for `after`, then clears the clipboard if it still
+/// holds that text, or if no paste command is found.
+pub fn run_clear(after: Duration) -> Result<(), Box<dyn std::error::Error>> {
+  let mut sha256 = String::new();
+  std::io::stdin().read_to_string(&mut sha256)?;
+  let sha256 = sha256.trim();
+  if sha256.is_empty() {
+    return Err("Expected a SHA-256 digest on standard input.".into());
+  }
+  std::thread::sleep(after);
+  if !unchanged(sha256, paste()?.as_deref()) {
+    return Ok(());
+  }
+  copy("")
+}
+
+/// Returns `true` if `clipboard`, the text on the clipboard, is the text
+/// whose digest is `sha256`, or if it is unknown.
+fn unchanged(sha256: &str, clipboard: Option<&str>) -> bool {
+  let Some(text) = clipboard else {
+    return true;
+  };
+  // Some paste commands add a line break.
+  let trimmed = text.trim_end_matches(['\r', '\n']);
+  digest(text) == sha256 || digest(trimmed) == sha256
+}
+
+fn digest(text: &str) -> String {
+  hex::encode(Sha256::digest(text))
+}
+
+/// Returns the text on the clipboard, read with the first available paste
+/// command, or with `$PWDG_PASTE_COMMAND` if set. Returns `None` if there is
+/// no paste command.
+fn paste() -> Result<Option<String>, Box<dyn std::error::Error>> {
+  let custom = std::env::var(PASTE_COMMAND_ENV).ok();
+  let custom: Vec<&str> =
+    custom.iter().flat_map(|c| c.split_whitespace()).collect();
+  let commands: Vec<&[&str]> = if custom.is_empty() {
+    PASTE_COMMANDS.to_vec()
+  } else {
+    vec![&custom]
+  };
+  for command in commands {
+    let (program, args) =
+      command.split_first().ok_or("Empty paste command.")?;
+    match Command::new(program)
+      .args(args)
+      .stderr(Stdio::null())
+      .output()
+    {
+      Ok(output) if output.status.success() => {
+        return Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()))
+      }
+      Ok(output) => {
+        return Err(
+          format!("Paste command '{}' failed: {}", program, output.status)
+            .into(),
+        )
+      }
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+      Err(e) => return Err(e.into()),
+    }
+  }
+  Ok(None)
+}
+
+/// Runs `command` with `text` on its standard input. Returns `false` if the
+/// command does not exist.
+fn pipe(
+  command: &[&str],
+  text: &str,
+) -> Result<bool, Box<dyn std::error::Error>> {
+  let (program, args) = command.split_first().ok_or("Empty copy command.")?;
+  let mut child = match Command::new(program)
+    .args(args)
+    .stdin(Stdio::piped())
+    .stdout(Stdio::null())
+    .spawn()
+  {
+    Ok(child) => child,
+    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+    Err(e) => return Err(e.into()),
+  };
+
+  child
+    .stdin
+    .take()
+    .expect("stdin is piped")
+    .write_all(text.as_bytes())?;
+  let status = child.wait()?;
+  if !status.success() {
+    return Err(
+      format!("Copy command '{}' failed: {}", program, status).into(),
+    );
+  }
+
+  Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_digest() {
+    // printf abc | sha256sum
+    assert_eq!(
+      digest("abc"),
+      "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+  }
+
+  #[test]
+  fn test_unchanged() {
+    let sha256 = digest("k7#Qp2!x");
+    assert!(unchanged(&sha256, Some("k7#Qp2!x")));
+    assert!(unchanged(&sha256, Some("k7#Qp2!x\r\n")));
+    assert!(unchanged(&sha256, None));
+    assert!(!unchanged(&sha256, Some("other")));
+    assert!(!unchanged(&sha256, Some("")));
+    assert!(!unchanged(&sha256, Some("k7#Qp2!x ")));
+  }
+}
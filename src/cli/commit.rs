@@ -0,0 +1,56 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use clap::ValueEnum;
+use sha2::{Digest, Sha256};
+
+/// Bytes of the salt of a commitment.
+const SALT_LEN: usize = 16;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum CommitKind {
+  /// SHA-256 of the salt bytes followed by the password.
+  Sha256,
+}
+
+/// Returns a commitment of `kind` to `password` and its random salt, both in
+/// hexadecimal. Revealing the password and the salt later opens it.
+pub fn commit(kind: CommitKind, password: &str) -> (String, String) {
+  let salt = pwdg::gen_salt(SALT_LEN);
+  (commit_with_salt(kind, password, &salt), hex::encode(salt))
+}
+
+fn commit_with_salt(kind: CommitKind, password: &str, salt: &[u8]) -> String {
+  match kind {
+    CommitKind::Sha256 => hex::encode(
+      Sha256::new()
+        .chain_update(salt)
+        .chain_update(password)
+        .finalize(),
+    ),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_commit_known_answer() {
+    // printf saltpassword | sha256sum
+    assert_eq!(
+      commit_with_salt(CommitKind::Sha256, "password", b"salt"),
+      "13601bda4ea78e55a07b98866d2be6be0744e3866f13c00c811cab608a28f322"
+    );
+  }
+
+  #[test]
+  fn test_commit_is_salted() {
+    let (first, first_salt) = commit(CommitKind::Sha256, "password");
+    let (second, second_salt) = commit(CommitKind::Sha256, "password");
+    assert_eq!(first_salt.len(), 2 * SALT_LEN);
+    assert_ne!(first_salt, second_salt);
+    assert_ne!(first, second);
+  }
+}
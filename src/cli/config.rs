@@ -0,0 +1,98 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+
+use super::PolicyArgs;
+
+/// Environment variable overriding the location of the configuration file.
+const CONFIG_ENV: &str = "PWDG_CONFIG";
+
+/// The configuration file, holding named profiles of options.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Config {
+  #[serde(default)]
+  pub profiles: BTreeMap<String, PolicyArgs>,
+}
+
+impl Config {
+  /// Returns the path of the configuration file: `$PWDG_CONFIG` if set, or
+  /// else `pwdg/config.toml` in the user's configuration directory.
+  pub fn path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    if let Some(path) = std::env::var_os(CONFIG_ENV) {
+      return Ok(path.into());
+    }
+    let dir = dirs::config_dir().ok_or("No configuration directory found.")?;
+    Ok(dir.join("pwdg").join("config.toml"))
+  }
+
+  /// Loads the configuration file, or an empty configuration if there is no
+  /// file.
+  pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+    let path = Self::path()?;
+    match std::fs::read_to_string(&path) {
+      Ok(text) => toml::from_str(&text)
+        .map_err(|e| format!("Invalid config file {}: {}", path.display(), e))
+        .map_err(Into::into),
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+      Err(e) => Err(e.into()),
+    }
+  }
+
+  /// Writes the configuration file, creating its directory if needed.
+  pub fn save(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let path = Self::path()?;
+    if let Some(dir) = path.parent() {
+      std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, toml::to_string_pretty(self)?)?;
+    Ok(path)
+  }
+
+  /// Returns the profile called `name`.
+  pub fn profile(
+    &self,
+    name: &str,
+  ) -> Result<&PolicyArgs, Box<dyn std::error::Error>> {
+    self
+      .profiles
+      .get(name)
+      .ok_or_else(|| format!("No profile named '{}'.", name).into())
+  }
+}
+
+/// Saves `args` as the profile called `name`, asking for confirmation before
+/// replacing an existing profile.
+pub fn save_profile(
+  name: &str,
+  args: &PolicyArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let profile = args.to_profile()?;
+  let mut config = Config::load()?;
+
+  if config.profiles.contains_key(name) && !confirm(name)? {
+    return Err("Profile not saved.".into());
+  }
+
+  config.profiles.insert(name.to_string(), profile);
+  let path = config.save()?;
+  eprintln!("Saved profile '{}' to {}", name, path.display());
+
+  Ok(())
+}
+
+fn confirm(name: &str) -> std::io::Result<bool> {
+  eprint!("Profile '{}' exists. Overwrite it? [y/N] ", name);
+  std::io::stderr().flush()?;
+
+  let mut answer = String::new();
+  std::io::stdin().read_line(&mut answer)?;
+  Ok(matches!(
+    answer.trim().to_ascii_lowercase().as_str(),
+    "y" | "yes"
+  ))
+}
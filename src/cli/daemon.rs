@@ -0,0 +1,295 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime};
+
+use rand::rngs::OsRng;
+
+use super::config::Config;
+use super::metrics::Metrics;
+use super::request::Request;
+use super::{Filter, PolicyArgs};
+
+/// Maximum number of generators kept warm. The cache is cleared when full.
+const MAX_CACHED: usize = 256;
+
+/// Maximum size of a request line, in bytes. A connection sending a longer
+/// one is answered with an error and closed.
+const MAX_REQUEST_BYTES: usize = 64 * 1024;
+
+/// A validated generator, the filter its passwords must pass, and the
+/// fingerprint of its policy.
+struct Warm {
+  generator: pwdg::PwdGen<'static>,
+  filter: Filter,
+  policy_hash: String,
+}
+
+/// The answer to a request.
+#[derive(Debug)]
+struct Answer {
+  passwords: Vec<String>,
+  /// Passwords discarded for failing the filter.
+  retries: usize,
+  policy_hash: String,
+}
+
+/// Warm generators, which are dropped when the configuration file changes so
+/// that edited profiles take effect without a restart.
+#[derive(Default)]
+struct Generators {
+  /// Generators keyed by the profile and options they were loaded from, as
+  /// JSON.
+  warm: HashMap<String, Arc<Warm>>,
+  /// Modification time of the configuration file they were loaded with.
+  config_modified: Option<SystemTime>,
+}
+
+type Cache = Arc<Mutex<Generators>>;
+
+/// Listens on the Unix domain socket at `socket`, readable and writable by
+/// the owner only, and answers each line of a connection, a JSON object of
+/// policy options in their long form and an optional `count` and `profile`,
+/// with a JSON object of `passwords` or an `error`. A request of
+/// `{"metrics": true}` is answered with the request metrics, in the
+/// Prometheus text format, as the string `metrics`.
+///
+/// Requests without a `profile` start from `profile`, if given. Profiles are
+/// reloaded when the configuration file changes, and requests already being
+/// answered finish with the profiles they started with.
+///
+/// A stale socket left by a previous daemon is replaced.
+pub fn run(
+  socket: &Path,
+  profile: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+  if let Some(name) = profile {
+    Config::load()?.profile(name)?;
+  }
+
+  if let Ok(metadata) = std::fs::symlink_metadata(socket) {
+    if !metadata.file_type().is_socket() {
+      return Err(format!("{} is not a socket.", socket.display()).into());
+    }
+    if UnixStream::connect(socket).is_ok() {
+      return Err(format!("{} is already in use.", socket.display()).into());
+    }
+    std::fs::remove_file(socket)?;
+  }
+
+  let listener = bind(socket)?;
+  eprintln!("Listening on {}", socket.display());
+
+  let cache = Cache::default();
+  let metrics = Arc::new(Metrics::default());
+  for stream in listener.incoming() {
+    let stream = stream?;
+    let cache = Arc::clone(&cache);
+    let metrics = Arc::clone(&metrics);
+    let profile = profile.map(str::to_string);
+    std::thread::spawn(move || {
+      if let Err(e) = serve(stream, &cache, &metrics, profile.as_deref()) {
+        eprintln!("{}", e);
+      }
+    });
+  }
+
+  Ok(())
+}
+
+/// Binds a socket at `socket` that only the owner can connect to. It is bound
+/// in a new directory that only the owner can enter, and moved into place
+/// once its permissions are set, so that no one else can connect before.
+fn bind(socket: &Path) -> std::io::Result<UnixListener> {
+  let name = socket.file_name().unwrap_or_default().to_string_lossy();
+  let dir = socket.with_file_name(format!(".{}.{}", name, std::process::id()));
+  std::fs::DirBuilder::new().mode(0o700).create(&dir)?;
+  let private = dir.join("socket");
+  let result = UnixListener::bind(&private).and_then(|listener| {
+    std::fs::set_permissions(&private, std::fs::Permissions::from_mode(0o600))?;
+    std::fs::rename(&private, socket)?;
+    Ok(listener)
+  });
+  let _ = std::fs::remove_file(&private);
+  std::fs::remove_dir(&dir)?;
+  result
+}
+
+fn serve(
+  stream: UnixStream,
+  cache: &Cache,
+  metrics: &Metrics,
+  profile: Option<&str>,
+) -> std::io::Result<()> {
+  let mut writer = stream.try_clone()?;
+  let mut reader = BufReader::new(stream);
+  loop {
+    let mut line = String::new();
+    let limit = MAX_REQUEST_BYTES as u64 + 1;
+    if (&mut reader).take(limit).read_line(&mut line)? == 0 {
+      break;
+    }
+    if line.len() > MAX_REQUEST_BYTES && !line.ends_with('\n') {
+      let error =
+        format!("requests must be at most {} bytes", MAX_REQUEST_BYTES);
+      writeln!(writer, "{}", serde_json::json!({ "error": error }))?;
+      break;
+    }
+    if line.trim().is_empty() {
+      continue;
+    }
+    if is_metrics_request(&line) {
+      let response = serde_json::json!({ "metrics": metrics.render() });
+      writeln!(writer, "{}", response)?;
+      continue;
+    }
+
+    let started = Instant::now();
+    let result = respond(&line, cache, profile);
+    metrics.record(
+      started.elapsed(),
+      result
+        .as_ref()
+        .ok()
+        .map(|answer| (answer.passwords.len(), answer.retries)),
+    );
+    let response = match result {
+      Ok(answer) => serde_json::json!({
+        "passwords": answer.passwords,
+        "policy_hash": answer.policy_hash,
+      }),
+      Err(e) => serde_json::json!({ "error": e.to_string() }),
+    };
+    writeln!(writer, "{}", response)?;
+  }
+  Ok(())
+}
+
+/// Returns `true` if `line` is `{"metrics": true}`.
+fn is_metrics_request(line: &str) -> bool {
+  serde_json::from_str::<serde_json::Value>(line)
+    .is_ok_and(|request| request == serde_json::json!({ "metrics": true }))
+}
+
+/// Answers a request, starting from `profile` unless it names its own.
+fn respond(
+  line: &str,
+  cache: &Cache,
+  profile: Option<&str>,
+) -> Result<Answer, Box<dyn std::error::Error>> {
+  let request = Request::parse(line, profile)?;
+  let warm = warm(&request, cache)?;
+  let mut passwords = Vec::new();
+  let mut retries = 0;
+  for _ in 0..request.count {
+    let (password, discarded) =
+      PolicyArgs::generate_counting(&warm.generator, &warm.filter, &mut OsRng)?;
+    passwords.push(password);
+    retries += discarded;
+  }
+  Ok(Answer {
+    passwords,
+    retries,
+    policy_hash: warm.policy_hash.clone(),
+  })
+}
+
+/// Returns the cached generator for `args`, loading and validating it first
+/// if needed.
+fn warm(
+  request: &Request,
+  cache: &Cache,
+) -> Result<Arc<Warm>, Box<dyn std::error::Error>> {
+  let args = &request.args;
+  let key = serde_json::to_string(&(args.profile_name(), args))?;
+  let config_modified = config_modified();
+  {
+    let mut cache = cache.lock().expect("cache lock");
+    if cache.config_modified != config_modified {
+      if !cache.warm.is_empty() {
+        eprintln!("Configuration file changed; reloading profiles");
+      }
+      cache.warm.clear();
+      cache.config_modified = config_modified;
+    }
+    if let Some(warm) = cache.warm.get(&key) {
+      return Ok(Arc::clone(warm));
+    }
+  }
+
+  let generator = request.policy()?.generator()?;
+  let warm = Arc::new(Warm {
+    policy_hash: generator.config().fingerprint(),
+    generator,
+    filter: args.filter()?,
+  });
+  let mut cache = cache.lock().expect("cache lock");
+  // A generator loaded while the file changed may use the old profiles.
+  if cache.config_modified == config_modified {
+    if cache.warm.len() >= MAX_CACHED {
+      cache.warm.clear();
+    }
+    cache.warm.insert(key, Arc::clone(&warm));
+  }
+  Ok(warm)
+}
+
+/// Returns the modification time of the configuration file, or `None` if
+/// there is none.
+fn config_modified() -> Option<SystemTime> {
+  let path = Config::path().ok()?;
+  std::fs::metadata(path).ok()?.modified().ok()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_respond() {
+    let cache = Cache::default();
+    let answer = respond(
+      r#"{"length": 12, "min-digit": 2, "count": 3}"#,
+      &cache,
+      None,
+    )
+    .unwrap();
+    assert_eq!(answer.passwords.len(), 3);
+    assert!(answer.passwords.iter().all(|password| password.len() == 12));
+    assert_eq!(answer.policy_hash.len(), 64);
+
+    let again =
+      respond(r#"{"length": 12, "min-digit": 2}"#, &cache, None).unwrap();
+    assert_eq!(cache.lock().unwrap().warm.len(), 1);
+    assert_eq!(again.policy_hash, answer.policy_hash);
+  }
+
+  #[test]
+  fn test_is_metrics_request() {
+    assert!(is_metrics_request(r#"{"metrics": true}"#));
+    assert!(!is_metrics_request(r#"{"metrics": false}"#));
+    assert!(!is_metrics_request(r#"{"metrics": true, "length": 8}"#));
+  }
+
+  #[test]
+  fn test_respond_errors() {
+    let cache = Cache::default();
+    assert!(respond("not json", &cache, None).is_err());
+    assert!(respond(r#"{"count": 0}"#, &cache, None).is_err());
+    assert!(respond(r#"{"exclude": "-"}"#, &cache, None).is_err());
+    assert!(respond(r#"{"unknown": 1}"#, &cache, None).is_err());
+    assert!(respond(r#"{"profile": 1}"#, &cache, None).is_err());
+    assert!(respond(r#"{"length": 1000000000000}"#, &cache, None).is_err());
+
+    let error = respond(r#"{"length": 4}"#, &cache, None).unwrap_err();
+    assert!(error.to_string().contains("[Error::Length]"));
+    assert!(cache.lock().unwrap().warm.is_empty());
+  }
+}
@@ -0,0 +1,132 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use std::collections::HashMap;
+use zbus::blocking::Connection;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
+
+use super::{PolicyArgs, Validity};
+
+/// Well-known name of `pwdg serve-dbus` on the session bus.
+pub const BUS_NAME: &str = "io.github.OTheDev.pwdg";
+
+/// Object path of the `Generator` interface.
+pub const OBJECT_PATH: &str = "/io/github/OTheDev/pwdg";
+
+const SECRETS: &str = "org.freedesktop.secrets";
+const SECRETS_PATH: &str = "/org/freedesktop/secrets";
+
+struct Generator;
+
+#[zbus::interface(name = "io.github.OTheDev.pwdg.Generator")]
+impl Generator {
+  /// Generates a password from policy options as given on the command line,
+  /// e.g. `["--length", "20", "--min-digit", "2"]`.
+  fn generate(&self, options: Vec<String>) -> zbus::fdo::Result<String> {
+    generate(&options).map_err(|e| zbus::fdo::Error::InvalidArgs(e.to_string()))
+  }
+}
+
+fn generate(options: &[String]) -> Result<String, Box<dyn std::error::Error>> {
+  let args = PolicyArgs::try_parse_from(options).map_err(|e| {
+    let message = e.to_string();
+    message.lines().next().unwrap_or("").to_string()
+  })?;
+  if args.reads_stdin() {
+    return Err("Exclusions cannot be read from standard input.".into());
+  }
+  args.generate(&args.policy()?)
+}
+
+/// Serves the `Generator` interface at `OBJECT_PATH` under `BUS_NAME` on the
+/// session bus until interrupted.
+pub fn serve() -> Result<(), Box<dyn std::error::Error>> {
+  let _connection = zbus::blocking::connection::Builder::session()?
+    .name(BUS_NAME)?
+    .serve_at(OBJECT_PATH, Generator)?
+    .build()?;
+  eprintln!("Serving {} on the session bus", BUS_NAME);
+  loop {
+    std::thread::park();
+  }
+}
+
+/// Stores `secret` as an item labelled `name` in the default collection of
+/// the Secret Service, replacing any item with the same `name`, and prints
+/// the path of the item. The item's `created_at` and `expires_at` attributes
+/// record `validity`.
+///
+/// The secret is sent over the session bus unencrypted, as the bus is
+/// private to the user.
+pub fn store(
+  name: &str,
+  secret: &str,
+  validity: Option<&Validity>,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let connection = Connection::session()?;
+  let service = "org.freedesktop.Secret.Service";
+
+  let (_, session): (OwnedValue, OwnedObjectPath) = call(
+    &connection,
+    SECRETS_PATH,
+    service,
+    "OpenSession",
+    &("plain", Value::from("")),
+  )?;
+  let collection: OwnedObjectPath = call(
+    &connection,
+    SECRETS_PATH,
+    service,
+    "ReadAlias",
+    &("default",),
+  )?;
+  if collection.as_str() == "/" {
+    return Err("The Secret Service has no default collection.".into());
+  }
+
+  let validity: Vec<_> =
+    validity.into_iter().flat_map(Validity::fields).collect();
+  let mut attributes = HashMap::from([("application", "pwdg"), ("name", name)]);
+  attributes.extend(validity.iter().map(|(key, value)| (*key, &**value)));
+  let properties = HashMap::from([
+    ("org.freedesktop.Secret.Item.Label", Value::from(name)),
+    (
+      "org.freedesktop.Secret.Item.Attributes",
+      Value::from(attributes),
+    ),
+  ]);
+  let secret = (&session, Vec::<u8>::new(), secret.as_bytes(), "text/plain");
+  let (item, prompt): (OwnedObjectPath, OwnedObjectPath) = call(
+    &connection,
+    collection.as_str(),
+    "org.freedesktop.Secret.Collection",
+    "CreateItem",
+    &(properties, secret, true),
+  )?;
+  if prompt.as_str() != "/" {
+    return Err(
+      "The default collection is locked. Unlock it and retry.".into(),
+    );
+  }
+
+  println!("{}", item.as_str());
+  Ok(())
+}
+
+/// Calls `method` of the Secret Service object at `path`.
+fn call<R>(
+  connection: &Connection,
+  path: &str,
+  interface: &str,
+  method: &str,
+  body: &(impl serde::Serialize + zbus::zvariant::DynamicType),
+) -> zbus::Result<R>
+where
+  R: serde::de::DeserializeOwned + zbus::zvariant::Type,
+{
+  connection
+    .call_method(Some(SECRETS), path, Some(interface), method, body)?
+    .body()
+    .deserialize()
+}
@@ -0,0 +1,75 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use rand::rngs::{OsRng, StdRng};
+use rand::{RngCore, SeedableRng};
+use sha2::{Digest, Sha256};
+
+/// Bytes drawn from the operating system's RNG for each seed.
+const OS_SEED_LEN: usize = 32;
+
+/// Reads the bytes of `--extra-entropy`: hexadecimal, or everything typed or
+/// piped on standard input if `-`.
+pub fn read(value: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+  let bytes = if value == "-" {
+    let mut input = Vec::new();
+    std::io::Read::read_to_end(&mut std::io::stdin(), &mut input)?;
+    input
+  } else {
+    parse_hex(value)?
+  };
+
+  if bytes.is_empty() {
+    return Err("--extra-entropy is empty.".into());
+  }
+  Ok(bytes)
+}
+
+/// Returns an RNG seeded with the SHA-256 hash of bytes from the operating
+/// system's RNG followed by `extra`, so that its output is unpredictable if
+/// either source is.
+pub fn mixed_rng(extra: &[u8]) -> StdRng {
+  let mut os_seed = [0u8; OS_SEED_LEN];
+  OsRng.fill_bytes(&mut os_seed);
+  StdRng::from_seed(seed(&os_seed, extra))
+}
+
+fn seed(os_seed: &[u8], extra: &[u8]) -> [u8; 32] {
+  Sha256::new()
+    .chain_update(b"pwdg extra entropy\0")
+    .chain_update(os_seed)
+    .chain_update(extra)
+    .finalize()
+    .into()
+}
+
+fn parse_hex(digits: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+  let digits: String = digits.chars().filter(|c| !c.is_whitespace()).collect();
+  hex::decode(&digits).map_err(|e| match e {
+    hex::FromHexError::OddLength => {
+      "--extra-entropy must have an even number of hex digits.".into()
+    }
+    e => format!("--extra-entropy is not hexadecimal: {}.", e).into(),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_hex() {
+    assert_eq!(parse_hex("00ff 7A").unwrap(), vec![0x00, 0xff, 0x7a]);
+    assert!(parse_hex("abc").is_err());
+    assert!(parse_hex("zz").is_err());
+  }
+
+  #[test]
+  fn test_seed_depends_on_both_sources() {
+    let os_seed = [1u8; OS_SEED_LEN];
+    assert_eq!(seed(&os_seed, b"mash"), seed(&os_seed, b"mash"));
+    assert_ne!(seed(&os_seed, b"mash"), seed(&os_seed, b"mosh"));
+    assert_ne!(seed(&os_seed, b"mash"), seed(&[2u8; OS_SEED_LEN], b"mash"));
+  }
+}
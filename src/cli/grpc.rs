@@ -0,0 +1,570 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tonic::{Request, Response, Status};
+
+use super::hash::constant_time_eq;
+use super::metrics::{self, Metrics};
+
+include!(concat!(env!("OUT_DIR"), "/pwdg.v1.Generator.rs"));
+
+use generator_server::{Generator, GeneratorServer};
+
+/// Maximum number of passwords per `Generate` call.
+const MAX_COUNT: u32 = 1000;
+
+/// Maximum length of the policy of a call, so that no `Generate` call can
+/// make the server allocate more than `MAX_COUNT * MAX_LENGTH` characters,
+/// and no `Validate` call can keep it checking fixes for long.
+const MAX_LENGTH: u64 = 1024;
+
+/// Maximum number of clients whose request rate is tracked. When a new client
+/// arrives at the limit, buckets that have refilled are dropped, or else the
+/// fullest one, so that limited clients stay limited.
+const MAX_CLIENTS: usize = 4096;
+
+// Messages of `proto/pwdg.proto`.
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Policy {
+  #[prost(uint64, tag = "1")]
+  pub length: u64,
+  #[prost(uint64, tag = "2")]
+  pub min_upper: u64,
+  #[prost(uint64, tag = "3")]
+  pub min_lower: u64,
+  #[prost(uint64, tag = "4")]
+  pub min_digit: u64,
+  #[prost(uint64, tag = "5")]
+  pub min_special: u64,
+  #[prost(string, tag = "6")]
+  pub exclude: String,
+  #[prost(string, optional, tag = "7")]
+  pub charset: Option<String>,
+  #[prost(uint64, tag = "8")]
+  pub min_entropy_bits: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GenerateRequest {
+  #[prost(message, optional, tag = "1")]
+  pub policy: Option<Policy>,
+  #[prost(uint32, tag = "2")]
+  pub count: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GenerateResponse {
+  #[prost(string, repeated, tag = "1")]
+  pub passwords: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ValidateRequest {
+  #[prost(message, optional, tag = "1")]
+  pub policy: Option<Policy>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ValidateResponse {
+  #[prost(bool, tag = "1")]
+  pub valid: bool,
+  #[prost(string, repeated, tag = "2")]
+  pub errors: Vec<String>,
+}
+
+impl From<Policy> for pwdg::Policy {
+  fn from(policy: Policy) -> Self {
+    let to_usize = |n: u64| usize::try_from(n).unwrap_or(usize::MAX);
+    let length = match policy.length {
+      0 => pwdg::MIN_LENGTH,
+      length => to_usize(length),
+    };
+    let options = pwdg::PwdGenOptions {
+      min_upper: to_usize(policy.min_upper),
+      min_lower: to_usize(policy.min_lower),
+      min_digit: to_usize(policy.min_digit),
+      min_special: to_usize(policy.min_special),
+      exclude: Some(policy.exclude)
+        .filter(|exclude| !exclude.is_empty())
+        .map(Cow::Owned),
+      charset: policy.charset.map(Cow::Owned),
+      min_entropy_bits: to_usize(policy.min_entropy_bits),
+      ..Default::default()
+    };
+    pwdg::Policy::new(length, options)
+  }
+}
+
+/// Fails with `INVALID_ARGUMENT` if the length of `policy` is above
+/// `MAX_LENGTH`.
+#[allow(clippy::result_large_err)]
+fn check_length(policy: &Policy) -> Result<(), Status> {
+  if policy.length > MAX_LENGTH {
+    return Err(Status::invalid_argument(format!(
+      "length must be at most {}",
+      MAX_LENGTH
+    )));
+  }
+  Ok(())
+}
+
+#[derive(Default)]
+struct Service {
+  metrics: Arc<Metrics>,
+}
+
+impl Service {
+  #[allow(clippy::result_large_err)]
+  fn passwords(request: GenerateRequest) -> Result<Vec<String>, Status> {
+    let policy = request.policy.unwrap_or_default();
+    check_length(&policy)?;
+    let count = match request.count {
+      0 => 1,
+      count if count <= MAX_COUNT => count,
+      _ => {
+        return Err(Status::invalid_argument(format!(
+          "count must be at most {}",
+          MAX_COUNT
+        )))
+      }
+    };
+
+    let policy = pwdg::Policy::from(policy);
+    let generator = policy
+      .generator()
+      .map_err(|_| Status::invalid_argument(policy.validate().to_string()))?;
+    Ok((0..count).map(|_| generator.gen()).collect())
+  }
+}
+
+#[tonic::async_trait]
+impl Generator for Service {
+  #[allow(clippy::result_large_err)]
+  async fn generate(
+    &self,
+    request: Request<GenerateRequest>,
+  ) -> Result<Response<GenerateResponse>, Status> {
+    let started = Instant::now();
+    // Generation is CPU-bound, so it runs off the threads serving calls.
+    let request = request.into_inner();
+    let result = tokio::task::spawn_blocking(|| Service::passwords(request))
+      .await
+      .unwrap_or_else(|e| Err(Status::internal(e.to_string())));
+    self.metrics.record(
+      started.elapsed(),
+      result.as_ref().ok().map(|passwords| (passwords.len(), 0)),
+    );
+
+    Ok(Response::new(GenerateResponse { passwords: result? }))
+  }
+
+  async fn validate(
+    &self,
+    request: Request<ValidateRequest>,
+  ) -> Result<Response<ValidateResponse>, Status> {
+    let policy = request.into_inner().policy.unwrap_or_default();
+    check_length(&policy)?;
+    // Validation suggests fixes, which takes several checks.
+    let report =
+      tokio::task::spawn_blocking(|| pwdg::Policy::from(policy).validate())
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?;
+
+    Ok(Response::new(ValidateResponse {
+      valid: report.is_valid(),
+      errors: report.errors().iter().map(ToString::to_string).collect(),
+    }))
+  }
+}
+
+/// A token bucket: `tokens` requests may be made now, and one more every
+/// `1 / rate` seconds, up to the burst.
+struct Bucket {
+  tokens: f64,
+  updated: Instant,
+}
+
+/// Limits each client address to `rate` requests per second, with bursts of
+/// up to `burst` requests.
+#[derive(Clone)]
+pub struct RateLimit {
+  rate: f64,
+  burst: f64,
+  buckets: Arc<Mutex<HashMap<IpAddr, Bucket>>>,
+}
+
+impl RateLimit {
+  pub fn new(rate: u32, burst: u32) -> Self {
+    RateLimit {
+      rate: rate as f64,
+      burst: burst.max(1) as f64,
+      buckets: Arc::default(),
+    }
+  }
+
+  /// Takes a token from the bucket of `client`, returning `false` if it is
+  /// empty.
+  fn allow(&self, client: IpAddr, now: Instant) -> bool {
+    let mut buckets = self.buckets.lock().expect("bucket lock");
+    if buckets.len() >= MAX_CLIENTS && !buckets.contains_key(&client) {
+      let tokens = |bucket: &Bucket| {
+        let elapsed = now.saturating_duration_since(bucket.updated);
+        bucket.tokens + elapsed.as_secs_f64() * self.rate
+      };
+      // A bucket that has refilled is the same as a new one.
+      buckets.retain(|_, bucket| tokens(bucket) < self.burst);
+      if buckets.len() >= MAX_CLIENTS {
+        let fullest = buckets
+          .iter()
+          .max_by(|(_, a), (_, b)| tokens(a).total_cmp(&tokens(b)))
+          .map(|(&client, _)| client);
+        if let Some(fullest) = fullest {
+          buckets.remove(&fullest);
+        }
+      }
+    }
+    let bucket = buckets.entry(client).or_insert(Bucket {
+      tokens: self.burst,
+      updated: now,
+    });
+    let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+    bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+    bucket.updated = now;
+    if bucket.tokens < 1.0 {
+      return false;
+    }
+    bucket.tokens -= 1.0;
+    true
+  }
+}
+
+/// Checks the bearer token and the rate limit of each call, if configured.
+#[derive(Clone, Default)]
+struct Guard {
+  token: Option<Arc<str>>,
+  rate_limit: Option<RateLimit>,
+}
+
+impl tonic::service::Interceptor for Guard {
+  fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+    if let Some(token) = &self.token {
+      let given = request
+        .metadata()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or("");
+      if !constant_time_eq(given.as_bytes(), token.as_bytes()) {
+        return Err(Status::unauthenticated("invalid or missing bearer token"));
+      }
+    }
+
+    if let Some(rate_limit) = &self.rate_limit {
+      let client = request
+        .remote_addr()
+        .map_or(IpAddr::from([0, 0, 0, 0]), |addr| addr.ip());
+      if !rate_limit.allow(client, Instant::now()) {
+        return Err(Status::resource_exhausted("rate limit exceeded"));
+      }
+    }
+
+    Ok(request)
+  }
+}
+
+/// TLS options of `serve-grpc`.
+#[cfg(feature = "tls")]
+#[derive(clap::Args, Debug, Default)]
+pub struct TlsArgs {
+  /// Serves over TLS with the PEM certificate chain in this file.
+  #[clap(long, value_name = "PATH", requires = "tls_key")]
+  tls_cert: Option<std::path::PathBuf>,
+
+  /// PEM private key of --tls-cert.
+  #[clap(long, value_name = "PATH", requires = "tls_cert")]
+  tls_key: Option<std::path::PathBuf>,
+
+  /// Requires clients to present a certificate issued by a CA in this PEM
+  /// file.
+  #[clap(long, value_name = "PATH", requires = "tls_cert")]
+  tls_client_ca: Option<std::path::PathBuf>,
+}
+
+/// TLS options of `serve-grpc`, which need the `tls` feature.
+#[cfg(not(feature = "tls"))]
+#[derive(clap::Args, Debug, Default)]
+pub struct TlsArgs {}
+
+#[cfg(feature = "tls")]
+impl TlsArgs {
+  /// Returns the server's TLS configuration, or `None` to serve plaintext.
+  fn config(
+    &self,
+  ) -> Result<
+    Option<tonic::transport::ServerTlsConfig>,
+    Box<dyn std::error::Error>,
+  > {
+    use tonic::transport::{Certificate, Identity, ServerTlsConfig};
+
+    let read = |path: &Path| {
+      std::fs::read(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+    };
+    let (Some(cert), Some(key)) = (&self.tls_cert, &self.tls_key) else {
+      return Ok(None);
+    };
+    let mut config = ServerTlsConfig::new()
+      .identity(Identity::from_pem(read(cert)?, read(key)?));
+    if let Some(ca) = &self.tls_client_ca {
+      config = config.client_ca_root(Certificate::from_pem(read(ca)?));
+    }
+    Ok(Some(config))
+  }
+}
+
+/// Serves the `pwdg.v1.Generator` service of `proto/pwdg.proto` on `addr`
+/// until interrupted.
+///
+/// If `token_file` is given, calls must carry its contents, without
+/// surrounding whitespace, as an `authorization: Bearer <token>` header.
+/// Calls over `rate_limit` are rejected with `RESOURCE_EXHAUSTED`. If
+/// `metrics_addr` is given, request metrics are served there over HTTP as
+/// `/metrics`. With the `tls` feature, calls may be served over TLS.
+#[cfg_attr(not(feature = "tls"), allow(unused_variables))]
+pub fn serve(
+  addr: SocketAddr,
+  token_file: Option<&Path>,
+  rate_limit: Option<RateLimit>,
+  metrics_addr: Option<SocketAddr>,
+  tls: &TlsArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let token = match token_file {
+    Some(path) => {
+      let token = std::fs::read_to_string(path)?.trim().to_string();
+      if token.is_empty() {
+        return Err(format!("{} is empty.", path.display()).into());
+      }
+      Some(token.into())
+    }
+    None => None,
+  };
+  let guard = Guard { token, rate_limit };
+  let service = Service::default();
+  if let Some(metrics_addr) = metrics_addr {
+    metrics::serve(metrics_addr, Arc::clone(&service.metrics))?;
+  }
+
+  #[allow(unused_mut)]
+  let mut builder = tonic::transport::Server::builder();
+  #[cfg(feature = "tls")]
+  if let Some(config) = tls.config()? {
+    builder = builder.tls_config(config)?;
+  }
+
+  let runtime = tokio::runtime::Runtime::new()?;
+  runtime.block_on(async {
+    eprintln!("Serving gRPC on {}", addr);
+    builder
+      .add_service(GeneratorServer::with_interceptor(service, guard))
+      .serve(addr)
+      .await
+  })?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Runtime::new().unwrap().block_on(future)
+  }
+
+  #[test]
+  fn test_generate() {
+    let request = GenerateRequest {
+      policy: Some(Policy {
+        length: 12,
+        min_digit: 2,
+        ..Default::default()
+      }),
+      count: 3,
+    };
+    let response =
+      block_on(Service::default().generate(Request::new(request))).unwrap();
+    let passwords = response.into_inner().passwords;
+
+    assert_eq!(passwords.len(), 3);
+    for password in passwords {
+      assert_eq!(password.len(), 12);
+      assert!(password.chars().filter(char::is_ascii_digit).count() >= 2);
+    }
+  }
+
+  #[test]
+  fn test_generate_invalid_policy() {
+    let request = GenerateRequest {
+      policy: Some(Policy {
+        length: 4,
+        ..Default::default()
+      }),
+      count: 0,
+    };
+    let status =
+      block_on(Service::default().generate(Request::new(request))).unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    assert!(status.message().contains("[Error::Length]"));
+  }
+
+  #[test]
+  fn test_generate_limits() {
+    let request = |length, count| GenerateRequest {
+      policy: Some(Policy {
+        length,
+        ..Default::default()
+      }),
+      count,
+    };
+    for request in [
+      request(MAX_LENGTH + 1, 1),
+      request(u64::MAX, MAX_COUNT),
+      request(16, MAX_COUNT + 1),
+    ] {
+      let status = block_on(Service::default().generate(Request::new(request)))
+        .unwrap_err();
+      assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    let response = block_on(
+      Service::default().generate(Request::new(request(MAX_LENGTH, 2))),
+    )
+    .unwrap();
+    assert_eq!(
+      response.into_inner().passwords[0].len(),
+      MAX_LENGTH as usize
+    );
+  }
+
+  #[test]
+  fn test_generate_records_metrics() {
+    let service = Service::default();
+    let request = |length, count| GenerateRequest {
+      policy: Some(Policy {
+        length,
+        ..Default::default()
+      }),
+      count,
+    };
+    block_on(service.generate(Request::new(request(16, 3)))).unwrap();
+    block_on(service.generate(Request::new(request(4, 1)))).unwrap_err();
+
+    let text = service.metrics.render();
+    assert!(text.contains("pwdg_requests_total{outcome=\"ok\"} 1\n"));
+    assert!(text.contains("pwdg_requests_total{outcome=\"error\"} 1\n"));
+    assert!(text.contains("pwdg_passwords_total 3\n"));
+  }
+
+  #[cfg(feature = "tls")]
+  #[test]
+  fn test_tls_config() {
+    assert!(TlsArgs::default().config().unwrap().is_none());
+
+    let missing = TlsArgs {
+      tls_cert: Some("/nonexistent/cert.pem".into()),
+      tls_key: Some("/nonexistent/key.pem".into()),
+      tls_client_ca: None,
+    };
+    let error = missing.config().unwrap_err().to_string();
+    assert!(error.starts_with("Failed to read /nonexistent/cert.pem"));
+  }
+
+  #[test]
+  fn test_guard_token() {
+    use tonic::service::Interceptor;
+
+    let mut guard = Guard {
+      token: Some("s3cret".into()),
+      rate_limit: None,
+    };
+    let mut request = Request::new(());
+    request
+      .metadata_mut()
+      .insert("authorization", "Bearer s3cret".parse().unwrap());
+    assert!(guard.call(request).is_ok());
+
+    let mut request = Request::new(());
+    request
+      .metadata_mut()
+      .insert("authorization", "Bearer s3cre".parse().unwrap());
+    let status = guard.call(request).unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    assert!(guard.call(Request::new(())).is_err());
+  }
+
+  #[test]
+  fn test_rate_limit() {
+    let limit = RateLimit::new(2, 3);
+    let client = IpAddr::from([10, 0, 0, 1]);
+    let start = Instant::now();
+
+    assert!((0..3).all(|_| limit.allow(client, start)));
+    assert!(!limit.allow(client, start));
+    assert!(limit.allow(IpAddr::from([10, 0, 0, 2]), start));
+
+    let later = start + std::time::Duration::from_millis(500);
+    assert!(limit.allow(client, later));
+    assert!(!limit.allow(client, later));
+  }
+
+  #[test]
+  fn test_rate_limit_keeps_limited_clients() {
+    let limit = RateLimit::new(1, 2);
+    let client = IpAddr::from([10, 0, 0, 1]);
+    let start = Instant::now();
+    assert!((0..2).all(|_| limit.allow(client, start)));
+
+    // Fill the table with clients seen later, which must not reset `client`.
+    let later = start + std::time::Duration::from_millis(100);
+    for i in 1..=MAX_CLIENTS as u32 {
+      assert!(limit.allow(IpAddr::from((11 << 24 | i).to_be_bytes()), later));
+    }
+    assert!(!limit.allow(client, later));
+    assert!(limit.buckets.lock().unwrap().len() <= MAX_CLIENTS);
+  }
+
+  #[test]
+  fn test_validate() {
+    let request = ValidateRequest {
+      policy: Some(Policy {
+        length: 8,
+        min_upper: 5,
+        min_digit: 5,
+        ..Default::default()
+      }),
+    };
+    let response =
+      block_on(Service::default().validate(Request::new(request))).unwrap();
+    let response = response.into_inner();
+
+    assert!(!response.valid);
+    assert!(response.errors[0].contains("[Error::MinLimitExceeded]"));
+
+    let request = ValidateRequest {
+      policy: Some(Policy {
+        length: MAX_LENGTH + 1,
+        ..Default::default()
+      }),
+    };
+    let status =
+      block_on(Service::default().validate(Request::new(request))).unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+  }
+}
@@ -0,0 +1,372 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use argon2::password_hash::{PasswordHasher, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use clap::{Args, ValueEnum};
+use hmac::{Hmac, Mac};
+use md4::Md4;
+use rand::{rngs::OsRng, seq::SliceRandom};
+use sha2::{Digest, Sha256};
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum HashKind {
+  /// Argon2id with a random salt, as a PHC string, e.g. for Django, Keycloak,
+  /// or libsodium. Its costs are set with --argon2-memory,
+  /// --argon2-iterations, and --argon2-parallelism.
+  Argon2id,
+  /// INSECURE: the unsalted, MD4-based NT hash used by NTLM. Only for
+  /// provisioning lab and test Active Directory environments.
+  Ntlm,
+}
+
+impl HashKind {
+  /// Whether the hash is too weak to protect real credentials.
+  pub fn is_insecure(self) -> bool {
+    matches!(self, HashKind::Ntlm)
+  }
+}
+
+/// Costs of Argon2 hashes. The defaults are those recommended by OWASP.
+#[derive(Args)]
+pub struct Argon2Args {
+  /// Memory cost of --hash argon2id, in KiB.
+  #[clap(
+    long,
+    value_name = "KIB",
+    default_value_t = Params::DEFAULT_M_COST,
+    requires = "hash"
+  )]
+  argon2_memory: u32,
+
+  /// Number of passes of --hash argon2id over its memory.
+  #[clap(
+    long,
+    value_name = "N",
+    default_value_t = Params::DEFAULT_T_COST,
+    requires = "hash"
+  )]
+  argon2_iterations: u32,
+
+  /// Number of lanes of --hash argon2id.
+  #[clap(
+    long,
+    value_name = "N",
+    default_value_t = Params::DEFAULT_P_COST,
+    requires = "hash"
+  )]
+  argon2_parallelism: u32,
+}
+
+impl Default for Argon2Args {
+  fn default() -> Self {
+    Argon2Args {
+      argon2_memory: Params::DEFAULT_M_COST,
+      argon2_iterations: Params::DEFAULT_T_COST,
+      argon2_parallelism: Params::DEFAULT_P_COST,
+    }
+  }
+}
+
+impl Argon2Args {
+  /// Returns the parameters, or an error naming the invalid one.
+  fn params(&self) -> Result<Params, Box<dyn std::error::Error>> {
+    Params::new(
+      self.argon2_memory,
+      self.argon2_iterations,
+      self.argon2_parallelism,
+      None,
+    )
+    .map_err(|e| format!("Invalid Argon2 parameters: {}.", e).into())
+  }
+
+  /// Whether the costs are below the defaults, i.e. weaker than the OWASP
+  /// baseline.
+  pub fn is_weak(&self) -> bool {
+    (self.argon2_memory as u64) * (self.argon2_iterations as u64)
+      < (Params::DEFAULT_M_COST as u64) * (Params::DEFAULT_T_COST as u64)
+  }
+}
+
+/// Returns the `kind` hash of `password`: a PHC string for Argon2id, with
+/// the costs of `argon2`, and lowercase hexadecimal for NTLM.
+pub fn hash(
+  kind: HashKind,
+  password: &str,
+  argon2: &Argon2Args,
+) -> Result<String, Box<dyn std::error::Error>> {
+  match kind {
+    HashKind::Argon2id => {
+      let hasher =
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, argon2.params()?);
+      let salt = SaltString::encode_b64(&pwdg::gen_salt(ARGON2_SALT_LEN))
+        .map_err(|e| e.to_string())?;
+      Ok(
+        hasher
+          .hash_password(password.as_bytes(), &salt)
+          .map_err(|e| e.to_string())?
+          .to_string(),
+      )
+    }
+    HashKind::Ntlm => {
+      let utf16: Vec<u8> =
+        password.encode_utf16().flat_map(u16::to_le_bytes).collect();
+      Ok(hex::encode(Md4::digest(utf16)))
+    }
+  }
+}
+
+/// Salt length, in bytes, of Argon2 hashes.
+const ARGON2_SALT_LEN: usize = 16;
+
+/// Iteration count of PostgreSQL's default `scram_iterations`.
+const SCRAM_ITERATIONS: u32 = 4096;
+
+/// Salt length, in bytes, that PostgreSQL uses for SCRAM verifiers.
+const SCRAM_SALT_LEN: usize = 16;
+
+/// Returns a PostgreSQL `SCRAM-SHA-256` verifier for `password` with a random
+/// salt, for `ALTER ROLE ... PASSWORD '<verifier>'`.
+///
+/// PostgreSQL normalizes passwords with SASLprep first, which leaves ASCII
+/// passwords unchanged. Other passwords are hashed as given.
+pub fn pg_scram(password: &str) -> String {
+  let salt = pwdg::gen_salt(SCRAM_SALT_LEN);
+  pg_scram_with_salt(password, &salt, SCRAM_ITERATIONS)
+}
+
+fn pg_scram_with_salt(password: &str, salt: &[u8], iterations: u32) -> String {
+  let mut salted = [0u8; 32];
+  pbkdf2::pbkdf2_hmac::<Sha256>(
+    password.as_bytes(),
+    salt,
+    iterations,
+    &mut salted,
+  );
+
+  let client_key = hmac_sha256(&salted, b"Client Key");
+  let stored_key = Sha256::digest(client_key);
+  let server_key = hmac_sha256(&salted, b"Server Key");
+
+  format!(
+    "SCRAM-SHA-256${}:{}${}:{}",
+    iterations,
+    BASE64.encode(salt),
+    BASE64.encode(stored_key),
+    BASE64.encode(server_key)
+  )
+}
+
+/// Rounds of SHA-crypt used by MySQL's `caching_sha2_password`, which stores
+/// them divided by 1000.
+const MYSQL_ROUNDS: usize = 5000;
+
+/// Salt length, in bytes, of MySQL's `caching_sha2_password`.
+const MYSQL_SALT_LEN: usize = 20;
+
+/// Returns the `caching_sha2_password` authentication string of `password`
+/// with a random salt, as a hexadecimal literal for
+/// `CREATE USER ... IDENTIFIED WITH caching_sha2_password AS 0x...`.
+pub fn mysql_sha2(password: &str) -> String {
+  // Printable ASCII except `$`, which separates the fields.
+  let alphabet: Vec<u8> = (b'!'..=b'~').filter(|&b| b != b'$').collect();
+  let salt: Vec<u8> = (0..MYSQL_SALT_LEN)
+    .map(|_| *alphabet.choose(&mut OsRng).expect("Alphabet is nonempty"))
+    .collect();
+  mysql_sha2_with_salt(password, &salt)
+}
+
+fn mysql_sha2_with_salt(password: &str, salt: &[u8]) -> String {
+  let mut auth = format!("$A${:03}$", MYSQL_ROUNDS / 1000).into_bytes();
+  auth.extend_from_slice(salt);
+  auth.extend_from_slice(
+    sha256_crypt(password.as_bytes(), salt, MYSQL_ROUNDS).as_bytes(),
+  );
+
+  format!("0x{}", hex::encode_upper(auth))
+}
+
+/// The SHA-256 variant of SHA-crypt, returning the encoded digest without
+/// the `$5$` prefix and salt. Unlike `crypt(3)`, the salt is not truncated
+/// to 16 bytes, as in MySQL.
+fn sha256_crypt(password: &[u8], salt: &[u8], rounds: usize) -> String {
+  /// Repeats `digest` to fill `len` bytes.
+  fn repeat(digest: &[u8], len: usize) -> Vec<u8> {
+    digest.iter().cycle().take(len).cloned().collect()
+  }
+
+  let b = Sha256::new()
+    .chain_update(password)
+    .chain_update(salt)
+    .chain_update(password)
+    .finalize();
+
+  let mut a = Sha256::new()
+    .chain_update(password)
+    .chain_update(salt)
+    .chain_update(repeat(&b, password.len()));
+  let mut len = password.len();
+  while len > 0 {
+    match len & 1 {
+      1 => a.update(b),
+      _ => a.update(password),
+    }
+    len >>= 1;
+  }
+  let a = a.finalize();
+
+  let dp = Sha256::digest(password.repeat(password.len()));
+  let p = repeat(&dp, password.len());
+  let ds = Sha256::digest(salt.repeat(16 + a[0] as usize));
+  let s = repeat(&ds, salt.len());
+
+  let mut c = a;
+  for i in 0..rounds {
+    let mut hasher = Sha256::new();
+    match i % 2 {
+      1 => hasher.update(&p),
+      _ => hasher.update(c),
+    }
+    if i % 3 != 0 {
+      hasher.update(&s);
+    }
+    if i % 7 != 0 {
+      hasher.update(&p);
+    }
+    match i % 2 {
+      1 => hasher.update(c),
+      _ => hasher.update(&p),
+    }
+    c = hasher.finalize();
+  }
+
+  const ITOA64: &[u8] =
+    b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+  const ORDER: [(usize, usize, usize); 10] = [
+    (0, 10, 20),
+    (21, 1, 11),
+    (12, 22, 2),
+    (3, 13, 23),
+    (24, 4, 14),
+    (15, 25, 5),
+    (6, 16, 26),
+    (27, 7, 17),
+    (18, 28, 8),
+    (9, 19, 29),
+  ];
+  let mut encoded = String::with_capacity(43);
+  let mut push = |bytes: [u8; 3], chars: usize| {
+    let mut w =
+      (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+    for _ in 0..chars {
+      encoded.push(ITOA64[(w & 0x3f) as usize] as char);
+      w >>= 6;
+    }
+  };
+  for (i, j, k) in ORDER {
+    push([c[i], c[j], c[k]], 4);
+  }
+  push([0, c[31], c[30]], 3);
+
+  encoded
+}
+
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+  let mut mac =
+    Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+  mac.update(message);
+  mac.finalize().into_bytes().into()
+}
+
+/// Compares `a` and `b` in time that depends only on their lengths.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+  a.len() == b.len()
+    && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_pg_scram_known_answer() {
+    let salt: Vec<u8> = (0..16).collect();
+    assert_eq!(
+      pg_scram_with_salt("hunter2", &salt, 4096),
+      concat!(
+        "SCRAM-SHA-256$4096:AAECAwQFBgcICQoLDA0ODw==",
+        "$ijmdkBF6VaUuRpEzxe8P8NyZKzuHGkoluJBVZ5DF5+Q=",
+        ":naMMD2q/yKfDcTgfNhpZqsbkzoRQwOPfT/TgHEPWP7w="
+      )
+    );
+  }
+
+  #[test]
+  fn test_ntlm() {
+    let ntlm = |password| {
+      hash(HashKind::Ntlm, password, &Argon2Args::default()).unwrap()
+    };
+    assert_eq!(ntlm("password"), "8846f7eaee8fb117ad06bdd830b7586c");
+    assert_eq!(ntlm(""), "31d6cfe0d16ae931b73c59d7e0c089c0");
+  }
+
+  #[test]
+  fn test_argon2id() {
+    let args = Argon2Args {
+      argon2_memory: 1024,
+      argon2_iterations: 3,
+      argon2_parallelism: 2,
+    };
+    assert!(args.is_weak());
+    assert!(!Argon2Args::default().is_weak());
+
+    let phc = hash(HashKind::Argon2id, "hunter2", &args).unwrap();
+    assert!(phc.starts_with("$argon2id$v=19$m=1024,t=3,p=2$"));
+    assert_ne!(phc, hash(HashKind::Argon2id, "hunter2", &args).unwrap());
+
+    let invalid = Argon2Args {
+      argon2_parallelism: 0,
+      ..Argon2Args::default()
+    };
+    assert!(hash(HashKind::Argon2id, "hunter2", &invalid).is_err());
+  }
+
+  #[test]
+  fn test_sha256_crypt_known_answers() {
+    // From the SHA-crypt specification.
+    assert_eq!(
+      sha256_crypt(b"Hello world!", b"saltstring", 5000),
+      "5B8vYYiY.CVt1RlTTf8KbXBH3hsxY/GNooZaBBGWEc5"
+    );
+    assert_eq!(
+      sha256_crypt(b"hunter2", b"abcdefghijklmnop", 5000),
+      "/xm2M1oTvGc2fCTR8/zBr3MgGwgm.aL3PLXlM2aQy86"
+    );
+  }
+
+  fn unhex(digits: &str) -> String {
+    // Skips the 0x prefix.
+    String::from_utf8(hex::decode(&digits[2..]).unwrap()).unwrap()
+  }
+
+  #[test]
+  fn test_mysql_sha2() {
+    let auth = unhex(&mysql_sha2_with_salt("hunter2", b"abcdefghijklmnopqrst"));
+    assert_eq!(auth.len(), 70);
+    assert!(auth.starts_with("$A$005$abcdefghijklmnopqrst"));
+
+    let auth = unhex(&mysql_sha2("hunter2"));
+    assert_eq!(auth.len(), 70);
+    assert_eq!(auth.matches('$').count(), 3);
+  }
+
+  #[test]
+  fn test_pg_scram_random_salt() {
+    let verifier = pg_scram("hunter2");
+    assert!(verifier.starts_with("SCRAM-SHA-256$4096:"));
+    assert_ne!(verifier, pg_scram("hunter2"));
+  }
+}
@@ -0,0 +1,65 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use pwdg::Policy;
+
+/// Reads a requirements paragraph from standard input and prints the policy
+/// inferred from it, with the options that generate passwords of it, for
+/// the user to check against the paragraph. Prints the policy document
+/// instead if `json` is `true`. Fails if the inferred policy is invalid.
+pub fn run(json: bool) -> Result<(), Box<dyn std::error::Error>> {
+  let text = std::io::read_to_string(std::io::stdin())?;
+  let policy = Policy::from_requirements_text(&text)?;
+
+  if json {
+    println!("{}", policy.to_document().to_json());
+  } else {
+    println!("Inferred policy: {}", policy);
+    println!("Options: {}", options(&policy).join(" "));
+    eprintln!("Check the inferred policy against the requirements before use.");
+  }
+
+  let report = policy.validate();
+  if !report.is_valid() {
+    return Err(report.into());
+  }
+  Ok(())
+}
+
+/// Returns the command-line options that give `policy`.
+fn options(policy: &Policy) -> Vec<String> {
+  let options = &policy.options;
+  let mut args = vec!["--length".to_string(), policy.length.to_string()];
+  let mins = [
+    ("--min-upper", options.min_upper),
+    ("--min-lower", options.min_lower),
+    ("--min-digit", options.min_digit),
+    ("--min-special", options.min_special),
+    ("--min-bits", options.min_entropy_bits),
+  ];
+  for (flag, min) in mins.into_iter().filter(|&(_, min)| min > 0) {
+    args.extend([flag.to_string(), min.to_string()]);
+  }
+  if let Some(category) = &options.first_char_class {
+    args.extend(["--first".to_string(), category.to_string()]);
+  }
+  if options.reject_structures {
+    args.push("--reject-structures".to_string());
+  }
+  if options.avoid_ambiguous {
+    args.push("--avoid-ambiguous".to_string());
+  }
+  if let Some(charset) = options.charset.as_deref() {
+    args.push(format!("--charset={}", quote(charset)));
+  }
+  if let Some(exclude) = options.exclude.as_deref() {
+    args.push(format!("--exclude={}", quote(exclude)));
+  }
+  args
+}
+
+/// Quotes `value` for a POSIX shell.
+fn quote(value: &str) -> String {
+  format!("'{}'", value.replace('\'', r"'\''"))
+}
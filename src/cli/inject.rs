@@ -0,0 +1,43 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use pwdg::{Category, Injection};
+use rand::rngs::OsRng;
+
+/// Returns `password`, generated under `policy`, with the digits and symbols
+/// of `injection` inserted at random positions, and the estimated entropy
+/// this adds, in bits. The digits and symbols are those of the built-in
+/// classes less exclusions, even if a charset leaves them out, e.g. for a
+/// password of letters only.
+pub fn inject(
+  password: &str,
+  injection: Injection,
+  policy: &pwdg::Policy,
+) -> Result<(String, f64), Box<dyn std::error::Error>> {
+  let classes = pwdg::PwdGenOptions {
+    charset: None,
+    ..policy.options.clone()
+  }
+  .effective_classes();
+  let chars = |category: Category, count: usize| {
+    let chars = classes
+      .iter()
+      .find(|class| class.category == category)
+      .map_or_else(Vec::new, |class| class.chars.clone());
+    if count > 0 && chars.is_empty() {
+      return Err(format!("--inject has no {} characters left.", category));
+    }
+    Ok(chars)
+  };
+  let digits = chars(Category::Digit, injection.digits)?;
+  let symbols = chars(Category::Special, injection.symbols)?;
+
+  let gain = injection.entropy_bits(
+    password.chars().count(),
+    digits.len(),
+    symbols.len(),
+  );
+  let password = injection.apply(password, &digits, &symbols, &mut OsRng);
+  Ok((password, gain))
+}
@@ -0,0 +1,30 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+
+/// Creates or updates the generic password item of `service` and `account`
+/// in the default macOS keychain, and prints its service and account.
+#[cfg(target_os = "macos")]
+pub fn store(
+  service: &str,
+  account: &str,
+  secret: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+  security_framework::passwords::set_generic_password(
+    service,
+    account,
+    secret.as_bytes(),
+  )?;
+  println!("{} {}", service, account);
+  Ok(())
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn store(
+  _service: &str,
+  _account: &str,
+  _secret: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+  Err("The keychain store is only available on macOS.".into())
+}
@@ -0,0 +1,161 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Environment variable overriding the location of the metadata store.
+const META_ENV: &str = "PWDG_META";
+
+const SECONDS_PER_DAY: u64 = 86400;
+
+/// A generated credential, recorded by `--record` without the password.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct MetaEntry {
+  pub label: String,
+  /// Fingerprint of the policy the credential was generated under.
+  pub policy_hash: String,
+  /// Seconds since the Unix epoch.
+  pub created_at: u64,
+}
+
+/// The metadata store, holding the latest entry of each label.
+#[derive(Default, Serialize, Deserialize)]
+pub struct MetaStore {
+  #[serde(default, rename = "entry")]
+  pub entries: Vec<MetaEntry>,
+}
+
+impl MetaStore {
+  /// Returns the path of the metadata store: `$PWDG_META` if set, or else
+  /// `pwdg/meta.toml` in the user's data directory.
+  pub fn path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    if let Some(path) = std::env::var_os(META_ENV) {
+      return Ok(path.into());
+    }
+    let dir = dirs::data_dir().ok_or("No data directory found.")?;
+    Ok(dir.join("pwdg").join("meta.toml"))
+  }
+
+  /// Loads the metadata store, or an empty store if there is no file.
+  pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+    let path = Self::path()?;
+    match std::fs::read_to_string(&path) {
+      Ok(text) => toml::from_str(&text)
+        .map_err(|e| {
+          format!("Invalid metadata store {}: {}", path.display(), e)
+        })
+        .map_err(Into::into),
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+      Err(e) => Err(e.into()),
+    }
+  }
+
+  /// Writes the metadata store, creating its directory if needed.
+  pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+    let path = Self::path()?;
+    if let Some(dir) = path.parent() {
+      std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, toml::to_string_pretty(self)?)?;
+    Ok(())
+  }
+
+  /// Records `entry`, replacing any entry of the same label, as the
+  /// credential has been rotated.
+  fn insert(&mut self, entry: MetaEntry) {
+    self.entries.retain(|other| other.label != entry.label);
+    self.entries.push(entry);
+  }
+
+  /// Returns the entries created at least `days` days before `now`, oldest
+  /// first.
+  fn due(&self, days: u64, now: u64) -> Vec<&MetaEntry> {
+    let threshold = days.saturating_mul(SECONDS_PER_DAY);
+    let mut due: Vec<&MetaEntry> = self
+      .entries
+      .iter()
+      .filter(|entry| now.saturating_sub(entry.created_at) >= threshold)
+      .collect();
+    due.sort_by_key(|entry| entry.created_at);
+    due
+  }
+}
+
+fn now() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Records that a credential labelled `label` was generated now under
+/// `policy`, in the metadata store.
+pub fn record(
+  label: &str,
+  policy: &pwdg::Policy,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let mut store = MetaStore::load()?;
+  store.insert(MetaEntry {
+    label: label.to_string(),
+    policy_hash: policy.generator()?.config().fingerprint(),
+    created_at: now(),
+  });
+  store.save()
+}
+
+/// Prints the label, age in days, and policy hash of every entry of the
+/// metadata store created at least `days` days ago, oldest first.
+pub fn run_due(days: u64) -> Result<(), Box<dyn std::error::Error>> {
+  let store = MetaStore::load()?;
+  let now = now();
+  for entry in store.due(days, now) {
+    println!(
+      "{}\t{} days\t{}",
+      entry.label,
+      now.saturating_sub(entry.created_at) / SECONDS_PER_DAY,
+      entry.policy_hash
+    );
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn entry(label: &str, created_at: u64) -> MetaEntry {
+    MetaEntry {
+      label: label.to_string(),
+      policy_hash: "hash".to_string(),
+      created_at,
+    }
+  }
+
+  #[test]
+  fn test_insert_replaces_label() {
+    let mut store = MetaStore::default();
+    store.insert(entry("db", 1));
+    store.insert(entry("api", 2));
+    store.insert(entry("db", 3));
+    assert_eq!(store.entries, [entry("api", 2), entry("db", 3)]);
+  }
+
+  #[test]
+  fn test_due() {
+    let mut store = MetaStore::default();
+    store.insert(entry("new", 100 * SECONDS_PER_DAY));
+    store.insert(entry("old", 0));
+    store.insert(entry("edge", 10 * SECONDS_PER_DAY));
+    let labels = |days| -> Vec<&str> {
+      store
+        .due(days, 100 * SECONDS_PER_DAY)
+        .iter()
+        .map(|entry| entry.label.as_str())
+        .collect()
+    };
+    assert_eq!(labels(90), ["old", "edge"]);
+    assert_eq!(labels(0), ["old", "edge", "new"]);
+  }
+}
@@ -0,0 +1,193 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use std::fmt::Write;
+#[cfg(feature = "grpc")]
+use std::net::{SocketAddr, TcpListener};
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "grpc")]
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Upper bounds, in seconds, of the request duration histogram buckets.
+const BUCKETS: [f64; 8] = [0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0];
+
+/// Counters of a server's requests, in the Prometheus text format. Never
+/// records anything about the passwords but their number.
+#[derive(Default)]
+pub struct Metrics {
+  ok: AtomicU64,
+  errors: AtomicU64,
+  passwords: AtomicU64,
+  retries: AtomicU64,
+  duration_micros: AtomicU64,
+  /// Requests per histogram bucket, not cumulative.
+  buckets: [AtomicU64; BUCKETS.len()],
+}
+
+impl Metrics {
+  /// Records a request that took `duration` and either generated `passwords`
+  /// after discarding `retries` that failed the filter, such as for
+  /// containing a forbidden substring, or failed.
+  pub fn record(&self, duration: Duration, outcome: Option<(usize, usize)>) {
+    match outcome {
+      Some((passwords, retries)) => {
+        self.ok.fetch_add(1, Ordering::Relaxed);
+        self
+          .passwords
+          .fetch_add(passwords as u64, Ordering::Relaxed);
+        self.retries.fetch_add(retries as u64, Ordering::Relaxed);
+      }
+      None => {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+      }
+    }
+    self
+      .duration_micros
+      .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    if let Some(bucket) = BUCKETS
+      .iter()
+      .position(|&bound| duration.as_secs_f64() <= bound)
+    {
+      self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+    }
+  }
+
+  /// Renders the counters in the Prometheus text exposition format.
+  pub fn render(&self) -> String {
+    let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+    let (ok, errors) = (load(&self.ok), load(&self.errors));
+    let mut text = String::new();
+
+    let _ = writeln!(
+      text,
+      "# HELP pwdg_requests_total Requests handled, by outcome.\n\
+       # TYPE pwdg_requests_total counter\n\
+       pwdg_requests_total{{outcome=\"ok\"}} {}\n\
+       pwdg_requests_total{{outcome=\"error\"}} {}",
+      ok, errors
+    );
+    let _ = writeln!(
+      text,
+      "# HELP pwdg_passwords_total Passwords generated.\n\
+       # TYPE pwdg_passwords_total counter\n\
+       pwdg_passwords_total {}",
+      load(&self.passwords)
+    );
+    let _ = writeln!(
+      text,
+      "# HELP pwdg_retries_total Passwords discarded for containing a \
+       forbidden substring or failing pwquality checks.\n\
+       # TYPE pwdg_retries_total counter\n\
+       pwdg_retries_total {}",
+      load(&self.retries)
+    );
+
+    let _ = writeln!(
+      text,
+      "# HELP pwdg_request_duration_seconds Time taken to handle requests.\n\
+       # TYPE pwdg_request_duration_seconds histogram"
+    );
+    let mut cumulative = 0;
+    for (bound, count) in BUCKETS.iter().zip(&self.buckets) {
+      cumulative += load(count);
+      let _ = writeln!(
+        text,
+        "pwdg_request_duration_seconds_bucket{{le=\"{}\"}} {}",
+        bound, cumulative
+      );
+    }
+    let _ = write!(
+      text,
+      "pwdg_request_duration_seconds_bucket{{le=\"+Inf\"}} {}\n\
+       pwdg_request_duration_seconds_sum {}\n\
+       pwdg_request_duration_seconds_count {}\n",
+      ok + errors,
+      load(&self.duration_micros) as f64 / 1e6,
+      ok + errors
+    );
+
+    text
+  }
+}
+
+/// Serves `metrics` as `GET /metrics` over plain HTTP on `addr`, from a
+/// background thread.
+#[cfg(feature = "grpc")]
+pub fn serve(addr: SocketAddr, metrics: Arc<Metrics>) -> std::io::Result<()> {
+  let listener = TcpListener::bind(addr)?;
+  eprintln!("Serving metrics on http://{}/metrics", addr);
+  std::thread::spawn(move || {
+    for stream in listener.incoming().flatten() {
+      if let Err(e) = respond(stream, &metrics) {
+        eprintln!("{}", e);
+      }
+    }
+  });
+  Ok(())
+}
+
+#[cfg(feature = "grpc")]
+fn respond(
+  stream: std::net::TcpStream,
+  metrics: &Metrics,
+) -> std::io::Result<()> {
+  use std::io::{BufRead, BufReader, Write as _};
+
+  stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+  let mut writer = stream.try_clone()?;
+  let mut reader = BufReader::new(stream);
+  let mut request_line = String::new();
+  reader.read_line(&mut request_line)?;
+  // Reads the headers, which are ignored, so that closing the connection does
+  // not reset it.
+  let mut header = String::new();
+  while reader.read_line(&mut header)? > 2 {
+    header.clear();
+  }
+
+  let (status, body) =
+    match request_line.split_whitespace().take(2).collect::<Vec<_>>()[..] {
+      ["GET", "/metrics"] => ("200 OK", metrics.render()),
+      _ => ("404 Not Found", String::from("Not found.\n")),
+    };
+  write!(
+    writer,
+    "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\n\
+     Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+    status,
+    body.len(),
+    body
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_render() {
+    let metrics = Metrics::default();
+    metrics.record(Duration::from_micros(300), Some((3, 1)));
+    metrics.record(Duration::from_millis(20), Some((1, 0)));
+    metrics.record(Duration::from_secs(2), None);
+    let text = metrics.render();
+
+    assert!(text.contains("pwdg_requests_total{outcome=\"ok\"} 2\n"));
+    assert!(text.contains("pwdg_requests_total{outcome=\"error\"} 1\n"));
+    assert!(text.contains("pwdg_passwords_total 4\n"));
+    assert!(text.contains("pwdg_retries_total 1\n"));
+    assert!(
+      text.contains("pwdg_request_duration_seconds_bucket{le=\"0.0005\"} 1\n")
+    );
+    assert!(
+      text.contains("pwdg_request_duration_seconds_bucket{le=\"0.05\"} 2\n")
+    );
+    assert!(
+      text.contains("pwdg_request_duration_seconds_bucket{le=\"+Inf\"} 3\n")
+    );
+    assert!(text.contains("pwdg_request_duration_seconds_sum 2.0203\n"));
+    assert!(text.ends_with("pwdg_request_duration_seconds_count 3\n"));
+  }
+}
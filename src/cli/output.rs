@@ -0,0 +1,78 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use clap::ValueEnum;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+  /// The password itself.
+  #[default]
+  Plain,
+  /// The password encrypted with `systemd-creds encrypt` under --name, for
+  /// `LoadCredentialEncrypted=`.
+  SystemdCred,
+  /// The password, then on the next line a PostgreSQL SCRAM-SHA-256 verifier
+  /// of it, for `ALTER ROLE ... PASSWORD '<verifier>'`.
+  PgScram,
+  /// The password, then on the next line a MySQL `caching_sha2_password`
+  /// hash of it, for `CREATE USER ... IDENTIFIED WITH caching_sha2_password
+  /// AS <hash>`.
+  MysqlSha2,
+}
+
+/// Formats `password` as `format`. `name` is the credential name of
+/// `OutputFormat::SystemdCred`.
+pub fn format(
+  format: OutputFormat,
+  password: &str,
+  name: Option<&str>,
+) -> Result<String, Box<dyn std::error::Error>> {
+  match format {
+    OutputFormat::Plain => Ok(password.to_string()),
+    OutputFormat::SystemdCred => {
+      let name = name.ok_or("--format systemd-cred requires --name.")?;
+      let name = format!("--name={}", name);
+      pipe("systemd-creds", &["encrypt", &name, "-", "-"], password)
+    }
+    OutputFormat::PgScram => {
+      Ok(format!("{}\n{}", password, super::hash::pg_scram(password)))
+    }
+    OutputFormat::MysqlSha2 => Ok(format!(
+      "{}\n{}",
+      password,
+      super::hash::mysql_sha2(password)
+    )),
+  }
+}
+
+/// Runs `program` with `input` on its standard input and returns its output,
+/// without a trailing newline.
+fn pipe(
+  program: &str,
+  args: &[&str],
+  input: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+  let mut child = Command::new(program)
+    .args(args)
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .map_err(|e| format!("Could not run {}: {}", program, e))?;
+
+  child
+    .stdin
+    .take()
+    .expect("stdin is piped")
+    .write_all(input.as_bytes())?;
+  let output = child.wait_with_output()?;
+
+  if !output.status.success() {
+    let error = String::from_utf8_lossy(&output.stderr);
+    return Err(format!("{} failed: {}", program, error.trim()).into());
+  }
+  Ok(String::from_utf8(output.stdout)?.trim_end().to_string())
+}
@@ -0,0 +1,28 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use std::path::Path;
+
+/// Prints a passphrase of `count` words of the wordlist at `wordlist`, one
+/// word per line or a diceware list, or of the EFF long wordlist if
+/// `wordlist` is `None`, joined by `separator`.
+pub fn run(
+  wordlist: Option<&Path>,
+  count: usize,
+  separator: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let gen = match wordlist {
+    Some(wordlist) => {
+      let text = std::fs::read_to_string(wordlist)?;
+      pwdg::PassphraseGen::new(
+        pwdg::PassphraseGen::parse_wordlist(&text),
+        count,
+        separator,
+      )?
+    }
+    None => pwdg::PassphraseGen::eff(count, separator)?,
+  };
+  println!("{}", gen.gen());
+  Ok(())
+}
@@ -0,0 +1,13 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+/// Prints a PIN of `length` digits, regenerating weak PINs if `reject_weak`
+/// is set.
+pub fn run(
+  length: usize,
+  reject_weak: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+  println!("{}", pwdg::PinGen::new(length, reject_weak)?.gen());
+  Ok(())
+}
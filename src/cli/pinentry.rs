@@ -0,0 +1,178 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use std::io::{BufRead, Write};
+
+use rand::rngs::OsRng;
+
+use super::PolicyArgs;
+
+/// `GPG_ERR_CANCELED` from the pinentry source, as a real pinentry reports
+/// a dialog dismissed by the user.
+const ERR_CANCELED: u32 = 83886179;
+
+/// `GPG_ERR_GENERAL` from the pinentry source.
+const ERR_GENERAL: u32 = 83886081;
+
+/// `GPG_ERR_ASS_UNKNOWN_CMD`, as reported by libassuan.
+const ERR_UNKNOWN_COMMAND: u32 = 536871187;
+
+/// Commands that only configure the dialog, which has none to configure.
+const IGNORED: &[&str] = &[
+  "OPTION",
+  "SETDESC",
+  "SETPROMPT",
+  "SETTITLE",
+  "SETOK",
+  "SETNOTOK",
+  "SETCANCEL",
+  "SETERROR",
+  "SETQUALITYBAR",
+  "SETQUALITYBAR_TT",
+  "SETGENPIN",
+  "SETGENPIN_TT",
+  "SETREPEATERROR",
+  "SETREPEATOK",
+  "SETKEYINFO",
+  "SETTIMEOUT",
+  "CLEARPASSPHRASE",
+  "CONFIRM",
+  "MESSAGE",
+  "NOP",
+];
+
+/// Speaks the Assuan pinentry protocol on standard input and output, so that
+/// pwdg can stand in for the pinentry of gpg-agent. A passphrase requested
+/// with a repeat field, as when creating a key or changing its passphrase,
+/// is generated from the policy options instead of typed. Any other request
+/// is cancelled, since pwdg cannot know an existing passphrase.
+///
+/// With `copy`, each generated passphrase is also copied to the clipboard.
+pub fn run(
+  args: &PolicyArgs,
+  #[cfg(feature = "clipboard")] copy: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+  if args.reads_stdin() {
+    return Err(
+      "--exclude cannot be read from standard input in pinentry mode.".into(),
+    );
+  }
+  let args = args.resolve()?;
+  let generator = args.policy()?.generator()?;
+  let filter = args.filter()?;
+
+  serve(std::io::stdin().lock(), std::io::stdout().lock(), || {
+    let passphrase =
+      PolicyArgs::generate_with(&generator, &filter, &mut OsRng)?;
+    #[cfg(feature = "clipboard")]
+    if copy {
+      super::copy(&passphrase)?;
+    }
+    Ok(passphrase)
+  })?;
+  Ok(())
+}
+
+fn serve<R: BufRead, W: Write>(
+  input: R,
+  mut output: W,
+  mut generate: impl FnMut() -> Result<String, Box<dyn std::error::Error>>,
+) -> std::io::Result<()> {
+  writeln!(output, "OK Pleased to meet you")?;
+  output.flush()?;
+
+  let mut repeat = false;
+  for line in input.lines() {
+    let line = line?;
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+    let (command, argument) = line.split_once(' ').unwrap_or((&line, ""));
+    match command.to_ascii_uppercase().as_str() {
+      "BYE" => {
+        writeln!(output, "OK closing connection")?;
+        break;
+      }
+      "RESET" => {
+        repeat = false;
+        writeln!(output, "OK")?;
+      }
+      "SETREPEAT" => {
+        repeat = true;
+        writeln!(output, "OK")?;
+      }
+      "GETPIN" if repeat => match generate() {
+        Ok(passphrase) => {
+          writeln!(output, "S PIN_REPEATED")?;
+          writeln!(output, "D {}", escape(&passphrase))?;
+          writeln!(output, "OK")?;
+        }
+        Err(e) => writeln!(output, "ERR {} {} <Pinentry>", ERR_GENERAL, e)?,
+      },
+      "GETPIN" => writeln!(
+        output,
+        "ERR {} Operation cancelled <Pinentry>",
+        ERR_CANCELED
+      )?,
+      "GETINFO" => match argument.trim() {
+        // WASI has no process IDs.
+        "pid" if cfg!(not(target_os = "wasi")) => {
+          writeln!(output, "D {}\nOK", std::process::id())?
+        }
+        "version" => writeln!(output, "D {}\nOK", env!("CARGO_PKG_VERSION"))?,
+        "flavor" => writeln!(output, "D pwdg\nOK")?,
+        _ => writeln!(output, "OK")?,
+      },
+      command if IGNORED.contains(&command) => writeln!(output, "OK")?,
+      _ => writeln!(
+        output,
+        "ERR {} Unknown IPC command <User defined source 1>",
+        ERR_UNKNOWN_COMMAND
+      )?,
+    }
+    output.flush()?;
+  }
+
+  Ok(())
+}
+
+/// Percent-escapes `data` for a `D` line.
+fn escape(data: &str) -> String {
+  data
+    .replace('%', "%25")
+    .replace('\r', "%0D")
+    .replace('\n', "%0A")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn session(input: &str) -> String {
+    let mut output = Vec::new();
+    serve(input.as_bytes(), &mut output, || Ok("50%off".to_string())).unwrap();
+    String::from_utf8(output).unwrap()
+  }
+
+  #[test]
+  fn test_new_passphrase() {
+    let output = session(
+      "OPTION ttyname=/dev/pts/1\nSETDESC Enter%0Anew\nSETREPEAT Repeat:\n\
+       GETPIN\nBYE\n",
+    );
+    assert_eq!(
+      output,
+      "OK Pleased to meet you\nOK\nOK\nOK\nS PIN_REPEATED\nD 50%25off\nOK\n\
+       OK closing connection\n"
+    );
+  }
+
+  #[test]
+  fn test_existing_passphrase_is_cancelled() {
+    let output = session("SETREPEAT\nRESET\nGETPIN\nFROB\n");
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines[3], "ERR 83886179 Operation cancelled <Pinentry>");
+    assert!(lines[4].starts_with("ERR 536871187 "));
+  }
+}
@@ -0,0 +1,210 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use clap::{Subcommand, ValueEnum};
+use std::path::{Path, PathBuf};
+
+use super::PolicyArgs;
+
+#[derive(Subcommand)]
+pub enum PolicyCommand {
+  /// Prints a plain-language description of the policy.
+  Show {
+    #[command(flatten)]
+    policy: PolicyArgs,
+  },
+  /// Checks the policy and lists every violated constraint. Exits with a
+  /// nonzero status if the policy is invalid.
+  Validate {
+    #[command(flatten)]
+    policy: PolicyArgs,
+  },
+  /// Prints the policy as a machine-readable document.
+  Export {
+    /// Output format.
+    #[clap(long, value_enum, default_value_t = ExportFormat::Json)]
+    format: ExportFormat,
+
+    #[command(flatten)]
+    policy: PolicyArgs,
+  },
+  /// Estimates how long attackers of several named profiles would take to
+  /// guess a password of the policy.
+  Strength {
+    /// Output format.
+    #[clap(long, value_enum, default_value_t)]
+    format: StrengthFormat,
+
+    #[command(flatten)]
+    policy: PolicyArgs,
+  },
+  /// Prints the fingerprint of the policy, a stable hash of the passwords it
+  /// generates, as recorded in JSON output.
+  Fingerprint {
+    #[command(flatten)]
+    policy: PolicyArgs,
+  },
+  /// Converts an external policy file, such as an Active Directory-style
+  /// policy, into a pwdg policy document.
+  Import {
+    /// Policy file to import.
+    file: PathBuf,
+  },
+  /// Checks whether every password valid under policy A is also valid under
+  /// policy B. Exits with a nonzero status if not.
+  Compare {
+    /// Policy file A.
+    a: PathBuf,
+    /// Policy file B.
+    b: PathBuf,
+  },
+  /// Prints the entropy that policies A and B yield at the same length, and
+  /// the length each needs to match the other.
+  CompareEntropy {
+    /// Policy file A.
+    a: PathBuf,
+    /// Policy file B.
+    b: PathBuf,
+
+    /// Length to compare the policies at [default: the longer of their
+    /// lengths].
+    #[clap(short, long)]
+    length: Option<usize>,
+  },
+}
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum StrengthFormat {
+  /// One line per attacker.
+  #[default]
+  Text,
+  /// A JSON attack estimate, e.g. for a risk assessment.
+  Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+  /// A pwdg policy document.
+  Json,
+  /// A JSON Schema accepting the passwords that satisfy the policy.
+  JsonSchema,
+}
+
+pub fn run(command: PolicyCommand) -> Result<(), Box<dyn std::error::Error>> {
+  match command {
+    PolicyCommand::Show { policy } => {
+      println!("{}", policy.load()?);
+    }
+    PolicyCommand::Validate { policy } => {
+      let policy = policy.load()?;
+      let report = policy.validate();
+      if !report.is_valid() {
+        return Err(report.into());
+      }
+      println!("Valid policy: {}", policy);
+    }
+    PolicyCommand::Export { format, policy } => {
+      let document = policy.policy()?.to_document();
+      match format {
+        ExportFormat::Json => println!("{}", document.to_json()),
+        ExportFormat::JsonSchema => println!("{}", document.to_json_schema()),
+      }
+    }
+    PolicyCommand::Strength { format, policy } => {
+      let policy = policy.policy()?;
+      let estimate = policy.options.validate(policy.length)?.attack_estimate();
+      match format {
+        StrengthFormat::Text => println!("{}", estimate),
+        StrengthFormat::Json => println!("{}", estimate.to_json()),
+      }
+    }
+    PolicyCommand::Fingerprint { policy } => {
+      println!("{}", policy.policy()?.fingerprint());
+    }
+    PolicyCommand::Import { file } => {
+      println!("{}", read_policy(&file)?.to_document().to_json());
+    }
+    PolicyCommand::Compare { a, b } => {
+      let violations = read_policy(&a)?.subset_violations(&read_policy(&b)?);
+      if !violations.is_empty() {
+        for violation in &violations {
+          println!("{}", violation);
+        }
+        return Err(
+          format!("{} is not a subset of {}.", a.display(), b.display()).into(),
+        );
+      }
+
+      println!(
+        "Every password valid under {} is valid under {}.",
+        a.display(),
+        b.display()
+      );
+    }
+    PolicyCommand::CompareEntropy { a, b, length } => {
+      let (policy_a, policy_b) = (read_policy(&a)?, read_policy(&b)?);
+      let length = length.unwrap_or(policy_a.length.max(policy_b.length));
+      let comparison = policy_a.compare_entropy(&policy_b, length);
+      println!("At {} characters:", length);
+      println!("{}: {:.1} bits", a.display(), comparison.bits);
+      println!("{}: {:.1} bits", b.display(), comparison.other_bits);
+      for (policy, other, length) in [
+        (&a, &b, comparison.length_to_match),
+        (&b, &a, comparison.other_length_to_match),
+      ] {
+        match length {
+          Some(length) => println!(
+            "{} matches {} at {} characters",
+            policy.display(),
+            other.display(),
+            length
+          ),
+          None => println!(
+            "{} cannot match {} at any length",
+            policy.display(),
+            other.display()
+          ),
+        }
+      }
+    }
+  }
+
+  Ok(())
+}
+
+/// Prints the policy, the characters each class draws from after exclusions,
+/// the entropy, and any warnings, without generating a password.
+pub fn dry_run(
+  policy: &pwdg::Policy,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let description = policy.generator()?.describe();
+
+  println!("{}", policy);
+  for class in &description.classes {
+    let chars: String = if class.chars.is_empty() {
+      "(none)".to_string()
+    } else {
+      class.chars.iter().collect()
+    };
+    println!("{} (min {}): {}", class.category, class.min, chars);
+  }
+  println!("charset size: {}", description.charset_size);
+  println!("entropy: {:.1} bits", description.entropy_bits);
+  println!("fingerprint: {}", policy.fingerprint());
+
+  for warning in &description.warnings {
+    println!("warning: {}", warning);
+  }
+  for conflict in policy.options.conflicts() {
+    println!("warning: {}", conflict);
+  }
+
+  Ok(())
+}
+
+fn read_policy(
+  path: &Path,
+) -> Result<pwdg::Policy, Box<dyn std::error::Error>> {
+  Ok(pwdg::Policy::parse(&std::fs::read_to_string(path)?)?)
+}
@@ -0,0 +1,113 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use std::io::{BufRead, IsTerminal, Write};
+
+use super::PolicyArgs;
+
+/// Turns off the echo of the terminal until dropped, with `stty`, so that a
+/// typed password is not shown.
+#[cfg(unix)]
+struct NoEcho;
+
+#[cfg(unix)]
+impl NoEcho {
+  fn new() -> std::io::Result<Self> {
+    stty("-echo")?;
+    Ok(NoEcho)
+  }
+}
+
+#[cfg(unix)]
+impl Drop for NoEcho {
+  fn drop(&mut self) {
+    let _ = stty("echo");
+  }
+}
+
+#[cfg(unix)]
+fn stty(setting: &str) -> std::io::Result<()> {
+  let status = std::process::Command::new("stty")
+    .arg(setting)
+    .stdin(std::fs::File::open("/dev/tty")?)
+    .status()?;
+  if !status.success() {
+    return Err(std::io::Error::other(format!("stty {} failed", setting)));
+  }
+  Ok(())
+}
+
+/// Prompts for a password on standard error and reads it from standard
+/// input, without echo if that is a terminal.
+fn read_password(prompt: &str) -> std::io::Result<String> {
+  let stdin = std::io::stdin();
+  let terminal = stdin.is_terminal();
+  if terminal {
+    eprint!("{}", prompt);
+    std::io::stderr().flush()?;
+  }
+  let mut line = String::new();
+  {
+    #[cfg(unix)]
+    let _no_echo = if terminal { Some(NoEcho::new()?) } else { None };
+    stdin.lock().read_line(&mut line)?;
+  }
+  if terminal {
+    // The newline typed was not echoed.
+    eprintln!();
+  }
+  if line.is_empty() {
+    return Err(std::io::ErrorKind::UnexpectedEof.into());
+  }
+  Ok(line.trim_end_matches(['\n', '\r']).to_string())
+}
+
+/// Prompts for a new password twice and checks it against the policy of
+/// `args`, as a generated password would be, and prints a report: the rules
+/// it breaks, if any, and its estimated strength. Fails if the two entries
+/// differ or the password breaks a rule.
+pub fn run(args: &PolicyArgs) -> Result<(), Box<dyn std::error::Error>> {
+  if args.reads_stdin() {
+    return Err(
+      "--exclude cannot be read from standard input with prompt-new.".into(),
+    );
+  }
+  let args = args.resolve()?;
+  let policy = args.policy()?;
+  let filter = args.filter()?;
+
+  let password = read_password("New password: ")?;
+  if read_password("Repeat password: ")? != password {
+    return Err("Passwords do not match.".into());
+  }
+
+  let violations = policy.password_violations(&password);
+  let filtered = !filter.allows(&password);
+  if violations.is_empty() && !filtered {
+    println!("The password satisfies the policy.");
+  } else {
+    println!("The password does not satisfy the policy:");
+    for violation in &violations {
+      println!("  {}", violation);
+    }
+    if filtered {
+      println!(
+        "  password is rejected by --exclude-file, --pwquality, \
+         --account-name, or --display-name"
+      );
+    }
+  }
+
+  let report = pwdg::score(&password);
+  println!("strength: {}/4", report.score());
+  for m in &report.matches {
+    println!("  {}", m.kind);
+  }
+  println!("{}", report.attack_estimate());
+
+  if !violations.is_empty() || filtered {
+    return Err("Password rejected.".into());
+  }
+  Ok(())
+}
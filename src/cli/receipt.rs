@@ -0,0 +1,160 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::hash::hmac_sha256;
+
+/// First field of a receipt, versioning its format and the message it
+/// authenticates.
+const VERSION: &str = "pwdg-receipt-v1";
+
+/// A generation receipt: the policy hash, the creation time, and a SHA-256
+/// commitment to a password, authenticated by an HMAC-SHA-256 under a key
+/// the auditor shares. Written as one line of space-separated fields.
+#[derive(Debug, PartialEq)]
+struct Receipt {
+  policy_hash: String,
+  created_at: u64,
+  commitment: String,
+  mac: String,
+}
+
+impl Receipt {
+  fn new(
+    key: &[u8],
+    policy_hash: String,
+    created_at: u64,
+    password: &str,
+  ) -> Self {
+    let mut receipt = Receipt {
+      policy_hash,
+      created_at,
+      commitment: hex::encode(Sha256::digest(password)),
+      mac: String::new(),
+    };
+    receipt.mac = receipt.expected_mac(key);
+    receipt
+  }
+
+  fn parse(text: &str) -> Result<Self, Box<dyn std::error::Error>> {
+    let fields: Vec<&str> = text.split_whitespace().collect();
+    match fields[..] {
+      [VERSION, policy_hash, created_at, commitment, mac] => Ok(Receipt {
+        policy_hash: policy_hash.to_string(),
+        created_at: created_at.parse().map_err(|_| "Invalid receipt time.")?,
+        commitment: commitment.to_string(),
+        mac: mac.to_string(),
+      }),
+      _ => Err("Invalid receipt.".into()),
+    }
+  }
+
+  fn expected_mac(&self, key: &[u8]) -> String {
+    let message = format!(
+      "{}\n{}\n{}\n{}\n",
+      VERSION, self.policy_hash, self.created_at, self.commitment
+    );
+    hex::encode(hmac_sha256(key, message.as_bytes()))
+  }
+}
+
+impl std::fmt::Display for Receipt {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    write!(
+      f,
+      "{} {} {} {} {}",
+      VERSION, self.policy_hash, self.created_at, self.commitment, self.mac
+    )
+  }
+}
+
+/// Reads the HMAC key of receipts, the whole contents of the file at `path`.
+fn read_key(path: &Path) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+  let key = std::fs::read(path)?;
+  if key.is_empty() {
+    return Err(
+      format!("Receipt key file {} is empty.", path.display()).into(),
+    );
+  }
+  Ok(key)
+}
+
+/// Returns the receipt of `password`, generated now under `policy`, keyed by
+/// the contents of the file at `key_file`.
+pub fn issue(
+  key_file: &Path,
+  policy: &pwdg::Policy,
+  password: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+  let created_at = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map_or(0, |elapsed| elapsed.as_secs());
+  let policy_hash = policy.generator()?.config().fingerprint();
+  let receipt =
+    Receipt::new(&read_key(key_file)?, policy_hash, created_at, password);
+  Ok(receipt.to_string())
+}
+
+/// Checks the HMAC of `receipt` with the key in the file at `key_file` and,
+/// if given, that it commits to `password`, read from standard input if it
+/// is `-`. Prints the policy hash and creation time of a valid receipt.
+pub fn verify(
+  receipt: &str,
+  key_file: &Path,
+  password: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let receipt = Receipt::parse(receipt)?;
+  if receipt.expected_mac(&read_key(key_file)?) != receipt.mac {
+    return Err("Invalid receipt.".into());
+  }
+  if let Some(password) = password {
+    let password = match password {
+      "-" => {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)?;
+        line.trim_end_matches(['\n', '\r']).to_string()
+      }
+      password => password.to_string(),
+    };
+    if hex::encode(Sha256::digest(password)) != receipt.commitment {
+      return Err("Receipt is not for this password.".into());
+    }
+  }
+
+  println!(
+    "Valid receipt: policy {} at {}.",
+    receipt.policy_hash, receipt.created_at
+  );
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_receipt_round_trip() {
+    let receipt = Receipt::new(b"key", "ab12".to_string(), 1_700_000_000, "pw");
+    let text = receipt.to_string();
+    assert!(text.starts_with("pwdg-receipt-v1 ab12 1700000000 "));
+    assert!(!text.contains(" pw "));
+
+    let parsed = Receipt::parse(&text).unwrap();
+    assert_eq!(parsed, receipt);
+    assert_eq!(parsed.expected_mac(b"key"), parsed.mac);
+    assert_ne!(parsed.expected_mac(b"other"), parsed.mac);
+  }
+
+  #[test]
+  fn test_receipt_tampered() {
+    let receipt = Receipt::new(b"key", "ab12".to_string(), 1_700_000_000, "pw");
+    let tampered = receipt.to_string().replace("1700000000", "1700000001");
+    let tampered = Receipt::parse(&tampered).unwrap();
+    assert_ne!(tampered.expected_mac(b"key"), tampered.mac);
+    assert!(Receipt::parse("pwdg-receipt-v1 ab12").is_err());
+  }
+}
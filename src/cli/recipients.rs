@@ -0,0 +1,190 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use super::batch::Secret;
+
+/// Name of the manifest written next to the encrypted files.
+const MANIFEST: &str = "manifest.json";
+
+/// A key that a secret is encrypted to.
+#[derive(Debug, PartialEq)]
+enum Recipient {
+  /// An age recipient, `age1...`, or an SSH public key, encrypted to with
+  /// the `age` CLI.
+  Age(String),
+  /// A GPG key ID, fingerprint, or user ID, given as `gpg:<key>`, encrypted
+  /// to with the `gpg` CLI.
+  Gpg(String),
+}
+
+impl Recipient {
+  fn parse(key: &str) -> Result<Self, String> {
+    if let Some(key) = key.strip_prefix("gpg:") {
+      return Ok(Recipient::Gpg(key.to_string()));
+    }
+    if key.starts_with("age1") || key.starts_with("ssh-") {
+      return Ok(Recipient::Age(key.to_string()));
+    }
+    Err(format!(
+      "Unknown recipient '{}': expected an age1... or ssh- key, or gpg:<key>.",
+      key
+    ))
+  }
+
+  /// Extension of the files encrypted to the recipient.
+  fn extension(&self) -> &'static str {
+    match self {
+      Recipient::Age(_) => "age",
+      Recipient::Gpg(_) => "gpg",
+    }
+  }
+
+  fn key(&self) -> &str {
+    match self {
+      Recipient::Age(key) | Recipient::Gpg(key) => key,
+    }
+  }
+
+  /// Encrypts `secret` to the recipient into the file at `path`, passing it
+  /// to the CLI on standard input.
+  fn encrypt(
+    &self,
+    secret: &str,
+    path: &Path,
+  ) -> Result<(), Box<dyn std::error::Error>> {
+    let path = path.to_str().ok_or("Path is not valid UTF-8.")?;
+    let (program, args) = match self {
+      Recipient::Age(key) => (
+        "age",
+        vec!["--encrypt", "--recipient", key, "--output", path],
+      ),
+      Recipient::Gpg(key) => (
+        "gpg",
+        vec![
+          "--batch",
+          "--yes",
+          "--encrypt",
+          "--recipient",
+          key,
+          "--output",
+          path,
+        ],
+      ),
+    };
+    let mut child = Command::new(program)
+      .args(args)
+      .stdin(Stdio::piped())
+      .stdout(Stdio::null())
+      .stderr(Stdio::piped())
+      .spawn()
+      .map_err(|e| format!("Could not run {}: {}", program, e))?;
+    child
+      .stdin
+      .take()
+      .expect("stdin is piped")
+      .write_all(secret.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+      return Err(
+        format!(
+          "{} failed: {}",
+          program,
+          String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into(),
+      );
+    }
+    Ok(())
+  }
+}
+
+/// Reads a recipients file: a TOML table mapping each label to the key its
+/// secret is encrypted to.
+fn read(
+  path: &Path,
+) -> Result<BTreeMap<String, Recipient>, Box<dyn std::error::Error>> {
+  let keys: BTreeMap<String, String> =
+    toml::from_str(&std::fs::read_to_string(path)?).map_err(|e| {
+      format!("Invalid recipients file {}: {}", path.display(), e)
+    })?;
+  keys
+    .into_iter()
+    .map(|(label, key)| Ok((label, Recipient::parse(&key)?)))
+    .collect()
+}
+
+/// Encrypts each of `secrets` to the recipient of its label in the
+/// recipients file at `recipients`, as `<label>.age` or `<label>.gpg` under
+/// `dir`, and writes a `manifest.json` listing each label, file, recipient,
+/// and policy hash, but no secrets. Nothing is written unless every label
+/// has a recipient. Prints the path of each file.
+pub fn write(
+  dir: &Path,
+  secrets: &[Secret],
+  recipients: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let recipients = read(recipients)?;
+  let mut files = Vec::new();
+  for secret in secrets {
+    let label = &secret.label;
+    if label.is_empty() || label.contains(['/', '\\']) || label == ".." {
+      return Err(format!("Invalid secret file name '{}'.", label).into());
+    }
+    let recipient = recipients
+      .get(label)
+      .ok_or_else(|| format!("No recipient for '{}'.", label))?;
+    files.push((
+      secret,
+      recipient,
+      format!("{}.{}", label, recipient.extension()),
+    ));
+  }
+
+  std::fs::create_dir_all(dir)?;
+  let mut manifest = Vec::new();
+  for (secret, recipient, file) in files {
+    let path = dir.join(&file);
+    recipient.encrypt(&secret.password, &path)?;
+    println!("{}", path.display());
+    manifest.push(serde_json::json!({
+      "label": secret.label,
+      "file": file,
+      "recipient": recipient.key(),
+      "encryption": recipient.extension(),
+      "policy_hash": secret.policy_hash,
+    }));
+  }
+  let path = dir.join(MANIFEST);
+  std::fs::write(&path, serde_json::to_string_pretty(&manifest)? + "\n")?;
+  println!("{}", path.display());
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_recipient() {
+    assert_eq!(
+      Recipient::parse("age1qyqszqgpqyqszqgp"),
+      Ok(Recipient::Age("age1qyqszqgpqyqszqgp".to_string()))
+    );
+    assert_eq!(
+      Recipient::parse("ssh-ed25519 AAAA"),
+      Ok(Recipient::Age("ssh-ed25519 AAAA".to_string()))
+    );
+    assert_eq!(
+      Recipient::parse("gpg:alice@example.com"),
+      Ok(Recipient::Gpg("alice@example.com".to_string()))
+    );
+    assert!(Recipient::parse("alice@example.com").is_err());
+    assert_eq!(Recipient::parse("gpg:ABCD").unwrap().extension(), "gpg");
+  }
+}
@@ -0,0 +1,138 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use std::path::Path;
+
+use rand::rngs::OsRng;
+
+use super::PolicyArgs;
+
+/// Maximum number of passwords per request.
+const MAX_COUNT: u64 = 1000;
+
+/// Maximum length of the policy of a request, so that no request can make
+/// the daemon allocate more than `MAX_COUNT * MAX_LENGTH` characters.
+const MAX_LENGTH: usize = 1024;
+
+/// A request for passwords: a JSON object of policy options in their long
+/// form, as in JSON batch specs, with an optional `count` and `profile`.
+pub struct Request {
+  pub args: PolicyArgs,
+  pub count: u64,
+}
+
+impl Request {
+  /// Parses a request, which starts from `profile` unless it names its own.
+  pub fn parse(
+    json: &str,
+    profile: Option<&str>,
+  ) -> Result<Self, Box<dyn std::error::Error>> {
+    let mut request: serde_json::Map<String, serde_json::Value> =
+      serde_json::from_str(json)?;
+    let count = match request.remove("count") {
+      None => 1,
+      Some(count) => count
+        .as_u64()
+        .filter(|count| (1..=MAX_COUNT).contains(count))
+        .ok_or(format!("count must be from 1 to {}", MAX_COUNT))?,
+    };
+    let profile = match request.remove("profile") {
+      None => profile.map(str::to_string),
+      Some(serde_json::Value::String(name)) => Some(name),
+      Some(_) => return Err("profile must be a string".into()),
+    };
+    let mut args: PolicyArgs = serde_json::from_value(request.into())?;
+    args.set_profile(profile);
+    if args.reads_stdin() {
+      return Err("exclude cannot be read from standard input".into());
+    }
+    Ok(Request { args, count })
+  }
+
+  /// Loads the policy of the request, reporting every violated constraint,
+  /// or failing if its length is above `MAX_LENGTH`.
+  pub fn policy(&self) -> Result<pwdg::Policy, Box<dyn std::error::Error>> {
+    let policy = self.args.load()?;
+    if policy.length > MAX_LENGTH {
+      return Err(format!("length must be at most {}", MAX_LENGTH).into());
+    }
+    let report = policy.validate();
+    if !report.is_valid() {
+      return Err(report.into());
+    }
+    Ok(policy)
+  }
+}
+
+/// Reads a request from the file at `path`, or standard input if `-`, and
+/// prints a JSON object of the `passwords` and the `policy_hash`, or of the
+/// `error` if it fails.
+pub fn run(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+  let result = answer(path);
+  let response = match &result {
+    Ok((passwords, policy_hash)) => serde_json::json!({
+      "passwords": passwords,
+      "policy_hash": policy_hash,
+    }),
+    Err(e) => serde_json::json!({ "error": e.to_string() }),
+  };
+  println!("{}", response);
+  result.map(|_| ())
+}
+
+fn answer(
+  path: &Path,
+) -> Result<(Vec<String>, String), Box<dyn std::error::Error>> {
+  let json = if path == Path::new("-") {
+    std::io::read_to_string(std::io::stdin())?
+  } else {
+    std::fs::read_to_string(path)?
+  };
+  let request = Request::parse(&json, None)?;
+  let generator = request.policy()?.generator()?;
+  let filter = request.args.filter()?;
+  let passwords = (0..request.count)
+    .map(|_| PolicyArgs::generate_with(&generator, &filter, &mut OsRng))
+    .collect::<Result<_, _>>()?;
+  Ok((passwords, generator.config().fingerprint()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse() {
+    let request =
+      Request::parse(r#"{"length": 12, "count": 3}"#, None).unwrap();
+    assert_eq!(request.count, 3);
+    assert_eq!(request.args.profile_name(), None);
+
+    let request = Request::parse("{}", Some("default")).unwrap();
+    assert_eq!(request.count, 1);
+    assert_eq!(request.args.profile_name(), Some("default"));
+    let request =
+      Request::parse(r#"{"profile": "ci"}"#, Some("default")).unwrap();
+    assert_eq!(request.args.profile_name(), Some("ci"));
+  }
+
+  #[test]
+  fn test_parse_errors() {
+    assert!(Request::parse("not json", None).is_err());
+    assert!(Request::parse(r#"{"count": 0}"#, None).is_err());
+    assert!(Request::parse(r#"{"count": 1001}"#, None).is_err());
+    assert!(Request::parse(r#"{"profile": 1}"#, None).is_err());
+    assert!(Request::parse(r#"{"exclude": "-"}"#, None).is_err());
+    assert!(Request::parse(r#"{"unknown": 1}"#, None).is_err());
+  }
+
+  #[test]
+  fn test_policy_length_limit() {
+    let request = Request::parse(r#"{"length": 1024}"#, None).unwrap();
+    assert_eq!(request.policy().unwrap().length, 1024);
+    let request = Request::parse(r#"{"length": 1000000000000}"#, None).unwrap();
+    let error = request.policy().unwrap_err();
+    assert_eq!(error.to_string(), "length must be at most 1024");
+  }
+}
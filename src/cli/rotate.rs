@@ -0,0 +1,118 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use std::path::Path;
+
+use super::bundle::env_quote;
+use super::PolicyArgs;
+
+/// Printed in place of secrets in the summary.
+const MASK: &str = "********";
+
+/// Regenerates the values of `keys` in the `.env` file at `path` in place,
+/// after copying it to `<path>.bak`, and prints a diff of the changed lines
+/// with their values masked. Nothing is written unless every key is found.
+pub fn run(
+  path: &Path,
+  keys: &[String],
+  args: &PolicyArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let args = args.resolve()?;
+  let policy = args.policy()?;
+  let text = std::fs::read_to_string(path)?;
+  let (rotated, changed) = rotate(&text, keys, || args.generate(&policy))?;
+
+  let mut backup = path.as_os_str().to_owned();
+  backup.push(".bak");
+  std::fs::copy(path, &backup)?;
+  std::fs::write(path, rotated)?;
+
+  println!("--- {}", Path::new(&backup).display());
+  println!("+++ {}", path.display());
+  for key in changed {
+    println!("-{}={}", key, MASK);
+    println!("+{}={}", key, MASK);
+  }
+  Ok(())
+}
+
+/// Returns `text` with the value of every `KEY=value` or
+/// `export KEY=value` line of `keys` replaced by one from `generate`, quoted
+/// for a `.env` file, and the key of each replaced line. Lines of the same
+/// key get the same value.
+fn rotate(
+  text: &str,
+  keys: &[String],
+  mut generate: impl FnMut() -> Result<String, Box<dyn std::error::Error>>,
+) -> Result<(String, Vec<String>), Box<dyn std::error::Error>> {
+  let mut values: Vec<(&str, String)> = Vec::new();
+  let mut changed = Vec::new();
+  let mut rotated = String::with_capacity(text.len());
+
+  for line in text.split_inclusive('\n') {
+    let content = line.trim_end_matches(['\r', '\n']);
+    let assignment = content.trim_start();
+    let assignment = assignment.strip_prefix("export ").unwrap_or(assignment);
+    let key = assignment
+      .split_once('=')
+      .map(|(key, _)| key.trim())
+      .and_then(|key| keys.iter().find(|k| *k == key));
+
+    let Some(key) = key else {
+      rotated.push_str(line);
+      continue;
+    };
+    let value = match values.iter().find(|(k, _)| k == key) {
+      Some((_, value)) => value.clone(),
+      None => {
+        let value = env_quote(&generate()?);
+        values.push((key, value.clone()));
+        value
+      }
+    };
+    let (assigned, _) = content.split_once('=').expect("line has a value");
+    rotated.push_str(assigned);
+    rotated.push('=');
+    rotated.push_str(&value);
+    rotated.push_str(&line[content.len()..]);
+    changed.push(key.clone());
+  }
+
+  if let Some(key) = keys.iter().find(|key| !changed.contains(key)) {
+    return Err(format!("Key '{}' not found.", key).into());
+  }
+  Ok((rotated, changed))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_rotate() {
+    let text = "# db\nDB_PASSWORD=old\r\nexport API_KEY = 'x'\nOTHER=1\n\
+                DB_PASSWORD=again";
+    let keys = ["DB_PASSWORD".to_string(), "API_KEY".to_string()];
+    let mut n = 0;
+    let (rotated, changed) = rotate(text, &keys, || {
+      n += 1;
+      Ok(format!("new{}", n))
+    })
+    .unwrap();
+
+    assert_eq!(
+      rotated,
+      "# db\nDB_PASSWORD='new1'\r\nexport API_KEY ='new2'\nOTHER=1\n\
+       DB_PASSWORD='new1'"
+    );
+    assert_eq!(changed, ["DB_PASSWORD", "API_KEY", "DB_PASSWORD"]);
+  }
+
+  #[test]
+  fn test_rotate_missing_key() {
+    let keys = ["MISSING".to_string()];
+    let error = rotate("A=1\n", &keys, || Ok("x".to_string())).unwrap_err();
+    assert_eq!(error.to_string(), "Key 'MISSING' not found.");
+  }
+}
@@ -0,0 +1,47 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum SaltEncoding {
+  /// Lowercase hexadecimal.
+  #[default]
+  Hex,
+  /// Standard base64, with padding.
+  Base64,
+}
+
+/// Prints a random salt of `bytes` bytes, encoded as `encoding`.
+pub fn run(
+  bytes: usize,
+  encoding: SaltEncoding,
+) -> Result<(), Box<dyn std::error::Error>> {
+  if bytes == 0 {
+    return Err("A salt must be at least one byte.".into());
+  }
+  println!("{}", encode(&pwdg::gen_salt(bytes), encoding));
+  Ok(())
+}
+
+fn encode(salt: &[u8], encoding: SaltEncoding) -> String {
+  match encoding {
+    SaltEncoding::Hex => hex::encode(salt),
+    SaltEncoding::Base64 => BASE64.encode(salt),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_encode() {
+    let salt = [0x00, 0xff, 0x10];
+    assert_eq!(encode(&salt, SaltEncoding::Hex), "00ff10");
+    assert_eq!(encode(&salt, SaltEncoding::Base64), "AP8Q");
+  }
+}
@@ -0,0 +1,38 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use std::io::Write;
+use std::path::Path;
+
+/// Writes each secret to a file named by its label under `dir`, readable and
+/// writable by the owner only, as used by Docker secrets and the `secrets:`
+/// section of Compose files. Files hold the secret without a trailing
+/// newline. Prints the path of each file.
+pub fn write(
+  dir: &Path,
+  secrets: &[(String, String)],
+) -> Result<(), Box<dyn std::error::Error>> {
+  for (label, _) in secrets {
+    if label.is_empty() || label.contains(['/', '\\']) || label == ".." {
+      return Err(format!("Invalid secret file name '{}'.", label).into());
+    }
+  }
+
+  std::fs::create_dir_all(dir)?;
+  for (label, secret) in secrets {
+    let path = dir.join(label);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(&path)?;
+    #[cfg(unix)]
+    file
+      .set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+    file.write_all(secret.as_bytes())?;
+    println!("{}", path.display());
+  }
+
+  Ok(())
+}
@@ -0,0 +1,214 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+
+use super::hash::constant_time_eq;
+use super::PolicyArgs;
+
+/// Bytes of the random token in the path of the secret's URL, so that it
+/// cannot be fetched by guessing.
+const TOKEN_LEN: usize = 16;
+
+/// How long to wait between checks for a connection.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Maximum number of connections answered at once. Further connections are
+/// closed.
+const MAX_CONNECTIONS: usize = 16;
+
+/// Maximum number of bytes read of the request line and headers of a request.
+const MAX_REQUEST_BYTES: u64 = 8192;
+
+/// How long a client may take to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Parses a time to live such as `90s`, `10m`, or `1h`. A number without a
+/// unit is in seconds.
+pub fn parse_ttl(ttl: &str) -> Result<Duration, String> {
+  let (number, unit) = match ttl.find(|c: char| !c.is_ascii_digit()) {
+    Some(i) => ttl.split_at(i),
+    None => (ttl, "s"),
+  };
+  let seconds = match unit {
+    "s" => 1,
+    "m" => 60,
+    "h" => 3600,
+    _ => return Err(format!("invalid unit '{}', expected s, m, or h", unit)),
+  };
+  match number.parse::<u64>() {
+    Ok(number) if number > 0 => {
+      Ok(Duration::from_secs(number.saturating_mul(seconds)))
+    }
+    _ => Err(format!("invalid time to live '{}'", ttl)),
+  }
+}
+
+/// Generates a password for `args` and serves it over plain HTTP on
+/// `listen`, at a URL with a random token, which is printed. Serves it only
+/// to the first request if `once` is set, and exits then; otherwise serves it
+/// until `ttl` has passed. Fails if the secret was not fetched within `ttl`.
+///
+/// TLS is out of scope: the secret crosses the network unencrypted, so a
+/// warning is printed unless `listen` is a loopback address. Each connection
+/// is answered on its own thread, up to `MAX_CONNECTIONS` at once, and may
+/// send at most `MAX_REQUEST_BYTES` within `REQUEST_TIMEOUT`.
+pub fn run(
+  listen: SocketAddr,
+  once: bool,
+  ttl: Duration,
+  args: &PolicyArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let args = args.resolve()?;
+  let policy = args.policy()?;
+  let secret: Arc<str> = args.generate(&policy)?.into();
+  let token: Arc<str> = hex::encode(pwdg::gen_salt(TOKEN_LEN)).into();
+
+  let listener = TcpListener::bind(listen)?;
+  listener.set_nonblocking(true)?;
+  if !listen.ip().is_loopback() {
+    eprintln!(
+      "warning: the secret is served over plain HTTP beyond this machine; \
+       use this only on trusted networks."
+    );
+  }
+  println!("http://{}/{}", listener.local_addr()?, token);
+  std::io::stdout().flush()?;
+
+  let claimed = Arc::new(AtomicBool::new(false));
+  let (done, responses) = mpsc::channel();
+  let mut active = 0;
+  let deadline = Instant::now() + ttl;
+  let mut served = false;
+  while Instant::now() < deadline {
+    while let Ok(response) = responses.try_recv() {
+      active -= 1;
+      match response {
+        Ok(true) => {
+          served = true;
+          if once {
+            eprintln!("Secret served; exiting.");
+            return Ok(());
+          }
+        }
+        Ok(false) => {}
+        Err(e) => eprintln!("{}", e),
+      }
+    }
+
+    let stream = match listener.accept() {
+      Ok((stream, _)) => stream,
+      Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+        std::thread::sleep(POLL_INTERVAL);
+        continue;
+      }
+      Err(e) => return Err(e.into()),
+    };
+    // Dropping the stream closes connections beyond the limit.
+    if active >= MAX_CONNECTIONS {
+      continue;
+    }
+    active += 1;
+    let (token, secret) = (Arc::clone(&token), Arc::clone(&secret));
+    let (claimed, done) = (once.then(|| Arc::clone(&claimed)), done.clone());
+    std::thread::spawn(move || {
+      let response = respond(stream, &token, &secret, claimed.as_deref());
+      // The server has stopped listening if the channel is closed.
+      let _ = done.send(response);
+    });
+  }
+
+  if !served {
+    return Err("The secret was not fetched before it expired.".into());
+  }
+  Ok(())
+}
+
+/// Answers one request, with `secret` if it is for the path of `token`, and
+/// returns whether it was. If `claimed` is given, the secret is served only
+/// if it has not been claimed by another request, and claims it.
+fn respond(
+  stream: TcpStream,
+  token: &str,
+  secret: &str,
+  claimed: Option<&AtomicBool>,
+) -> std::io::Result<bool> {
+  stream.set_nonblocking(false)?;
+  let mut writer = stream.try_clone()?;
+  let mut reader = BufReader::new(
+    Deadline {
+      stream,
+      deadline: Instant::now() + REQUEST_TIMEOUT,
+    }
+    .take(MAX_REQUEST_BYTES),
+  );
+  let mut request_line = String::new();
+  reader.read_line(&mut request_line)?;
+  // Reads the headers, which are ignored, so that closing the connection does
+  // not reset it.
+  let mut header = String::new();
+  while reader.read_line(&mut header)? > 2 {
+    header.clear();
+  }
+
+  let path = format!("/{}", token);
+  let is_secret = matches!(
+    request_line.split_whitespace().take(2).collect::<Vec<_>>()[..],
+    ["GET", requested] if constant_time_eq(requested.as_bytes(), path.as_bytes())
+  ) && claimed
+    .is_none_or(|claimed| !claimed.swap(true, Ordering::SeqCst));
+  let (status, body) = if is_secret {
+    ("200 OK", format!("{}\n", secret))
+  } else {
+    ("404 Not Found", String::from("Not found.\n"))
+  };
+  write!(
+    writer,
+    "HTTP/1.1 {}\r\nContent-Type: text/plain; charset=utf-8\r\n\
+     Cache-Control: no-store\r\nContent-Length: {}\r\n\
+     Connection: close\r\n\r\n{}",
+    status,
+    body.len(),
+    body
+  )?;
+  Ok(is_secret)
+}
+
+/// A stream whose reads fail once `deadline` has passed, so that a client
+/// sending slowly cannot hold a connection open.
+struct Deadline {
+  stream: TcpStream,
+  deadline: Instant,
+}
+
+impl Read for Deadline {
+  fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+    let remaining = self.deadline.saturating_duration_since(Instant::now());
+    if remaining.is_zero() {
+      return Err(std::io::ErrorKind::TimedOut.into());
+    }
+    self.stream.set_read_timeout(Some(remaining))?;
+    self.stream.read(buf)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_ttl() {
+    assert_eq!(parse_ttl("10m"), Ok(Duration::from_secs(600)));
+    assert_eq!(parse_ttl("90s"), Ok(Duration::from_secs(90)));
+    assert_eq!(parse_ttl("1h"), Ok(Duration::from_secs(3600)));
+    assert_eq!(parse_ttl("45"), Ok(Duration::from_secs(45)));
+    for ttl in ["", "0m", "m", "10d", "1.5h"] {
+      assert!(parse_ttl(ttl).is_err(), "{}", ttl);
+    }
+  }
+}
@@ -0,0 +1,72 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use rand::{rngs::OsRng, RngCore};
+
+/// Splits `password` into two shares, in hexadecimal, to be sent over
+/// different channels: a random pad of its length, and its bytes XORed with
+/// the pad. Each share alone reveals only the length of the password.
+pub fn split(password: &str) -> [String; 2] {
+  let mut pad = vec![0u8; password.len()];
+  OsRng.fill_bytes(&mut pad);
+  let masked: Vec<u8> = xor(password.as_bytes(), &pad);
+  [hex::encode(pad), hex::encode(masked)]
+}
+
+/// Prints the password reconstructed from the two shares of `split`, in
+/// either order.
+pub fn run_combine(
+  first: &str,
+  second: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+  println!("{}", combine(first, second)?);
+  Ok(())
+}
+
+fn combine(
+  first: &str,
+  second: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+  let (first, second) = (parse_hex(first)?, parse_hex(second)?);
+  if first.len() != second.len() {
+    return Err("Shares must be of the same length.".into());
+  }
+  String::from_utf8(xor(&first, &second))
+    .map_err(|_| "Shares are not of the same password.".into())
+}
+
+fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+  a.iter().zip(b).map(|(a, b)| a ^ b).collect()
+}
+
+fn parse_hex(share: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+  let share = share.trim();
+  hex::decode(share).map_err(|_| format!("Invalid share '{}'.", share).into())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_split_and_combine() {
+    let password = "k7#Qp2!x\u{e9}";
+    let [pad, masked] = split(password);
+    assert_eq!(pad.len(), 2 * password.len());
+    assert_ne!(masked, hex::encode(password));
+    assert_eq!(combine(&pad, &masked).unwrap(), password);
+    assert_eq!(combine(&masked, &pad).unwrap(), password);
+  }
+
+  #[test]
+  fn test_combine_invalid() {
+    assert_eq!(
+      combine("00", "0000").unwrap_err().to_string(),
+      "Shares must be of the same length."
+    );
+    assert!(combine("0g", "00").is_err());
+    assert!(combine("0", "0").is_err());
+    assert!(combine("ff", "00").is_err());
+  }
+}
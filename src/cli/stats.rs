@@ -0,0 +1,77 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use pwdg::{EffectiveConfig, Policy};
+
+/// Prints composition statistics of `password`, generated under `policy`, to
+/// standard error.
+pub fn print(
+  policy: &Policy,
+  password: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let config = policy.options.validate(policy.length)?;
+  for line in report(&config, password) {
+    eprintln!("{}", line);
+  }
+  Ok(())
+}
+
+/// Describes the effective charset, the entropy, and how many characters of
+/// each class `password` has. A minimum is binding if the password has no
+/// more characters of its class than required.
+fn report(config: &EffectiveConfig, password: &str) -> Vec<String> {
+  let mut lines = vec![
+    format!("charset size: {}", config.charset.len()),
+    format!(
+      "entropy: {:.2} bits per character, {:.1} bits",
+      (config.charset.len() as f64).log2(),
+      config.entropy_bits()
+    ),
+  ];
+
+  for class in &config.classes {
+    if class.chars.is_empty() {
+      continue;
+    }
+    let count = password.chars().filter(|c| class.chars.contains(c)).count();
+    let binding = class.min > 0 && count == class.min;
+    lines.push(format!(
+      "{}: {} (min {}{})",
+      class.category,
+      count,
+      class.min,
+      if binding { ", binding" } else { "" }
+    ));
+  }
+
+  lines
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use pwdg::PwdGenOptions;
+
+  #[test]
+  fn test_report() {
+    let options = PwdGenOptions {
+      min_digit: 2,
+      min_upper: 1,
+      charset: Some("ABCDabcd0123".into()),
+      ..Default::default()
+    };
+    let config = options.validate(8).unwrap();
+    let lines = report(&config, "AAbcd012");
+
+    assert_eq!(lines[0], "charset size: 12");
+    assert_eq!(lines[1], "entropy: 3.58 bits per character, 28.7 bits");
+    assert_eq!(lines[2], "upper: 2 (min 1)");
+    assert_eq!(lines[3], "lower: 3 (min 0)");
+    assert_eq!(lines[4], "digit: 3 (min 2)");
+    assert_eq!(lines.len(), 5);
+
+    let lines = report(&config, "ABbcdd01");
+    assert_eq!(lines[4], "digit: 2 (min 2, binding)");
+  }
+}
@@ -0,0 +1,107 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use clap::ValueEnum;
+
+/// A secret store that `--store` writes to. Each store requires a feature.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Store {
+  /// AWS Secrets Manager, through the aws CLI.
+  #[cfg(feature = "aws")]
+  AwsSm,
+  /// AWS Systems Manager Parameter Store, as a SecureString, through the aws
+  /// CLI.
+  #[cfg(feature = "aws")]
+  AwsSsm,
+  /// The default collection of the freedesktop Secret Service, e.g. GNOME
+  /// Keyring or KeePassXC.
+  #[cfg(feature = "dbus")]
+  SecretService,
+  /// The default macOS keychain, as a generic password whose service is
+  /// --name and account is --account.
+  #[cfg(feature = "keychain")]
+  Keychain,
+  /// The Windows Credential Manager, as a generic credential whose target
+  /// name is --name, rendered as a template, and user name is --account.
+  #[cfg(feature = "wincred")]
+  Wincred,
+}
+
+/// Creates or rotates the secret `name` in `store`, and prints only its
+/// identifier and version. `account` names the owner of the secret in stores
+/// that have one, and defaults to the current user. `validity` is recorded
+/// in stores with attributes for it, currently the Secret Service.
+#[cfg_attr(
+  any(
+    not(feature = "dbus"),
+    not(any(feature = "keychain", feature = "wincred"))
+  ),
+  allow(unused_variables)
+)]
+pub fn push(
+  store: Store,
+  name: &str,
+  account: Option<&str>,
+  secret: &str,
+  validity: Option<&super::Validity>,
+) -> Result<(), Box<dyn std::error::Error>> {
+  match store {
+    #[cfg(feature = "aws")]
+    Store::AwsSm => super::aws::secrets_manager(name, secret),
+    #[cfg(feature = "aws")]
+    Store::AwsSsm => super::aws::parameter_store(name, secret),
+    #[cfg(feature = "dbus")]
+    Store::SecretService => super::dbus::store(name, secret, validity),
+    #[cfg(feature = "keychain")]
+    Store::Keychain => super::keychain::store(
+      name,
+      &account.map_or_else(user, String::from),
+      secret,
+    ),
+    #[cfg(feature = "wincred")]
+    Store::Wincred => {
+      let account = account.map_or_else(user, String::from);
+      super::wincred::store(&target_name(name, &account)?, &account, secret)
+    }
+  }
+}
+
+/// Renders the target name template `name` of a Windows credential, whose
+/// placeholders are {{user}}, {{account}}, and {{host}}.
+#[cfg(feature = "wincred")]
+fn target_name(
+  name: &str,
+  account: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+  let host = std::env::var("COMPUTERNAME")
+    .or_else(|_| std::env::var("HOSTNAME"))
+    .unwrap_or_default();
+  super::render(
+    name,
+    &[("user", &user()), ("account", account), ("host", &host)],
+  )
+}
+
+/// Returns the name of the current user, from the environment.
+#[cfg(any(feature = "keychain", feature = "wincred"))]
+fn user() -> String {
+  std::env::var("USER")
+    .or_else(|_| std::env::var("USERNAME"))
+    .unwrap_or_else(|_| "pwdg".to_string())
+}
+
+#[cfg(all(test, feature = "wincred"))]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_target_name() {
+    assert_eq!(
+      target_name("svc/{{account}}", "deploy").unwrap(),
+      "svc/deploy"
+    );
+    assert_eq!(target_name("prod-db", "deploy").unwrap(), "prod-db");
+    assert!(target_name("svc/{{nope}}", "deploy").is_err());
+  }
+}
@@ -0,0 +1,34 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+
+/// Replaces each `{{name}}` placeholder of `template` with the value of the
+/// variable `name`. This is plain substitution: there is no escaping, and
+/// an unknown placeholder is an error.
+pub fn render(
+  template: &str,
+  variables: &[(&str, &str)],
+) -> Result<String, Box<dyn std::error::Error>> {
+  let mut output = String::with_capacity(template.len());
+  let mut rest = template;
+
+  while let Some(start) = rest.find("{{") {
+    output.push_str(&rest[..start]);
+    let after = &rest[start + 2..];
+    let end = after
+      .find("}}")
+      .ok_or("Unterminated placeholder in template.")?;
+    let name = after[..end].trim();
+    let value = variables
+      .iter()
+      .find(|(variable, _)| *variable == name)
+      .map(|(_, value)| *value)
+      .ok_or_else(|| format!("Unknown template placeholder '{}'.", name))?;
+    output.push_str(value);
+    rest = &after[end + 2..];
+  }
+  output.push_str(rest);
+
+  Ok(output)
+}
@@ -0,0 +1,62 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 86400;
+
+/// When a credential was created and when it should be rotated, in seconds
+/// since the Unix epoch, as stamped by `--valid-days`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Validity {
+  pub created_at: u64,
+  pub expires_at: u64,
+}
+
+impl Validity {
+  /// Returns the validity of a credential created now and valid for
+  /// `valid_days` days.
+  pub fn new(valid_days: u64) -> Self {
+    let now = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map_or(0, |elapsed| elapsed.as_secs());
+    Self::starting_at(now, valid_days)
+  }
+
+  fn starting_at(created_at: u64, valid_days: u64) -> Self {
+    Validity {
+      created_at,
+      expires_at: created_at
+        .saturating_add(valid_days.saturating_mul(SECONDS_PER_DAY)),
+    }
+  }
+
+  /// Returns `created_at` and `expires_at` as named decimal strings, for
+  /// templates and store attributes.
+  pub fn fields(&self) -> [(&'static str, String); 2] {
+    [
+      ("created_at", self.created_at.to_string()),
+      ("expires_at", self.expires_at.to_string()),
+    ]
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_validity() {
+    let validity = Validity::starting_at(1_700_000_000, 90);
+    assert_eq!(validity.expires_at, 1_700_000_000 + 90 * 86400);
+    assert_eq!(
+      validity.fields(),
+      [
+        ("created_at", "1700000000".to_string()),
+        ("expires_at", "1707776000".to_string()),
+      ]
+    );
+    assert_eq!(Validity::starting_at(1, u64::MAX).expires_at, u64::MAX);
+  }
+}
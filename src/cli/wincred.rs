@@ -0,0 +1,68 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+
+/// Creates or replaces the generic credential `target` of `account` in the
+/// Windows Credential Manager, persisted for the current user on this
+/// computer, and prints its target name and account.
+#[cfg(windows)]
+pub fn store(
+  target: &str,
+  account: &str,
+  secret: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+  use windows_sys::Win32::Foundation::FILETIME;
+  use windows_sys::Win32::Security::Credentials::{
+    CredWriteW, CREDENTIALW, CRED_MAX_CREDENTIAL_BLOB_SIZE,
+    CRED_PERSIST_LOCAL_MACHINE, CRED_TYPE_GENERIC,
+  };
+
+  fn wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+  }
+
+  // Stored as UTF-16, as by cmdkey and PowerShell's PSCredential.
+  let mut blob: Vec<u8> =
+    secret.encode_utf16().flat_map(u16::to_le_bytes).collect();
+  if blob.len() > CRED_MAX_CREDENTIAL_BLOB_SIZE as usize {
+    return Err("The password is too long for the Credential Manager.".into());
+  }
+  let mut target_name = wide(target);
+  let mut user_name = wide(account);
+
+  let credential = CREDENTIALW {
+    Flags: 0,
+    Type: CRED_TYPE_GENERIC,
+    TargetName: target_name.as_mut_ptr(),
+    Comment: std::ptr::null_mut(),
+    LastWritten: FILETIME {
+      dwLowDateTime: 0,
+      dwHighDateTime: 0,
+    },
+    CredentialBlobSize: blob.len() as u32,
+    CredentialBlob: blob.as_mut_ptr(),
+    Persist: CRED_PERSIST_LOCAL_MACHINE,
+    AttributeCount: 0,
+    Attributes: std::ptr::null_mut(),
+    TargetAlias: std::ptr::null_mut(),
+    UserName: user_name.as_mut_ptr(),
+  };
+  // SAFETY: the strings are null-terminated and, like the blob, outlive the
+  // call, which copies them.
+  if unsafe { CredWriteW(&credential, 0) } == 0 {
+    return Err(std::io::Error::last_os_error().into());
+  }
+
+  println!("{} {}", target, account);
+  Ok(())
+}
+
+#[cfg(not(windows))]
+pub fn store(
+  _target: &str,
+  _account: &str,
+  _secret: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+  Err("The wincred store is only available on Windows.".into())
+}
@@ -0,0 +1,157 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use crate::{Category, EffectiveClass};
+
+/// A contradiction between the characters a configuration requires and the
+/// characters it excludes.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Conflict {
+  /// Exclusions leave a class with fewer characters than its minimum.
+  ClassEmptied {
+    category: Category,
+    min: usize,
+    /// Number of characters of the class left after exclusions.
+    available: usize,
+    /// Characters of the class that are excluded.
+    excluded: Vec<char>,
+  },
+  /// Characters listed by a custom class with a nonzero minimum are also
+  /// excluded, although enough of the class remains.
+  RequiredCharsExcluded {
+    category: Category,
+    excluded: Vec<char>,
+  },
+}
+
+impl std::fmt::Display for Conflict {
+  /// Describes the conflict and how to fix it.
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      Conflict::ClassEmptied {
+        category,
+        min,
+        available,
+        excluded,
+      } => {
+        write!(
+          f,
+          "Only {} {} characters remain after exclusions, fewer than the \
+           minimum of {}. Lower the minimum to {} or stop excluding some of: \
+           {}. [Conflict::ClassEmptied]",
+          available,
+          category,
+          min,
+          available,
+          excluded.iter().collect::<String>()
+        )
+      }
+      Conflict::RequiredCharsExcluded { category, excluded } => {
+        write!(
+          f,
+          "The {} class requires characters that are also excluded: {}. \
+           Remove them from the class or from the exclusions. \
+           [Conflict::RequiredCharsExcluded]",
+          category,
+          excluded.iter().collect::<String>()
+        )
+      }
+    }
+  }
+}
+
+/// Returns every conflict between the classes of a configuration before
+/// exclusions, `unexcluded`, and after, `classes`, in the same order.
+/// Classes already short of their minimum before exclusions are not in
+/// conflict with them.
+pub(crate) fn check(
+  unexcluded: &[EffectiveClass],
+  classes: &[EffectiveClass],
+) -> Vec<Conflict> {
+  unexcluded
+    .iter()
+    .zip(classes)
+    .filter(|(before, _)| before.min > 0 && before.chars.len() >= before.min)
+    .filter_map(|(before, after)| {
+      let excluded: Vec<char> = before
+        .chars
+        .iter()
+        .filter(|c| !after.chars.contains(c))
+        .cloned()
+        .collect();
+      if excluded.is_empty() {
+        None
+      } else if after.chars.len() < after.min {
+        Some(Conflict::ClassEmptied {
+          category: after.category.clone(),
+          min: after.min,
+          available: after.chars.len(),
+          excluded,
+        })
+      } else if matches!(after.category, Category::Custom(_)) {
+        Some(Conflict::RequiredCharsExcluded {
+          category: after.category.clone(),
+          excluded,
+        })
+      } else {
+        None
+      }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn class(category: Category, chars: &str, min: usize) -> EffectiveClass {
+    EffectiveClass {
+      category,
+      chars: chars.chars().collect(),
+      min,
+    }
+  }
+
+  #[test]
+  fn test_check() {
+    let symbols = Category::Custom("symbols".to_string());
+    let unexcluded = [
+      class(Category::Lower, "abc", 2),
+      class(Category::Digit, "012", 1),
+      class(symbols.clone(), "#$%", 1),
+      class(Category::Upper, "AB", 0),
+      class(Category::Special, "!", 2),
+    ];
+    let classes = [
+      class(Category::Lower, "c", 2),
+      class(Category::Digit, "2", 1),
+      class(symbols.clone(), "%", 1),
+      class(Category::Upper, "", 0),
+      class(Category::Special, "", 2),
+    ];
+
+    let conflicts = check(&unexcluded, &classes);
+    assert_eq!(
+      conflicts,
+      vec![
+        Conflict::ClassEmptied {
+          category: Category::Lower,
+          min: 2,
+          available: 1,
+          excluded: vec!['a', 'b'],
+        },
+        Conflict::RequiredCharsExcluded {
+          category: symbols,
+          excluded: vec!['#', '$'],
+        },
+      ]
+    );
+    assert_eq!(
+      conflicts[0].to_string(),
+      "Only 1 lower characters remain after exclusions, fewer than the \
+       minimum of 2. Lower the minimum to 1 or stop excluding some of: ab. \
+       [Conflict::ClassEmptied]"
+    );
+  }
+}
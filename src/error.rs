@@ -2,9 +2,10 @@
 Copyright 2024 Owain Davies
 SPDX-License-Identifier: Apache-2.0
 */
-use crate::MIN_LENGTH;
+use crate::{Category, MIN_LENGTH};
 
 #[derive(Debug)]
+#[cfg_attr(feature = "uniffi", derive(uniffi::Error), uniffi(flat_error))]
 pub enum Error {
   /// Specified length is less than `MIN_LENGTH`.
   Length,
@@ -13,7 +14,33 @@ pub enum Error {
   /// There exists a category (upper, lower, digit, or special) such that the
   /// number of characters in that category is less than any minimum specified
   /// for that category, after applying any exclusions.
-  InsufficientCharacters(&'static str),
+  InsufficientCharacters(Category),
+  /// No characters remain for password generation, after applying any
+  /// exclusions.
+  EmptyCharset,
+  /// A policy could not be parsed or converted into options.
+  InvalidPolicy(String),
+  /// The operating system's random number generator failed a startup health
+  /// test of `rng_selftest`.
+  RngHealth(&'static str),
+  /// An exclusion pattern could not be parsed.
+  InvalidExclusion(String),
+  /// The estimated entropy of a password, in bits, is below the required
+  /// `min_entropy_bits`.
+  EntropyTooLow { actual: f64, required: usize },
+  /// No characters remain in the `fill_classes` after applying any
+  /// exclusions, although the minimums leave characters to fill.
+  EmptyFill,
+  /// The first character cannot be drawn from the `first_char_class`
+  /// because the minimums of other classes take up every position.
+  FirstCharClass(Category),
+  /// No password meets every constraint, for the reason given, e.g. a
+  /// pattern with too few positions for the minimums.
+  ///
+  /// Each position counts toward the minimum of one class only, so if
+  /// classes overlap, e.g. a custom class of lowercase letters, this may be
+  /// reported although one character could meet two minimums at once.
+  Infeasible(String),
 }
 
 impl std::error::Error for Error {}
@@ -37,14 +64,79 @@ impl std::fmt::Display for Error {
           )
         )
       }
-      Error::InsufficientCharacters(char_type) => {
+      Error::InsufficientCharacters(category) => {
         write!(
           f,
           concat!(
             "Insufficient characters available for {}. ",
             "[Error::InsufficientCharacters]"
           ),
-          char_type
+          category
+        )
+      }
+      Error::EmptyCharset => {
+        write!(
+          f,
+          concat!(
+            "No characters available for password generation. ",
+            "[Error::EmptyCharset]"
+          )
+        )
+      }
+      Error::InvalidPolicy(reason) => {
+        write!(f, "Invalid policy: {}. [Error::InvalidPolicy]", reason)
+      }
+      Error::RngHealth(test) => {
+        write!(
+          f,
+          concat!(
+            "Random number generator failed the {} startup health test. ",
+            "[Error::RngHealth]"
+          ),
+          test
+        )
+      }
+      Error::InvalidExclusion(reason) => {
+        write!(
+          f,
+          "Invalid exclusion: {}. [Error::InvalidExclusion]",
+          reason
+        )
+      }
+      Error::EntropyTooLow { actual, required } => {
+        write!(
+          f,
+          concat!(
+            "Estimated entropy of {:.1} bits is below the required {} bits. ",
+            "[Error::EntropyTooLow]"
+          ),
+          actual, required
+        )
+      }
+      Error::EmptyFill => {
+        write!(
+          f,
+          concat!(
+            "No characters available in the fill classes. ",
+            "[Error::EmptyFill]"
+          )
+        )
+      }
+      Error::FirstCharClass(category) => {
+        write!(
+          f,
+          concat!(
+            "The minimums of other classes leave no room for a first {} ",
+            "character. [Error::FirstCharClass]"
+          ),
+          category
+        )
+      }
+      Error::Infeasible(reason) => {
+        write!(
+          f,
+          "The constraints cannot all be met: {}. [Error::Infeasible]",
+          reason
         )
       }
     }
@@ -74,8 +166,70 @@ mod tests {
 
   #[test]
   fn test_insufficient_characters_error_display() {
-    let error = Error::InsufficientCharacters("upper");
+    let error = Error::InsufficientCharacters(Category::Upper);
     assert!(format!("{}", error)
       .contains("Insufficient characters available for upper"));
   }
+
+  #[test]
+  fn test_empty_charset_error_display() {
+    let error = Error::EmptyCharset;
+    assert!(format!("{}", error)
+      .contains("No characters available for password generation."));
+  }
+
+  #[test]
+  fn test_invalid_policy_error_display() {
+    let error = Error::InvalidPolicy("no minimum password length".to_string());
+    assert!(format!("{}", error)
+      .contains("Invalid policy: no minimum password length."));
+  }
+
+  #[test]
+  fn test_rng_health_error_display() {
+    let error = Error::RngHealth("repetition count");
+    assert!(format!("{}", error)
+      .contains("Random number generator failed the repetition count"));
+  }
+
+  #[test]
+  fn test_invalid_exclusion_error_display() {
+    let error = Error::InvalidExclusion("unknown class 'foo'".to_string());
+    assert!(
+      format!("{}", error).contains("Invalid exclusion: unknown class 'foo'.")
+    );
+  }
+
+  #[test]
+  fn test_entropy_too_low_error_display() {
+    let error = Error::EntropyTooLow {
+      actual: 52.44,
+      required: 80,
+    };
+    assert!(format!("{}", error)
+      .contains("Estimated entropy of 52.4 bits is below the required 80"));
+  }
+
+  #[test]
+  fn test_empty_fill_error_display() {
+    assert!(format!("{}", Error::EmptyFill)
+      .contains("No characters available in the fill classes."));
+  }
+
+  #[test]
+  fn test_first_char_class_error_display() {
+    assert!(
+      format!("{}", Error::FirstCharClass(Category::Digit)).contains(
+        "The minimums of other classes leave no room for a first digit"
+      )
+    );
+  }
+
+  #[test]
+  fn test_infeasible_error_display() {
+    let error = Error::Infeasible("no position can hold a digit".to_string());
+    assert!(format!("{}", error).contains(
+      "The constraints cannot all be met: no position can hold a digit."
+    ));
+  }
 }
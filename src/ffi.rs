@@ -0,0 +1,102 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+//! Bindings generated with UniFFI, e.g. for Kotlin and Swift.
+use std::borrow::Cow;
+
+use crate::{Error, PwdGenOptions};
+
+/// A password policy, as given on the command line.
+#[derive(uniffi::Record)]
+pub struct Policy {
+  pub length: u32,
+  #[uniffi(default = 0)]
+  pub min_upper: u32,
+  #[uniffi(default = 0)]
+  pub min_lower: u32,
+  #[uniffi(default = 0)]
+  pub min_digit: u32,
+  #[uniffi(default = 0)]
+  pub min_special: u32,
+  /// Characters to exclude, as for `--exclude`.
+  #[uniffi(default = None)]
+  pub exclude: Option<String>,
+  /// Characters to use instead of the built-in classes.
+  #[uniffi(default = None)]
+  pub charset: Option<String>,
+  #[uniffi(default = 0)]
+  pub min_entropy_bits: u32,
+}
+
+impl From<Policy> for crate::Policy {
+  fn from(policy: Policy) -> Self {
+    let options = PwdGenOptions {
+      min_upper: policy.min_upper as usize,
+      min_lower: policy.min_lower as usize,
+      min_digit: policy.min_digit as usize,
+      min_special: policy.min_special as usize,
+      exclude: policy.exclude.map(Cow::Owned),
+      charset: policy.charset.map(Cow::Owned),
+      min_entropy_bits: policy.min_entropy_bits as usize,
+      ..Default::default()
+    };
+    crate::Policy::new(policy.length as usize, options)
+  }
+}
+
+/// Generates `count` passwords satisfying `policy`.
+#[uniffi::export]
+pub fn generate(policy: Policy, count: u32) -> Result<Vec<String>, Error> {
+  let generator = crate::Policy::from(policy).generator()?;
+  Ok(generator.gen_n(count as usize))
+}
+
+/// Returns the messages of every constraint `policy` violates, or none if it
+/// is valid.
+#[uniffi::export]
+pub fn validate(policy: Policy) -> Vec<String> {
+  crate::Policy::from(policy)
+    .validate()
+    .errors()
+    .iter()
+    .map(ToString::to_string)
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn policy(length: u32) -> Policy {
+    Policy {
+      length,
+      min_upper: 0,
+      min_lower: 0,
+      min_digit: 2,
+      min_special: 0,
+      exclude: None,
+      charset: None,
+      min_entropy_bits: 0,
+    }
+  }
+
+  #[test]
+  fn test_generate() {
+    let passwords = generate(policy(12), 3).unwrap();
+    assert_eq!(passwords.len(), 3);
+    for password in passwords {
+      assert_eq!(password.len(), 12);
+      assert!(password.chars().filter(char::is_ascii_digit).count() >= 2);
+    }
+    assert!(matches!(generate(policy(4), 1), Err(Error::Length)));
+  }
+
+  #[test]
+  fn test_validate() {
+    assert!(validate(policy(12)).is_empty());
+    let errors = validate(policy(4));
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("[Error::Length]"));
+  }
+}
@@ -0,0 +1,277 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use crate::util::checked_sum;
+use crate::{Category, PwdGenOptions, MIN_LENGTH};
+
+/// Lengths tried beyond the shortest one the minimums allow, e.g. to reach
+/// `min_entropy_bits`.
+const MAX_EXTRA_LENGTH: usize = 1024;
+
+/// A change to a single option that alone makes an invalid configuration
+/// valid. Returned by `ValidationReport::fixes`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Fix {
+  /// Increase the length to this.
+  Length(usize),
+  /// Reduce the minimum of a class to this.
+  Minimum { category: Category, min: usize },
+  /// Reduce `min_entropy_bits` to this.
+  MinEntropyBits(usize),
+}
+
+impl std::fmt::Display for Fix {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      Fix::Length(length) => write!(f, "increase length to {}", length),
+      Fix::Minimum {
+        category: Category::Custom(name),
+        min,
+      } => write!(f, "reduce the minimum of class '{}' to {}", name, min),
+      Fix::Minimum { category, min } => {
+        write!(f, "reduce min_{} to {}", category, min)
+      }
+      Fix::MinEntropyBits(bits) => {
+        write!(f, "reduce min_entropy_bits to {}", bits)
+      }
+    }
+  }
+}
+
+/// Returns the smallest change to each of the length, the minimum of each
+/// class, and `min_entropy_bits` that alone makes `length` and `options`
+/// valid, if any. Each fix is checked against every constraint. Valid
+/// configurations need no fix.
+pub(crate) fn find(length: usize, options: &PwdGenOptions) -> Vec<Fix> {
+  let valid =
+    |length: usize, options: &PwdGenOptions| options.check(length).1.is_empty();
+  let mut fixes = Vec::new();
+  if valid(length, options) {
+    return fixes;
+  }
+
+  let min_total = checked_sum(
+    [
+      options.min_upper,
+      options.min_lower,
+      options.min_digit,
+      options.min_special,
+    ]
+    .into_iter()
+    .chain(options.classes.iter().map(|class| class.min)),
+  );
+  if let Some(min_total) = min_total {
+    let start = (length + 1).max(MIN_LENGTH).max(min_total);
+    if let Some(length) = (start..=start.saturating_add(MAX_EXTRA_LENGTH))
+      .find(|&length| valid(length, options))
+    {
+      fixes.push(Fix::Length(length));
+    }
+  }
+
+  // A minimum can be at most the number of distinct characters of its class,
+  // and the length less the other minimums.
+  let classes = options.check(length).0.classes;
+  let total = min_total.unwrap_or(usize::MAX);
+  let cap = |category: &Category, min: usize| {
+    let distinct = classes
+      .iter()
+      .find(|class| class.category == *category)
+      .map_or(0, |class| class.chars.len());
+    distinct.min(length.saturating_sub(total.saturating_sub(min)))
+  };
+
+  let builtin = [
+    (Category::Upper, options.min_upper),
+    (Category::Lower, options.min_lower),
+    (Category::Digit, options.min_digit),
+    (Category::Special, options.min_special),
+  ];
+  for (category, min) in builtin {
+    let with_min = |min| {
+      let mut options = options.clone();
+      match category {
+        Category::Upper => options.min_upper = min,
+        Category::Lower => options.min_lower = min,
+        Category::Digit => options.min_digit = min,
+        _ => options.min_special = min,
+      }
+      options
+    };
+    if let Some(min) = lower_min(min, cap(&category, min), |min| {
+      valid(length, &with_min(min))
+    }) {
+      fixes.push(Fix::Minimum {
+        category: category.clone(),
+        min,
+      });
+    }
+  }
+  for (i, class) in options.classes.iter().enumerate() {
+    let with_min = |min| {
+      let mut options = options.clone();
+      options.classes.to_mut()[i].min = min;
+      options
+    };
+    let category = Category::Custom(class.name.to_string());
+    if let Some(min) = lower_min(class.min, cap(&category, class.min), |min| {
+      valid(length, &with_min(min))
+    }) {
+      fixes.push(Fix::Minimum { category, min });
+    }
+  }
+
+  if options.min_entropy_bits > 0 {
+    let bits = options.check(length).0.entropy_bits().floor() as usize;
+    let lowered = PwdGenOptions {
+      min_entropy_bits: bits,
+      ..options.clone()
+    };
+    if bits < options.min_entropy_bits && valid(length, &lowered) {
+      fixes.push(Fix::MinEntropyBits(bits));
+    }
+  }
+
+  fixes
+}
+
+/// Returns the largest minimum below `min` and at most `cap` for which
+/// `valid` holds, if any. Tries `cap` first, then searches below it
+/// assuming that smaller minimums are more often valid, with a logarithmic
+/// number of checks.
+fn lower_min(
+  min: usize,
+  cap: usize,
+  valid: impl Fn(usize) -> bool,
+) -> Option<usize> {
+  let top = cap.min(min.checked_sub(1)?);
+  if valid(top) {
+    return Some(top);
+  }
+  if top == 0 || !valid(0) {
+    return None;
+  }
+  // `valid(low)` holds and `valid(high)` does not.
+  let (mut low, mut high) = (0, top);
+  while high - low > 1 {
+    let mid = low + (high - low) / 2;
+    if valid(mid) {
+      low = mid;
+    } else {
+      high = mid;
+    }
+  }
+  Some(low)
+}
+
+#[cfg(all(test, feature = "builtin-charsets"))]
+mod tests {
+  use super::*;
+  use crate::CharClass;
+
+  #[test]
+  fn test_find() {
+    let options = PwdGenOptions {
+      min_special: 6,
+      min_digit: 6,
+      ..Default::default()
+    };
+    assert_eq!(
+      find(10, &options),
+      vec![
+        Fix::Length(12),
+        Fix::Minimum {
+          category: Category::Digit,
+          min: 4
+        },
+        Fix::Minimum {
+          category: Category::Special,
+          min: 4
+        },
+      ]
+    );
+    assert!(find(12, &options).is_empty());
+
+    // No length helps, and both minimums must be reduced at once.
+    let options = PwdGenOptions {
+      min_digit: 1,
+      min_upper: 1,
+      exclude: Some("0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ".into()),
+      ..Default::default()
+    };
+    assert!(find(12, &options).is_empty());
+
+    let options = PwdGenOptions {
+      classes: vec![CharClass {
+        name: "vowels".into(),
+        chars: "aeiouAEIOU".into(),
+        min: 10,
+      }]
+      .into(),
+      ..Default::default()
+    };
+    assert_eq!(
+      find(8, &options),
+      vec![
+        Fix::Length(10),
+        Fix::Minimum {
+          category: Category::Custom("vowels".to_string()),
+          min: 8
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn test_find_large_minimum() {
+    // Found without trying every minimum below 100000000.
+    let options = PwdGenOptions {
+      min_upper: 100_000_000,
+      exclude: Some("ABCDEFGHIJKLMNOPQRSTUVWXYZ".into()),
+      ..Default::default()
+    };
+    assert_eq!(
+      find(100_000_000, &options),
+      vec![Fix::Minimum {
+        category: Category::Upper,
+        min: 0
+      }]
+    );
+  }
+
+  #[test]
+  fn test_find_entropy() {
+    let options = PwdGenOptions {
+      charset: Some("ab".into()),
+      min_entropy_bits: 12,
+      ..Default::default()
+    };
+    assert_eq!(
+      find(8, &options),
+      vec![Fix::Length(12), Fix::MinEntropyBits(8)]
+    );
+    assert_eq!(find(6, &options), vec![Fix::Length(12)]);
+  }
+
+  #[test]
+  fn test_fix_display() {
+    assert_eq!(Fix::Length(14).to_string(), "increase length to 14");
+    assert_eq!(
+      Fix::Minimum {
+        category: Category::Special,
+        min: 2
+      }
+      .to_string(),
+      "reduce min_special to 2"
+    );
+    assert_eq!(
+      Fix::Minimum {
+        category: Category::Custom("accents".to_string()),
+        min: 1
+      }
+      .to_string(),
+      "reduce the minimum of class 'accents' to 1"
+    );
+  }
+}
@@ -0,0 +1,137 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "json")]
+use serde::Serialize;
+
+use crate::{Category, EffectiveConfig};
+
+/// Number of characters of a password in each class.
+///
+/// A character is counted in the first class, in generation order, that
+/// contains it. Characters of custom classes count as `other`.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct CategoryCounts {
+  pub upper: usize,
+  pub lower: usize,
+  pub digit: usize,
+  pub special: usize,
+  pub other: usize,
+}
+
+impl CategoryCounts {
+  pub(crate) fn new(password: &str, config: &EffectiveConfig) -> Self {
+    let mut counts = CategoryCounts::default();
+    for c in password.chars() {
+      let category = config
+        .classes
+        .iter()
+        .find(|class| class.chars.contains(&c))
+        .map(|class| &class.category);
+      let count = match category {
+        Some(Category::Upper) => &mut counts.upper,
+        Some(Category::Lower) => &mut counts.lower,
+        Some(Category::Digit) => &mut counts.digit,
+        Some(Category::Special) => &mut counts.special,
+        Some(Category::Custom(_)) | None => &mut counts.other,
+      };
+      *count += 1;
+    }
+    counts
+  }
+}
+
+/// A generated password with the facts about it that callers would
+/// otherwise recompute. Returned by `PwdGen::gen_with_meta`.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct GeneratedPassword {
+  pub value: String,
+  /// Estimated entropy in bits. See `EffectiveConfig::entropy_bits`.
+  pub entropy_bits: f64,
+  pub counts: CategoryCounts,
+  /// Fingerprint of the configuration the password was generated with. See
+  /// `EffectiveConfig::fingerprint`.
+  pub policy_hash: String,
+  /// When the password was generated, in seconds since the Unix epoch.
+  pub created_at: u64,
+}
+
+impl GeneratedPassword {
+  pub(crate) fn new(value: String, config: &EffectiveConfig) -> Self {
+    GeneratedPassword {
+      counts: CategoryCounts::new(&value, config),
+      value,
+      entropy_bits: config.entropy_bits(),
+      policy_hash: config.fingerprint(),
+      created_at: SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs()),
+    }
+  }
+
+  /// Serializes the password and its metadata as pretty-printed JSON.
+  #[cfg(feature = "json")]
+  pub fn to_json(&self) -> String {
+    serde_json::to_string_pretty(self)
+      .expect("Generated password is serializable")
+  }
+}
+
+#[cfg(all(test, feature = "builtin-charsets"))]
+mod tests {
+  use super::*;
+  use crate::{PwdGen, PwdGenOptions};
+
+  #[test]
+  fn test_gen_with_meta() {
+    let options = PwdGenOptions {
+      min_digit: 3,
+      ..Default::default()
+    };
+    let pwdgen = PwdGen::new(16, Some(options)).unwrap();
+    let generated = pwdgen.gen_with_meta();
+    let counts = generated.counts;
+
+    assert_eq!(generated.value.chars().count(), 16);
+    assert!(counts.digit >= 3);
+    assert_eq!(
+      counts.upper + counts.lower + counts.digit + counts.special,
+      16
+    );
+    assert_eq!(counts.other, 0);
+    assert_eq!(generated.entropy_bits, pwdgen.config().entropy_bits());
+    assert_eq!(generated.policy_hash, pwdgen.config().fingerprint());
+    assert!(generated.created_at > 0);
+  }
+
+  #[test]
+  fn test_category_counts() {
+    let pwdgen = PwdGen::new(8, None).unwrap();
+    assert_eq!(
+      CategoryCounts::new("Ab1!cd2é", pwdgen.config()),
+      CategoryCounts {
+        upper: 1,
+        lower: 3,
+        digit: 2,
+        special: 1,
+        other: 1,
+      }
+    );
+  }
+
+  #[cfg(feature = "json")]
+  #[test]
+  fn test_to_json() {
+    let generated = PwdGen::new(12, None).unwrap().gen_with_meta();
+    let json: serde_json::Value =
+      serde_json::from_str(&generated.to_json()).unwrap();
+    assert_eq!(json["value"], generated.value);
+    assert!(json["counts"]["lower"].is_number());
+    assert_eq!(json["policy_hash"].as_str().unwrap().len(), 64);
+  }
+}
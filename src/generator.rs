@@ -2,25 +2,78 @@
 Copyright 2024 Owain Davies
 SPDX-License-Identifier: Apache-2.0
 */
-use rand::{rngs::OsRng, seq::SliceRandom};
+use rand::{rngs::OsRng, seq::SliceRandom, Rng};
+use std::borrow::Cow;
 use std::collections::HashSet;
 
+use crate::charset::{builtin_chars, AMBIGUOUS_CHARS, BUILTIN_CLASSES};
 use crate::util::checked_sum;
-use crate::util::filtered_range;
-use crate::Error;
-use crate::SPECIAL_CHARS;
+use crate::util::{filtered_range, unique};
+use crate::{
+  conflict, find_structure, warning, Category, Conflict, EffectiveClass,
+  EffectiveConfig, Error, GeneratedPassword, GeneratorDescription,
+  ValidationReport, Warning,
+};
 
 pub const MIN_LENGTH: usize = 8;
 pub const DEFAULT_PWDGEN_OPTIONS: PwdGenOptions = PwdGenOptions::default_();
 
+/// A caller-defined character class.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CharClass<'a> {
+  /// Name of the class, reported in `Category::Custom` errors.
+  pub name: Cow<'a, str>,
+  /// Characters belonging to the class. Duplicates are ignored.
+  pub chars: Cow<'a, str>,
+  /// Minimum number of characters from this class.
+  pub min: usize,
+}
+
+impl<'a> CharClass<'a> {
+  /// Converts the class into a class that owns all of its data.
+  pub fn into_owned(self) -> CharClass<'static> {
+    CharClass {
+      name: Cow::Owned(self.name.into_owned()),
+      chars: Cow::Owned(self.chars.into_owned()),
+      min: self.min,
+    }
+  }
+}
+
 /// Configuration options for a password generator.
+///
+/// String fields are `Cow`s, so options may borrow from the caller or own
+/// their data. Use `into_owned` to obtain `PwdGenOptions<'static>`, e.g. to
+/// store options built from user input in a long-lived struct.
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct PwdGenOptions<'a> {
   pub min_upper: usize,
   pub min_lower: usize,
   pub min_digit: usize,
   pub min_special: usize,
-  pub exclude: Option<&'a str>,
+  pub exclude: Option<Cow<'a, str>>,
+  /// Restricts passwords to these characters, if set. Every class keeps only
+  /// the characters in the set, and characters outside every class are drawn
+  /// as well. Exclusions are applied afterwards.
+  pub charset: Option<Cow<'a, str>>,
+  /// Custom character classes, used in addition to the built-in classes.
+  pub classes: Cow<'a, [CharClass<'a>]>,
+  /// Minimum estimated entropy of a password, in bits, or `0` for none. See
+  /// `EffectiveConfig::entropy_bits`.
+  pub min_entropy_bits: usize,
+  /// Classes that the characters beyond the minimums are drawn from, if set,
+  /// e.g. letters and digits only, so that a password has exactly
+  /// `min_special` special characters. Otherwise, the whole charset.
+  pub fill_classes: Option<Cow<'a, [Category]>>,
+  /// Rejects and regenerates passwords with a simple structure, such as
+  /// palindromes, that some filters reject. See `find_structure`.
+  pub reject_structures: bool,
+  /// Class that the first character is drawn from, if set, e.g. for systems
+  /// that require passwords to start with a letter. May be a custom class.
+  pub first_char_class: Option<Category>,
+  /// Removes characters easily mistaken for one another, `AMBIGUOUS_CHARS`,
+  /// from every class, as if excluded.
+  pub avoid_ambiguous: bool,
 }
 
 impl<'a> PwdGenOptions<'a> {
@@ -31,6 +84,344 @@ impl<'a> PwdGenOptions<'a> {
       min_digit: 0,
       min_special: 0,
       exclude: None,
+      charset: None,
+      classes: Cow::Borrowed(&[]),
+      min_entropy_bits: 0,
+      fill_classes: None,
+      first_char_class: None,
+      reject_structures: false,
+      avoid_ambiguous: false,
+    }
+  }
+
+  /// Converts the options into options that own all of their data.
+  pub fn into_owned(self) -> PwdGenOptions<'static> {
+    PwdGenOptions {
+      min_upper: self.min_upper,
+      min_lower: self.min_lower,
+      min_digit: self.min_digit,
+      min_special: self.min_special,
+      exclude: self.exclude.map(|exclude| Cow::Owned(exclude.into_owned())),
+      charset: self.charset.map(|charset| Cow::Owned(charset.into_owned())),
+      classes: Cow::Owned(
+        self
+          .classes
+          .iter()
+          .cloned()
+          .map(CharClass::into_owned)
+          .collect(),
+      ),
+      min_entropy_bits: self.min_entropy_bits,
+      fill_classes: self
+        .fill_classes
+        .map(|fill_classes| Cow::Owned(fill_classes.into_owned())),
+      first_char_class: self.first_char_class,
+      reject_structures: self.reject_structures,
+      avoid_ambiguous: self.avoid_ambiguous,
+    }
+  }
+
+  /// Validates the options for passwords of the given `length`, without
+  /// constructing a generator.
+  ///
+  /// # Returns
+  ///
+  /// Returns the `EffectiveConfig` that a generator would use, that is, the
+  /// characters of each class after exclusions, or the first violated
+  /// constraint. Use `PwdGen::validate` to obtain every violated constraint.
+  pub fn validate(&self, length: usize) -> Result<EffectiveConfig, Error> {
+    let (config, errors) = self.check(length);
+    match errors.into_iter().next() {
+      Some(error) => Err(error),
+      None => Ok(config),
+    }
+  }
+
+  /// Returns the characters of each class after exclusions, in generation
+  /// order, even if the options are invalid, e.g. to show which characters
+  /// an exclusion removed.
+  pub fn effective_classes(&self) -> Vec<EffectiveClass> {
+    self.check(0).0.classes
+  }
+
+  /// Returns every contradiction between the minimums of the classes and
+  /// the exclusions, with suggested fixes.
+  pub fn conflicts(&self) -> Vec<Conflict> {
+    conflict::check(&self.unexcluded_classes(), &self.effective_classes())
+  }
+
+  /// Returns the classes as `effective_classes` would without `exclude` and
+  /// `avoid_ambiguous`.
+  fn unexcluded_classes(&self) -> Vec<EffectiveClass> {
+    PwdGenOptions {
+      exclude: None,
+      avoid_ambiguous: false,
+      ..self.clone()
+    }
+    .effective_classes()
+  }
+
+  /// Returns the excluded characters that no class or charset contains,
+  /// without duplicates.
+  pub(crate) fn unused_exclusions(&self) -> Vec<char> {
+    let available: HashSet<char> = self
+      .unexcluded_classes()
+      .into_iter()
+      .flat_map(|class| class.chars)
+      .collect();
+    unique(self.exclude.as_deref().unwrap_or("").chars())
+      .into_iter()
+      .filter(|c| !available.contains(c))
+      .collect()
+  }
+
+  pub(crate) fn check(&self, length: usize) -> (EffectiveConfig, Vec<Error>) {
+    let mut errors = Vec::new();
+
+    if length < MIN_LENGTH {
+      errors.push(Error::Length);
+    }
+
+    let builtin_mins = [
+      self.min_upper,
+      self.min_lower,
+      self.min_digit,
+      self.min_special,
+    ];
+
+    let min_total = checked_sum(
+      builtin_mins
+        .iter()
+        .cloned()
+        .chain(self.classes.iter().map(|class| class.min)),
+    );
+    if min_total.is_none() || min_total.unwrap() > length {
+      errors.push(Error::MinLimitExceeded);
+    }
+
+    let mut exclude: HashSet<char> =
+      self.exclude.as_deref().unwrap_or("").chars().collect();
+    if self.avoid_ambiguous {
+      exclude.extend(AMBIGUOUS_CHARS.chars());
+    }
+    let charset: Option<Vec<char>> = self
+      .charset
+      .as_deref()
+      .map(|charset| unique(charset.chars()));
+    if let Some(charset) = &charset {
+      // Characters of a class that are outside the charset are excluded too.
+      let classes = BUILTIN_CLASSES
+        .iter()
+        .flat_map(builtin_chars)
+        .chain(self.classes.iter().flat_map(|class| class.chars.chars()));
+      exclude.extend(classes.filter(|c| !charset.contains(c)));
+    }
+    let exclude = Some(exclude);
+
+    let mut classes = Vec::new();
+
+    for (category, min) in BUILTIN_CLASSES.into_iter().zip(builtin_mins) {
+      let chars =
+        filtered_range(builtin_chars(&category).into_iter(), &exclude);
+      if chars.len() < min {
+        errors.push(Error::InsufficientCharacters(category.clone()));
+      }
+      classes.push(EffectiveClass {
+        category,
+        chars,
+        min,
+      });
+    }
+
+    for class in self.classes.iter() {
+      let category = Category::Custom(class.name.to_string());
+      let chars =
+        filtered_range(unique(class.chars.chars()).into_iter(), &exclude);
+      if chars.len() < class.min {
+        errors.push(Error::InsufficientCharacters(category.clone()));
+      }
+      classes.push(EffectiveClass {
+        category,
+        chars,
+        min: class.min,
+      });
+    }
+
+    if let Some(charset) = charset {
+      let in_class: HashSet<char> = classes
+        .iter()
+        .flat_map(|class| class.chars.iter().cloned())
+        .collect();
+      let chars = filtered_range(
+        charset.into_iter().filter(|c| !in_class.contains(c)),
+        &exclude,
+      );
+      if !chars.is_empty() {
+        classes.push(EffectiveClass {
+          category: Category::Custom("charset".to_string()),
+          chars,
+          min: 0,
+        });
+      }
+    }
+
+    if classes.iter().all(|class| class.chars.is_empty()) {
+      errors.push(Error::EmptyCharset);
+    }
+
+    let fill = self.fill_classes.as_deref().map(|fill_classes| {
+      for category in fill_classes {
+        if !classes.iter().any(|class| class.category == *category) {
+          errors.push(Error::InvalidPolicy(format!(
+            "unknown fill class '{}'",
+            category
+          )));
+        }
+      }
+      unique(
+        classes
+          .iter()
+          .filter(|class| fill_classes.contains(&class.category))
+          .flat_map(|class| class.chars.iter().cloned()),
+      )
+    });
+    if fill.as_ref().is_some_and(Vec::is_empty) && min_total != Some(length) {
+      errors.push(Error::EmptyFill);
+    }
+
+    let first = self.first_char_class.as_ref().map(|category| {
+      let Some(first) = classes.iter().find(|c| c.category == *category) else {
+        errors.push(Error::InvalidPolicy(format!(
+          "unknown first character class '{}'",
+          category
+        )));
+        return Vec::new();
+      };
+      // Without characters beyond the minimums, the first character must
+      // be one that a minimum requires.
+      let required = classes.iter().any(|class| {
+        class.min > 0 && class.chars.iter().all(|c| first.chars.contains(c))
+      });
+      if first.chars.is_empty() {
+        errors.push(Error::InsufficientCharacters(category.clone()));
+      } else if min_total == Some(length) && !required {
+        errors.push(Error::FirstCharClass(category.clone()));
+      }
+      first.chars.clone()
+    });
+
+    if self.reject_structures {
+      // Passwords of a single repeated character are always rejected.
+      let mut reachable: HashSet<char> = classes
+        .iter()
+        .filter(|class| class.min > 0)
+        .flat_map(|class| class.chars.iter().cloned())
+        .chain(first.iter().flatten().cloned())
+        .collect();
+      if min_total.is_some_and(|min_total| min_total < length) {
+        match &fill {
+          Some(fill) => reachable.extend(fill),
+          None => reachable.extend(classes.iter().flat_map(|c| &c.chars)),
+        }
+      }
+      if reachable.len() < 2 {
+        errors.push(Error::InvalidPolicy(
+          "rejecting structures needs at least two distinct characters"
+            .to_string(),
+        ));
+      }
+    }
+
+    let mut config = EffectiveConfig::new(length, classes);
+    config.fill = fill;
+    config.first = first;
+    config.reject_structures = self.reject_structures;
+
+    if self.min_entropy_bits > 0 {
+      let actual = config.entropy_bits();
+      if actual < self.min_entropy_bits as f64 {
+        errors.push(Error::EntropyTooLow {
+          actual,
+          required: self.min_entropy_bits,
+        });
+      }
+    }
+
+    (config, errors)
+  }
+}
+
+impl<'a> PwdGenOptions<'a> {
+  /// Human-readable requirements, one entry per nonzero minimum and one for
+  /// any exclusions.
+  pub(crate) fn requirements(&self) -> Vec<String> {
+    let mut mins: Vec<String> = [
+      (self.min_upper, "uppercase"),
+      (self.min_lower, "lowercase"),
+      (self.min_digit, "digit"),
+      (self.min_special, "special"),
+    ]
+    .into_iter()
+    .filter(|&(min, _)| min > 0)
+    .map(|(min, name)| match name {
+      "digit" if min > 1 => format!("{} digits", min),
+      _ => format!("{} {}", min, name),
+    })
+    .collect();
+
+    mins.extend(
+      self
+        .classes
+        .iter()
+        .filter(|class| class.min > 0)
+        .map(|class| format!("{} from {}", class.min, class.name)),
+    );
+
+    let mut requirements = Vec::new();
+    if let Some((first, rest)) = mins.split_first() {
+      requirements.push(format!("at least {}", first));
+      requirements.extend(rest.iter().cloned());
+    }
+    if let Some(charset) = self.charset.as_deref() {
+      requirements.push(format!("only from: {}", charset));
+    }
+    if let Some(fill_classes) = self.fill_classes.as_deref() {
+      let names: Vec<String> =
+        fill_classes.iter().map(ToString::to_string).collect();
+      requirements.push(format!("rest from: {}", names.join(", ")));
+    }
+    if let Some(category) = &self.first_char_class {
+      requirements.push(format!("starts with: {}", category));
+    }
+    if self.reject_structures {
+      requirements.push("no simple structures".to_string());
+    }
+    if self.avoid_ambiguous {
+      requirements.push("no ambiguous characters".to_string());
+    }
+    if self.min_entropy_bits > 0 {
+      requirements.push(format!(
+        "at least {} bits of entropy",
+        self.min_entropy_bits
+      ));
+    }
+    if let Some(exclude) = self.exclude.as_deref().filter(|e| !e.is_empty()) {
+      requirements.push(format!("excludes: {}", exclude));
+    }
+
+    requirements
+  }
+}
+
+impl<'a> std::fmt::Display for PwdGenOptions<'a> {
+  /// Describes the options in plain language, e.g. "at least 2 uppercase,
+  /// 1 digit, excludes: O0Il".
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    let requirements = self.requirements();
+    if requirements.is_empty() {
+      write!(f, "no character requirements")
+    } else {
+      write!(f, "{}", requirements.join(", "))
     }
   }
 }
@@ -38,30 +429,18 @@ impl<'a> PwdGenOptions<'a> {
 impl<'a> Default for PwdGenOptions<'a> {
   /// Default constructor for `PwdGenOptions`.
   ///
-  /// Sets the minimum count of each character type to `0` and does not exclude
-  /// any characters.
+  /// Sets the minimum count of each character type to `0`, does not exclude
+  /// any characters, and adds no custom classes.
   fn default() -> Self {
     PwdGenOptions::default_()
   }
 }
 
-struct CharacterSet {
-  upper: Vec<char>,
-  lower: Vec<char>,
-  digit: Vec<char>,
-  special: Vec<char>,
-}
-
 /// Password generator struct.
 pub struct PwdGen<'a> {
   length: usize,
   options: PwdGenOptions<'a>,
-  // TODO?: charset same as union of upper, lower, digit, special.
-  charset: Vec<char>,
-  upper: Vec<char>,
-  lower: Vec<char>,
-  digit: Vec<char>,
-  special: Vec<char>,
+  config: EffectiveConfig,
 }
 
 impl<'a> PwdGen<'a> {
@@ -85,113 +464,169 @@ impl<'a> PwdGen<'a> {
   ) -> Result<Self, Error> {
     let options = options.unwrap_or_default();
 
-    let cset = Self::validate_input(length, &options)?;
-
-    let charset = [
-      &cset.upper[..],
-      &cset.lower[..],
-      &cset.digit[..],
-      &cset.special[..],
-    ]
-    .concat();
+    let config = Self::validate_input(length, &options)?;
 
     Ok(PwdGen {
       length,
       options,
-      charset,
-      upper: cset.upper,
-      lower: cset.lower,
-      digit: cset.digit,
-      special: cset.special,
+      config,
     })
   }
 
+  /// Creates a new password generator like `PwdGen::new`, and returns it
+  /// together with any warnings about its configuration.
+  pub fn new_with_warnings(
+    length: usize,
+    options: Option<PwdGenOptions<'a>>,
+  ) -> Result<(Self, Vec<Warning>), Error> {
+    let pwdgen = Self::new(length, options)?;
+    let warnings = pwdgen.warnings();
+    Ok((pwdgen, warnings))
+  }
+
+  /// Returns the risks of a valid configuration, such as low entropy, a tiny
+  /// charset, or minimums that fix the composition of every password.
+  pub fn warnings(&self) -> Vec<Warning> {
+    let options = &self.options;
+    let min_total = checked_sum(
+      [
+        options.min_upper,
+        options.min_lower,
+        options.min_digit,
+        options.min_special,
+      ]
+      .into_iter()
+      .chain(options.classes.iter().map(|class| class.min)),
+    )
+    .expect("validated minimums do not overflow");
+    warning::check(
+      &self.config,
+      min_total,
+      options.charset.is_some(),
+      options.unused_exclusions(),
+    )
+  }
+
   /// Generates a random password, respecting the constraints specified in the
   /// constructor.
   pub fn gen(&self) -> String {
+    self.gen_with_rng(&mut OsRng)
+  }
+
+  /// Generates `n` random passwords like `gen`, validating the options only
+  /// once.
+  pub fn gen_n(&self, n: usize) -> Vec<String> {
+    std::iter::repeat_with(|| self.gen()).take(n).collect()
+  }
+
+  /// Generates a random password like `gen`, together with its entropy,
+  /// composition, and policy fingerprint.
+  pub fn gen_with_meta(&self) -> GeneratedPassword {
+    GeneratedPassword::new(self.gen(), &self.config)
+  }
+
+  /// Generates a random password like `gen`, drawing randomness from `rng`
+  /// instead of the operating system. `rng` must be cryptographically secure
+  /// for the password to be.
+  pub fn gen_with_rng<R: Rng + ?Sized>(&self, rng: &mut R) -> String {
+    loop {
+      let password = self.gen_candidate(rng);
+      if !self.config.reject_structures || find_structure(&password).is_none() {
+        return password;
+      }
+    }
+  }
+
+  /// Generates a password like `gen_with_rng`, without rejecting simple
+  /// structures.
+  fn gen_candidate<R: Rng + ?Sized>(&self, rng: &mut R) -> String {
     let mut chars: Vec<char> = Vec::with_capacity(self.length);
 
-    Self::add_random_chars(&mut chars, &self.upper, self.options.min_upper);
-    Self::add_random_chars(&mut chars, &self.lower, self.options.min_lower);
-    Self::add_random_chars(&mut chars, &self.digit, self.options.min_digit);
-    Self::add_random_chars(&mut chars, &self.special, self.options.min_special);
+    for class in &self.config.classes {
+      Self::add_random_chars(&mut chars, &class.chars, class.min, rng);
+    }
 
+    let required = chars.len();
+    let fill = self.config.fill.as_ref().unwrap_or(&self.config.charset);
     chars.extend(
       std::iter::repeat_with(|| {
-        *self
-          .charset
-          .choose(&mut OsRng)
-          .expect("Filtered charset is nonempty")
+        *fill.choose(rng).expect("Filtered charset is nonempty")
       })
       .take(self.length - chars.len()),
     );
 
-    chars.shuffle(&mut OsRng);
+    let Some(first) = &self.config.first else {
+      chars.shuffle(rng);
+      return chars.into_iter().collect();
+    };
+    if !chars.iter().any(|c| first.contains(c)) {
+      // A character beyond the minimums, which none of them depends on.
+      chars[required] = *first.choose(rng).expect("First class is nonempty");
+    }
+    chars.shuffle(rng);
+    let starts: Vec<usize> = (0..chars.len())
+      .filter(|&i| first.contains(&chars[i]))
+      .collect();
+    let start = *starts.choose(rng).expect("A first character is present");
+    chars.swap(0, start);
 
     chars.into_iter().collect()
   }
 
-  fn add_random_chars(chars: &mut Vec<char>, range: &[char], count: usize) {
-    chars.extend((0..count).filter_map(|_| range.choose(&mut OsRng)));
+  fn add_random_chars<R: Rng + ?Sized>(
+    chars: &mut Vec<char>,
+    range: &[char],
+    count: usize,
+    rng: &mut R,
+  ) {
+    chars.extend((0..count).filter_map(|_| range.choose(rng)));
+  }
+
+  /// Checks `length` and `options` against every constraint, without
+  /// stopping at the first violation.
+  ///
+  /// Returns a `ValidationReport` listing all problems found, so that they
+  /// can be fixed at once, along with the minimal changes that fix them.
+  /// `PwdGen::new` succeeds if and only if the report is valid.
+  pub fn validate(length: usize, options: &PwdGenOptions) -> ValidationReport {
+    let (_, errors) = options.check(length);
+    let fixes = crate::fix::find(length, options);
+    ValidationReport::new(errors, options.conflicts(), fixes)
   }
 
   fn validate_input(
     length: usize,
     options: &PwdGenOptions,
-  ) -> Result<CharacterSet, Error> {
-    if length < MIN_LENGTH {
-      return Err(Error::Length);
-    }
-
-    let min_total = checked_sum(
-      [
-        options.min_upper,
-        options.min_lower,
-        options.min_digit,
-        options.min_special,
-      ]
-      .iter()
-      .cloned(),
-    );
-    if min_total.is_none() || min_total.unwrap() > length {
-      return Err(Error::MinLimitExceeded);
-    }
-
-    let exclude: Option<HashSet<char>> =
-      Some(options.exclude.unwrap_or("").chars().collect());
-
-    let upper = filtered_range('A'..='Z', &exclude);
-    if upper.len() < options.min_upper {
-      return Err(Error::InsufficientCharacters("upper"));
-    }
-    let lower = filtered_range('a'..='z', &exclude);
-    if lower.len() < options.min_lower {
-      return Err(Error::InsufficientCharacters("lower"));
-    }
-    let digit = filtered_range('0'..='9', &exclude);
-    if digit.len() < options.min_digit {
-      return Err(Error::InsufficientCharacters("digit"));
-    }
-    let special = filtered_range(SPECIAL_CHARS.iter().cloned(), &exclude);
-    if special.len() < options.min_special {
-      return Err(Error::InsufficientCharacters("special"));
-    }
-
-    Ok(CharacterSet {
-      upper,
-      lower,
-      digit,
-      special,
-    })
+  ) -> Result<EffectiveConfig, Error> {
+    options.validate(length)
   }
 
   pub fn length(&self) -> usize {
     self.length
   }
 
-  pub fn options(&self) -> &PwdGenOptions {
+  pub fn options(&self) -> &PwdGenOptions<'a> {
     &self.options
   }
+
+  /// Returns the effective configuration used for generation.
+  pub fn config(&self) -> &EffectiveConfig {
+    &self.config
+  }
+
+  /// Describes the generator: its length, the classes it draws from after
+  /// exclusions, the exclusions, the entropy, and its constraints.
+  pub fn describe(&self) -> GeneratorDescription {
+    GeneratorDescription {
+      length: self.length,
+      classes: self.config.classes.clone(),
+      excluded: unique(self.options.exclude.as_deref().unwrap_or("").chars()),
+      charset_size: self.config.charset.len(),
+      entropy_bits: self.config.entropy_bits(),
+      constraints: self.options.requirements(),
+      warnings: self.warnings(),
+    }
+  }
 }
 
 pub fn gen(
@@ -202,9 +637,10 @@ pub fn gen(
   Ok(pwdgen.gen())
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "builtin-charsets"))]
 mod tests {
   use super::*;
+  use crate::SPECIAL_CHARS;
 
   #[test]
   fn test_password_length() {
@@ -214,6 +650,25 @@ mod tests {
     assert_eq!(password.len(), length);
   }
 
+  #[test]
+  fn test_gen_n() {
+    let passwords = PwdGen::new(12, None).unwrap().gen_n(5);
+    assert_eq!(passwords.len(), 5);
+    assert!(passwords.iter().all(|password| password.len() == 12));
+    assert_eq!(crate::util::unique(passwords.iter()).len(), 5);
+  }
+
+  #[test]
+  fn test_gen_with_rng_is_deterministic_for_a_seed() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let pwdgen = PwdGen::new(16, None).unwrap();
+    let password = pwdgen.gen_with_rng(&mut StdRng::seed_from_u64(7));
+    assert_eq!(password.chars().count(), 16);
+    assert_eq!(password, pwdgen.gen_with_rng(&mut StdRng::seed_from_u64(7)));
+    assert_ne!(password, pwdgen.gen_with_rng(&mut StdRng::seed_from_u64(8)));
+  }
+
   #[test]
   fn test_minimum_length_password() {
     let pwdgen = PwdGen::new(MIN_LENGTH, None).unwrap();
@@ -243,6 +698,7 @@ mod tests {
       min_digit: 3,
       min_special: 3,
       exclude: None,
+      ..Default::default()
     };
     let pwdgen = PwdGen::new(10, Some(options));
     assert!(matches!(pwdgen, Err(Error::MinLimitExceeded)));
@@ -256,6 +712,7 @@ mod tests {
       min_digit: 3,
       min_special: 3,
       exclude: None,
+      ..Default::default()
     };
 
     let pwdgen = PwdGen::new(15, Some(options)).unwrap();
@@ -263,11 +720,11 @@ mod tests {
 
     assert!(password.chars().filter(|c| c.is_uppercase()).count() >= 3);
     assert!(password.chars().filter(|c| c.is_lowercase()).count() >= 3);
-    assert!(password.chars().filter(|c| c.is_digit(10)).count() >= 3);
+    assert!(password.chars().filter(|c| c.is_ascii_digit()).count() >= 3);
     assert!(
       password
         .chars()
-        .filter(|c| SPECIAL_CHARS.contains(&c))
+        .filter(|c| SPECIAL_CHARS.contains(c))
         .count()
         >= 3
     );
@@ -281,7 +738,8 @@ mod tests {
       min_lower: 2,
       min_digit: 2,
       min_special: 2,
-      exclude: Some(exclude),
+      exclude: Some(exclude.into()),
+      ..Default::default()
     };
 
     let pwdgen = PwdGen::new(12, Some(options)).unwrap();
@@ -303,6 +761,7 @@ mod tests {
       min_digit: min_count,
       min_special: min_count,
       exclude: None,
+      ..Default::default()
     };
 
     let pwdgen = PwdGen::new(length, Some(options)).unwrap();
@@ -319,13 +778,13 @@ mod tests {
       min_count
     );
     assert_eq!(
-      password.chars().filter(|c| c.is_digit(10)).count(),
+      password.chars().filter(|c| c.is_ascii_digit()).count(),
       min_count
     );
     assert_eq!(
       password
         .chars()
-        .filter(|c| SPECIAL_CHARS.contains(&c))
+        .filter(|c| SPECIAL_CHARS.contains(c))
         .count(),
       min_count
     );
@@ -360,12 +819,12 @@ mod tests {
     let exclude: String = ('A'..='Z').collect();
     let options = PwdGenOptions {
       min_upper: 1,
-      exclude: Some(&exclude),
+      exclude: Some(exclude.into()),
       ..Default::default()
     };
     assert!(matches!(
       PwdGen::validate_input(10, &options),
-      Err(Error::InsufficientCharacters("upper"))
+      Err(Error::InsufficientCharacters(Category::Upper))
     ));
   }
 
@@ -374,12 +833,12 @@ mod tests {
     let exclude: String = ('a'..='z').collect();
     let options = PwdGenOptions {
       min_lower: 1,
-      exclude: Some(&exclude),
+      exclude: Some(exclude.into()),
       ..Default::default()
     };
     assert!(matches!(
       PwdGen::validate_input(10, &options),
-      Err(Error::InsufficientCharacters("lower"))
+      Err(Error::InsufficientCharacters(Category::Lower))
     ));
   }
 
@@ -388,12 +847,12 @@ mod tests {
     let exclude: String = ('0'..='9').collect();
     let options = PwdGenOptions {
       min_digit: 1,
-      exclude: Some(&exclude),
+      exclude: Some(exclude.into()),
       ..Default::default()
     };
     assert!(matches!(
       PwdGen::validate_input(10, &options),
-      Err(Error::InsufficientCharacters("digit"))
+      Err(Error::InsufficientCharacters(Category::Digit))
     ));
   }
 
@@ -402,12 +861,12 @@ mod tests {
     let exclude_special: String = SPECIAL_CHARS.iter().collect();
     let options = PwdGenOptions {
       min_special: 1,
-      exclude: Some(&exclude_special),
+      exclude: Some(exclude_special.into()),
       ..Default::default()
     };
     assert!(matches!(
       PwdGen::validate_input(10, &options),
-      Err(Error::InsufficientCharacters("special"))
+      Err(Error::InsufficientCharacters(Category::Special))
     ));
   }
 
@@ -427,11 +886,576 @@ mod tests {
       min_lower: 2,
       min_digit: 3,
       min_special: 0,
-      exclude: Some(&exclude),
+      exclude: Some(exclude.into()),
+      ..Default::default()
     };
     let options_clone = options.clone();
     let pwdgen = PwdGen::new(length, Some(options)).unwrap();
 
     assert_eq!(options_clone, *pwdgen.options());
   }
+
+  #[test]
+  fn test_custom_class_minimum() {
+    let classes = vec![CharClass {
+      name: "accents".into(),
+      chars: "\u{e9}\u{e8}".into(),
+      min: 2,
+    }];
+    let options = PwdGenOptions {
+      classes: classes.into(),
+      ..Default::default()
+    };
+
+    let pwdgen = PwdGen::new(10, Some(options)).unwrap();
+    let password = pwdgen.gen();
+
+    assert_eq!(password.chars().count(), 10);
+    assert!(
+      password
+        .chars()
+        .filter(|c| ['\u{e9}', '\u{e8}'].contains(c))
+        .count()
+        >= 2
+    );
+  }
+
+  #[test]
+  fn test_custom_class_counts_toward_min_total() {
+    let classes = vec![CharClass {
+      name: "letters".into(),
+      chars: "xyz".into(),
+      min: 5,
+    }];
+    let options = PwdGenOptions {
+      min_upper: 4,
+      classes: classes.into(),
+      ..Default::default()
+    };
+    assert!(matches!(
+      PwdGen::new(8, Some(options)),
+      Err(Error::MinLimitExceeded)
+    ));
+  }
+
+  #[test]
+  fn validate_input_insufficient_custom_chars() {
+    let classes = vec![CharClass {
+      name: "xyz".into(),
+      chars: "xyzx".into(),
+      min: 3,
+    }];
+    let options = PwdGenOptions {
+      exclude: Some("y".into()),
+      classes: classes.into(),
+      ..Default::default()
+    };
+    assert!(matches!(
+      PwdGen::validate_input(10, &options),
+      Err(Error::InsufficientCharacters(Category::Custom(name))) if name == "xyz"
+    ));
+  }
+
+  #[test]
+  fn validate_input_empty_charset() {
+    let exclude: String = ('A'..='Z')
+      .chain('a'..='z')
+      .chain('0'..='9')
+      .chain(SPECIAL_CHARS.iter().cloned())
+      .collect();
+    let options = PwdGenOptions {
+      exclude: Some(exclude.into()),
+      ..Default::default()
+    };
+    assert!(matches!(
+      PwdGen::validate_input(10, &options),
+      Err(Error::EmptyCharset)
+    ));
+  }
+
+  #[test]
+  fn test_validate_reports_every_problem() {
+    let exclude: String = ('0'..='9').collect();
+    let options = PwdGenOptions {
+      min_upper: 4,
+      min_digit: 4,
+      exclude: Some(exclude.into()),
+      ..Default::default()
+    };
+
+    let report = PwdGen::validate(6, &options);
+
+    assert!(!report.is_valid());
+    assert!(matches!(
+      report.errors(),
+      [
+        Error::Length,
+        Error::MinLimitExceeded,
+        Error::InsufficientCharacters(Category::Digit)
+      ]
+    ));
+  }
+
+  #[test]
+  fn test_validate_valid_options() {
+    let options = PwdGenOptions {
+      min_upper: 2,
+      ..Default::default()
+    };
+    let report = PwdGen::validate(12, &options);
+    assert!(report.is_valid());
+    assert!(report.errors().is_empty());
+  }
+
+  #[test]
+  fn test_options_validate_effective_config() {
+    let options = PwdGenOptions {
+      min_digit: 2,
+      exclude: Some("0123".into()),
+      ..Default::default()
+    };
+
+    let config = options.validate(16).unwrap();
+
+    assert_eq!(config.length, 16);
+    assert_eq!(config.classes[2].category, Category::Digit);
+    assert_eq!(config.classes[2].chars, vec!['4', '5', '6', '7', '8', '9']);
+    assert_eq!(config.classes[2].min, 2);
+    assert_eq!(config.charset.len(), 26 + 26 + 6 + SPECIAL_CHARS.len());
+  }
+
+  #[test]
+  fn test_options_validate_first_error() {
+    let options = PwdGenOptions {
+      min_upper: 20,
+      ..Default::default()
+    };
+    assert!(matches!(options.validate(4), Err(Error::Length)));
+  }
+
+  #[test]
+  fn test_generator_config_matches_validate() {
+    let options = PwdGenOptions {
+      exclude: Some("abc".into()),
+      ..Default::default()
+    };
+    let config = options.validate(12).unwrap();
+    let pwdgen = PwdGen::new(12, Some(options)).unwrap();
+    assert_eq!(*pwdgen.config(), config);
+  }
+
+  #[test]
+  fn test_effective_classes() {
+    let options = PwdGenOptions {
+      min_digit: 20,
+      exclude: Some("0123456789".into()),
+      ..Default::default()
+    };
+    assert!(options.validate(12).is_err());
+
+    let classes = options.effective_classes();
+    assert_eq!(classes.len(), 4);
+    assert_eq!(classes[2].category, Category::Digit);
+    assert!(classes[2].chars.is_empty());
+    assert_eq!(classes[2].min, 20);
+    assert_eq!(classes[0].chars.len(), 26);
+  }
+
+  #[test]
+  fn test_describe() {
+    let options = PwdGenOptions {
+      min_digit: 2,
+      exclude: Some("0011".into()),
+      ..Default::default()
+    };
+    let description = PwdGen::new(10, Some(options)).unwrap().describe();
+
+    assert_eq!(description.length, 10);
+    assert_eq!(description.classes[2].category, Category::Digit);
+    assert_eq!(description.classes[2].chars.len(), 8);
+    assert_eq!(description.excluded, vec!['0', '1']);
+    assert_eq!(description.charset_size, 92);
+    assert_eq!(description.entropy_bits, 10.0 * 92f64.log2());
+    assert_eq!(
+      description.constraints,
+      vec!["at least 2 digits", "excludes: 0011"]
+    );
+  }
+
+  #[test]
+  fn test_new_with_warnings() {
+    let (_, warnings) = PwdGen::new_with_warnings(32, None).unwrap();
+    assert!(warnings.is_empty());
+
+    let options = PwdGenOptions {
+      min_upper: 4,
+      min_digit: 4,
+      ..Default::default()
+    };
+    let (_, warnings) = PwdGen::new_with_warnings(8, Some(options)).unwrap();
+    assert!(warnings.contains(&Warning::FixedComposition));
+  }
+
+  #[test]
+  fn test_conflicts() {
+    let options = PwdGenOptions {
+      min_digit: 2,
+      min_upper: 1,
+      exclude: Some("012345678#".into()),
+      classes: Cow::Owned(vec![CharClass {
+        name: "symbols".into(),
+        chars: "#%".into(),
+        min: 1,
+      }]),
+      ..Default::default()
+    };
+    let report = PwdGen::validate(16, &options);
+    assert_eq!(report.errors().len(), 1);
+    assert_eq!(
+      report.conflicts(),
+      [
+        Conflict::ClassEmptied {
+          category: Category::Digit,
+          min: 2,
+          available: 1,
+          excluded: "012345678".chars().collect(),
+        },
+        Conflict::RequiredCharsExcluded {
+          category: Category::Custom("symbols".to_string()),
+          excluded: vec!['#'],
+        },
+      ]
+    );
+
+    let options = PwdGenOptions {
+      min_digit: 2,
+      charset: Some("abc0".into()),
+      ..Default::default()
+    };
+    assert!(options.conflicts().is_empty());
+  }
+
+  #[test]
+  fn test_unused_exclusions_warning() {
+    let options = PwdGenOptions {
+      exclude: Some("a§bA§".into()),
+      charset: Some("abcdef0123456789".into()),
+      ..Default::default()
+    };
+    let (_, warnings) = PwdGen::new_with_warnings(32, Some(options)).unwrap();
+    assert_eq!(warnings, vec![Warning::UnusedExclusions(vec!['§', 'A'])]);
+
+    let options = PwdGenOptions {
+      exclude: Some("abc".into()),
+      ..Default::default()
+    };
+    let (_, warnings) = PwdGen::new_with_warnings(32, Some(options)).unwrap();
+    assert!(warnings.is_empty());
+  }
+
+  #[test]
+  fn test_min_entropy_bits() {
+    let options = PwdGenOptions {
+      min_entropy_bits: 80,
+      ..Default::default()
+    };
+    assert!(PwdGen::new(13, Some(options.clone())).is_ok());
+    assert!(matches!(
+      PwdGen::new(12, Some(options)),
+      Err(Error::EntropyTooLow { required: 80, .. })
+    ));
+  }
+
+  #[test]
+  fn test_fill_classes() {
+    let options = PwdGenOptions {
+      min_special: 3,
+      fill_classes: Some(
+        vec![Category::Upper, Category::Lower, Category::Digit].into(),
+      ),
+      ..Default::default()
+    };
+    assert_eq!(
+      options.to_string(),
+      "at least 3 special, rest from: upper, lower, digit"
+    );
+
+    let config = options.validate(16).unwrap();
+    assert_eq!(config.fill.as_ref().map(Vec::len), Some(62));
+    assert!(
+      config.entropy_bits() < 16.0 * (config.charset.len() as f64).log2()
+    );
+
+    let generator = PwdGen::new(16, Some(options)).unwrap();
+    for _ in 0..20 {
+      let password = generator.gen();
+      let specials = password.chars().filter(|c| SPECIAL_CHARS.contains(c));
+      assert_eq!(specials.count(), 3);
+    }
+  }
+
+  #[test]
+  fn test_fill_classes_errors() {
+    let options = PwdGenOptions {
+      fill_classes: Some(vec![Category::Custom("accents".to_string())].into()),
+      ..Default::default()
+    };
+    assert!(matches!(
+      options.validate(12),
+      Err(Error::InvalidPolicy(message)) if message == "unknown fill class 'accents'"
+    ));
+
+    let options = PwdGenOptions {
+      min_lower: 2,
+      exclude: Some("0123456789".into()),
+      fill_classes: Some(vec![Category::Digit].into()),
+      ..Default::default()
+    };
+    assert!(matches!(options.validate(12), Err(Error::EmptyFill)));
+    let options = PwdGenOptions {
+      min_lower: 12,
+      ..options
+    };
+    assert!(options.validate(12).is_ok());
+  }
+
+  #[test]
+  fn test_first_char_class() {
+    let options = PwdGenOptions {
+      min_digit: 4,
+      first_char_class: Some(Category::Upper),
+      ..Default::default()
+    };
+    assert_eq!(options.to_string(), "at least 4 digits, starts with: upper");
+    let generator = PwdGen::new(12, Some(options)).unwrap();
+    for _ in 0..20 {
+      let password = generator.gen();
+      assert!(password.starts_with(|c: char| c.is_ascii_uppercase()));
+      assert!(password.chars().filter(char::is_ascii_digit).count() >= 4);
+    }
+
+    // Every position is required, so the first is one of the digits.
+    let options = PwdGenOptions {
+      min_digit: 8,
+      first_char_class: Some(Category::Digit),
+      ..Default::default()
+    };
+    assert!(PwdGen::new(8, Some(options.clone())).is_ok());
+    let options = PwdGenOptions {
+      first_char_class: Some(Category::Upper),
+      ..options
+    };
+    assert!(matches!(
+      options.validate(8),
+      Err(Error::FirstCharClass(Category::Upper))
+    ));
+  }
+
+  #[test]
+  fn test_first_char_class_errors() {
+    let options = PwdGenOptions {
+      exclude: Some("0123456789".into()),
+      first_char_class: Some(Category::Digit),
+      ..Default::default()
+    };
+    assert!(matches!(
+      options.validate(12),
+      Err(Error::InsufficientCharacters(Category::Digit))
+    ));
+
+    let options = PwdGenOptions {
+      first_char_class: Some(Category::Custom("alpha".to_string())),
+      ..Default::default()
+    };
+    assert!(matches!(
+      options.validate(12),
+      Err(Error::InvalidPolicy(message))
+        if message == "unknown first character class 'alpha'"
+    ));
+  }
+
+  #[test]
+  fn test_reject_structures() {
+    let options = PwdGenOptions {
+      charset: Some("ab".into()),
+      reject_structures: true,
+      ..Default::default()
+    };
+    assert_eq!(options.to_string(), "only from: ab, no simple structures");
+    let generator = PwdGen::new(8, Some(options.clone())).unwrap();
+    for _ in 0..100 {
+      assert_eq!(find_structure(&generator.gen()), None);
+    }
+
+    let options = PwdGenOptions {
+      charset: Some("a".into()),
+      ..options
+    };
+    assert!(matches!(options.validate(8), Err(Error::InvalidPolicy(_))));
+  }
+
+  #[test]
+  fn test_avoid_ambiguous() {
+    let options = PwdGenOptions {
+      min_digit: 8,
+      avoid_ambiguous: true,
+      ..Default::default()
+    };
+    assert_eq!(
+      options.to_string(),
+      "at least 8 digits, no ambiguous characters"
+    );
+    let config = options.validate(16).unwrap();
+    assert!(!config.charset.iter().any(|&c| AMBIGUOUS_CHARS.contains(c)));
+    assert_eq!(config.classes[2].chars.len(), 8);
+
+    // Only two digits remain for a minimum of nine.
+    let options = PwdGenOptions {
+      min_digit: 9,
+      exclude: Some("2345".into()),
+      ..options
+    };
+    assert!(matches!(
+      options.conflicts()[..],
+      [Conflict::ClassEmptied { ref excluded, .. }]
+        if excluded.iter().collect::<String>() == "012345"
+    ));
+  }
+
+  #[test]
+  fn test_options_into_owned() {
+    let exclude = String::from("abc");
+    let options = PwdGenOptions {
+      min_lower: 1,
+      exclude: Some(exclude.as_str().into()),
+      classes: vec![CharClass {
+        name: "extra".into(),
+        chars: "\u{e9}".into(),
+        min: 1,
+      }]
+      .into(),
+      ..Default::default()
+    };
+
+    let owned: PwdGenOptions<'static> = options.clone().into_owned();
+    drop(options);
+    drop(exclude);
+
+    assert!(matches!(owned.exclude, Some(Cow::Owned(ref s)) if s == "abc"));
+    assert!(matches!(owned.classes, Cow::Owned(_)));
+    assert!(PwdGen::new(12, Some(owned)).is_ok());
+  }
+
+  #[test]
+  fn test_charset_restricts_alphabet() {
+    let options = PwdGenOptions {
+      min_lower: 2,
+      exclude: Some("c".into()),
+      charset: Some("abcc\u{e9}".into()),
+      ..Default::default()
+    };
+    let config = options.validate(16).unwrap();
+    assert_eq!(config.charset, vec!['a', 'b', '\u{e9}']);
+
+    let password = PwdGen::new(16, Some(options)).unwrap().gen();
+    assert!(password.chars().all(|c| "ab\u{e9}".contains(c)));
+    assert!(password.chars().filter(|c| c.is_ascii_lowercase()).count() >= 2);
+  }
+
+  #[test]
+  fn test_charset_insufficient_class() {
+    let options = PwdGenOptions {
+      min_digit: 1,
+      charset: Some("abc".into()),
+      ..Default::default()
+    };
+    assert!(matches!(
+      options.validate(8),
+      Err(Error::InsufficientCharacters(Category::Digit))
+    ));
+  }
+
+  #[test]
+  fn test_options_display() {
+    let options = PwdGenOptions {
+      min_upper: 2,
+      min_digit: 1,
+      exclude: Some("O0Il".into()),
+      ..Default::default()
+    };
+    assert_eq!(
+      options.to_string(),
+      "at least 2 uppercase, 1 digit, excludes: O0Il"
+    );
+  }
+
+  #[test]
+  fn test_options_display_custom_class() {
+    let options = PwdGenOptions {
+      min_digit: 3,
+      classes: vec![CharClass {
+        name: "accents".into(),
+        chars: "\u{e9}\u{e8}".into(),
+        min: 1,
+      }]
+      .into(),
+      ..Default::default()
+    };
+    assert_eq!(options.to_string(), "at least 3 digits, 1 from accents");
+  }
+
+  #[test]
+  fn test_default_options_display() {
+    assert_eq!(
+      PwdGenOptions::default().to_string(),
+      "no character requirements"
+    );
+  }
+}
+
+#[cfg(all(test, not(feature = "builtin-charsets")))]
+mod core_tests {
+  use super::*;
+
+  #[test]
+  fn test_no_classes_is_empty_charset() {
+    assert!(matches!(PwdGen::new(10, None), Err(Error::EmptyCharset)));
+  }
+
+  #[test]
+  fn test_builtin_minimum_is_insufficient() {
+    let options = PwdGenOptions {
+      min_upper: 1,
+      ..Default::default()
+    };
+    assert!(matches!(
+      PwdGen::new(10, Some(options)),
+      Err(Error::InsufficientCharacters(Category::Upper))
+    ));
+  }
+
+  #[test]
+  fn test_custom_classes_only() {
+    let classes = vec![
+      CharClass {
+        name: "letters".into(),
+        chars: "abc".into(),
+        min: 2,
+      },
+      CharClass {
+        name: "digits".into(),
+        chars: "123".into(),
+        min: 2,
+      },
+    ];
+    let options = PwdGenOptions {
+      classes: classes.into(),
+      ..Default::default()
+    };
+
+    let password = PwdGen::new(12, Some(options)).unwrap().gen();
+
+    assert_eq!(password.chars().count(), 12);
+    assert!(password.chars().all(|c| "abc123".contains(c)));
+  }
 }
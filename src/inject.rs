@@ -0,0 +1,174 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use rand::{seq::SliceRandom, Rng};
+
+use crate::Error;
+
+/// Numbers of digits and symbols to insert at random positions of a password
+/// or passphrase, rather than appended where attackers expect them. Parsed
+/// from specs such as `2d1s`, where a missing count means one.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Injection {
+  pub digits: usize,
+  pub symbols: usize,
+}
+
+impl std::str::FromStr for Injection {
+  type Err = Error;
+
+  fn from_str(spec: &str) -> Result<Self, Error> {
+    let invalid =
+      || Error::InvalidPolicy(format!("invalid injection spec '{}'", spec));
+    let mut injection = Injection::default();
+    let mut count = String::new();
+
+    for c in spec.chars().filter(|c| !c.is_whitespace()) {
+      if c.is_ascii_digit() {
+        count.push(c);
+        continue;
+      }
+      let n = match count.as_str() {
+        "" => 1,
+        count => count.parse::<usize>().map_err(|_| invalid())?,
+      };
+      count.clear();
+      let total = match c.to_ascii_lowercase() {
+        'd' => &mut injection.digits,
+        's' => &mut injection.symbols,
+        _ => return Err(invalid()),
+      };
+      *total = total.checked_add(n).ok_or_else(invalid)?;
+    }
+
+    if !count.is_empty() || injection == Injection::default() {
+      return Err(invalid());
+    }
+    Ok(injection)
+  }
+}
+
+impl Injection {
+  /// Returns `text` with the digits and symbols inserted at uniformly random
+  /// positions, each drawn uniformly from `digits` or `symbols`.
+  ///
+  /// # Panics
+  ///
+  /// Panics if characters are requested from an empty set.
+  pub fn apply<R: Rng + ?Sized>(
+    &self,
+    text: &str,
+    digits: &[char],
+    symbols: &[char],
+    rng: &mut R,
+  ) -> String {
+    let mut inserted: Vec<char> = std::iter::repeat_with(|| {
+      *digits.choose(rng).expect("Digits are nonempty")
+    })
+    .take(self.digits)
+    .collect();
+    inserted.extend(
+      std::iter::repeat_with(|| {
+        *symbols.choose(rng).expect("Symbols are nonempty")
+      })
+      .take(self.symbols),
+    );
+    inserted.shuffle(rng);
+
+    let mut chars: Vec<char> = text.chars().collect();
+    for c in inserted {
+      let position = rng.gen_range(0..=chars.len());
+      chars.insert(position, c);
+    }
+    chars.into_iter().collect()
+  }
+
+  /// Estimates the entropy, in bits, that `apply` adds to a text of `length`
+  /// characters, drawing from `digit_count` digits and `symbol_count`
+  /// symbols: the choice of each character and of their positions. An upper
+  /// bound, as some placements next to equal characters coincide.
+  pub fn entropy_bits(
+    &self,
+    length: usize,
+    digit_count: usize,
+    symbol_count: usize,
+  ) -> f64 {
+    let log2_factorial =
+      |n: usize| -> f64 { (2..=n).map(|k| (k as f64).log2()).sum() };
+    let bits = |count: usize, size: usize| match count {
+      0 => 0.0,
+      _ => count as f64 * (size as f64).log2(),
+    };
+    let total = length + self.digits + self.symbols;
+    let positions: f64 = log2_factorial(total)
+      - log2_factorial(length)
+      - log2_factorial(self.digits)
+      - log2_factorial(self.symbols);
+    bits(self.digits, digit_count)
+      + bits(self.symbols, symbol_count)
+      + positions
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::{rngs::StdRng, SeedableRng};
+
+  #[test]
+  fn test_parse() {
+    let parse = |spec: &str| spec.parse::<Injection>();
+    assert_eq!(
+      parse("2d1s").unwrap(),
+      Injection {
+        digits: 2,
+        symbols: 1
+      }
+    );
+    assert_eq!(
+      parse("S 3D d").unwrap(),
+      Injection {
+        digits: 4,
+        symbols: 1
+      }
+    );
+    for spec in ["", "2", "2x", "d2"] {
+      assert!(parse(spec).is_err(), "{}", spec);
+    }
+    assert_eq!(
+      parse("2x").unwrap_err().to_string(),
+      "Invalid policy: invalid injection spec '2x'. [Error::InvalidPolicy]"
+    );
+  }
+
+  #[test]
+  fn test_apply() {
+    let injection = Injection {
+      digits: 2,
+      symbols: 1,
+    };
+    let mut rng = StdRng::seed_from_u64(7);
+    let mut first_positions = Vec::new();
+    for _ in 0..50 {
+      let text = injection.apply("correcthorse", &['7'], &['!'], &mut rng);
+      assert_eq!(text.chars().count(), 15);
+      assert_eq!(text.replace(['7', '!'], ""), "correcthorse");
+      assert_eq!(text.matches('7').count(), 2);
+      first_positions.push(text.find(['7', '!']).unwrap());
+    }
+    assert!(first_positions.iter().any(|&i| i > 0));
+    assert!(first_positions.contains(&0));
+  }
+
+  #[test]
+  fn test_entropy_bits() {
+    let injection = Injection {
+      digits: 1,
+      symbols: 0,
+    };
+    // Ten digits at any of five positions.
+    let expected = 10f64.log2() + 5f64.log2();
+    assert!((injection.entropy_bits(4, 10, 32) - expected).abs() < 1e-9);
+  }
+}
@@ -3,13 +3,77 @@ Copyright 2024 Owain Davies
 SPDX-License-Identifier: Apache-2.0
 */
 #![doc = include_str!("../README.md")]
+mod account;
+#[cfg(feature = "proptest")]
+mod arbitrary;
+mod attack;
+mod case;
 mod charset;
+mod checksum;
+mod conflict;
 mod error;
+#[cfg(feature = "uniffi")]
+mod ffi;
+mod fix;
+mod generated;
 mod generator;
+mod inject;
+mod passphrase;
+mod pattern;
+mod pin;
+mod placement;
+mod policy;
+mod preset;
+mod pwquality;
+mod rng;
+mod strength;
+mod structure;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
 mod util;
+mod validation;
+mod warning;
 
+pub use account::{contains_forbidden_name, windows_forbidden_names};
+pub use attack::{
+  AttackEstimate, AttackerProfile, CrackTime, ATTACKER_PROFILES,
+};
+pub use case::{has_case, random_case_gain, randomize_case};
+#[cfg(feature = "builtin-charsets")]
 pub use charset::SPECIAL_CHARS;
+pub use charset::{Category, ExclusionGroup, AMBIGUOUS_CHARS};
+pub use checksum::Checksum;
+pub use conflict::Conflict;
 pub use error::Error;
+pub use fix::Fix;
+pub use generated::{CategoryCounts, GeneratedPassword};
 pub use generator::{
-  gen, PwdGen, PwdGenOptions, DEFAULT_PWDGEN_OPTIONS, MIN_LENGTH,
+  gen, CharClass, PwdGen, PwdGenOptions, DEFAULT_PWDGEN_OPTIONS, MIN_LENGTH,
+};
+pub use inject::Injection;
+pub use passphrase::{
+  PassphraseGen, DEFAULT_SEPARATOR, DEFAULT_WORD_COUNT, EFF_LARGE_WORDLIST,
+};
+pub use pin::{
+  pin_weakness, PinGen, PinWeakness, MAX_PIN_LENGTH, MIN_PIN_LENGTH,
+};
+pub use policy::{
+  ClassRequirement, EntropyComparison, LengthBounds, PasswordViolation, Policy,
+  PolicyDocument, SubsetViolation, POLICY_DOCUMENT_VERSION,
 };
+pub use preset::Preset;
+pub use pwquality::{PwQuality, PwQualityViolation};
+pub use rng::{gen_salt, rng_selftest};
+pub use strength::{
+  render_strength_bar, score, PatternKind, StrengthMatch, StrengthReport,
+  DEFAULT_BAR_WIDTH,
+};
+pub use structure::{find_structure, Structure};
+pub use util::parse_exclusions;
+pub use validation::{
+  EffectiveClass, EffectiveConfig, GeneratorDescription, ValidationReport,
+};
+pub use warning::{Warning, LOW_ENTROPY_BITS, SMALL_CHARSET_SIZE};
+
+#[cfg(feature = "uniffi")]
+uniffi::setup_scaffolding!();
@@ -2,45 +2,590 @@
 Copyright 2024 Owain Davies
 SPDX-License-Identifier: Apache-2.0
 */
-use clap::Parser;
-use pwdg::DEFAULT_PWDGEN_OPTIONS as DEF;
+use clap::{Parser, Subcommand};
+
+mod cli;
+
+use cli::{
+  BatchFormat, BundleFormat, ChecksumKind, OutputFormat, PolicyArgs,
+  PolicyCommand,
+};
 
 #[derive(Parser)]
 #[clap(about, version, author)]
 struct Cli {
-  /// Sets the length of the password. Must be at least 8.
-  #[clap(short, long, default_value_t = pwdg::MIN_LENGTH)]
-  length: usize,
-
-  /// Minimum number of uppercase characters (A to Z).
-  #[clap(long, default_value_t = DEF.min_upper)]
-  min_upper: usize,
-
-  /// Minimum number of lowercase characters (a to z).
-  #[clap(long, default_value_t = DEF.min_lower)]
-  min_lower: usize,
-
-  /// Minimum number of digit characters (0 to 9).
-  #[clap(long, default_value_t = DEF.min_digit)]
-  min_digit: usize,
-
-  /// Minimum number of special characters.
-  #[clap(long, default_value_t = DEF.min_special, help = &format!(
-    "Minimum number of special characters.\nSpecial characters: {}",
-    pwdg::SPECIAL_CHARS.iter().collect::<String>()
+  #[command(subcommand)]
+  command: Option<Command>,
+
+  #[command(flatten)]
+  policy: PolicyArgs,
+
+  /// Runs startup health tests on a sample of the system random number
+  /// generator's output before generating, and fails if the entropy source
+  /// appears to be broken. The output used for passwords is not tested.
+  #[clap(long, action = clap::ArgAction::SetTrue)]
+  rng_selftest: bool,
+
+  /// Mixes extra entropy into the randomness the password is drawn from:
+  /// hexadecimal bytes, or - to read bytes, e.g. typed at random, from
+  /// standard input. The password stays as strong as the operating system's
+  /// random number generator if this input is weak.
+  #[clap(
+    long,
+    value_name = "HEX",
+    conflicts_with_all = ["options_json", "save_profile", "from_csv"]
+  )]
+  extra_entropy: Option<String>,
+
+  /// Reads a JSON object of options in their long form, with an optional
+  /// count and profile, from a file or - for standard input, and prints a
+  /// JSON object of the passwords or the error.
+  #[clap(long, value_name = "PATH", conflicts_with = "PolicyArgs")]
+  options_json: Option<std::path::PathBuf>,
+
+  /// Number of passwords to generate, one per line, validating the options
+  /// once.
+  #[clap(
+    short,
+    long,
+    value_name = "N",
+    default_value_t = 1,
+    value_parser = clap::value_parser!(u64).range(1..),
+    conflicts_with_all = ["record", "name"]
+  )]
+  count: u64,
+
+  /// Prints a plain-language description of the password policy given by the
+  /// other options instead of generating a password.
+  #[clap(long, action = clap::ArgAction::SetTrue)]
+  explain: bool,
+
+  /// Checks the options and prints the characters each class draws from,
+  /// the entropy, and any warnings instead of generating a password.
+  #[clap(long, action = clap::ArgAction::SetTrue)]
+  dry_run: bool,
+
+  /// Prints the effective charset size, the entropy, and the number of
+  /// characters of each class, noting which minimums were binding, to
+  /// standard error.
+  #[clap(long, action = clap::ArgAction::SetTrue)]
+  stats: bool,
+
+  /// Prints a colored bar of the strength of the password, from 0 to 4 as
+  /// estimated by pwdg::score, to standard error.
+  #[clap(long, action = clap::ArgAction::SetTrue)]
+  meter: bool,
+
+  /// Saves the other options as a named profile in the configuration file,
+  /// for use with --profile, instead of generating a password.
+  #[clap(long, value_name = "NAME")]
+  save_profile: Option<String>,
+
+  /// Reads accounts from a CSV file and writes it to standard output with a
+  /// generated password for every row.
+  #[clap(long, value_name = "PATH")]
+  from_csv: Option<std::path::PathBuf>,
+
+  /// Column of --from-csv to write passwords to. Added if it does not exist.
+  #[clap(
+    long,
+    value_name = "NAME",
+    default_value = "password",
+    requires = "from_csv"
+  )]
+  password_column: String,
+
+  /// Lets nonempty cells of --from-csv columns named after an option, e.g.
+  /// length or min-digit, override that option for their row.
+  #[clap(long, action = clap::ArgAction::SetTrue, requires = "from_csv")]
+  override_columns: bool,
+
+  /// Prints the password through a template, e.g.
+  /// '{"user":"{{label}}","pass":"{{password}}"}'. Placeholders are
+  /// {{password}}, {{label}}, and {{length}}, and with --valid-days
+  /// {{created_at}} and {{expires_at}}.
+  #[clap(long)]
+  template: Option<String>,
+
+  /// Stamps the creation time and the time N days later when the password
+  /// expires, in seconds since the Unix epoch, into --template and the
+  /// attributes of --store secret-service, for rotation tooling.
+  #[clap(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+  valid_days: Option<u64>,
+
+  /// Chooses the case of every letter of the password at random, e.g. for
+  /// sites that require mixed case, and reports the estimated entropy this
+  /// adds. Minimums of classes with letters cannot be kept.
+  #[clap(long, action = clap::ArgAction::SetTrue)]
+  random_case: bool,
+
+  /// Generates the password with a fixed structure, one character per
+  /// symbol: U uppercase, L lowercase, d digit, s special, a any character,
+  /// or \ before a character for itself, e.g. ULLLddss, of at least 8
+  /// symbols. The length of the policy does not apply, but its minimums,
+  /// --first, and --reject-structures do, and --fill-classes is rejected.
+  #[clap(
+    long,
+    value_name = "PATTERN",
+    conflicts_with_all = ["random_case", "inject", "checksum"]
+  )]
+  pattern: Option<String>,
+
+  /// Inserts digits and symbols at random positions of the password rather
+  /// than at its end, e.g. 2d1s for two digits and a symbol, and reports the
+  /// estimated entropy this adds.
+  #[clap(long, value_name = "SPEC")]
+  inject: Option<pwdg::Injection>,
+
+  /// Records LABEL, the policy hash, and the creation time of the password,
+  /// never the password itself, in the metadata store, so that `pwdg due`
+  /// can list credentials to rotate. Replaces any earlier entry of LABEL.
+  #[clap(long, value_name = "LABEL")]
+  record: Option<String>,
+
+  /// Prints a receipt to standard error: the policy hash, the creation time,
+  /// and a SHA-256 commitment to the password, authenticated by an HMAC keyed
+  /// by the contents of this file, so that auditors holding the key can check
+  /// with `pwdg verify-receipt` that a credential was generated under the
+  /// policy without seeing it. Cannot be combined with --quiet, which would
+  /// hide it.
+  #[clap(long, value_name = "KEY_FILE")]
+  receipt: Option<std::path::PathBuf>,
+
+  /// Prints a salted commitment to the password to standard error, and its
+  /// salt on the next line. The issuer keeps only the commitment; revealing
+  /// the password and the salt later proves which credential was received.
+  /// Cannot be combined with --quiet, which would hide it.
+  #[clap(long, value_enum, value_name = "KIND")]
+  commit: Option<cli::CommitKind>,
+
+  /// Appends check characters to the password, so that typos made while
+  /// entering it by hand can be caught with `pwdg verify-checksum`.
+  #[clap(long, value_enum, value_name = "KIND")]
+  checksum: Option<ChecksumKind>,
+
+  /// Copies the password to the clipboard, using the first available of
+  /// pbcopy, wl-copy, xclip, xsel, and clip.exe, or $PWDG_COPY_COMMAND.
+  #[cfg(feature = "clipboard")]
+  #[clap(
+    long,
+    action = clap::ArgAction::SetTrue,
+    conflicts_with_all = ["options_json", "save_profile", "from_csv", "count"]
+  )]
+  copy: bool,
+
+  /// Clears the clipboard after this long, e.g. 45s or 2m, unless something
+  /// else has been copied since, from a process left running in the
+  /// background.
+  #[cfg(feature = "clipboard")]
+  #[clap(long, value_name = "TTL", value_parser = cli::parse_ttl, requires = "copy")]
+  clear_after: Option<std::time::Duration>,
+
+  /// With --copy, prints nothing, not even warnings, so that the password
+  /// never appears on screen. The exit status signals success.
+  #[cfg(feature = "clipboard")]
+  #[clap(
+    short,
+    long,
+    action = clap::ArgAction::SetTrue,
+    requires = "copy",
+    conflicts_with_all = ["receipt", "commit"]
+  )]
+  quiet: bool,
+
+  /// Creates or rotates the secret --name in a secret store instead of
+  /// printing it, printing only its identifier and version. The password
+  /// itself is stored, so options that change what is printed are rejected.
+  #[cfg(any(
+    feature = "aws",
+    feature = "dbus",
+    feature = "keychain",
+    feature = "wincred"
+  ))]
+  #[clap(
+    long,
+    value_enum,
+    requires = "name",
+    conflicts_with_all = [
+      "template", "format", "hash", "split_delivery", "checksum"
+    ]
+  )]
+  #[cfg_attr(feature = "clipboard", clap(conflicts_with = "copy"))]
+  store: Option<cli::Store>,
+
+  /// Account of the secret in --store keychain or wincred [default: the
+  /// current user].
+  #[cfg(any(
+    feature = "aws",
+    feature = "dbus",
+    feature = "keychain",
+    feature = "wincred"
   ))]
-  min_special: usize,
-
-  /// Characters to exclude from the overall character set used for password
-  /// generation.
-  #[clap(short, long)]
-  exclude: Option<String>,
-
-  /// Generates a password with at least 1 uppercase letter, 1 lowercase letter,
-  /// 1 digit, and 1 special character. This option overrides --min-upper,
-  /// --min-lower, --min-digit, and --min-special if they are also set.
-  #[clap(short, long, action = clap::ArgAction::SetTrue)]
-  strong: bool,
+  #[clap(long, requires = "store")]
+  account: Option<String>,
+
+  /// Prints the password as two random shares, one per line, to be sent
+  /// over different channels, e.g. email and SMS. Each share alone reveals
+  /// nothing but the length; `pwdg combine` reconstructs the password.
+  #[clap(
+    long,
+    action = clap::ArgAction::SetTrue,
+    conflicts_with_all = ["template", "format", "hash"]
+  )]
+  split_delivery: bool,
+
+  /// Output format of the password.
+  #[clap(long, value_enum, default_value_t, conflicts_with = "template")]
+  format: OutputFormat,
+
+  /// Prints a hash of the password on the line after it.
+  #[clap(long, value_enum, conflicts_with_all = ["template", "format"])]
+  hash: Option<cli::HashKind>,
+
+  #[command(flatten)]
+  argon2: cli::Argon2Args,
+
+  /// Name of the secret: the credential name for --format systemd-cred, or
+  /// the name in --store, e.g. prod/db/password. For --store wincred, the
+  /// target name may use {{user}}, {{account}}, and {{host}}.
+  #[clap(long)]
+  name: Option<String>,
+
+  /// Label substituted for {{label}} in --template.
+  #[clap(long, requires = "template")]
+  label: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+  /// Works with password policies.
+  #[command(subcommand)]
+  Policy(Box<PolicyCommand>),
+  /// Generates one password per spec of a file, each a label with options.
+  Batch {
+    /// Spec file: one `label: options` line per spec, or a `.toml` or
+    /// `.json` file of specs.
+    file: std::path::PathBuf,
+
+    /// Output format.
+    #[clap(long, value_enum, default_value_t)]
+    format: BatchFormat,
+
+    /// Sorts the passwords by label, for diffable output. Each keeps its
+    /// generation index in --format json and as {{index}} in --template.
+    #[clap(long, action = clap::ArgAction::SetTrue)]
+    sort: bool,
+
+    /// Shuffles the passwords, to decorrelate their order from the
+    /// generation sequence.
+    #[clap(long, action = clap::ArgAction::SetTrue, conflicts_with = "sort")]
+    shuffle_output: bool,
+
+    /// Directory to write secret files to, for --format docker-secret.
+    #[clap(
+      long,
+      value_name = "PATH",
+      required_if_eq("format", "docker-secret")
+    )]
+    dir: Option<std::path::PathBuf>,
+
+    /// Renders each password with a template instead of --format, replacing
+    /// {{label}}, {{password}}, and {{index}}.
+    #[clap(long)]
+    template: Option<String>,
+
+    /// Makes each password up to N characters longer than its spec's length,
+    /// at random.
+    #[clap(long, value_name = "N", default_value_t = 0)]
+    vary_length: usize,
+
+    /// Stamps the creation time and the time N days later when the passwords
+    /// expire, in seconds since the Unix epoch, into --format json as
+    /// created_at and expires_at, and into --template.
+    #[clap(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    valid_days: Option<u64>,
+
+    /// Encrypts each password to its owner instead, under --dir, with a
+    /// manifest.json. The file is a TOML table mapping each label to an age
+    /// recipient (age1... or ssh-...), encrypted to with age, or to
+    /// gpg:<key>, encrypted to with gpg.
+    #[clap(
+      long,
+      value_name = "PATH",
+      requires = "dir",
+      conflicts_with_all = ["format", "template"]
+    )]
+    recipients: Option<std::path::PathBuf>,
+  },
+  /// Generates a set of named secrets from a spec file, as for batch, and
+  /// prints them together.
+  Bundle {
+    /// Spec file naming each secret and its options.
+    #[clap(long, value_name = "PATH")]
+    spec: std::path::PathBuf,
+
+    /// Output format.
+    #[clap(long, value_enum, default_value_t)]
+    format: BundleFormat,
+
+    /// Directory to write secret files to, for --format docker-secret.
+    #[clap(
+      long,
+      value_name = "PATH",
+      required_if_eq("format", "docker-secret")
+    )]
+    dir: Option<std::path::PathBuf>,
+
+    /// Makes each secret up to N characters longer than its spec's length,
+    /// at random.
+    #[clap(long, value_name = "N", default_value_t = 0)]
+    vary_length: usize,
+
+    /// Encrypts each secret to its owner instead, as for batch.
+    #[clap(
+      long,
+      value_name = "PATH",
+      requires = "dir",
+      conflicts_with = "format"
+    )]
+    recipients: Option<std::path::PathBuf>,
+  },
+  /// Prints the characters of each class after presets and exclusions, with
+  /// their number and entropy per character, and those exclusions removed.
+  Charsets {
+    #[command(flatten)]
+    policy: Box<PolicyArgs>,
+  },
+  /// Regenerates the values of keys of a .env file in place, keeping a
+  /// backup, and prints which lines changed with their values masked.
+  Rotate {
+    /// The .env file, rewritten in place after copying it to <FILE>.bak.
+    #[clap(long, value_name = "PATH")]
+    file: std::path::PathBuf,
+
+    /// Comma-separated keys whose values to regenerate.
+    #[clap(long, value_name = "KEYS", value_delimiter = ',', required = true)]
+    keys: Vec<String>,
+
+    #[command(flatten)]
+    policy: Box<PolicyArgs>,
+  },
+  /// Checks the check characters of a password generated with --checksum,
+  /// given the same policy options. Exits with a nonzero status if invalid.
+  VerifyChecksum {
+    /// Password to check, or - to read it from standard input.
+    password: String,
+
+    /// Kind of checksum.
+    #[clap(long, value_enum, value_name = "KIND")]
+    checksum: ChecksumKind,
+
+    #[command(flatten)]
+    policy: Box<PolicyArgs>,
+  },
+  /// Checks the HMAC of a receipt printed by --receipt and, if given, that it
+  /// is for a password. Exits with a nonzero status if invalid.
+  VerifyReceipt {
+    /// The receipt, a line starting with pwdg-receipt-v1.
+    receipt: String,
+
+    /// File holding the HMAC key the receipt was issued with.
+    #[clap(long, value_name = "PATH")]
+    key: std::path::PathBuf,
+
+    /// Password the receipt should be for, or - to read it from standard
+    /// input.
+    #[clap(long)]
+    password: Option<String>,
+  },
+  /// Generates a diceware-style passphrase of words drawn at random from a
+  /// wordlist, easier to memorize than a password.
+  Passphrase {
+    /// Wordlist file: one word per line, or a diceware list such as the EFF
+    /// long wordlist, whose lines start with dice rolls. Defaults to the
+    /// embedded EFF long wordlist.
+    #[clap(long, value_name = "PATH")]
+    wordlist: Option<std::path::PathBuf>,
+
+    /// Number of words.
+    #[clap(long, value_name = "N", default_value_t = pwdg::DEFAULT_WORD_COUNT)]
+    words: usize,
+
+    /// Separator between the words.
+    #[clap(long, default_value = pwdg::DEFAULT_SEPARATOR)]
+    separator: String,
+  },
+  /// Lists the credentials recorded with --record at least N days ago, oldest
+  /// first, with their age and policy hash, so that they can be rotated.
+  Due {
+    /// Age in days from which a credential is due.
+    #[clap(long, value_name = "N", default_value_t = 90)]
+    days: u64,
+  },
+  /// Generates a numeric PIN, which may be shorter than a password.
+  Pin {
+    /// Number of digits, from 4 to 12.
+    #[clap(short, long, default_value_t = 6)]
+    length: usize,
+
+    /// Regenerates trivially weak PINs: repeated digits, e.g. 1111 or 1212,
+    /// sequences, e.g. 1234 or 8642, and those starting or ending with a
+    /// year from 1900 to 2099.
+    #[clap(long, action = clap::ArgAction::SetTrue)]
+    reject_weak: bool,
+  },
+  /// Reconstructs a password from the two shares printed by
+  /// --split-delivery, in either order.
+  Combine {
+    /// First share.
+    first: String,
+
+    /// Second share.
+    second: String,
+  },
+  /// Generates a password and serves it over plain HTTP at a URL with a
+  /// random token, printed on standard output, to hand it off across a LAN
+  /// without chat tools. Fails if it is not fetched in time. TLS is not
+  /// supported, so use it only on trusted networks.
+  Share {
+    /// Serves the password only to the first request for it, then exits.
+    #[clap(long, action = clap::ArgAction::SetTrue)]
+    once: bool,
+
+    /// How long to serve the password, e.g. 90s, 10m, or 1h.
+    #[clap(long, default_value = "10m", value_parser = cli::parse_ttl)]
+    ttl: std::time::Duration,
+
+    /// Address to listen on. Port 0 picks a free port. Defaults to this
+    /// machine only; give e.g. 0.0.0.0:0 to serve the network, over plain
+    /// HTTP.
+    #[clap(long, value_name = "ADDR", default_value = "127.0.0.1:0")]
+    listen: std::net::SocketAddr,
+
+    #[command(flatten)]
+    policy: Box<PolicyArgs>,
+  },
+  /// Checks the distribution of generated passwords for bias: the frequency
+  /// of each character against the others its classes give the same
+  /// probability, and of each class at each position against the others.
+  /// Prints anomalies with confidence intervals and exits with a nonzero
+  /// status if there are any.
+  Audit {
+    /// Audits the frequencies of characters and positions, the only mode.
+    #[clap(long, action = clap::ArgAction::SetTrue, required = true)]
+    distribution: bool,
+
+    /// Batch of passwords generated with the same policy options, one per
+    /// line, or - to read them from standard input, instead of generating
+    /// them.
+    #[clap(long, value_name = "PATH")]
+    input: Option<std::path::PathBuf>,
+
+    /// Number of passwords to generate and audit.
+    #[clap(
+      long,
+      value_name = "N",
+      default_value_t = 10000,
+      conflicts_with = "input"
+    )]
+    samples: usize,
+
+    #[command(flatten)]
+    policy: Box<PolicyArgs>,
+  },
+  /// Prompts twice for a password chosen by a person, without echo, and
+  /// checks it against the policy options as a generated password would be.
+  /// Prints the rules it breaks and its strength, and exits with a nonzero
+  /// status if it breaks any.
+  PromptNew {
+    #[command(flatten)]
+    policy: Box<PolicyArgs>,
+  },
+  /// Reads a pasted requirements paragraph, such as "Password must contain
+  /// at least one uppercase letter...", from standard input and prints the
+  /// policy inferred from it and the options that generate it, to check
+  /// before use. Exits with a nonzero status if the policy is invalid.
+  InferPolicy {
+    /// Prints the inferred policy as a policy document instead, e.g. for
+    /// --policy-file.
+    #[clap(long)]
+    json: bool,
+  },
+  /// Generates a random salt for hashing a password, from the same source as
+  /// passwords.
+  Salt {
+    /// Length of the salt, in bytes.
+    #[clap(long, default_value_t = 16)]
+    bytes: usize,
+
+    /// Encoding of the salt.
+    #[clap(long, value_enum, default_value_t)]
+    encode: cli::SaltEncoding,
+  },
+  /// Clears the clipboard for --clear-after, in the background, if it still
+  /// holds the text whose SHA-256 digest is read from standard input.
+  #[cfg(feature = "clipboard")]
+  #[command(hide = true)]
+  ClearClipboard {
+    #[clap(long, value_parser = cli::parse_ttl)]
+    after: std::time::Duration,
+  },
+  /// Speaks the Assuan pinentry protocol, so that gpg-agent gets generated
+  /// passphrases for new keys instead of prompting for them.
+  Pinentry {
+    /// Also copies each generated passphrase to the clipboard.
+    #[cfg(feature = "clipboard")]
+    #[clap(long, action = clap::ArgAction::SetTrue)]
+    copy: bool,
+
+    #[command(flatten)]
+    policy: Box<PolicyArgs>,
+  },
+  /// Answers newline-delimited JSON requests for passwords on a Unix domain
+  /// socket, keeping validated generators warm between requests.
+  #[cfg(unix)]
+  Daemon {
+    /// Path of the socket, created readable and writable by the owner only.
+    #[clap(long, value_name = "PATH")]
+    socket: std::path::PathBuf,
+
+    /// Profile that requests without a `profile` start from.
+    #[clap(long, value_name = "NAME")]
+    profile: Option<String>,
+  },
+  /// Serves password generation on the D-Bus session bus.
+  #[cfg(feature = "dbus")]
+  ServeDbus,
+  /// Serves password generation and policy validation over gRPC, as defined
+  /// by proto/pwdg.proto.
+  #[cfg(feature = "grpc")]
+  ServeGrpc {
+    /// Address to listen on.
+    #[clap(long, default_value = "127.0.0.1:50051")]
+    listen: std::net::SocketAddr,
+
+    /// Requires calls to carry the token in this file as an
+    /// `authorization: Bearer <token>` header.
+    #[clap(long, value_name = "PATH")]
+    token_file: Option<std::path::PathBuf>,
+
+    /// Maximum number of calls per second from each client address.
+    #[clap(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    rate_limit: Option<u32>,
+
+    /// Number of calls a client may make at once before --rate-limit applies
+    /// [default: the rate limit].
+    #[clap(long, value_name = "N", requires = "rate_limit")]
+    burst: Option<u32>,
+
+    /// Serves request metrics for Prometheus over HTTP on this address, as
+    /// /metrics.
+    #[clap(long, value_name = "ADDR")]
+    metrics_listen: Option<std::net::SocketAddr>,
+
+    #[clap(flatten)]
+    tls: cli::TlsArgs,
+  },
 }
 
 fn main() {
@@ -52,31 +597,300 @@ fn main() {
   }
 }
 
-fn run(cli: Cli) -> Result<(), pwdg::Error> {
-  let options = get_options(&cli)?;
-  let password = pwdg::gen(cli.length, Some(options))?;
+fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
+  // Before any subcommand, so that every path that draws randomness fails
+  // closed on a broken entropy source.
+  if cli.rng_selftest {
+    pwdg::rng_selftest()?;
+  }
 
-  println!("{}", password);
+  if let Some(command) = cli.command {
+    // Subcommands draw from the operating system and print their own
+    // output, so these would be silently ignored.
+    if cli.extra_entropy.is_some() {
+      return Err("--extra-entropy cannot be used with a subcommand.".into());
+    }
+    #[cfg(feature = "clipboard")]
+    if cli.copy {
+      return Err("--copy cannot be used with a subcommand.".into());
+    }
+    return match command {
+      Command::Policy(command) => cli::run_policy(*command),
+      Command::Batch {
+        file,
+        format,
+        sort,
+        shuffle_output,
+        dir,
+        template,
+        vary_length,
+        valid_days,
+        recipients,
+      } => cli::run_batch(
+        &file,
+        format,
+        if sort {
+          cli::BatchOrder::Sort
+        } else if shuffle_output {
+          cli::BatchOrder::Shuffle
+        } else {
+          cli::BatchOrder::Spec
+        },
+        dir.as_deref(),
+        template.as_deref(),
+        vary_length,
+        valid_days.map(cli::Validity::new),
+        recipients.as_deref(),
+      ),
+      Command::Bundle {
+        spec,
+        format,
+        dir,
+        vary_length,
+        recipients,
+      } => cli::run_bundle(
+        &spec,
+        format,
+        dir.as_deref(),
+        vary_length,
+        recipients.as_deref(),
+      ),
+      Command::Charsets { policy } => cli::run_charsets(&policy),
+      Command::Rotate { file, keys, policy } => {
+        cli::run_rotate(&file, &keys, &policy)
+      }
+      Command::VerifyChecksum {
+        password,
+        checksum,
+        policy,
+      } => cli::verify_checksum(checksum, &password, &policy),
+      Command::VerifyReceipt {
+        receipt,
+        key,
+        password,
+      } => cli::verify_receipt(&receipt, &key, password.as_deref()),
+      Command::Passphrase {
+        wordlist,
+        words,
+        separator,
+      } => cli::run_passphrase(wordlist.as_deref(), words, &separator),
+      Command::Due { days } => cli::run_due(days),
+      Command::Combine { first, second } => cli::run_combine(&first, &second),
+      Command::Share {
+        once,
+        ttl,
+        listen,
+        policy,
+      } => cli::run_share(listen, once, ttl, &policy),
+      Command::PromptNew { policy } => cli::run_prompt_new(&policy),
+      Command::Audit {
+        distribution: _,
+        input,
+        samples,
+        policy,
+      } => cli::run_audit(input.as_deref(), samples, &policy),
+      Command::Pin {
+        length,
+        reject_weak,
+      } => cli::run_pin(length, reject_weak),
+      Command::InferPolicy { json } => cli::run_infer_policy(json),
+      Command::Salt { bytes, encode } => cli::run_salt(bytes, encode),
+      #[cfg(feature = "clipboard")]
+      Command::ClearClipboard { after } => cli::run_clear_clipboard(after),
+      #[cfg(feature = "clipboard")]
+      Command::Pinentry { copy, policy } => cli::run_pinentry(&policy, copy),
+      #[cfg(not(feature = "clipboard"))]
+      Command::Pinentry { policy } => cli::run_pinentry(&policy),
+      #[cfg(unix)]
+      Command::Daemon { socket, profile } => {
+        cli::run_daemon(&socket, profile.as_deref())
+      }
+      #[cfg(feature = "dbus")]
+      Command::ServeDbus => cli::serve_dbus(),
+      #[cfg(feature = "grpc")]
+      Command::ServeGrpc {
+        listen,
+        token_file,
+        rate_limit,
+        burst,
+        metrics_listen,
+        tls,
+      } => cli::serve_grpc(
+        listen,
+        token_file.as_deref(),
+        rate_limit.map(|rate| cli::RateLimit::new(rate, burst.unwrap_or(rate))),
+        metrics_listen,
+        &tls,
+      ),
+    };
+  }
 
-  Ok(())
-}
+  if let Some(path) = &cli.options_json {
+    return cli::run_options_json(path);
+  }
+
+  if let Some(name) = &cli.save_profile {
+    return cli::save_profile(name, &cli.policy);
+  }
 
-fn get_options(cli: &Cli) -> Result<pwdg::PwdGenOptions, pwdg::Error> {
-  let mut options = pwdg::PwdGenOptions::default();
-
-  if cli.strong {
-    options.min_upper = 1;
-    options.min_lower = 1;
-    options.min_digit = 1;
-    options.min_special = 1;
-  } else {
-    options.min_upper = cli.min_upper;
-    options.min_lower = cli.min_lower;
-    options.min_digit = cli.min_digit;
-    options.min_special = cli.min_special;
+  if let Some(path) = &cli.from_csv {
+    return cli::run_accounts(
+      path,
+      &cli.password_column,
+      cli.override_columns,
+      &cli.policy,
+    );
   }
 
-  options.exclude = cli.exclude.as_deref();
+  let policy = cli.policy.policy()?;
 
-  Ok(options)
+  if cli.explain {
+    println!("{}", policy);
+    return Ok(());
+  }
+  if cli.dry_run {
+    return cli::dry_run(&policy);
+  }
+
+  #[cfg(feature = "clipboard")]
+  let quiet = cli.quiet;
+  #[cfg(not(feature = "clipboard"))]
+  let quiet = false;
+
+  let generator = policy.generator()?;
+  if !quiet {
+    for warning in generator.warnings() {
+      eprintln!("warning: {}", warning);
+    }
+    for conflict in policy.options.conflicts() {
+      eprintln!("warning: {}", conflict);
+    }
+  }
+
+  let filter = cli.policy.filter()?;
+  let mut rng: Box<dyn rand::RngCore> = match &cli.extra_entropy {
+    Some(extra) => {
+      if extra == "-" && cli.policy.reads_stdin() {
+        return Err(
+          "--extra-entropy and --exclude cannot both read standard input."
+            .into(),
+        );
+      }
+      Box::new(cli::mixed_rng(&cli::read_extra_entropy(extra)?))
+    }
+    None => Box::new(rand::rngs::OsRng),
+  };
+
+  for _ in 0..cli.count {
+    let mut password = match &cli.pattern {
+      Some(pattern) => PolicyArgs::generate_pattern_with(
+        &generator, pattern, &filter, &mut rng,
+      )?,
+      None => PolicyArgs::generate_with(&generator, &filter, &mut rng)?,
+    };
+    if cli.random_case {
+      let gain;
+      (password, gain) = cli::randomize_case(&password, &policy)?;
+      if !quiet {
+        eprintln!("random case: +{:.1} bits of entropy", gain);
+      }
+    }
+    if let Some(injection) = cli.inject {
+      let gain;
+      (password, gain) = cli::inject(&password, injection, &policy)?;
+      if !quiet {
+        eprintln!("inject: +{:.1} bits of entropy", gain);
+      }
+    }
+    if cli.stats {
+      cli::print_stats(&policy, &password)?;
+    }
+    if let Some(kind) = cli.checksum {
+      password = cli::append_checksum(kind, &password, &policy)?;
+    }
+    if cli.meter {
+      let score = pwdg::score(&password).score();
+      eprintln!(
+        "strength: {} {}/4",
+        pwdg::render_strength_bar(score, pwdg::DEFAULT_BAR_WIDTH),
+        score
+      );
+    }
+    if let Some(label) = &cli.record {
+      cli::record(label, &policy)?;
+    }
+    if let Some(kind) = cli.commit {
+      let (commitment, salt) = cli::commit(kind, &password);
+      eprintln!("commitment: {}", commitment);
+      eprintln!("commitment salt: {}", salt);
+    }
+    if let Some(key_file) = &cli.receipt {
+      let receipt = cli::issue_receipt(key_file, &policy, &password)?;
+      eprintln!("receipt: {}", receipt);
+    }
+
+    let validity = cli.valid_days.map(cli::Validity::new);
+    let output = match &cli.template {
+      Some(template) => {
+        let length = password.chars().count().to_string();
+        let validity: Vec<_> =
+          validity.iter().flat_map(cli::Validity::fields).collect();
+        let mut variables = vec![
+          ("password", password.as_str()),
+          ("label", cli.label.as_deref().unwrap_or("")),
+          ("length", length.as_str()),
+        ];
+        variables
+          .extend(validity.iter().map(|(name, value)| (*name, &**value)));
+        cli::render(template, &variables)?
+      }
+      None if cli.split_delivery => cli::split(&password).join("\n"),
+      None => match cli.hash {
+        Some(kind) => {
+          if !quiet {
+            if kind.is_insecure() {
+              eprintln!("warning: this hash is insecure; use it only in labs.");
+            }
+            if matches!(kind, cli::HashKind::Argon2id) && cli.argon2.is_weak() {
+              eprintln!("warning: these Argon2 costs are below the defaults.");
+            }
+          }
+          let hash = cli::hash_password(kind, &password, &cli.argon2)?;
+          format!("{}\n{}", password, hash)
+        }
+        None => {
+          cli::format_password(cli.format, &password, cli.name.as_deref())?
+        }
+      },
+    };
+
+    #[cfg(any(
+      feature = "aws",
+      feature = "dbus",
+      feature = "keychain",
+      feature = "wincred"
+    ))]
+    if let (Some(store), Some(name)) = (cli.store, &cli.name) {
+      return cli::push_secret(
+        store,
+        name,
+        cli.account.as_deref(),
+        &password,
+        validity.as_ref(),
+      );
+    }
+
+    #[cfg(feature = "clipboard")]
+    if cli.copy {
+      cli::copy(&output)?;
+      if let Some(after) = cli.clear_after {
+        cli::clear_clipboard_after(&output, after)?;
+      }
+    }
+    if !quiet {
+      println!("{}", output);
+    }
+  }
+
+  Ok(())
 }
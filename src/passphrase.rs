@@ -0,0 +1,189 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use std::collections::HashSet;
+
+use rand::{rngs::OsRng, seq::SliceRandom, Rng};
+
+use crate::Error;
+
+/// Default number of words of a passphrase: about 77.5 bits of entropy from
+/// a diceware list of 7776 words.
+pub const DEFAULT_WORD_COUNT: usize = 6;
+
+/// Default separator between the words of a passphrase.
+pub const DEFAULT_SEPARATOR: &str = "-";
+
+/// The EFF long wordlist of 7776 words, a diceware list. See
+/// `wordlists/README.md` for its license.
+pub const EFF_LARGE_WORDLIST: &str =
+  include_str!("../wordlists/eff_large_wordlist.txt");
+
+/// Generator of diceware-style passphrases: words drawn uniformly and
+/// independently from a wordlist, joined by a separator.
+#[derive(Debug, Clone)]
+pub struct PassphraseGen {
+  words: Vec<String>,
+  count: usize,
+  separator: String,
+}
+
+impl PassphraseGen {
+  /// Creates a generator of passphrases of `count` words of `words`, joined
+  /// by `separator`.
+  ///
+  /// # Errors
+  ///
+  /// Returns `Error::InvalidPolicy` if `count` is zero, or if `words` has
+  /// fewer than two words, an empty word, or a word more than once, as each
+  /// would make the entropy lower than estimated.
+  pub fn new(
+    words: Vec<String>,
+    count: usize,
+    separator: &str,
+  ) -> Result<Self, Error> {
+    let invalid = |reason: &str| Err(Error::InvalidPolicy(reason.to_string()));
+    if count == 0 {
+      return invalid("a passphrase must have at least one word");
+    }
+    if words.len() < 2 {
+      return invalid("a wordlist must have at least two words");
+    }
+    if words.iter().any(String::is_empty) {
+      return invalid("a wordlist cannot have empty words");
+    }
+    let mut seen = HashSet::new();
+    if let Some(word) = words.iter().find(|word| !seen.insert(*word)) {
+      return Err(Error::InvalidPolicy(format!(
+        "word '{}' appears more than once in the wordlist",
+        word
+      )));
+    }
+    Ok(Self {
+      words,
+      count,
+      separator: separator.to_string(),
+    })
+  }
+
+  /// Creates a generator of passphrases of `count` words of the EFF long
+  /// wordlist, joined by `separator`.
+  ///
+  /// # Errors
+  ///
+  /// Returns `Error::InvalidPolicy` if `count` is zero.
+  pub fn eff(count: usize, separator: &str) -> Result<Self, Error> {
+    Self::new(Self::parse_wordlist(EFF_LARGE_WORDLIST), count, separator)
+  }
+
+  /// Returns the words of `text`, one per nonempty line. Lines of a diceware
+  /// list, such as the EFF lists, start with their dice rolls, which are
+  /// dropped.
+  pub fn parse_wordlist(text: &str) -> Vec<String> {
+    text
+      .lines()
+      .filter_map(|line| {
+        let line = line.trim();
+        let word = match line.split_once(char::is_whitespace) {
+          Some((rolls, word)) if rolls.chars().all(|c| c.is_ascii_digit()) => {
+            word.trim()
+          }
+          _ => line,
+        };
+        (!word.is_empty()).then(|| word.to_string())
+      })
+      .collect()
+  }
+
+  /// Generates a passphrase using the operating system's random number
+  /// generator.
+  pub fn gen(&self) -> String {
+    self.gen_with_rng(&mut OsRng)
+  }
+
+  /// Generates a passphrase using `rng`.
+  pub fn gen_with_rng<R: Rng + ?Sized>(&self, rng: &mut R) -> String {
+    (0..self.count)
+      .map(|_| {
+        self
+          .words
+          .choose(rng)
+          .expect("Wordlist is nonempty")
+          .as_str()
+      })
+      .collect::<Vec<_>>()
+      .join(&self.separator)
+  }
+
+  /// Returns the entropy of a passphrase, in bits.
+  pub fn entropy_bits(&self) -> f64 {
+    self.count as f64 * (self.words.len() as f64).log2()
+  }
+
+  /// Returns the number of words of a passphrase.
+  pub fn count(&self) -> usize {
+    self.count
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::{rngs::StdRng, SeedableRng};
+
+  fn words(words: &[&str]) -> Vec<String> {
+    words.iter().map(|word| word.to_string()).collect()
+  }
+
+  #[test]
+  fn test_parse_wordlist() {
+    let text = "11111\tabacus\n11112 abdomen\n\n  zebra \n";
+    assert_eq!(
+      PassphraseGen::parse_wordlist(text),
+      ["abacus", "abdomen", "zebra"]
+    );
+  }
+
+  #[test]
+  fn test_gen() {
+    let gen =
+      PassphraseGen::new(words(&["apple", "berry", "cherry"]), 4, " ").unwrap();
+    let mut rng = StdRng::seed_from_u64(7);
+    for _ in 0..20 {
+      let passphrase = gen.gen_with_rng(&mut rng);
+      let parts: Vec<&str> = passphrase.split(' ').collect();
+      assert_eq!(parts.len(), 4);
+      assert!(parts
+        .iter()
+        .all(|w| ["apple", "berry", "cherry"].contains(w)));
+    }
+    assert!((gen.entropy_bits() - 4.0 * 3f64.log2()).abs() < 1e-9);
+  }
+
+  #[test]
+  fn test_eff() {
+    let gen = PassphraseGen::eff(DEFAULT_WORD_COUNT, " ").unwrap();
+    assert_eq!(gen.words.len(), 7776);
+    assert_eq!(gen.words.first().unwrap(), "abacus");
+    assert_eq!(gen.words.last().unwrap(), "zoom");
+    assert!((gen.entropy_bits() - 6.0 * 7776f64.log2()).abs() < 1e-9);
+    assert!(PassphraseGen::eff(0, " ").is_err());
+  }
+
+  #[test]
+  fn test_new_invalid() {
+    assert!(PassphraseGen::new(words(&["a", "b"]), 0, "-").is_err());
+    assert!(PassphraseGen::new(words(&["a"]), 6, "-").is_err());
+    assert!(PassphraseGen::new(words(&["a", ""]), 6, "-").is_err());
+    assert_eq!(
+      PassphraseGen::new(words(&["a", "b", "a"]), 6, "-")
+        .unwrap_err()
+        .to_string(),
+      concat!(
+        "Invalid policy: word 'a' appears more than once in the wordlist. ",
+        "[Error::InvalidPolicy]"
+      )
+    );
+  }
+}
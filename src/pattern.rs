@@ -0,0 +1,258 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use rand::{rngs::OsRng, seq::SliceRandom, Rng};
+
+use crate::placement::place;
+use crate::{find_structure, Category, Error, PwdGen, MIN_LENGTH};
+
+/// Number of passwords drawn for a pattern before giving up on one without a
+/// simple structure.
+const MAX_STRUCTURE_ATTEMPTS: usize = 1000;
+
+impl PwdGen<'_> {
+  /// Generates a random password with the structure of `pattern`, one
+  /// character per symbol, e.g. `ULLddss` for an uppercase letter, two
+  /// lowercase letters, two digits, and two special characters:
+  ///
+  /// - `U`: an uppercase character.
+  /// - `L`: a lowercase character.
+  /// - `d`: a digit character.
+  /// - `s`: a special character.
+  /// - `a`: any character of the charset.
+  /// - `\` followed by any character: that character.
+  ///
+  /// Characters are drawn from the classes after exclusions. The length of
+  /// the generator does not apply, but its minimums and first character
+  /// class do: before drawing any character, each class is assigned as many
+  /// positions that can hold it as its minimum, such as `a` positions, so
+  /// that generation never needs retries. Fails with `Error::Infeasible` if
+  /// the pattern leaves too few positions for them.
+  ///
+  /// Fails with `Error::Length` if the pattern has fewer than `MIN_LENGTH`
+  /// symbols. With `reject_structures`, passwords with a simple structure
+  /// are drawn again, and `fill_classes` is rejected with
+  /// `Error::InvalidPolicy`, as the pattern decides the class of every
+  /// position.
+  pub fn gen_from_pattern(&self, pattern: &str) -> Result<String, Error> {
+    self.gen_from_pattern_with_rng(pattern, &mut OsRng)
+  }
+
+  /// Generates a password like `gen_from_pattern`, drawing randomness from
+  /// `rng`. `rng` must be cryptographically secure for the password to be.
+  pub fn gen_from_pattern_with_rng<R: Rng + ?Sized>(
+    &self,
+    pattern: &str,
+    rng: &mut R,
+  ) -> Result<String, Error> {
+    let mut positions = self.parse_pattern(pattern)?;
+    if positions.len() < MIN_LENGTH {
+      return Err(Error::Length);
+    }
+    if self.options().fill_classes.is_some() {
+      return Err(Error::InvalidPolicy(
+        "fill classes do not apply to a pattern".to_string(),
+      ));
+    }
+    if let (Some(first), Some(category)) =
+      (&self.config().first, &self.options().first_char_class)
+    {
+      positions[0].retain(|c| first.contains(c));
+      if positions[0].is_empty() {
+        return Err(Error::Infeasible(format!(
+          "the first position of the pattern cannot hold a {} character",
+          category
+        )));
+      }
+    }
+    for _ in 0..MAX_STRUCTURE_ATTEMPTS {
+      let mut placed = positions.clone();
+      place(&mut placed, &self.config().classes, rng)?;
+      let password: String = placed
+        .iter()
+        .map(|chars| *chars.choose(rng).expect("Pattern sets are nonempty"))
+        .collect();
+      if !self.config().reject_structures || find_structure(&password).is_none()
+      {
+        return Ok(password);
+      }
+    }
+    Err(Error::Infeasible(
+      "the pattern gave only passwords with a simple structure".to_string(),
+    ))
+  }
+
+  /// Returns the estimated entropy, in bits, of a password generated with
+  /// `gen_from_pattern`, the sum of `log2(set size)` over its positions,
+  /// before any are assigned to minimums.
+  pub fn pattern_entropy_bits(&self, pattern: &str) -> Result<f64, Error> {
+    Ok(
+      self
+        .parse_pattern(pattern)?
+        .iter()
+        .map(|chars| (chars.len() as f64).log2())
+        .sum(),
+    )
+  }
+
+  /// Returns the characters each position of `pattern` is drawn from.
+  fn parse_pattern(&self, pattern: &str) -> Result<Vec<Vec<char>>, Error> {
+    if pattern.is_empty() {
+      return Err(Error::InvalidPolicy("empty pattern".to_string()));
+    }
+    let config = self.config();
+    let class = |category: Category| {
+      config
+        .classes
+        .iter()
+        .find(|class| class.category == category)
+        .map(|class| class.chars.clone())
+        .filter(|chars| !chars.is_empty())
+        .ok_or(Error::InsufficientCharacters(category))
+    };
+
+    let mut positions = Vec::new();
+    let mut symbols = pattern.chars();
+    while let Some(symbol) = symbols.next() {
+      positions.push(match symbol {
+        'U' => class(Category::Upper)?,
+        'L' => class(Category::Lower)?,
+        'd' => class(Category::Digit)?,
+        's' => class(Category::Special)?,
+        'a' => config.charset.clone(),
+        '\\' => vec![symbols.next().ok_or_else(|| {
+          Error::InvalidPolicy("pattern ends with an escape".to_string())
+        })?],
+        _ => {
+          return Err(Error::InvalidPolicy(format!(
+            "unknown pattern symbol '{}'",
+            symbol
+          )))
+        }
+      });
+    }
+    Ok(positions)
+  }
+}
+
+#[cfg(all(test, feature = "builtin-charsets"))]
+mod tests {
+  use super::*;
+  use crate::{PwdGenOptions, SPECIAL_CHARS};
+
+  #[test]
+  fn test_gen_from_pattern() {
+    let pwdgen = PwdGen::new(8, None).unwrap();
+    for _ in 0..100 {
+      let password: Vec<char> = pwdgen
+        .gen_from_pattern("ULLLddss")
+        .unwrap()
+        .chars()
+        .collect();
+      assert_eq!(password.len(), 8);
+      assert!(password[0].is_ascii_uppercase());
+      assert!(password[1..4].iter().all(char::is_ascii_lowercase));
+      assert!(password[4..6].iter().all(char::is_ascii_digit));
+      assert!(password[6..].iter().all(|c| SPECIAL_CHARS.contains(c)));
+    }
+
+    let password = pwdgen.gen_from_pattern(r"d\-\\aaaaaa").unwrap();
+    assert_eq!(&password[1..3], r"-\");
+    assert_eq!(password.chars().count(), 9);
+  }
+
+  #[test]
+  fn test_pattern_length() {
+    let pwdgen = PwdGen::new(8, None).unwrap();
+    assert!(matches!(pwdgen.gen_from_pattern("a"), Err(Error::Length)));
+    assert!(matches!(
+      pwdgen.gen_from_pattern("ULLddss"),
+      Err(Error::Length)
+    ));
+  }
+
+  #[test]
+  fn test_pattern_structures_and_fill() {
+    let options = PwdGenOptions {
+      reject_structures: true,
+      ..Default::default()
+    };
+    let pwdgen = PwdGen::new(8, Some(options)).unwrap();
+    for _ in 0..100 {
+      let password = pwdgen.gen_from_pattern("dddddddd").unwrap();
+      assert!(crate::find_structure(&password).is_none());
+    }
+    assert!(matches!(
+      pwdgen.gen_from_pattern(r"\a\a\a\a\a\a\a\a"),
+      Err(Error::Infeasible(_))
+    ));
+
+    let options = PwdGenOptions {
+      fill_classes: Some(vec![Category::Lower].into()),
+      ..Default::default()
+    };
+    let pwdgen = PwdGen::new(8, Some(options)).unwrap();
+    assert!(matches!(
+      pwdgen.gen_from_pattern("aaaaaaaa"),
+      Err(Error::InvalidPolicy(_))
+    ));
+  }
+
+  #[test]
+  fn test_invalid_pattern() {
+    let pwdgen = PwdGen::new(
+      8,
+      Some(PwdGenOptions {
+        exclude: Some("0123456789".into()),
+        ..Default::default()
+      }),
+    )
+    .unwrap();
+    assert!(matches!(
+      pwdgen.gen_from_pattern("UUUUUUUd"),
+      Err(Error::InsufficientCharacters(Category::Digit))
+    ));
+    assert!(matches!(
+      pwdgen.gen_from_pattern("UUUUUUUx"),
+      Err(Error::InvalidPolicy(_))
+    ));
+    assert!(matches!(
+      pwdgen.gen_from_pattern(r"U\"),
+      Err(Error::InvalidPolicy(_))
+    ));
+    assert!(pwdgen.gen_from_pattern("").is_err());
+  }
+
+  #[test]
+  fn test_pattern_minimums() {
+    let options = PwdGenOptions {
+      min_digit: 2,
+      min_upper: 1,
+      first_char_class: Some(Category::Lower),
+      ..Default::default()
+    };
+    let pwdgen = PwdGen::new(8, Some(options)).unwrap();
+    for _ in 0..100 {
+      let password = pwdgen.gen_from_pattern("aaaaaaaU").unwrap();
+      assert!(password.starts_with(|c: char| c.is_ascii_lowercase()));
+      assert!(password.chars().filter(char::is_ascii_digit).count() >= 2);
+    }
+    assert_eq!(
+      pwdgen.gen_from_pattern("aLLLLLdU").unwrap_err().to_string(),
+      "The constraints cannot all be met: the minimums of digit characters \
+       require 2 positions, but only 1 can hold them. [Error::Infeasible]"
+    );
+    assert!(matches!(
+      pwdgen.gen_from_pattern("UddaLLLL"),
+      Err(Error::Infeasible(_))
+    ));
+  }
+
+  #[test]
+  fn test_pattern_entropy_bits() {
+    let pwdgen = PwdGen::new(8, None).unwrap();
+    let bits = pwdgen.pattern_entropy_bits(r"Ld\-").unwrap();
+    assert!((bits - (26.0f64 * 10.0).log2()).abs() < 1e-9);
+  }
+}
@@ -0,0 +1,147 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use rand::{rngs::OsRng, Rng};
+
+use crate::{find_structure, Error, Structure};
+
+/// Shortest PIN that `PinGen` generates.
+pub const MIN_PIN_LENGTH: usize = 4;
+
+/// Longest PIN that `PinGen` generates.
+pub const MAX_PIN_LENGTH: usize = 12;
+
+/// Years that PINs commonly start or end with, such as birth years.
+const YEARS: std::ops::RangeInclusive<u32> = 1900..=2099;
+
+/// A pattern that makes a PIN trivially guessable.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PinWeakness {
+  /// The PIN repeats a shorter run of digits, e.g. `1111` or `1212`.
+  Repetition,
+  /// Every digit is the previous one shifted by the same step, e.g. `1234`
+  /// or `8642`.
+  Sequence,
+  /// The PIN starts or ends with a year from 1900 to 2099, e.g. `1987`.
+  Year,
+}
+
+impl std::fmt::Display for PinWeakness {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      PinWeakness::Repetition => write!(f, "repeated digits"),
+      PinWeakness::Sequence => write!(f, "sequence"),
+      PinWeakness::Year => write!(f, "year"),
+    }
+  }
+}
+
+/// Returns the weakness of the PIN `pin`, if any.
+pub fn pin_weakness(pin: &str) -> Option<PinWeakness> {
+  match find_structure(pin) {
+    Some(Structure::Repetition) => return Some(PinWeakness::Repetition),
+    Some(Structure::Sequence) => return Some(PinWeakness::Sequence),
+    _ => {}
+  }
+  let is_year = |digits: Option<&str>| {
+    digits
+      .and_then(|digits| digits.parse::<u32>().ok())
+      .is_some_and(|year| YEARS.contains(&year))
+  };
+  if is_year(pin.get(..4)) || is_year(pin.get(pin.len().saturating_sub(4)..)) {
+    return Some(PinWeakness::Year);
+  }
+  None
+}
+
+/// Generator of numeric PINs, which passwords cannot be: they are shorter
+/// than `MIN_LENGTH` and of digits only.
+#[derive(Debug, Clone, Copy)]
+pub struct PinGen {
+  length: usize,
+  reject_weak: bool,
+}
+
+impl PinGen {
+  /// Creates a generator of PINs of `length` digits, regenerating those with
+  /// a `PinWeakness` if `reject_weak` is set.
+  ///
+  /// # Errors
+  ///
+  /// Returns `Error::InvalidPolicy` if `length` is not from `MIN_PIN_LENGTH`
+  /// to `MAX_PIN_LENGTH`.
+  pub fn new(length: usize, reject_weak: bool) -> Result<Self, Error> {
+    if !(MIN_PIN_LENGTH..=MAX_PIN_LENGTH).contains(&length) {
+      return Err(Error::InvalidPolicy(format!(
+        "PIN length must be from {} to {} digits",
+        MIN_PIN_LENGTH, MAX_PIN_LENGTH
+      )));
+    }
+    Ok(Self {
+      length,
+      reject_weak,
+    })
+  }
+
+  /// Generates a PIN using the operating system's random number generator.
+  pub fn gen(&self) -> String {
+    self.gen_with_rng(&mut OsRng)
+  }
+
+  /// Generates a PIN using `rng`.
+  pub fn gen_with_rng<R: Rng + ?Sized>(&self, rng: &mut R) -> String {
+    loop {
+      let pin: String = (0..self.length)
+        .map(|_| char::from(b'0' + rng.gen_range(0..10)))
+        .collect();
+      if !self.reject_weak || pin_weakness(&pin).is_none() {
+        return pin;
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::{rngs::StdRng, SeedableRng};
+
+  #[test]
+  fn test_pin_weakness() {
+    assert_eq!(pin_weakness("1111"), Some(PinWeakness::Repetition));
+    assert_eq!(pin_weakness("121212"), Some(PinWeakness::Repetition));
+    assert_eq!(pin_weakness("1234"), Some(PinWeakness::Sequence));
+    assert_eq!(pin_weakness("8642"), Some(PinWeakness::Sequence));
+    assert_eq!(pin_weakness("1987"), Some(PinWeakness::Year));
+    assert_eq!(pin_weakness("200431"), Some(PinWeakness::Year));
+    assert_eq!(pin_weakness("311999"), Some(PinWeakness::Year));
+    assert_eq!(pin_weakness("7351"), None);
+    assert_eq!(pin_weakness("18007352"), None);
+  }
+
+  #[test]
+  fn test_gen() {
+    let mut rng = StdRng::seed_from_u64(7);
+    let gen = PinGen::new(4, true).unwrap();
+    for _ in 0..200 {
+      let pin = gen.gen_with_rng(&mut rng);
+      assert_eq!(pin.len(), 4);
+      assert!(pin.chars().all(|c| c.is_ascii_digit()));
+      assert_eq!(pin_weakness(&pin), None);
+    }
+  }
+
+  #[test]
+  fn test_new_invalid_length() {
+    assert!(PinGen::new(3, false).is_err());
+    assert!(PinGen::new(13, false).is_err());
+    assert_eq!(
+      PinGen::new(2, true).unwrap_err().to_string(),
+      concat!(
+        "Invalid policy: PIN length must be from 4 to 12 digits. ",
+        "[Error::InvalidPolicy]"
+      )
+    );
+  }
+}
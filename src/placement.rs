@@ -0,0 +1,219 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use rand::{seq::SliceRandom, Rng};
+use std::collections::HashSet;
+
+use crate::{EffectiveClass, Error};
+
+/// Restricts the characters that each of `positions` may be drawn from, so
+/// that any password drawn from them meets the minimum of every class of
+/// `classes`, before a single character is drawn. Each class is assigned as
+/// many positions as its minimum, among those that can hold one of its
+/// characters, chosen at random with `rng`.
+///
+/// The assignment is a bipartite matching, so it succeeds whenever one
+/// exists, and otherwise fails with `Error::Infeasible` naming classes whose
+/// minimums exceed the positions that can hold them. A position serves one
+/// class, so the search is conservative if classes overlap.
+pub(crate) fn place<R: Rng + ?Sized>(
+  positions: &mut [Vec<char>],
+  classes: &[EffectiveClass],
+  rng: &mut R,
+) -> Result<(), Error> {
+  let sets: Vec<HashSet<char>> = classes
+    .iter()
+    .map(|class| class.chars.iter().cloned().collect())
+    .collect();
+  let holds: Vec<Vec<bool>> = positions
+    .iter()
+    .map(|chars| {
+      sets
+        .iter()
+        .map(|set| chars.iter().any(|c| set.contains(c)))
+        .collect()
+    })
+    .collect();
+  // One unit for each character that a minimum requires.
+  let mut units: Vec<usize> = classes
+    .iter()
+    .enumerate()
+    .flat_map(|(i, class)| std::iter::repeat_n(i, class.min))
+    .collect();
+  let mut order: Vec<usize> = (0..positions.len()).collect();
+
+  // Whether a matching exists does not depend on the order of the search,
+  // so a search in a fixed order describes any infeasibility the same way
+  // every time.
+  Matching::new(&holds, &units, &order).solve(classes)?;
+  units.shuffle(rng);
+  order.shuffle(rng);
+  let owner = Matching::new(&holds, &units, &order).solve(classes)?;
+
+  for (position, owner) in owner.iter().enumerate() {
+    if let Some(unit) = owner {
+      positions[position].retain(|c| sets[units[*unit]].contains(c));
+    }
+  }
+  Ok(())
+}
+
+/// The state of Kuhn's algorithm, matching units of the minimums of classes
+/// to positions.
+struct Matching<'a> {
+  /// Whether each position can hold a character of each class.
+  holds: &'a [Vec<bool>],
+  /// Class of each unit.
+  units: &'a [usize],
+  /// Order in which positions are tried.
+  order: &'a [usize],
+  /// Unit assigned to each position, if any.
+  owner: Vec<Option<usize>>,
+  visited: Vec<bool>,
+}
+
+impl<'a> Matching<'a> {
+  fn new(
+    holds: &'a [Vec<bool>],
+    units: &'a [usize],
+    order: &'a [usize],
+  ) -> Self {
+    Matching {
+      holds,
+      units,
+      order,
+      owner: vec![None; holds.len()],
+      visited: Vec::new(),
+    }
+  }
+
+  /// Assigns every unit a position, and returns the unit assigned to each
+  /// position, if any.
+  fn solve(
+    mut self,
+    classes: &[EffectiveClass],
+  ) -> Result<Vec<Option<usize>>, Error> {
+    for unit in 0..self.units.len() {
+      self.visited = vec![false; self.holds.len()];
+      if !self.augment(unit) {
+        return Err(self.infeasible(unit, classes));
+      }
+    }
+    Ok(self.owner)
+  }
+
+  /// Assigns `unit` a position, moving other units to free one if needed.
+  /// Returns `false` if there is no way to.
+  fn augment(&mut self, unit: usize) -> bool {
+    for i in 0..self.order.len() {
+      let position = self.order[i];
+      if self.visited[position] || !self.holds[position][self.units[unit]] {
+        continue;
+      }
+      self.visited[position] = true;
+      if self.owner[position].is_none_or(|other| self.augment(other)) {
+        self.owner[position] = Some(unit);
+        return true;
+      }
+    }
+    false
+  }
+
+  /// Describes why `unit` could not be assigned. The failed search visited
+  /// every position that can hold the class of `unit` or of the units
+  /// occupying them, and those classes require more characters than there
+  /// are such positions.
+  fn infeasible(&self, unit: usize, classes: &[EffectiveClass]) -> Error {
+    let mut involved = vec![self.units[unit]];
+    for (position, owner) in self.owner.iter().enumerate() {
+      if let (true, Some(other)) = (self.visited[position], owner) {
+        involved.push(self.units[*other]);
+      }
+    }
+    involved.sort_unstable();
+    involved.dedup();
+
+    let names: Vec<String> = involved
+      .iter()
+      .map(|&i| classes[i].category.to_string())
+      .collect();
+    let available = self.visited.iter().filter(|&&visited| visited).count();
+    let required: usize = involved.iter().map(|&i| classes[i].min).sum();
+    Error::Infeasible(format!(
+      "the minimums of {} characters require {} positions, but only {} can \
+       hold them",
+      names.join(" and "),
+      required,
+      available
+    ))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::Category;
+  use rand::rngs::OsRng;
+
+  fn class(category: Category, chars: &str, min: usize) -> EffectiveClass {
+    EffectiveClass {
+      category,
+      chars: chars.chars().collect(),
+      min,
+    }
+  }
+
+  #[test]
+  fn test_place() {
+    let classes = [
+      class(Category::Upper, "AB", 1),
+      class(Category::Digit, "01", 2),
+    ];
+    let any: Vec<char> = "AB01xy".chars().collect();
+    for _ in 0..100 {
+      // Only the second position can hold an uppercase letter.
+      let mut positions =
+        vec![vec!['x'], any.clone(), vec!['0', 'y'], vec!['1', 'y']];
+      place(&mut positions, &classes, &mut OsRng).unwrap();
+      assert_eq!(positions[2], vec!['0']);
+      assert_eq!(positions[3], vec!['1']);
+      assert_eq!(positions[1], vec!['A', 'B']);
+    }
+  }
+
+  #[test]
+  fn test_place_infeasible() {
+    let classes = [
+      class(Category::Upper, "AB", 1),
+      class(Category::Digit, "01", 2),
+      class(Category::Lower, "ab", 0),
+    ];
+    let mut positions = vec![vec!['A', '0'], vec!['B', '1'], vec!['a']];
+    let error = place(&mut positions, &classes, &mut OsRng).unwrap_err();
+    assert_eq!(
+      error.to_string(),
+      "The constraints cannot all be met: the minimums of upper and digit \
+       characters require 3 positions, but only 2 can hold them. \
+       [Error::Infeasible]"
+    );
+  }
+
+  #[test]
+  fn test_place_overlapping_classes() {
+    let classes = [
+      class(Category::Lower, "ab", 1),
+      class(Category::Custom("first".to_string()), "a", 1),
+    ];
+    let mut positions = vec![vec!['a', 'b'], vec!['a', 'x']];
+    place(&mut positions, &classes, &mut OsRng).unwrap();
+    assert!(positions.contains(&vec!['a']));
+
+    // 'a' would meet both minimums, but a position serves one class only.
+    let mut positions = vec![vec!['a'], vec!['x']];
+    assert!(matches!(
+      place(&mut positions, &classes, &mut OsRng),
+      Err(Error::Infeasible(_))
+    ));
+  }
+}
@@ -0,0 +1,119 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+mod check;
+mod compare;
+mod document;
+mod entropy;
+mod import;
+mod infer;
+
+pub use check::PasswordViolation;
+pub use compare::SubsetViolation;
+pub use document::{
+  ClassRequirement, LengthBounds, PolicyDocument, POLICY_DOCUMENT_VERSION,
+};
+pub use entropy::EntropyComparison;
+
+use crate::{Error, PwdGen, PwdGenOptions, ValidationReport};
+
+/// A password policy: a password length together with the options that
+/// constrain its characters.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Policy {
+  pub length: usize,
+  pub options: PwdGenOptions<'static>,
+}
+
+impl Policy {
+  /// Creates a policy, taking ownership of any data borrowed by `options`.
+  pub fn new(length: usize, options: PwdGenOptions) -> Self {
+    Policy {
+      length,
+      options: options.into_owned(),
+    }
+  }
+
+  /// Checks the policy against every constraint. See `PwdGen::validate`.
+  pub fn validate(&self) -> ValidationReport {
+    PwdGen::validate(self.length, &self.options)
+  }
+
+  /// Creates a password generator for this policy.
+  pub fn generator(&self) -> Result<PwdGen<'static>, Error> {
+    PwdGen::new(self.length, Some(self.options.clone()))
+  }
+
+  /// Returns a stable fingerprint of the passwords the policy generates, so
+  /// that a credential can be traced to the policy it was generated under.
+  ///
+  /// Policies that differ only in how their exclusions are written share a
+  /// fingerprint. See `EffectiveConfig::fingerprint`.
+  pub fn fingerprint(&self) -> String {
+    self.options.check(self.length).0.fingerprint()
+  }
+}
+
+impl std::fmt::Display for Policy {
+  /// Describes the policy in plain language, e.g. "16+ characters, at least
+  /// 2 uppercase, 1 digit, excludes: O0Il", suitable for showing password
+  /// requirements to end users.
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    write!(f, "{}+ characters", self.length)?;
+    for requirement in self.options.requirements() {
+      write!(f, ", {}", requirement)?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[cfg(feature = "builtin-charsets")]
+  #[test]
+  fn test_fingerprint() {
+    let policy = |exclude: &str| {
+      Policy::new(
+        12,
+        PwdGenOptions {
+          exclude: Some(exclude.to_string().into()),
+          ..Default::default()
+        },
+      )
+    };
+
+    let fingerprint = policy("abc").fingerprint();
+    assert_eq!(fingerprint, policy("cba").fingerprint());
+    assert_ne!(fingerprint, policy("abcd").fingerprint());
+    assert_eq!(
+      fingerprint,
+      policy("abc").generator().unwrap().config().fingerprint()
+    );
+  }
+
+  #[test]
+  fn test_policy_display() {
+    let policy = Policy::new(
+      16,
+      PwdGenOptions {
+        min_upper: 2,
+        min_digit: 1,
+        exclude: Some("O0Il".into()),
+        ..Default::default()
+      },
+    );
+    assert_eq!(
+      policy.to_string(),
+      "16+ characters, at least 2 uppercase, 1 digit, excludes: O0Il"
+    );
+  }
+
+  #[test]
+  fn test_policy_display_length_only() {
+    let policy = Policy::new(12, PwdGenOptions::default());
+    assert_eq!(policy.to_string(), "12+ characters");
+  }
+}
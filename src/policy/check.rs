@@ -0,0 +1,316 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use std::collections::HashSet;
+
+use crate::{
+  find_structure, score, Category, EffectiveClass, Policy, Structure,
+};
+
+/// A reason why a password is not valid under a policy. Returned by
+/// `Policy::password_violations`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PasswordViolation {
+  /// The password is shorter than the policy's length.
+  Length { actual: usize, required: usize },
+  /// Characters of the password are not in the policy's charset after
+  /// exclusions.
+  DisallowedChars(Vec<char>),
+  /// The password has fewer characters of a class than the policy requires.
+  ClassMinimum {
+    category: Category,
+    actual: usize,
+    required: usize,
+  },
+  /// The password has a simple structure that the policy rejects.
+  Structure(Structure),
+  /// The first character is not in the policy's `first_char_class`.
+  FirstChar(Category),
+  /// Characters beyond the class minimums are not in the policy's
+  /// `fill_classes`.
+  FillChars(Vec<char>),
+  /// The estimated strength of the password, in bits as by `score`, is below
+  /// the policy's `min_entropy_bits`.
+  Entropy { actual: usize, required: usize },
+}
+
+impl std::fmt::Display for PasswordViolation {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      PasswordViolation::Length { actual, required } => write!(
+        f,
+        "password has {} characters, but at least {} are required",
+        actual, required
+      ),
+      PasswordViolation::DisallowedChars(chars) => write!(
+        f,
+        "characters {} are not allowed",
+        chars.iter().collect::<String>()
+      ),
+      PasswordViolation::ClassMinimum {
+        category,
+        actual,
+        required,
+      } => write!(
+        f,
+        "password has {} {} characters, but at least {} are required",
+        actual, category, required
+      ),
+      PasswordViolation::Structure(structure) => {
+        write!(f, "password is a {}", structure)
+      }
+      PasswordViolation::FirstChar(category) => {
+        write!(f, "first character is not from the {} class", category)
+      }
+      PasswordViolation::FillChars(chars) => write!(
+        f,
+        "characters {} are allowed only to meet a class minimum",
+        chars.iter().collect::<String>()
+      ),
+      PasswordViolation::Entropy { actual, required } => write!(
+        f,
+        "password has an estimated {} bits of entropy, but at least {} are \
+         required",
+        actual, required
+      ),
+    }
+  }
+}
+
+impl Policy {
+  /// Returns `true` if `password` is valid under this policy, as defined by
+  /// `is_subset_of`.
+  pub fn allows(&self, password: &str) -> bool {
+    self.password_violations(password).is_empty()
+  }
+
+  /// Returns every reason why `password` is not valid under this policy.
+  /// Empty if and only if `allows` is `true`.
+  pub fn password_violations(&self, password: &str) -> Vec<PasswordViolation> {
+    let config = self.options.check(self.length).0;
+    let classes = config.classes;
+    let mut violations = Vec::new();
+
+    let actual = password.chars().count();
+    if actual < self.length {
+      violations.push(PasswordViolation::Length {
+        actual,
+        required: self.length,
+      });
+    }
+
+    let charset: HashSet<char> = classes
+      .iter()
+      .flat_map(|c| c.chars.iter().cloned())
+      .collect();
+    let mut disallowed: Vec<char> = password
+      .chars()
+      .filter(|c| !charset.contains(c))
+      .collect::<HashSet<char>>()
+      .into_iter()
+      .collect();
+    if !disallowed.is_empty() {
+      disallowed.sort_unstable();
+      violations.push(PasswordViolation::DisallowedChars(disallowed));
+    }
+
+    for class in classes.iter().filter(|c| c.min > 0) {
+      let actual = password.chars().filter(|c| class.chars.contains(c)).count();
+      if actual < class.min {
+        violations.push(PasswordViolation::ClassMinimum {
+          category: class.category.clone(),
+          actual,
+          required: class.min,
+        });
+      }
+    }
+
+    if self.options.reject_structures {
+      if let Some(structure) = find_structure(password) {
+        violations.push(PasswordViolation::Structure(structure));
+      }
+    }
+
+    let mut rest = password.chars();
+    if let (Some(first), Some(category)) =
+      (&config.first, &self.options.first_char_class)
+    {
+      if !rest.next().is_some_and(|c| first.contains(&c)) {
+        violations.push(PasswordViolation::FirstChar(category.clone()));
+      }
+    }
+
+    if let Some(fill) = &config.fill {
+      // The first character is drawn from its own class, and every other
+      // character outside the fill must meet a class minimum.
+      let outside: Vec<char> = rest.filter(|c| !fill.contains(c)).collect();
+      if !fits_minimums(&outside, &classes) {
+        let mut chars: Vec<char> = outside
+          .into_iter()
+          .collect::<HashSet<char>>()
+          .into_iter()
+          .collect();
+        chars.sort_unstable();
+        violations.push(PasswordViolation::FillChars(chars));
+      }
+    }
+
+    if self.options.min_entropy_bits > 0 {
+      let actual = score(password).guesses_log2 as usize;
+      if actual < self.options.min_entropy_bits {
+        violations.push(PasswordViolation::Entropy {
+          actual,
+          required: self.options.min_entropy_bits,
+        });
+      }
+    }
+
+    violations
+  }
+}
+
+/// Returns `true` if each of `chars` can count towards the minimum of a
+/// different slot of `classes`, that is, if the classes can take every
+/// character without exceeding their minimums.
+fn fits_minimums(chars: &[char], classes: &[EffectiveClass]) -> bool {
+  if chars.len() > classes.iter().map(|class| class.min).sum() {
+    return false;
+  }
+  // Characters assigned to each class, found by augmenting paths.
+  let mut assigned: Vec<Vec<usize>> = vec![Vec::new(); classes.len()];
+  (0..chars.len()).all(|i| {
+    let mut visited = vec![false; classes.len()];
+    assign(i, chars, classes, &mut assigned, &mut visited)
+  })
+}
+
+fn assign(
+  i: usize,
+  chars: &[char],
+  classes: &[EffectiveClass],
+  assigned: &mut [Vec<usize>],
+  visited: &mut [bool],
+) -> bool {
+  for (j, class) in classes.iter().enumerate() {
+    if visited[j] || !class.chars.contains(&chars[i]) {
+      continue;
+    }
+    visited[j] = true;
+    if assigned[j].len() < class.min {
+      assigned[j].push(i);
+      return true;
+    }
+    for k in 0..assigned[j].len() {
+      if assign(assigned[j][k], chars, classes, assigned, visited) {
+        assigned[j][k] = i;
+        return true;
+      }
+    }
+  }
+  false
+}
+
+#[cfg(all(test, feature = "builtin-charsets"))]
+mod tests {
+  use super::*;
+  use crate::PwdGenOptions;
+
+  fn policy() -> Policy {
+    Policy::new(
+      10,
+      PwdGenOptions {
+        min_digit: 2,
+        exclude: Some("O0".into()),
+        ..Default::default()
+      },
+    )
+  }
+
+  #[test]
+  fn test_generated_passwords_are_allowed() {
+    let policy = policy();
+    let generator = policy.generator().unwrap();
+    for _ in 0..100 {
+      assert!(policy.allows(&generator.gen()));
+    }
+  }
+
+  #[test]
+  fn test_password_violations() {
+    assert_eq!(
+      policy().password_violations("Oops0 1"),
+      vec![
+        PasswordViolation::Length {
+          actual: 7,
+          required: 10
+        },
+        PasswordViolation::DisallowedChars(vec![' ', '0', 'O']),
+        PasswordViolation::ClassMinimum {
+          category: Category::Digit,
+          actual: 1,
+          required: 2
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn test_first_char_violation() {
+    let mut policy = policy();
+    policy.options.first_char_class = Some(Category::Upper);
+    assert!(policy.allows("Xk9qzvbwp8"));
+    assert_eq!(
+      policy.password_violations("9xkqzvbwp8"),
+      vec![PasswordViolation::FirstChar(Category::Upper)]
+    );
+  }
+
+  #[test]
+  fn test_fill_violation() {
+    let mut policy = policy();
+    policy.options.fill_classes = Some(vec![Category::Lower].into());
+    assert!(policy.allows("xk9qzvbwp8"));
+    assert_eq!(
+      policy.password_violations("xk9qzvbw78"),
+      vec![PasswordViolation::FillChars(vec!['7', '8', '9'])]
+    );
+    assert_eq!(
+      policy.password_violations("xK9qzvbwp8"),
+      vec![PasswordViolation::FillChars(vec!['8', '9', 'K'])]
+    );
+  }
+
+  #[test]
+  fn test_entropy_violation() {
+    let mut policy = policy();
+    policy.options.min_entropy_bits = 40;
+    assert!(policy.allows("xk9Qz#vbwp8"));
+    assert!(matches!(
+      policy.password_violations("password12")[..],
+      [PasswordViolation::Entropy { required: 40, .. }]
+    ));
+  }
+
+  #[test]
+  fn test_generated_passwords_meet_first_and_fill() {
+    let mut policy = policy();
+    policy.options.first_char_class = Some(Category::Upper);
+    policy.options.fill_classes = Some(vec![Category::Lower].into());
+    let generator = policy.generator().unwrap();
+    for _ in 0..100 {
+      assert!(policy.allows(&generator.gen()));
+    }
+  }
+
+  #[test]
+  fn test_structure_violation() {
+    let mut policy = policy();
+    assert!(policy.allows("ab12XX21ba"));
+    policy.options.reject_structures = true;
+    assert_eq!(
+      policy.password_violations("ab12XX21ba"),
+      vec![PasswordViolation::Structure(Structure::Palindrome)]
+    );
+  }
+}
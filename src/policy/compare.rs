@@ -0,0 +1,418 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use std::collections::HashSet;
+
+use crate::{Category, EffectiveClass, EffectiveConfig, Policy};
+
+/// A reason why some password valid under one policy is not valid under
+/// another. Returned by `Policy::subset_violations`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SubsetViolation {
+  /// The other policy requires longer passwords.
+  Length { actual: usize, required: usize },
+  /// Characters allowed by this policy are not allowed by the other.
+  DisallowedChars(Vec<char>),
+  /// This policy guarantees fewer characters of a class than the other
+  /// policy requires.
+  ClassMinimum {
+    category: Category,
+    guaranteed: usize,
+    required: usize,
+  },
+  /// Passwords may not start with a character of the other policy's
+  /// `first_char_class`.
+  FirstChar(Category),
+  /// Passwords may have characters outside the other policy's
+  /// `fill_classes` beyond its minimums.
+  Fill,
+  /// This policy requires less estimated entropy than the other.
+  Entropy { actual: usize, required: usize },
+  /// The other policy rejects simple structures, but this policy does not.
+  Structures,
+}
+
+impl std::fmt::Display for SubsetViolation {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      SubsetViolation::Length { actual, required } => write!(
+        f,
+        "passwords may have {} characters, but at least {} are required",
+        actual, required
+      ),
+      SubsetViolation::DisallowedChars(chars) => write!(
+        f,
+        "characters {} are allowed, but not by the other policy",
+        chars.iter().collect::<String>()
+      ),
+      SubsetViolation::ClassMinimum {
+        category,
+        guaranteed,
+        required,
+      } => write!(
+        f,
+        "only {} {} characters are guaranteed, but {} are required",
+        guaranteed, category, required
+      ),
+      SubsetViolation::FirstChar(category) => write!(
+        f,
+        "passwords may start with a character outside the {} class",
+        category
+      ),
+      SubsetViolation::Fill => write!(
+        f,
+        "passwords may have characters outside the fill classes of the \
+         other policy beyond its minimums"
+      ),
+      SubsetViolation::Entropy { actual, required } => write!(
+        f,
+        "at least {} bits of entropy are required, but only {} are checked",
+        required, actual
+      ),
+      SubsetViolation::Structures => write!(
+        f,
+        "simple structures are allowed, but not by the other policy"
+      ),
+    }
+  }
+}
+
+impl Policy {
+  /// Returns `true` if every password valid under this policy is also valid
+  /// under `other`, e.g. to check that a generation policy satisfies an
+  /// upstream requirement.
+  ///
+  /// A password is valid under a policy if `password_violations` is empty.
+  /// Constraints on the first character, the fill, and structures are
+  /// compared conservatively: a policy that does not plainly imply those of
+  /// `other` is reported as not a subset.
+  pub fn is_subset_of(&self, other: &Policy) -> bool {
+    self.subset_violations(other).is_empty()
+  }
+
+  /// Returns every reason why a password valid under this policy may not be
+  /// valid under `other`. Empty if and only if `is_subset_of` is `true`.
+  pub fn subset_violations(&self, other: &Policy) -> Vec<SubsetViolation> {
+    let config = self.options.check(self.length).0;
+    let other_config = other.options.check(other.length).0;
+    let classes = &config.classes;
+    let other_classes = &other_config.classes;
+
+    let charset: HashSet<char> = classes
+      .iter()
+      .flat_map(|c| c.chars.iter().cloned())
+      .collect();
+
+    // A policy that no password satisfies is a subset of every policy.
+    if charset.is_empty()
+      || classes.iter().any(|c| c.min > 0 && c.chars.is_empty())
+    {
+      return Vec::new();
+    }
+
+    let mut violations = Vec::new();
+
+    if self.length < other.length {
+      violations.push(SubsetViolation::Length {
+        actual: self.length,
+        required: other.length,
+      });
+    }
+
+    let other_charset: HashSet<char> = other_classes
+      .iter()
+      .flat_map(|c| c.chars.iter().cloned())
+      .collect();
+    let mut disallowed: Vec<char> =
+      charset.difference(&other_charset).cloned().collect();
+    if !disallowed.is_empty() {
+      disallowed.sort_unstable();
+      violations.push(SubsetViolation::DisallowedChars(disallowed));
+    }
+
+    for other_class in other_classes.iter().filter(|c| c.min > 0) {
+      let target: HashSet<char> = other_class
+        .chars
+        .iter()
+        .filter(|c| charset.contains(c))
+        .cloned()
+        .collect();
+      let guaranteed =
+        guaranteed_count(self.length, classes, &charset, &target);
+      if guaranteed < other_class.min {
+        violations.push(SubsetViolation::ClassMinimum {
+          category: other_class.category.clone(),
+          guaranteed,
+          required: other_class.min,
+        });
+      }
+    }
+
+    if let (Some(other_first), Some(category)) =
+      (&other_config.first, &other.options.first_char_class)
+    {
+      let within =
+        |chars: &[char]| chars.iter().all(|c| other_first.contains(c));
+      if !config.first.as_deref().is_some_and(within)
+        && !within(&config.charset)
+      {
+        violations.push(SubsetViolation::FirstChar(category.clone()));
+      }
+    }
+
+    if let Some(other_fill) = &other_config.fill {
+      if !fill_within(&config, other_classes, other_fill) {
+        violations.push(SubsetViolation::Fill);
+      }
+    }
+
+    let required = other.options.min_entropy_bits;
+    if self.options.min_entropy_bits < required {
+      violations.push(SubsetViolation::Entropy {
+        actual: self.options.min_entropy_bits,
+        required,
+      });
+    }
+
+    if other.options.reject_structures && !self.options.reject_structures {
+      violations.push(SubsetViolation::Structures);
+    }
+
+    violations
+  }
+}
+
+/// Returns `true` if the characters of any password valid under `config`
+/// that lie outside `other_fill` are plainly required by the minimums of
+/// `other_classes`: the fill of `config` lies within `other_fill`, and each
+/// class with a minimum and characters outside it lies within the class of
+/// the same category, with at least that minimum.
+fn fill_within(
+  config: &EffectiveConfig,
+  other_classes: &[EffectiveClass],
+  other_fill: &[char],
+) -> bool {
+  let within = |chars: &[char]| chars.iter().all(|c| other_fill.contains(c));
+  if within(&config.charset) {
+    return true;
+  }
+  if !within(config.fill.as_deref().unwrap_or(&config.charset)) {
+    return false;
+  }
+  // A first character that no minimum provides is drawn beyond them.
+  if let Some(first) = &config.first {
+    let provided = config.classes.iter().any(|class| {
+      class.min > 0 && class.chars.iter().all(|c| first.contains(c))
+    });
+    if !provided && !within(first) {
+      return false;
+    }
+  }
+  config
+    .classes
+    .iter()
+    .filter(|class| class.min > 0 && !within(&class.chars))
+    .all(|class| {
+      other_classes.iter().any(|other| {
+        other.category == class.category
+          && other.min >= class.min
+          && class.chars.iter().all(|c| other.chars.contains(c))
+      })
+    })
+}
+
+/// Returns a lower bound on the number of characters from `target` in any
+/// password valid under a policy with the given length, classes, and
+/// charset.
+///
+/// The bound is exact unless classes lying entirely within `target` overlap,
+/// in which case it is conservative.
+fn guaranteed_count(
+  length: usize,
+  classes: &[EffectiveClass],
+  charset: &HashSet<char>,
+  target: &HashSet<char>,
+) -> usize {
+  // Classes that cannot be satisfied without characters from `target`.
+  let forced: Vec<&EffectiveClass> = classes
+    .iter()
+    .filter(|c| c.min > 0 && c.chars.iter().all(|ch| target.contains(ch)))
+    .collect();
+
+  let disjoint = forced.iter().enumerate().all(|(i, a)| {
+    forced[i + 1..]
+      .iter()
+      .all(|b| a.chars.iter().all(|ch| !b.chars.contains(ch)))
+  });
+
+  let from_classes = if disjoint {
+    forced.iter().map(|c| c.min).sum()
+  } else {
+    forced.iter().map(|c| c.min).max().unwrap_or(0)
+  };
+
+  if charset.is_subset(target) {
+    from_classes.max(length)
+  } else {
+    from_classes
+  }
+}
+
+#[cfg(all(test, feature = "builtin-charsets"))]
+mod tests {
+  use super::*;
+  use crate::PwdGenOptions;
+
+  fn policy(length: usize, options: PwdGenOptions) -> Policy {
+    Policy::new(length, options)
+  }
+
+  #[test]
+  fn test_policy_is_subset_of_itself() {
+    let a = policy(
+      12,
+      PwdGenOptions {
+        min_upper: 2,
+        exclude: Some("O0".into()),
+        ..Default::default()
+      },
+    );
+    assert!(a.is_subset_of(&a));
+  }
+
+  #[test]
+  fn test_stricter_policy_is_subset() {
+    let strict = policy(
+      16,
+      PwdGenOptions {
+        min_upper: 2,
+        min_digit: 2,
+        ..Default::default()
+      },
+    );
+    let lenient = policy(
+      12,
+      PwdGenOptions {
+        min_upper: 1,
+        ..Default::default()
+      },
+    );
+
+    assert!(strict.is_subset_of(&lenient));
+    assert!(!lenient.is_subset_of(&strict));
+  }
+
+  #[test]
+  fn test_subset_violations() {
+    let a = policy(10, PwdGenOptions::default());
+    let b = policy(
+      12,
+      PwdGenOptions {
+        min_digit: 1,
+        exclude: Some("!".into()),
+        ..Default::default()
+      },
+    );
+
+    assert_eq!(
+      a.subset_violations(&b),
+      vec![
+        SubsetViolation::Length {
+          actual: 10,
+          required: 12
+        },
+        SubsetViolation::DisallowedChars(vec!['!']),
+        SubsetViolation::ClassMinimum {
+          category: Category::Digit,
+          guaranteed: 0,
+          required: 1
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn test_charset_within_class_guarantees_length() {
+    let digits_only: String = ('A'..='Z')
+      .chain('a'..='z')
+      .chain(crate::SPECIAL_CHARS.iter().cloned())
+      .collect();
+    let a = policy(
+      10,
+      PwdGenOptions {
+        exclude: Some(digits_only.into()),
+        ..Default::default()
+      },
+    );
+    let b = policy(
+      8,
+      PwdGenOptions {
+        min_digit: 8,
+        ..Default::default()
+      },
+    );
+    assert!(a.is_subset_of(&b));
+  }
+
+  #[test]
+  fn test_first_fill_entropy_and_structures() {
+    let strict = policy(
+      12,
+      PwdGenOptions {
+        min_upper: 1,
+        min_digit: 2,
+        fill_classes: Some(vec![Category::Lower].into()),
+        first_char_class: Some(Category::Upper),
+        min_entropy_bits: 40,
+        reject_structures: true,
+        ..Default::default()
+      },
+    );
+    let lenient = policy(12, PwdGenOptions::default());
+    assert!(strict.is_subset_of(&strict));
+    assert!(strict.is_subset_of(&lenient));
+    assert_eq!(
+      lenient.subset_violations(&strict),
+      vec![
+        SubsetViolation::ClassMinimum {
+          category: Category::Upper,
+          guaranteed: 0,
+          required: 1
+        },
+        SubsetViolation::ClassMinimum {
+          category: Category::Digit,
+          guaranteed: 0,
+          required: 2
+        },
+        SubsetViolation::FirstChar(Category::Upper),
+        SubsetViolation::Fill,
+        SubsetViolation::Entropy {
+          actual: 0,
+          required: 40
+        },
+        SubsetViolation::Structures,
+      ]
+    );
+
+    let mut fewer_digits = strict.clone();
+    fewer_digits.options.min_digit = 1;
+    assert_eq!(
+      strict.subset_violations(&fewer_digits),
+      vec![SubsetViolation::Fill]
+    );
+  }
+
+  #[test]
+  fn test_subset_violation_display() {
+    let violation = SubsetViolation::ClassMinimum {
+      category: Category::Upper,
+      guaranteed: 1,
+      required: 2,
+    };
+    assert_eq!(
+      violation.to_string(),
+      "only 1 upper characters are guaranteed, but 2 are required"
+    );
+  }
+}
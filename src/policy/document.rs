@@ -0,0 +1,284 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+#[cfg(feature = "json")]
+use serde::{Deserialize, Serialize};
+
+use crate::charset::{builtin_chars, BUILTIN_CLASSES};
+use crate::Policy;
+
+/// Version of the policy document format.
+pub const POLICY_DOCUMENT_VERSION: u32 = 1;
+
+/// Bounds on the length of a password.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "json", derive(Serialize, Deserialize))]
+pub struct LengthBounds {
+  pub min: usize,
+  /// Maximum length, if any.
+  pub max: Option<usize>,
+}
+
+/// The characters of a class and the minimum number required from it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "json", derive(Serialize, Deserialize))]
+pub struct ClassRequirement {
+  /// `upper`, `lower`, `digit`, `special`, or the name of a custom class.
+  pub name: String,
+  /// Characters of the class, before exclusions.
+  pub chars: String,
+  pub min: usize,
+}
+
+/// A structured, machine-readable form of a `Policy`.
+///
+/// Lets other systems, such as frontend validators and identity providers,
+/// consume the exact rules that passwords are generated against.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "json", derive(Serialize, Deserialize))]
+pub struct PolicyDocument {
+  pub version: u32,
+  pub length: LengthBounds,
+  pub classes: Vec<ClassRequirement>,
+  /// Characters that never appear in passwords.
+  pub exclude: String,
+  /// The only characters that may appear in passwords, if restricted.
+  #[cfg_attr(
+    feature = "json",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub charset: Option<String>,
+  /// Minimum estimated entropy of a password, in bits, if required.
+  #[cfg_attr(
+    feature = "json",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub min_entropy_bits: Option<usize>,
+  /// Names of the classes that characters beyond the minimums are drawn
+  /// from, if restricted.
+  #[cfg_attr(
+    feature = "json",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub fill_classes: Option<Vec<String>>,
+  /// Name of the class that the first character is drawn from, if any.
+  #[cfg_attr(
+    feature = "json",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub first_char_class: Option<String>,
+  /// Whether passwords with a simple structure, such as palindromes, are
+  /// rejected.
+  #[cfg_attr(
+    feature = "json",
+    serde(default, skip_serializing_if = "std::ops::Not::not")
+  )]
+  pub reject_structures: bool,
+  /// Whether characters easily mistaken for one another, `AMBIGUOUS_CHARS`,
+  /// are excluded in addition to `exclude`.
+  #[cfg_attr(
+    feature = "json",
+    serde(default, skip_serializing_if = "std::ops::Not::not")
+  )]
+  pub avoid_ambiguous: bool,
+}
+
+impl PolicyDocument {
+  /// Serializes the document as pretty-printed JSON.
+  #[cfg(feature = "json")]
+  pub fn to_json(&self) -> String {
+    serde_json::to_string_pretty(self).expect("Policy document is serializable")
+  }
+
+  /// Returns a JSON Schema that accepts exactly the strings satisfying the
+  /// document's length bounds, class minimums, and exclusions.
+  #[cfg(feature = "json")]
+  pub fn to_json_schema(&self) -> String {
+    let ambiguous = if self.avoid_ambiguous {
+      crate::AMBIGUOUS_CHARS
+    } else {
+      ""
+    };
+    let excluded: Vec<char> =
+      self.exclude.chars().chain(ambiguous.chars()).collect();
+    let allowed = |chars: &str| -> String {
+      chars
+        .chars()
+        .filter(|c| !excluded.contains(c))
+        .filter(|c| self.charset.as_ref().is_none_or(|s| s.contains(*c)))
+        .collect()
+    };
+
+    let mut pattern = String::from("^");
+    for class in self.classes.iter().filter(|class| class.min > 0) {
+      let set = regex_class(&allowed(&class.chars));
+      pattern.push_str(&format!("(?=(?:.*{}){{{}}})", set, class.min));
+    }
+    let charset: String = match &self.charset {
+      Some(charset) => allowed(charset),
+      None => self
+        .classes
+        .iter()
+        .map(|class| allowed(&class.chars))
+        .collect(),
+    };
+    pattern.push_str(&format!("{}*$", regex_class(&charset)));
+
+    let mut schema = serde_json::json!({
+      "$schema": "https://json-schema.org/draft/2020-12/schema",
+      "type": "string",
+      "minLength": self.length.min,
+      "pattern": pattern,
+    });
+    if let Some(max) = self.length.max {
+      schema["maxLength"] = max.into();
+    }
+
+    serde_json::to_string_pretty(&schema).expect("JSON Schema is serializable")
+  }
+}
+
+/// Builds a regular expression character class matching any of `chars`.
+#[cfg(feature = "json")]
+fn regex_class(chars: &str) -> String {
+  let mut class = String::from("[");
+  for c in crate::util::unique(chars.chars()) {
+    if matches!(c, '\\' | ']' | '[' | '^' | '-') {
+      class.push('\\');
+    }
+    class.push(c);
+  }
+  class.push(']');
+  class
+}
+
+impl Policy {
+  /// Returns the policy as a structured `PolicyDocument`.
+  pub fn to_document(&self) -> PolicyDocument {
+    let options = &self.options;
+    let builtin_mins = [
+      options.min_upper,
+      options.min_lower,
+      options.min_digit,
+      options.min_special,
+    ];
+
+    let mut classes: Vec<ClassRequirement> = BUILTIN_CLASSES
+      .into_iter()
+      .zip(builtin_mins)
+      .map(|(category, min)| ClassRequirement {
+        name: category.to_string(),
+        chars: builtin_chars(&category).into_iter().collect(),
+        min,
+      })
+      .filter(|class| !class.chars.is_empty() || class.min > 0)
+      .collect();
+
+    classes.extend(options.classes.iter().map(|class| ClassRequirement {
+      name: class.name.to_string(),
+      chars: class.chars.to_string(),
+      min: class.min,
+    }));
+
+    PolicyDocument {
+      version: POLICY_DOCUMENT_VERSION,
+      length: LengthBounds {
+        min: self.length,
+        max: None,
+      },
+      classes,
+      exclude: options.exclude.as_deref().unwrap_or("").to_string(),
+      charset: options.charset.as_deref().map(String::from),
+      min_entropy_bits: Some(options.min_entropy_bits).filter(|&bits| bits > 0),
+      fill_classes: options.fill_classes.as_deref().map(|fill_classes| {
+        fill_classes.iter().map(ToString::to_string).collect()
+      }),
+      first_char_class: options
+        .first_char_class
+        .as_ref()
+        .map(|c| c.to_string()),
+      reject_structures: options.reject_structures,
+      avoid_ambiguous: options.avoid_ambiguous,
+    }
+  }
+}
+
+#[cfg(all(test, feature = "builtin-charsets"))]
+mod tests {
+  use super::*;
+  use crate::PwdGenOptions;
+
+  fn policy() -> Policy {
+    Policy::new(
+      16,
+      PwdGenOptions {
+        min_upper: 2,
+        min_digit: 1,
+        exclude: Some("O0".into()),
+        ..Default::default()
+      },
+    )
+  }
+
+  #[test]
+  fn test_to_document() {
+    let document = policy().to_document();
+
+    assert_eq!(document.version, POLICY_DOCUMENT_VERSION);
+    assert_eq!(document.length, LengthBounds { min: 16, max: None });
+    assert_eq!(document.exclude, "O0");
+    assert_eq!(document.classes.len(), 4);
+    assert_eq!(document.classes[0].name, "upper");
+    assert_eq!(document.classes[0].min, 2);
+    assert_eq!(document.classes[2].chars, "0123456789");
+  }
+
+  #[cfg(feature = "json")]
+  #[test]
+  fn test_to_json() {
+    let json: serde_json::Value =
+      serde_json::from_str(&policy().to_document().to_json()).unwrap();
+
+    assert_eq!(json["version"], 1);
+    assert_eq!(json["length"]["min"], 16);
+    assert_eq!(json["classes"][0]["name"], "upper");
+    assert_eq!(json["exclude"], "O0");
+  }
+
+  #[cfg(feature = "json")]
+  #[test]
+  fn test_to_json_schema() {
+    let schema: serde_json::Value =
+      serde_json::from_str(&policy().to_document().to_json_schema()).unwrap();
+    let pattern = schema["pattern"].as_str().unwrap();
+
+    assert_eq!(schema["type"], "string");
+    assert_eq!(schema["minLength"], 16);
+    assert!(pattern.starts_with("^(?=(?:.*[ABCDEFGHIJKLMNPQRSTUVWXYZ]){2})"));
+    assert!(pattern.contains("(?=(?:.*[123456789]){1})"));
+    assert!(pattern.ends_with("]*$"));
+  }
+
+  #[cfg(feature = "json")]
+  #[test]
+  fn test_to_json_schema_with_charset() {
+    let mut policy = policy();
+    policy.options.charset = Some("0123456789ABCDEF".into());
+    let document = policy.to_document();
+    let schema: serde_json::Value =
+      serde_json::from_str(&document.to_json_schema()).unwrap();
+
+    assert_eq!(document.charset.as_deref(), Some("0123456789ABCDEF"));
+    assert_eq!(
+      schema["pattern"],
+      "^(?=(?:.*[ABCDEF]){2})(?=(?:.*[123456789]){1})[123456789ABCDEF]*$"
+    );
+  }
+
+  #[cfg(feature = "json")]
+  #[test]
+  fn test_regex_class_escapes() {
+    assert_eq!(regex_class("a-]^\\a"), "[a\\-\\]\\^\\\\]");
+  }
+}
@@ -0,0 +1,124 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use crate::{Policy, MIN_LENGTH};
+
+/// Margin below a target entropy still counted as reaching it, for rounding.
+const EPSILON: f64 = 1e-9;
+
+/// The entropy two policies yield at the same length, and the length each
+/// needs to match the other. Returned by `Policy::compare_entropy`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct EntropyComparison {
+  /// Length both policies are compared at.
+  pub length: usize,
+  /// Entropy of a password of `length` under this policy, in bits.
+  pub bits: f64,
+  /// Entropy of a password of `length` under the other policy, in bits.
+  pub other_bits: f64,
+  /// Shortest length at which this policy yields at least `other_bits`, or
+  /// `None` if none does.
+  pub length_to_match: Option<usize>,
+  /// Shortest length at which the other policy yields at least `bits`, or
+  /// `None` if none does.
+  pub other_length_to_match: Option<usize>,
+}
+
+impl Policy {
+  /// Returns the estimated entropy, in bits, of a password of `length`
+  /// generated under this policy's options. See
+  /// `EffectiveConfig::entropy_bits`.
+  pub fn entropy_bits_at(&self, length: usize) -> f64 {
+    self.options.check(length).0.entropy_bits()
+  }
+
+  /// Returns the shortest length, not below `MIN_LENGTH`, at which this
+  /// policy's options yield at least `bits` of entropy, or `None` if no
+  /// length does, as when the charset has a single character.
+  pub fn length_for_bits(&self, bits: f64) -> Option<usize> {
+    let minimums: usize = self
+      .options
+      .check(MIN_LENGTH)
+      .0
+      .classes
+      .iter()
+      .map(|class| class.min)
+      .sum();
+    let mut length = MIN_LENGTH;
+    loop {
+      let current = self.entropy_bits_at(length);
+      if current + EPSILON >= bits {
+        return Some(length);
+      }
+      // Beyond the minimums, each character adds the same entropy, if any.
+      if length > minimums && self.entropy_bits_at(length + 1) <= current {
+        return None;
+      }
+      length += 1;
+    }
+  }
+
+  /// Compares the entropy this policy and `other` yield at `length`, and
+  /// finds the length each needs to match the other, e.g. to show how much
+  /// longer a password of lowercase letters must be to match one of every
+  /// class.
+  pub fn compare_entropy(
+    &self,
+    other: &Policy,
+    length: usize,
+  ) -> EntropyComparison {
+    let bits = self.entropy_bits_at(length);
+    let other_bits = other.entropy_bits_at(length);
+    EntropyComparison {
+      length,
+      bits,
+      other_bits,
+      length_to_match: self.length_for_bits(other_bits),
+      other_length_to_match: other.length_for_bits(bits),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::PwdGenOptions;
+
+  fn policy(charset: &str, min_digit: usize) -> Policy {
+    Policy::new(
+      MIN_LENGTH,
+      PwdGenOptions {
+        charset: Some(charset.to_string().into()),
+        min_digit,
+        ..Default::default()
+      },
+    )
+  }
+
+  #[test]
+  fn test_compare_entropy() {
+    // 16 and 256 characters: 4 and 8 bits per character.
+    let small = policy("abcdefghijklmnop", 0);
+    let chars: String = (0x100..0x200).filter_map(char::from_u32).collect();
+    let large = policy(&chars, 0);
+
+    let comparison = small.compare_entropy(&large, 12);
+    assert_eq!(comparison.length, 12);
+    assert!((comparison.bits - 48.0).abs() < 1e-9);
+    assert!((comparison.other_bits - 96.0).abs() < 1e-9);
+    assert_eq!(comparison.length_to_match, Some(24));
+    assert_eq!(comparison.other_length_to_match, Some(MIN_LENGTH));
+  }
+
+  #[test]
+  fn test_length_for_bits() {
+    let digits = policy("ab0123", 2);
+    assert_eq!(digits.length_for_bits(0.0), Some(MIN_LENGTH));
+    let bits = digits.entropy_bits_at(20);
+    assert_eq!(digits.length_for_bits(bits), Some(20));
+    assert_eq!(digits.length_for_bits(bits + 0.1), Some(21));
+
+    assert_eq!(policy("a", 0).length_for_bits(1.0), None);
+  }
+}
@@ -0,0 +1,372 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use std::borrow::Cow;
+
+use crate::charset::builtin_chars;
+use crate::{
+  Category, CharClass, Error, Policy, PolicyDocument, PwdGenOptions,
+};
+use crate::{MIN_LENGTH, POLICY_DOCUMENT_VERSION};
+
+/// Length of passwords for site requirements that only give a minimum.
+pub(super) const SITE_LENGTH: usize = 16;
+
+/// Returns the special characters to exclude so that only those of `allowed`
+/// remain, as for the `symbols:` site requirement.
+pub(super) fn other_specials(allowed: &str) -> Vec<char> {
+  builtin_chars(&Category::Special)
+    .into_iter()
+    .filter(|c| !allowed.contains(*c))
+    .collect()
+}
+
+#[cfg(feature = "json")]
+impl PolicyDocument {
+  /// Parses a policy document from JSON, as produced by `to_json`.
+  pub fn from_json(json: &str) -> Result<Self, Error> {
+    serde_json::from_str(json)
+      .map_err(|e| Error::InvalidPolicy(format!("malformed JSON: {}", e)))
+  }
+}
+
+impl Policy {
+  /// Builds a policy from a `PolicyDocument`.
+  ///
+  /// Built-in classes are identified by name (`upper`, `lower`, `digit`, and
+  /// `special`) and use the built-in characters. Any other class becomes a
+  /// custom class with the document's characters.
+  pub fn from_document(document: &PolicyDocument) -> Result<Self, Error> {
+    if document.version != POLICY_DOCUMENT_VERSION {
+      return Err(Error::InvalidPolicy(format!(
+        "unsupported document version {}",
+        document.version
+      )));
+    }
+
+    let mut options = PwdGenOptions::default();
+    let mut classes = Vec::new();
+
+    for class in &document.classes {
+      match class.name.as_str() {
+        "upper" => options.min_upper = class.min,
+        "lower" => options.min_lower = class.min,
+        "digit" => options.min_digit = class.min,
+        "special" => options.min_special = class.min,
+        name => classes.push(CharClass {
+          name: Cow::Owned(name.to_string()),
+          chars: Cow::Owned(class.chars.clone()),
+          min: class.min,
+        }),
+      }
+    }
+
+    options.classes = Cow::Owned(classes);
+    if !document.exclude.is_empty() {
+      options.exclude = Some(Cow::Owned(document.exclude.clone()));
+    }
+    options.charset = document.charset.clone().map(Cow::Owned);
+    options.min_entropy_bits = document.min_entropy_bits.unwrap_or(0);
+    options.fill_classes = document.fill_classes.as_ref().map(|names| {
+      Cow::Owned(names.iter().map(|name| Category::from_name(name)).collect())
+    });
+    options.first_char_class = document
+      .first_char_class
+      .as_deref()
+      .map(Category::from_name);
+    options.reject_structures = document.reject_structures;
+    options.avoid_ambiguous = document.avoid_ambiguous;
+
+    Ok(Policy::new(document.length.min, options))
+  }
+
+  /// Builds a policy from an Active Directory-style password policy, given as
+  /// `key: value` or `key = value` lines.
+  ///
+  /// Recognizes the `MinPasswordLength` and `ComplexityEnabled` keys of
+  /// `Get-ADDefaultDomainPasswordPolicy`, and the `MinimumPasswordLength` and
+  /// `PasswordComplexity` keys of `secedit` exports. Other lines are ignored.
+  /// Complexity, which requires characters from three of four categories, is
+  /// satisfied by requiring an uppercase letter, a lowercase letter, and a
+  /// digit. Lengths below `MIN_LENGTH` are raised to `MIN_LENGTH`.
+  pub fn from_ad_complexity(text: &str) -> Result<Self, Error> {
+    let mut length = None;
+    let mut complexity = false;
+
+    for line in text.lines() {
+      let Some((key, value)) = line.split_once([':', '=']) else {
+        continue;
+      };
+      let value = value.trim();
+
+      match key.trim().to_ascii_lowercase().as_str() {
+        "minpasswordlength" | "minimumpasswordlength" => {
+          length = Some(value.parse::<usize>().map_err(|_| {
+            Error::InvalidPolicy(format!("invalid minimum length '{}'", value))
+          })?);
+        }
+        "complexityenabled" | "passwordcomplexity" => {
+          complexity = match value.to_ascii_lowercase().as_str() {
+            "true" | "1" => true,
+            "false" | "0" => false,
+            _ => {
+              return Err(Error::InvalidPolicy(format!(
+                "invalid complexity setting '{}'",
+                value
+              )))
+            }
+          };
+        }
+        _ => {}
+      }
+    }
+
+    let length = length.ok_or_else(|| {
+      Error::InvalidPolicy("no minimum password length found".to_string())
+    })?;
+
+    let mut options = PwdGenOptions::default();
+    if complexity {
+      options.min_upper = 1;
+      options.min_lower = 1;
+      options.min_digit = 1;
+    }
+
+    Ok(Policy::new(length.max(MIN_LENGTH), options))
+  }
+
+  /// Builds a policy from the requirements that sites display, in a
+  /// forgiving mini-syntax of comma-separated terms, e.g.
+  /// `8-20,no-spaces,symbols:!@#,must:Ul d`:
+  ///
+  /// - `8-20`: passwords of 8 to 20 characters, generated at 20. A lone
+  ///   minimum, `8` or `8+`, generates at least `SITE_LENGTH` characters.
+  /// - `must:Ul d`: at least one of each listed class, `U` for uppercase,
+  ///   `l` for lowercase, `d` for digits, and `s` for symbols, in any case
+  ///   and with any spaces.
+  /// - `symbols:!@#`: only these symbols. The list cannot contain commas.
+  /// - `no-symbols`: no symbols.
+  /// - `no:abc`: none of these characters.
+  /// - `no-spaces`: accepted, as passwords never contain spaces.
+  pub fn from_site_requirements(text: &str) -> Result<Self, Error> {
+    let mut length = SITE_LENGTH;
+    let mut options = PwdGenOptions::default();
+    let mut exclude = String::new();
+    let specials = builtin_chars(&Category::Special);
+
+    for term in text.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+      let unknown =
+        || Error::InvalidPolicy(format!("unknown site requirement '{}'", term));
+      let (key, value) = term.split_once(':').unwrap_or((term, ""));
+
+      match key.trim().to_ascii_lowercase().as_str() {
+        "must" => {
+          for c in value.chars().filter(|c| !c.is_whitespace()) {
+            match c.to_ascii_lowercase() {
+              'u' => options.min_upper = 1,
+              'l' => options.min_lower = 1,
+              'd' => options.min_digit = 1,
+              's' => options.min_special = 1,
+              _ => return Err(unknown()),
+            }
+          }
+        }
+        "symbols" => exclude.extend(other_specials(value)),
+        "no-symbols" => exclude.extend(&specials),
+        "no" => exclude.push_str(value),
+        "no-spaces" => {}
+        range => {
+          let (min, max) = match range.split_once('-') {
+            Some((min, max)) => (min, Some(max)),
+            None => (range.strip_suffix('+').unwrap_or(range), None),
+          };
+          let min = min.trim().parse::<usize>().map_err(|_| unknown())?;
+          length = match max {
+            Some(max) => {
+              let max = max.trim().parse::<usize>().map_err(|_| unknown())?;
+              if max < min {
+                return Err(unknown());
+              }
+              max
+            }
+            None => min.max(SITE_LENGTH),
+          };
+        }
+      }
+    }
+
+    if !exclude.is_empty() {
+      options.exclude = Some(Cow::Owned(exclude));
+    }
+    Ok(Policy::new(length, options))
+  }
+
+  /// Parses a policy from either a JSON policy document or an Active
+  /// Directory-style description, detected from the content.
+  pub fn parse(text: &str) -> Result<Self, Error> {
+    if text.trim_start().starts_with('{') {
+      #[cfg(feature = "json")]
+      return Policy::from_document(&PolicyDocument::from_json(text)?);
+      #[cfg(not(feature = "json"))]
+      return Err(Error::InvalidPolicy(
+        "JSON policies require the json feature".to_string(),
+      ));
+    }
+    Policy::from_ad_complexity(text)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_document_round_trip() {
+    let policy = Policy::new(
+      14,
+      PwdGenOptions {
+        min_upper: 2,
+        min_special: 1,
+        exclude: Some("O0".into()),
+        classes: vec![CharClass {
+          name: "accents".into(),
+          chars: "\u{e9}\u{e8}".into(),
+          min: 1,
+        }]
+        .into(),
+        min_entropy_bits: 60,
+        fill_classes: Some(
+          vec![Category::Lower, Category::Custom("accents".to_string())].into(),
+        ),
+        first_char_class: Some(Category::Upper),
+        reject_structures: true,
+        avoid_ambiguous: true,
+        ..Default::default()
+      },
+    );
+
+    let imported = Policy::from_document(&policy.to_document()).unwrap();
+
+    assert_eq!(imported.length, policy.length);
+    assert_eq!(imported.options.min_upper, 2);
+    assert_eq!(imported.options.min_special, 1);
+    assert_eq!(imported.options.exclude.as_deref(), Some("O0"));
+    assert_eq!(imported.options.classes, policy.options.classes);
+    assert_eq!(imported.options.min_entropy_bits, 60);
+    assert_eq!(imported.options.fill_classes, policy.options.fill_classes);
+    assert_eq!(imported.options.first_char_class, Some(Category::Upper));
+    assert!(imported.options.reject_structures);
+    assert!(imported.options.avoid_ambiguous);
+  }
+
+  #[test]
+  fn test_document_unsupported_version() {
+    let mut document = Policy::new(12, Default::default()).to_document();
+    document.version = 99;
+    assert!(matches!(
+      Policy::from_document(&document),
+      Err(Error::InvalidPolicy(_))
+    ));
+  }
+
+  #[cfg(feature = "json")]
+  #[test]
+  fn test_parse_json() {
+    let json = r#"{
+      "version": 1,
+      "length": { "min": 20, "max": null },
+      "classes": [{ "name": "digit", "chars": "0123456789", "min": 3 }],
+      "exclude": ""
+    }"#;
+
+    let policy = Policy::parse(json).unwrap();
+
+    assert_eq!(policy.length, 20);
+    assert_eq!(policy.options.min_digit, 3);
+    assert_eq!(policy.options.exclude, None);
+  }
+
+  #[cfg(feature = "json")]
+  #[test]
+  fn test_parse_malformed_json() {
+    assert!(matches!(
+      Policy::parse("{ \"version\": "),
+      Err(Error::InvalidPolicy(_))
+    ));
+  }
+
+  #[test]
+  fn test_parse_ad_powershell_output() {
+    let text = "\
+ComplexityEnabled           : True
+LockoutThreshold            : 0
+MinPasswordLength           : 12
+";
+    let policy = Policy::parse(text).unwrap();
+
+    assert_eq!(policy.length, 12);
+    assert_eq!(policy.options.min_upper, 1);
+    assert_eq!(policy.options.min_lower, 1);
+    assert_eq!(policy.options.min_digit, 1);
+    assert_eq!(policy.options.min_special, 0);
+  }
+
+  #[test]
+  fn test_parse_ad_secedit_raises_short_length() {
+    let text =
+      "[System Access]\nMinimumPasswordLength = 7\nPasswordComplexity = 0\n";
+    let policy = Policy::from_ad_complexity(text).unwrap();
+
+    assert_eq!(policy.length, MIN_LENGTH);
+    assert_eq!(policy.options, PwdGenOptions::default());
+  }
+
+  #[test]
+  fn test_parse_ad_missing_length() {
+    assert!(matches!(
+      Policy::from_ad_complexity("ComplexityEnabled : True"),
+      Err(Error::InvalidPolicy(_))
+    ));
+  }
+
+  #[cfg(feature = "builtin-charsets")]
+  #[test]
+  fn test_from_site_requirements() {
+    let policy =
+      Policy::from_site_requirements("8-20, no-spaces,symbols:!@#,must:Ul d")
+        .unwrap();
+    assert_eq!(policy.length, 20);
+    assert_eq!((policy.options.min_upper, policy.options.min_lower), (1, 1));
+    assert_eq!(
+      (policy.options.min_digit, policy.options.min_special),
+      (1, 0)
+    );
+    let password = policy.generator().unwrap().gen();
+    assert!(password
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || "!@#".contains(c)));
+
+    let policy =
+      Policy::from_site_requirements("10+,no-symbols,no:O0").unwrap();
+    assert_eq!(policy.length, SITE_LENGTH);
+    let password = policy.generator().unwrap().gen();
+    assert!(password.chars().all(|c| c.is_ascii_alphanumeric()));
+    assert!(!password.contains(['O', '0']));
+    assert_eq!(Policy::from_site_requirements("24").unwrap().length, 24);
+  }
+
+  #[test]
+  fn test_from_site_requirements_unknown_term() {
+    for text in ["must:Ux", "emoji", "20-8"] {
+      assert!(
+        matches!(
+          Policy::from_site_requirements(text),
+          Err(Error::InvalidPolicy(_))
+        ),
+        "{}",
+        text
+      );
+    }
+  }
+}
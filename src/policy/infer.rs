@@ -0,0 +1,537 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use std::borrow::Cow;
+
+use super::import::{other_specials, SITE_LENGTH};
+use crate::charset::builtin_chars;
+use crate::{Category, Error, Policy, PwdGenOptions};
+
+/// Words that, shortly before a class, mean the class is not allowed, e.g.
+/// "no symbols" or "can't contain spaces or symbols".
+const NEGATIONS: [&str; 7] =
+  ["no", "not", "cannot", "never", "without", "t", "excluding"];
+
+/// Words before a list of allowed special characters, e.g. "allowed
+/// symbols: !@#".
+const ALLOWED: [&str; 6] = [
+  "allowed",
+  "only",
+  "following",
+  "permitted",
+  "accepted",
+  "valid",
+];
+
+/// Number of words before a class searched for a count or a negation.
+const WINDOW: usize = 3;
+
+impl Policy {
+  /// Builds a policy from a requirements paragraph pasted from a site, e.g.
+  /// "Password must be 8-64 characters and contain at least one uppercase
+  /// letter, a number, and a symbol (!@#$%). No spaces.", using heuristics.
+  ///
+  /// Recognizes the descriptions that `Policy` displays too, such as "16+
+  /// characters, at least 2 uppercase, 1 digit, excludes: O0Il", except
+  /// custom classes and `rest from:`, so that a displayed policy can be read
+  /// back. The result should be checked against the text before use:
+  ///
+  /// - Lengths: "8-64 characters", "between 8 and 64 characters", "at least
+  ///   12 characters", "no more than 64 characters", "minimum length of 12",
+  ///   "12+ characters". Passwords are generated at the maximum length, or
+  ///   at least `SITE_LENGTH` characters if there is only a minimum.
+  /// - Classes: "one uppercase letter", "2 numbers", "a symbol", "lowercase
+  ///   letters", each requiring as many characters as its count, or one.
+  /// - "At least 3 of the following" requires the first 3 classes listed
+  ///   after it.
+  /// - Negated classes, e.g. "no symbols", are excluded.
+  /// - Allowed symbols, e.g. "allowed special characters: !@#" or "one
+  ///   special character (!@#)", exclude the other special characters, as
+  ///   `symbols:` does in `from_site_requirements`.
+  /// - "starts with", "no ambiguous characters", "no simple structures",
+  ///   "at least N bits of entropy", "only from:", and "excludes:".
+  ///
+  /// Fails with `Error::InvalidPolicy` if no requirement is recognized.
+  pub fn from_requirements_text(text: &str) -> Result<Self, Error> {
+    let mut inference = Inference::default();
+    for line in text.lines() {
+      inference.line(line);
+    }
+    inference.finish()
+  }
+}
+
+/// Requirements found so far in a requirements paragraph.
+#[derive(Default)]
+struct Inference {
+  min_length: Option<usize>,
+  max_length: Option<usize>,
+  /// Minimum of each built-in class.
+  mins: Vec<(Category, usize)>,
+  /// Number of classes of a following list that are required, if any.
+  choose: Option<usize>,
+  /// Classes listed after "of the following".
+  listed: Vec<Category>,
+  /// Classes that are not allowed.
+  negated: Vec<Category>,
+  allowed_specials: Option<String>,
+  exclude: String,
+  options: PwdGenOptions<'static>,
+  found: bool,
+}
+
+impl Inference {
+  fn line(&mut self, line: &str) {
+    let mut line = line.to_string();
+    if let Some(exclude) = take_value(&mut line, "excludes: ", false) {
+      self.exclude.push_str(&exclude);
+      self.found = true;
+    }
+    if let Some(charset) = take_value(&mut line, "only from: ", true) {
+      self.options.charset = Some(Cow::Owned(charset));
+      self.found = true;
+    }
+    if let Some((before, after)) = line.split_once(':') {
+      let before = words(before);
+      if before
+        .iter()
+        .any(|word| class_of(word) == Some(Category::Special))
+        && before.iter().any(|word| ALLOWED.contains(&word.as_str()))
+      {
+        let specials = builtin_chars(&Category::Special);
+        let allowed = after.chars().filter(|c| specials.contains(c));
+        self
+          .allowed_specials
+          .get_or_insert_with(String::new)
+          .extend(allowed);
+        line = before.join(" ");
+      }
+    }
+    self.listed_specials(&mut line);
+    let lower = line.to_ascii_lowercase();
+    if lower.contains("no simple structures") {
+      self.options.reject_structures = true;
+      self.found = true;
+    }
+
+    let words = words(&line);
+    let mut skip = None;
+    for (i, word) in words.iter().enumerate() {
+      let before = |n: usize| i.checked_sub(n).map(|i| words[i].as_str());
+      let after = |n: usize| words.get(i + n).map(String::as_str);
+
+      if Some(i) == skip {
+        continue;
+      }
+      if matches!(word.as_str(), "start" | "starts" | "begin" | "begins") {
+        // "starts with: upper" or "must start with an uppercase letter".
+        let class = (1..=3)
+          .filter_map(after)
+          .enumerate()
+          .find_map(|(n, word)| class_of(word).map(|class| (n, class)));
+        if let Some((n, class)) = class {
+          // "must not start with a number" is not understood, and ignored.
+          if !window(&words, i).iter().any(|w| NEGATIONS.contains(w)) {
+            self.options.first_char_class = Some(class);
+            self.found = true;
+          }
+          skip = Some(i + n + 1);
+        }
+        continue;
+      }
+      if word == "ambiguous"
+        && window(&words, i)
+          .iter()
+          .any(|w| *w == "no" || *w == "avoid")
+      {
+        self.options.avoid_ambiguous = true;
+        self.found = true;
+        continue;
+      }
+      if word == "bits" && after(1) == Some("of") && after(2) == Some("entropy")
+      {
+        if let Some(bits) = before(1).and_then(count) {
+          self.options.min_entropy_bits = bits;
+          self.found = true;
+        }
+        continue;
+      }
+      if word == "of"
+        && matches!(after(1), Some("the" | "these" | "following"))
+        && before(1).and_then(count).is_some_and(|n| n > 1)
+      {
+        self.choose = before(1).and_then(count);
+        continue;
+      }
+      if let Some(class) = class_of(word) {
+        self.class(class, &words, i);
+        continue;
+      }
+      self.length(&words, i);
+    }
+  }
+
+  /// Records the special characters listed in parentheses right after a
+  /// mention of special characters, e.g. "one symbol (!@#)", as the only
+  /// ones allowed, and removes the list from `line`.
+  fn listed_specials(&mut self, line: &mut String) {
+    let specials = builtin_chars(&Category::Special);
+    let mut rest = line.as_str();
+    let mut kept = String::new();
+    while let Some((before, after)) = rest.split_once('(') {
+      let Some((inside, after)) = after.split_once(')') else {
+        break;
+      };
+      // Commas followed by spaces separate the characters, e.g. "(!, @)".
+      let listed: String = inside
+        .replace(", ", " ")
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+      let words = words(before);
+      let names_specials = window(&words, words.len())
+        .iter()
+        .any(|word| class_of(word) == Some(Category::Special));
+      kept.push_str(before);
+      if names_specials
+        && !listed.is_empty()
+        && listed.chars().all(|c| specials.contains(&c))
+      {
+        self
+          .allowed_specials
+          .get_or_insert_with(String::new)
+          .push_str(&listed);
+        self.found = true;
+      } else {
+        kept.push('(');
+        kept.push_str(inside);
+        kept.push(')');
+      }
+      rest = after;
+    }
+    kept.push_str(rest);
+    *line = kept;
+  }
+
+  /// Records the class mentioned by `words[i]`.
+  fn class(&mut self, class: Category, words: &[String], i: usize) {
+    let window = window(words, i);
+    if words.get(i + 1).is_some_and(|word| word == "of")
+      || window.contains(&"than")
+    {
+      // "number of characters" or "no more than 2 digits".
+      return;
+    }
+    self.found = true;
+    let count = window.last().and_then(|word| count(word));
+    if count.is_none() && window.iter().any(|w| NEGATIONS.contains(w)) {
+      self.negated.push(class);
+    } else if self.choose.is_some() {
+      if !self.listed.contains(&class) {
+        self.listed.push(class);
+      }
+    } else {
+      self.require(class, count.unwrap_or(1));
+    }
+  }
+
+  fn require(&mut self, class: Category, min: usize) {
+    match self
+      .mins
+      .iter_mut()
+      .find(|(category, _)| *category == class)
+    {
+      Some((_, current)) => *current = (*current).max(min),
+      None => self.mins.push((class, min)),
+    }
+  }
+
+  /// Records a length given by `words[i]`, if it is one.
+  fn length(&mut self, words: &[String], i: usize) {
+    let word = words[i].as_str();
+    let before = |n: usize| i.checked_sub(n).map(|i| words[i].as_str());
+    let after = |n: usize| words.get(i + n).map(String::as_str);
+    let is_char = |n| after(n).is_some_and(|w: &str| w.starts_with("char"));
+    let is_length = is_char(1)
+      || (after(1) == Some("or") && is_char(3))
+      || (1..=2).filter_map(before).any(|w| w == "length");
+    if !is_length {
+      return;
+    }
+
+    if let Some((min, max)) = word.split_once('-') {
+      if let (Ok(min), Ok(max)) = (min.parse(), max.parse()) {
+        self.set_length(Some(min), Some(max));
+      }
+      return;
+    }
+    let plus = word.ends_with('+');
+    let Ok(n) = word.trim_end_matches('+').parse::<usize>() else {
+      return;
+    };
+    let previous = before(2).and_then(|w| w.parse::<usize>().ok());
+    let (min, max) = match (before(3), before(2), before(1)) {
+      _ if plus => (Some(n), None),
+      (Some("between"), _, Some("and")) | (_, _, Some("to"))
+        if previous.is_some() =>
+      {
+        (previous, Some(n))
+      }
+      (_, Some("up"), Some("to")) => (None, Some(n)),
+      (_, _, Some("most" | "maximum" | "max" | "exceed")) => (None, Some(n)),
+      (Some("no"), Some("more" | "longer" | "greater"), Some("than")) => {
+        (None, Some(n))
+      }
+      (Some("no"), Some("fewer" | "less" | "shorter"), Some("than")) => {
+        (Some(n), None)
+      }
+      (_, Some("more" | "longer" | "greater"), Some("than")) => {
+        (Some(n + 1), None)
+      }
+      (_, Some("fewer" | "less" | "shorter"), Some("than")) => {
+        (None, Some(n.saturating_sub(1)))
+      }
+      _ => match (after(1), after(2)) {
+        (Some("or"), Some("fewer" | "less" | "shorter")) => (None, Some(n)),
+        _ => (Some(n), None),
+      },
+    };
+    self.set_length(min, max);
+  }
+
+  fn set_length(&mut self, min: Option<usize>, max: Option<usize>) {
+    if let Some(min) = min {
+      self.min_length = Some(self.min_length.map_or(min, |m| m.max(min)));
+    }
+    if let Some(max) = max {
+      self.max_length = Some(self.max_length.map_or(max, |m| m.min(max)));
+    }
+    self.found = true;
+  }
+
+  fn finish(mut self) -> Result<Policy, Error> {
+    if !self.found {
+      return Err(Error::InvalidPolicy(
+        "no password requirements found".to_string(),
+      ));
+    }
+    if let Some(choose) = self.choose {
+      for class in std::mem::take(&mut self.listed).into_iter().take(choose) {
+        self.require(class, 1);
+      }
+    }
+
+    let length = match (self.min_length, self.max_length) {
+      (Some(min), Some(max)) if max < min => {
+        return Err(Error::InvalidPolicy(format!(
+          "maximum length {} is below the minimum length {}",
+          max, min
+        )))
+      }
+      (_, Some(max)) => max,
+      (min, None) => min.unwrap_or(0).max(SITE_LENGTH),
+    };
+
+    let mut options = self.options;
+    for (class, min) in self.mins {
+      match class {
+        Category::Upper => options.min_upper = min,
+        Category::Lower => options.min_lower = min,
+        Category::Digit => options.min_digit = min,
+        _ => options.min_special = min,
+      }
+    }
+    let mut exclude = self.exclude;
+    for class in &self.negated {
+      exclude.extend(builtin_chars(class));
+    }
+    if let Some(allowed) = self.allowed_specials {
+      exclude.extend(other_specials(&allowed));
+    }
+    if !exclude.is_empty() {
+      options.exclude = Some(Cow::Owned(exclude));
+    }
+    Ok(Policy::new(length, options))
+  }
+}
+
+/// Removes `prefix` and the value after it from `line`, and returns the
+/// value. The value ends at the next ", " if `comma` is `true`, and at the
+/// end of the line otherwise.
+fn take_value(line: &mut String, prefix: &str, comma: bool) -> Option<String> {
+  let start = line.to_ascii_lowercase().find(prefix)?;
+  let value_start = start + prefix.len();
+  let end = match line[value_start..].find(", ") {
+    Some(end) if comma => value_start + end,
+    _ => line.len(),
+  };
+  let value = line[value_start..end].trim().to_string();
+  line.replace_range(start..end, "");
+  Some(value)
+}
+
+/// Splits `text` into lowercase words, keeping the hyphens and plus signs
+/// of lengths such as "8-64" and "12+".
+fn words(text: &str) -> Vec<String> {
+  let text = text
+    .to_ascii_lowercase()
+    .replace(['\u{2013}', '\u{2014}'], "-")
+    .replace("upper case", "uppercase")
+    .replace("upper-case", "uppercase")
+    .replace("lower case", "lowercase")
+    .replace("lower-case", "lowercase");
+  text
+    .split(|c: char| !(c.is_alphanumeric() || c == '-' || c == '+'))
+    .map(|word| word.trim_matches('-'))
+    .filter(|word| !word.is_empty())
+    .map(String::from)
+    .collect()
+}
+
+/// Returns up to `WINDOW` words before `words[i]`.
+fn window(words: &[String], i: usize) -> Vec<&str> {
+  words[i.saturating_sub(WINDOW)..i]
+    .iter()
+    .map(String::as_str)
+    .collect()
+}
+
+/// Returns the built-in class a word names, if any.
+fn class_of(word: &str) -> Option<Category> {
+  match word.strip_suffix('s').unwrap_or(word) {
+    "upper" | "uppercase" | "capital" => Some(Category::Upper),
+    "lower" | "lowercase" => Some(Category::Lower),
+    "digit" | "number" | "numeral" | "numeric" => Some(Category::Digit),
+    "special" | "symbol" | "punctuation" | "non-alphanumeric" => {
+      Some(Category::Special)
+    }
+    _ => None,
+  }
+}
+
+/// Returns the count a word gives, e.g. 2 for "2" or "two".
+fn count(word: &str) -> Option<usize> {
+  let words = ["one", "two", "three", "four", "five", "six", "seven"];
+  match word {
+    "a" | "an" => Some(1),
+    _ => word
+      .parse()
+      .ok()
+      .or_else(|| words.iter().position(|w| *w == word).map(|i| i + 1)),
+  }
+}
+
+#[cfg(all(test, feature = "builtin-charsets"))]
+mod tests {
+  use super::*;
+  use crate::SPECIAL_CHARS;
+
+  #[test]
+  fn test_from_requirements_text() {
+    let text = "\
+Your password must be 8-64 characters long and contain at least one
+uppercase letter, two numbers, and a symbol. Allowed symbols: ! @ # $
+Passwords cannot contain spaces.";
+    let policy = Policy::from_requirements_text(text).unwrap();
+
+    assert_eq!(policy.length, 64);
+    assert_eq!(policy.options.min_upper, 1);
+    assert_eq!(policy.options.min_lower, 0);
+    assert_eq!(policy.options.min_digit, 2);
+    assert_eq!(policy.options.min_special, 1);
+    let exclude = policy.options.exclude.unwrap();
+    assert!(!exclude.contains(['!', '@', '#', '$']));
+    assert_eq!(exclude.chars().count(), SPECIAL_CHARS.len() - 4);
+
+    let text = "At least 20 characters. Must not start with a number.";
+    let policy = Policy::from_requirements_text(text).unwrap();
+    assert_eq!(policy.options.first_char_class, None);
+    assert_eq!(policy.options.exclude, None);
+  }
+
+  #[test]
+  fn test_from_requirements_text_listed_specials() {
+    let text = "At least 12 characters, with one special character (!@#).";
+    let policy = Policy::from_requirements_text(text).unwrap();
+    assert_eq!(policy.options.min_special, 1);
+    let exclude = policy.options.exclude.unwrap();
+    assert!(!exclude.contains(['!', '@', '#']));
+    assert_eq!(exclude.chars().count(), SPECIAL_CHARS.len() - 3);
+    assert_eq!(
+      exclude,
+      Policy::from_site_requirements("symbols:!@#")
+        .unwrap()
+        .options
+        .exclude
+        .unwrap()
+    );
+
+    // Parentheses elsewhere are not a list of symbols.
+    let text = "One number (0-9) and a symbol.";
+    let policy = Policy::from_requirements_text(text).unwrap();
+    assert_eq!(policy.options.exclude, None);
+  }
+
+  #[test]
+  fn test_from_requirements_text_lengths() {
+    let length = |text| Policy::from_requirements_text(text).unwrap().length;
+    assert_eq!(length("Between 10 and 20 characters."), 20);
+    assert_eq!(length("At least 20 characters."), 20);
+    assert_eq!(
+      length("Minimum length of 12, no more than 30 characters."),
+      30
+    );
+    assert_eq!(length("8 or more characters"), SITE_LENGTH);
+    assert_eq!(length("Must include a lowercase letter."), SITE_LENGTH);
+    assert!(matches!(
+      Policy::from_requirements_text("Between 20 and 10 characters."),
+      Err(Error::InvalidPolicy(_))
+    ));
+  }
+
+  #[test]
+  fn test_from_requirements_text_choose() {
+    let text = "At least 12 characters, including 3 of the following: \
+                uppercase letters, lowercase letters, numbers, or symbols.";
+    let policy = Policy::from_requirements_text(text).unwrap();
+    assert_eq!(policy.length, SITE_LENGTH);
+    assert_eq!(policy.options.min_upper, 1);
+    assert_eq!(policy.options.min_lower, 1);
+    assert_eq!(policy.options.min_digit, 1);
+    assert_eq!(policy.options.min_special, 0);
+
+    let policy = Policy::from_requirements_text("No symbols.").unwrap();
+    let specials: String = SPECIAL_CHARS.iter().collect();
+    assert_eq!(policy.options.exclude.as_deref(), Some(specials.as_str()));
+  }
+
+  #[test]
+  fn test_from_requirements_text_round_trip() {
+    let policy = Policy::new(
+      20,
+      PwdGenOptions {
+        min_upper: 2,
+        min_lower: 1,
+        min_digit: 3,
+        min_special: 1,
+        exclude: Some("O0Il".into()),
+        min_entropy_bits: 80,
+        first_char_class: Some(Category::Upper),
+        reject_structures: true,
+        avoid_ambiguous: true,
+        ..Default::default()
+      },
+    );
+    let inferred = Policy::from_requirements_text(&policy.to_string());
+    assert_eq!(inferred.unwrap(), policy);
+  }
+
+  #[test]
+  fn test_from_requirements_text_nothing_found() {
+    assert!(matches!(
+      Policy::from_requirements_text("Choose something memorable."),
+      Err(Error::InvalidPolicy(_))
+    ));
+  }
+}
@@ -0,0 +1,172 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use std::borrow::Cow;
+
+use crate::{Policy, PwdGenOptions};
+
+const ALNUM: &str =
+  "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+const BASE58: &str =
+  "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const URLSAFE: &str =
+  "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// A named, ready-made password policy, such as `hex` or `strong`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Preset {
+  pub name: &'static str,
+  /// One-line description of the preset.
+  pub description: &'static str,
+  pub length: usize,
+  pub options: PwdGenOptions<'static>,
+}
+
+/// Every preset, in the order they are listed. New presets are added here.
+const PRESETS: &[Preset] = &[
+  preset("alnum", "Letters and digits", 16, [0; 4], Some(ALNUM), None),
+  preset(
+    "hex",
+    "Lowercase hexadecimal",
+    32,
+    [0; 4],
+    Some("0123456789abcdef"),
+    None,
+  ),
+  preset(
+    "base58",
+    "Bitcoin base58 alphabet",
+    22,
+    [0; 4],
+    Some(BASE58),
+    None,
+  ),
+  preset(
+    "urlsafe",
+    "URL-safe base64 alphabet",
+    22,
+    [0; 4],
+    Some(URLSAFE),
+    None,
+  ),
+  preset("pin", "Digits only", 8, [0; 4], Some("0123456789"), None),
+  preset(
+    "wifi",
+    "Letters and digits without look-alikes, easy to type on devices",
+    20,
+    [1, 1, 1, 0],
+    Some(ALNUM),
+    Some("O0Il1"),
+  ),
+  preset(
+    "strong",
+    "At least one character of every class",
+    16,
+    [1; 4],
+    None,
+    None,
+  ),
+  preset(
+    "paranoid",
+    "Long, with two characters of every class",
+    32,
+    [2; 4],
+    None,
+    None,
+  ),
+  preset(
+    "windows",
+    "Windows complexity: three of four classes; add --account-name",
+    14,
+    [1, 1, 1, 0],
+    None,
+    None,
+  ),
+];
+
+const fn preset(
+  name: &'static str,
+  description: &'static str,
+  length: usize,
+  [min_upper, min_lower, min_digit, min_special]: [usize; 4],
+  charset: Option<&'static str>,
+  exclude: Option<&'static str>,
+) -> Preset {
+  Preset {
+    name,
+    description,
+    length,
+    options: PwdGenOptions {
+      min_upper,
+      min_lower,
+      min_digit,
+      min_special,
+      exclude: match exclude {
+        Some(exclude) => Some(Cow::Borrowed(exclude)),
+        None => None,
+      },
+      charset: match charset {
+        Some(charset) => Some(Cow::Borrowed(charset)),
+        None => None,
+      },
+      classes: Cow::Borrowed(&[]),
+      min_entropy_bits: 0,
+      fill_classes: None,
+      first_char_class: None,
+      reject_structures: false,
+      avoid_ambiguous: false,
+    },
+  }
+}
+
+impl Preset {
+  /// Returns the preset called `name`, if any.
+  pub fn lookup(name: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|preset| preset.name == name)
+  }
+
+  /// Returns every preset.
+  pub fn all() -> &'static [Preset] {
+    PRESETS
+  }
+
+  /// Returns the policy of the preset.
+  pub fn policy(&self) -> Policy {
+    Policy::new(self.length, self.options.clone())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_lookup() {
+    assert_eq!(Preset::lookup("hex").unwrap().length, 32);
+    assert!(Preset::lookup("nope").is_none());
+  }
+
+  #[test]
+  fn test_names_are_unique() {
+    let names = crate::util::unique(Preset::all().iter().map(|p| p.name));
+    assert_eq!(names.len(), Preset::all().len());
+  }
+
+  #[test]
+  fn test_charset_presets() {
+    let password = Preset::lookup("hex").unwrap().policy().generator();
+    let password = password.unwrap().gen();
+    assert_eq!(password.len(), 32);
+    assert!(password.chars().all(|c| c.is_ascii_hexdigit()));
+    assert!(!password.chars().any(|c| c.is_ascii_uppercase()));
+  }
+
+  #[cfg(feature = "builtin-charsets")]
+  #[test]
+  fn test_presets_are_valid() {
+    for preset in Preset::all() {
+      assert!(preset.policy().validate().is_valid(), "{}", preset.name);
+    }
+  }
+}
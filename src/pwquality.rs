@@ -0,0 +1,420 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use crate::{Category, Error};
+
+/// Number of distinct characters below which cracklib rejects a password.
+const CRACKLIB_MIN_DIFF: usize = 5;
+
+/// Number of adjacent characters one apart, e.g. `ab` or `98`, above which
+/// cracklib rejects a password as systematic.
+const CRACKLIB_MAX_STEP: usize = 4;
+
+/// Shortest `minlen` that libpwquality enforces, whatever its settings.
+const BASE_MIN_LENGTH: usize = 6;
+
+/// The settings of libpwquality, as read from `pwquality.conf`, that apply to
+/// a new password without an old password or user account to compare with.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PwQuality {
+  /// Minimum length, less one for each credit earned.
+  pub minlen: usize,
+  /// Credits for digits if positive, or otherwise the negated minimum number
+  /// of digits. Likewise `ucredit`, `lcredit`, and `ocredit` for uppercase,
+  /// lowercase, and other characters.
+  pub dcredit: i64,
+  pub ucredit: i64,
+  pub lcredit: i64,
+  pub ocredit: i64,
+  /// Minimum number of the four classes present.
+  pub minclass: usize,
+  /// Maximum number of consecutive identical characters, or `0` for any.
+  pub maxrepeat: usize,
+  /// Maximum number of consecutive characters of one class, or `0` for any.
+  pub maxclassrepeat: usize,
+  /// Maximum length of a monotonic sequence, e.g. `1234`, or `0` for any.
+  pub maxsequence: usize,
+  /// Whether the checks of cracklib apply. Its dictionary is not consulted.
+  pub dictcheck: bool,
+  /// Words that must not appear in a password, ignoring case.
+  pub badwords: Vec<String>,
+}
+
+impl Default for PwQuality {
+  /// The defaults of libpwquality.
+  fn default() -> Self {
+    PwQuality {
+      minlen: 8,
+      dcredit: 0,
+      ucredit: 0,
+      lcredit: 0,
+      ocredit: 0,
+      minclass: 0,
+      maxrepeat: 0,
+      maxclassrepeat: 0,
+      maxsequence: 0,
+      dictcheck: true,
+      badwords: Vec::new(),
+    }
+  }
+}
+
+/// A reason why libpwquality would reject a password. Messages follow those
+/// of libpwquality.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PwQualityViolation {
+  Palindrome,
+  /// Shorter than `minlen`, after credits.
+  TooShort(usize),
+  /// Fewer characters of a class than a negative credit requires.
+  ClassMinimum {
+    category: Category,
+    required: usize,
+  },
+  TooFewClasses(usize),
+  MaxRepeat(usize),
+  MaxClassRepeat(usize),
+  MaxSequence(usize),
+  BadWord(String),
+  /// Fewer than five distinct characters, rejected by cracklib.
+  TooFewDistinct,
+  /// Too many adjacent characters one apart, rejected by cracklib.
+  Systematic,
+}
+
+impl std::fmt::Display for PwQualityViolation {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      PwQualityViolation::Palindrome => {
+        write!(f, "The password is a palindrome")
+      }
+      PwQualityViolation::TooShort(minlen) => {
+        write!(f, "The password is shorter than {} characters", minlen)
+      }
+      PwQualityViolation::ClassMinimum { category, required } => {
+        write!(
+          f,
+          "The password contains less than {} {} characters",
+          required, category
+        )
+      }
+      PwQualityViolation::TooFewClasses(minclass) => {
+        write!(
+          f,
+          "The password contains less than {} character classes",
+          minclass
+        )
+      }
+      PwQualityViolation::MaxRepeat(max) => {
+        write!(
+          f,
+          "The password contains more than {} same characters consecutively",
+          max
+        )
+      }
+      PwQualityViolation::MaxClassRepeat(max) => {
+        write!(
+          f,
+          "The password contains more than {} characters of the same class \
+           consecutively",
+          max
+        )
+      }
+      PwQualityViolation::MaxSequence(max) => {
+        write!(
+          f,
+          "The password contains monotonic sequence longer than {} characters",
+          max
+        )
+      }
+      PwQualityViolation::BadWord(word) => {
+        write!(f, "The password contains the forbidden word '{}'", word)
+      }
+      PwQualityViolation::TooFewDistinct => {
+        write!(
+          f,
+          "The password fails the dictionary check - it does not contain \
+           enough DIFFERENT characters"
+        )
+      }
+      PwQualityViolation::Systematic => {
+        write!(
+          f,
+          "The password fails the dictionary check - it is too \
+           simplistic/systematic"
+        )
+      }
+    }
+  }
+}
+
+impl PwQuality {
+  /// Parses the `key = value` lines of a `pwquality.conf` file, keeping the
+  /// defaults for settings that are not given. Comments, blank lines, and
+  /// settings that need an account, such as `usercheck`, are ignored.
+  pub fn parse(text: &str) -> Result<Self, Error> {
+    let mut settings = PwQuality::default();
+
+    for line in text.lines() {
+      let line = line.split('#').next().unwrap_or("").trim();
+      if line.is_empty() {
+        continue;
+      }
+      let (key, value) = match line.split_once('=') {
+        Some((key, value)) => (key.trim(), value.trim()),
+        None => (line, ""),
+      };
+      let invalid = || {
+        Error::InvalidPolicy(format!("invalid pwquality setting '{}'", line))
+      };
+      let count = || value.parse::<usize>().map_err(|_| invalid());
+      let credit = || value.parse::<i64>().map_err(|_| invalid());
+
+      match key {
+        "minlen" => settings.minlen = count()?,
+        "dcredit" => settings.dcredit = credit()?,
+        "ucredit" => settings.ucredit = credit()?,
+        "lcredit" => settings.lcredit = credit()?,
+        "ocredit" => settings.ocredit = credit()?,
+        "minclass" => settings.minclass = count()?,
+        "maxrepeat" => settings.maxrepeat = count()?,
+        "maxclassrepeat" => settings.maxclassrepeat = count()?,
+        "maxsequence" => settings.maxsequence = count()?,
+        "dictcheck" => settings.dictcheck = count()? != 0,
+        "badwords" => {
+          settings.badwords =
+            value.split_whitespace().map(String::from).collect()
+        }
+        _ => {}
+      }
+    }
+
+    Ok(settings)
+  }
+
+  /// Returns every reason why libpwquality would reject `password`, in the
+  /// order that it checks them. Empty if it would accept it, short of its
+  /// dictionary lookups.
+  pub fn violations(&self, password: &str) -> Vec<PwQualityViolation> {
+    let chars: Vec<char> = password.chars().collect();
+    let mut violations = Vec::new();
+
+    if chars.iter().eq(chars.iter().rev()) {
+      violations.push(PwQualityViolation::Palindrome);
+    }
+
+    let count = |category: &Category| {
+      chars.iter().filter(|&&c| class_of(c) == *category).count()
+    };
+    let mut size = chars.len();
+    let mut classes = 0;
+    for (category, credit) in [
+      (Category::Digit, self.dcredit),
+      (Category::Upper, self.ucredit),
+      (Category::Lower, self.lcredit),
+      (Category::Special, self.ocredit),
+    ] {
+      let n = count(&category);
+      if n > 0 {
+        classes += 1;
+      }
+      if credit >= 0 {
+        size += n.min(credit as usize);
+      } else if n < credit.unsigned_abs() as usize {
+        violations.push(PwQualityViolation::ClassMinimum {
+          category,
+          required: credit.unsigned_abs() as usize,
+        });
+      }
+    }
+    if classes < self.minclass {
+      violations.push(PwQualityViolation::TooFewClasses(self.minclass));
+    }
+    let minlen = self.minlen.max(BASE_MIN_LENGTH);
+    if size < minlen {
+      violations.push(PwQualityViolation::TooShort(minlen));
+    }
+
+    let longest = |same: &dyn Fn(char, char) -> bool| {
+      let mut longest = 0;
+      let mut run = 0;
+      for i in 0..chars.len() {
+        run = if i > 0 && same(chars[i - 1], chars[i]) {
+          run + 1
+        } else {
+          1
+        };
+        longest = longest.max(run);
+      }
+      longest
+    };
+    if self.maxrepeat > 0 && longest(&|a, b| a == b) > self.maxrepeat {
+      violations.push(PwQualityViolation::MaxRepeat(self.maxrepeat));
+    }
+    if self.maxclassrepeat > 0
+      && longest(&|a, b| class_of(a) == class_of(b)) > self.maxclassrepeat
+    {
+      violations.push(PwQualityViolation::MaxClassRepeat(self.maxclassrepeat));
+    }
+    if self.maxsequence > 0 && longest_sequence(&chars) > self.maxsequence {
+      violations.push(PwQualityViolation::MaxSequence(self.maxsequence));
+    }
+
+    let lowercase = password.to_lowercase();
+    if let Some(word) = self
+      .badwords
+      .iter()
+      .find(|word| lowercase.contains(&word.to_lowercase()))
+    {
+      violations.push(PwQualityViolation::BadWord(word.clone()));
+    }
+
+    if self.dictcheck {
+      let mut distinct = chars.clone();
+      distinct.sort_unstable();
+      distinct.dedup();
+      if distinct.len() < CRACKLIB_MIN_DIFF {
+        violations.push(PwQualityViolation::TooFewDistinct);
+      }
+      let lowercase: Vec<char> = lowercase.chars().collect();
+      let steps = lowercase
+        .windows(2)
+        .filter(|pair| (pair[1] as i64 - pair[0] as i64).abs() == 1)
+        .count();
+      if steps > CRACKLIB_MAX_STEP {
+        violations.push(PwQualityViolation::Systematic);
+      }
+    }
+
+    violations
+  }
+
+  /// Returns `true` if libpwquality would accept `password`, short of its
+  /// dictionary lookups.
+  pub fn allows(&self, password: &str) -> bool {
+    self.violations(password).is_empty()
+  }
+}
+
+/// Returns the class that libpwquality counts `c` in, with `Special` for any
+/// character that is not an ASCII letter or digit.
+fn class_of(c: char) -> Category {
+  if c.is_ascii_digit() {
+    Category::Digit
+  } else if c.is_ascii_uppercase() {
+    Category::Upper
+  } else if c.is_ascii_lowercase() {
+    Category::Lower
+  } else {
+    Category::Special
+  }
+}
+
+/// Returns the length of the longest run of characters that each differ from
+/// the previous one by the same step of `1` or `-1`, e.g. `4` for `x1234`.
+fn longest_sequence(chars: &[char]) -> usize {
+  let mut longest = chars.len().min(1);
+  let mut run = 1;
+  let mut step = 0;
+  for pair in chars.windows(2) {
+    let next = pair[1] as i64 - pair[0] as i64;
+    run = if next.abs() == 1 && next == step {
+      run + 1
+    } else if next.abs() == 1 {
+      2
+    } else {
+      1
+    };
+    step = next;
+    longest = longest.max(run);
+  }
+  longest
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse() {
+    let settings = PwQuality::parse(
+      "# pwquality.conf\nminlen = 12\ndcredit=-2\nminclass = 3\n\
+       maxrepeat = 2 # inline\nenforce_for_root\nbadwords = acme Secret\n\
+       dictcheck = 0\n",
+    )
+    .unwrap();
+    assert_eq!(
+      settings,
+      PwQuality {
+        minlen: 12,
+        dcredit: -2,
+        minclass: 3,
+        maxrepeat: 2,
+        dictcheck: false,
+        badwords: vec!["acme".to_string(), "Secret".to_string()],
+        ..Default::default()
+      }
+    );
+    assert!(PwQuality::parse("minlen = twelve").is_err());
+  }
+
+  #[test]
+  fn test_violations() {
+    let settings = PwQuality {
+      minlen: 12,
+      dcredit: -2,
+      ucredit: 1,
+      minclass: 3,
+      maxrepeat: 2,
+      maxclassrepeat: 4,
+      maxsequence: 3,
+      badwords: vec!["acme".to_string()],
+      ..Default::default()
+    };
+    assert!(settings.allows("Xk9#mQ2vLp7z"));
+    // The uppercase credit makes up for one missing character.
+    assert!(settings.allows("Xk9#mQ2vLp7"));
+    assert_eq!(
+      settings.violations("aaabcdefACME1"),
+      vec![
+        PwQualityViolation::ClassMinimum {
+          category: Category::Digit,
+          required: 2,
+        },
+        PwQualityViolation::MaxRepeat(2),
+        PwQualityViolation::MaxClassRepeat(4),
+        PwQualityViolation::MaxSequence(3),
+        PwQualityViolation::BadWord("acme".to_string()),
+        PwQualityViolation::Systematic,
+      ]
+    );
+    assert_eq!(
+      PwQuality::default().violations("abab"),
+      vec![
+        PwQualityViolation::TooShort(8),
+        PwQualityViolation::TooFewDistinct,
+      ]
+    );
+    assert_eq!(
+      PwQuality::default().violations("xy12#21yx"),
+      vec![PwQualityViolation::Palindrome]
+    );
+  }
+
+  #[test]
+  fn test_longest_sequence() {
+    assert_eq!(longest_sequence(&[]), 0);
+    assert_eq!(longest_sequence(&['a']), 1);
+    assert_eq!(longest_sequence(&['x', '1', '2', '3', '4', '3']), 4);
+    assert_eq!(longest_sequence(&['c', 'b', 'a', 'c']), 3);
+  }
+
+  #[test]
+  fn test_violation_display() {
+    assert_eq!(
+      PwQualityViolation::TooShort(12).to_string(),
+      "The password is shorter than 12 characters"
+    );
+  }
+}
@@ -0,0 +1,139 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use rand::{rngs::OsRng, RngCore};
+
+use crate::Error;
+
+/// Number of raw bytes drawn from the operating system's RNG for the startup
+/// health tests.
+const SAMPLES: usize = 4096;
+
+/// Repetition Count Test cutoff (NIST SP 800-90B, Section 4.4.1), assuming
+/// full entropy (8 bits per byte) and a false positive rate of 2^-40.
+const RCT_CUTOFF: usize = 6;
+
+/// Adaptive Proportion Test window size (NIST SP 800-90B, Section 4.4.2).
+const APT_WINDOW: usize = 512;
+
+/// Adaptive Proportion Test cutoff for `APT_WINDOW`, assuming full entropy and
+/// a false positive rate of 2^-40.
+const APT_CUTOFF: usize = 19;
+
+/// Runs startup health tests on raw output from the operating system's random
+/// number generator.
+///
+/// The Repetition Count Test and the Adaptive Proportion Test of NIST SP
+/// 800-90B are applied once, to a sample of raw bytes drawn for the test.
+/// These are the startup tests only: the output that passwords are later
+/// drawn from is not tested continuously, as Section 4.4 also requires of a
+/// validated entropy source, so a source that fails after the test is not
+/// detected. Passwords should not be generated if an error is returned, as
+/// the entropy source may be broken.
+pub fn rng_selftest() -> Result<(), Error> {
+  let mut samples = vec![0u8; SAMPLES];
+  OsRng
+    .try_fill_bytes(&mut samples)
+    .map_err(|_| Error::RngHealth("unavailable"))?;
+
+  check_samples(&samples)
+}
+
+/// Returns `len` random bytes from the operating system's random number
+/// generator, the same source passwords are drawn from, e.g. for salting a
+/// password hash.
+pub fn gen_salt(len: usize) -> Vec<u8> {
+  let mut salt = vec![0u8; len];
+  OsRng.fill_bytes(&mut salt);
+  salt
+}
+
+fn check_samples(samples: &[u8]) -> Result<(), Error> {
+  if !repetition_count_test(samples) {
+    return Err(Error::RngHealth("repetition count"));
+  }
+  if !adaptive_proportion_test(samples) {
+    return Err(Error::RngHealth("adaptive proportion"));
+  }
+  Ok(())
+}
+
+fn repetition_count_test(samples: &[u8]) -> bool {
+  let mut run = 0;
+  let mut prev = None;
+
+  for &sample in samples {
+    if prev == Some(sample) {
+      run += 1;
+      if run >= RCT_CUTOFF {
+        return false;
+      }
+    } else {
+      prev = Some(sample);
+      run = 1;
+    }
+  }
+
+  true
+}
+
+fn adaptive_proportion_test(samples: &[u8]) -> bool {
+  samples.chunks(APT_WINDOW).all(|window| {
+    let first = window[0];
+    window.iter().filter(|&&s| s == first).count() < APT_CUTOFF
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_rng_selftest_passes() {
+    assert!(rng_selftest().is_ok());
+  }
+
+  #[test]
+  fn test_gen_salt() {
+    assert!(gen_salt(0).is_empty());
+    assert_eq!(gen_salt(16).len(), 16);
+    assert_ne!(gen_salt(32), gen_salt(32));
+  }
+
+  #[test]
+  fn test_distinct_samples_pass() {
+    let samples: Vec<u8> = (0..SAMPLES).map(|i| i as u8).collect();
+    assert!(check_samples(&samples).is_ok());
+  }
+
+  #[test]
+  fn test_repetition_count_failure() {
+    let mut samples: Vec<u8> = (0..SAMPLES).map(|i| i as u8).collect();
+    samples[100..100 + RCT_CUTOFF].fill(0xAA);
+    assert!(matches!(
+      check_samples(&samples),
+      Err(Error::RngHealth("repetition count"))
+    ));
+  }
+
+  #[test]
+  fn test_repetition_count_below_cutoff() {
+    let mut samples: Vec<u8> = (0..SAMPLES).map(|i| i as u8).collect();
+    samples[100..100 + RCT_CUTOFF - 1].fill(0xAA);
+    assert!(repetition_count_test(&samples));
+  }
+
+  #[test]
+  fn test_adaptive_proportion_failure() {
+    // No long runs, but the first sample of the window is far too common.
+    let samples: Vec<u8> = (0..SAMPLES)
+      .map(|i| if i % 4 == 0 { 0 } else { i as u8 | 1 })
+      .collect();
+    assert!(repetition_count_test(&samples));
+    assert!(matches!(
+      check_samples(&samples),
+      Err(Error::RngHealth("adaptive proportion"))
+    ));
+  }
+}
@@ -0,0 +1,450 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use crate::AttackEstimate;
+
+/// A short list of very common passwords and words, most common first. A
+/// match's rank in the list is its number of guesses.
+const COMMON_WORDS: &[&str] = &[
+  "password", "123456", "qwerty", "letmein", "welcome", "admin", "iloveyou",
+  "monkey", "dragon", "football", "baseball", "master", "sunshine", "princess",
+  "shadow", "superman", "michael", "trustno1", "login", "abc123", "starwars",
+  "freedom", "whatever", "hello", "charlie", "donald", "secret", "summer",
+  "winter", "spring", "autumn", "flower", "batman", "hunter", "jordan",
+  "jennifer", "thomas", "robert", "daniel", "jessica", "ashley", "michelle",
+  "soccer", "hockey", "killer", "george", "computer", "internet", "cookie",
+  "cheese", "orange", "banana", "purple", "silver", "golden", "tigger",
+  "pepper", "ginger", "maggie", "buster", "yankees", "lakers", "matrix",
+  "access", "mustang", "ranger", "harley", "love", "angel", "money", "pass",
+  "test", "user", "guest", "root", "default", "change", "changeme", "company",
+  "office", "server", "system", "london", "paris", "berlin", "america",
+  "canada", "google", "apple", "samsung", "music", "family", "friend", "happy",
+  "lucky", "magic", "power", "smile", "house", "school", "baby", "blue",
+  "green", "black", "white", "red", "dog", "cat",
+];
+
+/// Rows of a US QWERTY keyboard, unshifted and shifted. Neighbors in a row
+/// are adjacent keys.
+const KEYBOARD_ROWS: &[&str] = &[
+  "`1234567890-=",
+  "qwertyuiop[]\\",
+  "asdfghjkl;'",
+  "zxcvbnm,./",
+  "~!@#$%^&*()_+",
+  "QWERTYUIOP{}|",
+  "ASDFGHJKL:\"",
+  "ZXCVBNM<>?",
+];
+
+/// Number of keys a keyboard pattern can start from.
+const KEYBOARD_KEYS: f64 = 47.0;
+
+/// Shortest sequence, repeated character, or keyboard run that is matched.
+const MIN_RUN: usize = 3;
+
+/// Guess counts from which `StrengthReport::score` is 1, 2, 3, and 4, as in
+/// zxcvbn.
+const SCORE_THRESHOLDS: [f64; 4] = [1e3, 1e6, 1e8, 1e10];
+
+/// Width of a strength bar in cells, as shown by `pwdg --meter`.
+pub const DEFAULT_BAR_WIDTH: usize = 20;
+
+/// ANSI color codes of a strength bar, by score: red, red, yellow, green,
+/// green.
+const BAR_COLORS: [&str; 5] = ["31", "31", "33", "32", "32"];
+
+/// The kind of a guessable pattern found in a password.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PatternKind {
+  /// A common password or word, ignoring case and substitutions such as `@`
+  /// for `a`.
+  Dictionary,
+  /// A run of consecutive letters or digits, e.g. `abcd` or `9876`.
+  Sequence,
+  /// A repeated character or string, e.g. `aaa` or `abab`.
+  Repeat,
+  /// A run of adjacent keys of a keyboard row, e.g. `qwert` or `asdf`.
+  Keyboard,
+}
+
+impl std::fmt::Display for PatternKind {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      PatternKind::Dictionary => write!(f, "dictionary word"),
+      PatternKind::Sequence => write!(f, "sequence"),
+      PatternKind::Repeat => write!(f, "repeat"),
+      PatternKind::Keyboard => write!(f, "keyboard pattern"),
+    }
+  }
+}
+
+/// A guessable pattern of a password, covering its characters from `start`
+/// up to `end`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct StrengthMatch {
+  pub kind: PatternKind,
+  pub token: String,
+  pub start: usize,
+  pub end: usize,
+  /// Estimated number of guesses of the token alone, as a base-2 logarithm.
+  pub guesses_log2: f64,
+}
+
+/// An estimate of how guessable a password is, from the patterns an attacker
+/// would try first.
+#[derive(Debug, PartialEq, Clone)]
+pub struct StrengthReport {
+  /// Estimated number of guesses, as a base-2 logarithm, so that it reads as
+  /// bits of entropy.
+  pub guesses_log2: f64,
+  /// The patterns the estimate is made of, in order. Characters outside them
+  /// are counted as guessed by brute force.
+  pub matches: Vec<StrengthMatch>,
+}
+
+impl StrengthReport {
+  /// Returns a score from 0, too guessable, to 4, very unguessable, by the
+  /// thresholds of zxcvbn.
+  pub fn score(&self) -> u8 {
+    let guesses = self.guesses_log2.exp2();
+    SCORE_THRESHOLDS
+      .iter()
+      .filter(|&&threshold| guesses >= threshold)
+      .count() as u8
+  }
+
+  /// Returns how long attackers would take to guess the password.
+  pub fn attack_estimate(&self) -> AttackEstimate {
+    AttackEstimate::new(self.guesses_log2)
+  }
+}
+
+/// Renders `score`, from 0 to 4 as returned by `StrengthReport::score`, as a
+/// bar of `width` cells, filled one fifth for 0 up to fully for 4, so that
+/// every program showing pwdg's meter shows the same one. The bar is red for
+/// 0 and 1, yellow for 2, and green for 3 and 4, with ANSI escape codes.
+/// Scores above 4 are rendered as 4.
+pub fn render_strength_bar(score: u8, width: usize) -> String {
+  let score = score.min(4) as usize;
+  let filled = width * (score + 1) / 5;
+  format!(
+    "\x1b[{}m{}{}\x1b[0m",
+    BAR_COLORS[score],
+    "█".repeat(filled),
+    "░".repeat(width - filled)
+  )
+}
+
+/// Estimates the strength of `password` in the manner of zxcvbn: it is split
+/// into dictionary words, sequences, repeats, and keyboard patterns, and
+/// characters guessed by brute force, so as to minimize the guesses needed.
+/// Unlike the entropy of a policy, this applies to any password, including
+/// one chosen by a person.
+pub fn score(password: &str) -> StrengthReport {
+  let chars: Vec<char> = password.chars().collect();
+  let n = chars.len();
+  let brute_force = cardinality(&chars).log2();
+
+  let mut candidates = dictionary_matches(&chars);
+  candidates.extend(sequence_matches(&chars));
+  candidates.extend(repeat_matches(&chars, brute_force));
+  candidates.extend(keyboard_matches(&chars));
+
+  // best[i] is the fewest guesses, as a logarithm, of the first i characters,
+  // and how the last of them was covered.
+  let mut best: Vec<(f64, Option<usize>)> = vec![(f64::INFINITY, None); n + 1];
+  best[0].0 = 0.0;
+  for i in 0..n {
+    let bits = best[i].0 + brute_force;
+    if bits < best[i + 1].0 {
+      best[i + 1] = (bits, None);
+    }
+    for (m, candidate) in candidates.iter().enumerate() {
+      let bits = best[i].0 + candidate.guesses_log2;
+      if candidate.start == i && bits < best[candidate.end].0 {
+        best[candidate.end] = (bits, Some(m));
+      }
+    }
+  }
+
+  let mut matches = Vec::new();
+  let mut i = n;
+  while i > 0 {
+    match best[i].1 {
+      Some(m) => {
+        i = candidates[m].start;
+        matches.push(candidates[m].clone());
+      }
+      None => i -= 1,
+    }
+  }
+  matches.reverse();
+
+  StrengthReport {
+    guesses_log2: best[n].0,
+    matches,
+  }
+}
+
+/// Returns the number of characters of the classes `chars` draws from.
+fn cardinality(chars: &[char]) -> f64 {
+  let classes = [
+    (chars.iter().any(char::is_ascii_lowercase), 26.0),
+    (chars.iter().any(char::is_ascii_uppercase), 26.0),
+    (chars.iter().any(char::is_ascii_digit), 10.0),
+    (chars.iter().any(char::is_ascii_punctuation), 33.0),
+    (chars.iter().any(|c| !c.is_ascii_graphic()), 100.0),
+  ];
+  let size: f64 = classes
+    .iter()
+    .filter(|(present, _)| *present)
+    .map(|(_, size)| size)
+    .sum();
+  size.max(10.0)
+}
+
+fn token(chars: &[char], start: usize, end: usize) -> String {
+  chars[start..end].iter().collect()
+}
+
+/// Returns `c` lowercased, with common substitutions undone, and whether a
+/// substitution was undone.
+fn unleet(c: char) -> (char, bool) {
+  match c {
+    '@' | '4' => ('a', true),
+    '3' => ('e', true),
+    '1' | '!' => ('i', true),
+    '0' => ('o', true),
+    '$' | '5' => ('s', true),
+    '7' => ('t', true),
+    c => (c.to_ascii_lowercase(), false),
+  }
+}
+
+fn dictionary_matches(chars: &[char]) -> Vec<StrengthMatch> {
+  let mut matches = Vec::new();
+  for (rank, word) in COMMON_WORDS.iter().enumerate() {
+    let word: Vec<char> = word.chars().collect();
+    for start in 0..chars.len().saturating_sub(word.len() - 1) {
+      let end = start + word.len();
+      let mut substituted = false;
+      let found = chars[start..end].iter().zip(&word).all(|(&c, &w)| {
+        if c.to_ascii_lowercase() == w {
+          return true;
+        }
+        let (plain, is_substitution) = unleet(c);
+        substituted |= is_substitution;
+        plain == w
+      });
+      if !found {
+        continue;
+      }
+      let mut guesses = (rank + 1) as f64;
+      if chars[start..end].iter().any(char::is_ascii_uppercase) {
+        guesses *= 2.0;
+      }
+      if substituted {
+        guesses *= 2.0;
+      }
+      matches.push(StrengthMatch {
+        kind: PatternKind::Dictionary,
+        token: token(chars, start, end),
+        start,
+        end,
+        guesses_log2: guesses.log2(),
+      });
+    }
+  }
+  matches
+}
+
+/// Returns the maximal runs of at least `MIN_RUN` characters in which each
+/// follows the previous by the same step, as given by `follows`.
+fn runs(
+  chars: &[char],
+  follows: impl Fn(char, char) -> Option<i64>,
+) -> Vec<(usize, usize, i64)> {
+  let mut runs = Vec::new();
+  let mut start = 0;
+  let mut step: Option<i64> = None;
+  for i in 1..=chars.len() {
+    let next = (i < chars.len())
+      .then(|| follows(chars[i - 1], chars[i]))
+      .flatten();
+    match (next, step) {
+      (Some(next), Some(step)) if next == step => continue,
+      (Some(next), None) => {
+        step = Some(next);
+        continue;
+      }
+      _ => {}
+    }
+    if i - start >= MIN_RUN {
+      runs.push((start, i, step.unwrap_or(0)));
+    }
+    match next {
+      Some(next) => {
+        start = i - 1;
+        step = Some(next);
+      }
+      None => {
+        start = i;
+        step = None;
+      }
+    }
+  }
+  runs
+}
+
+fn sequence_matches(chars: &[char]) -> Vec<StrengthMatch> {
+  let same_class = |a: char, b: char| {
+    (a.is_ascii_lowercase() && b.is_ascii_lowercase())
+      || (a.is_ascii_uppercase() && b.is_ascii_uppercase())
+      || (a.is_ascii_digit() && b.is_ascii_digit())
+  };
+  runs(chars, |a, b| {
+    let step = b as i64 - a as i64;
+    (same_class(a, b) && step.abs() == 1).then_some(step)
+  })
+  .into_iter()
+  .map(|(start, end, step)| {
+    let first = chars[start];
+    let base = if "aAzZ019".contains(first) {
+      4.0
+    } else if first.is_ascii_digit() {
+      10.0
+    } else {
+      26.0
+    };
+    let direction = if step < 0 { 2.0 } else { 1.0 };
+    StrengthMatch {
+      kind: PatternKind::Sequence,
+      token: token(chars, start, end),
+      start,
+      end,
+      guesses_log2: (base * (end - start) as f64 * direction).log2(),
+    }
+  })
+  .collect()
+}
+
+fn repeat_matches(chars: &[char], brute_force: f64) -> Vec<StrengthMatch> {
+  let n = chars.len();
+  let mut matches = Vec::new();
+  for start in 0..n {
+    for unit in 1..=(n - start) / 2 {
+      let count = (start + unit..n)
+        .take_while(|&i| chars[i] == chars[i - unit])
+        .count()
+        / unit
+        + 1;
+      let end = start + count * unit;
+      if count < 2 || end - start < MIN_RUN {
+        continue;
+      }
+      // The unit is guessed by brute force, then how often it repeats.
+      matches.push(StrengthMatch {
+        kind: PatternKind::Repeat,
+        token: token(chars, start, end),
+        start,
+        end,
+        guesses_log2: unit as f64 * brute_force + (count as f64).log2(),
+      });
+    }
+  }
+  matches
+}
+
+/// Returns the row and column of `c` in `KEYBOARD_ROWS`, if any.
+fn key(c: char) -> Option<(usize, usize)> {
+  KEYBOARD_ROWS.iter().enumerate().find_map(|(row, keys)| {
+    keys.chars().position(|k| k == c).map(|col| (row, col))
+  })
+}
+
+fn keyboard_matches(chars: &[char]) -> Vec<StrengthMatch> {
+  runs(chars, |a, b| {
+    let ((row_a, col_a), (row_b, col_b)) = (key(a)?, key(b)?);
+    let step = col_b as i64 - col_a as i64;
+    (row_a == row_b && step.abs() == 1).then_some(step)
+  })
+  .into_iter()
+  .map(|(start, end, _)| {
+    let shifted = chars[start..end]
+      .iter()
+      .any(|&c| key(c).is_some_and(|(row, _)| row >= KEYBOARD_ROWS.len() / 2));
+    let guesses = KEYBOARD_KEYS
+      * 2.0
+      * (end - start) as f64
+      * if shifted { 2.0 } else { 1.0 };
+    StrengthMatch {
+      kind: PatternKind::Keyboard,
+      token: token(chars, start, end),
+      start,
+      end,
+      guesses_log2: guesses.log2(),
+    }
+  })
+  .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn kinds(password: &str) -> Vec<(PatternKind, String)> {
+    score(password)
+      .matches
+      .into_iter()
+      .map(|m| (m.kind, m.token))
+      .collect()
+  }
+
+  #[test]
+  fn test_patterns() {
+    assert_eq!(
+      kinds("P@ssword"),
+      [(PatternKind::Dictionary, "P@ssword".to_string())]
+    );
+    assert_eq!(
+      kinds("xkcd9876"),
+      [(PatternKind::Sequence, "9876".to_string())]
+    );
+    assert_eq!(
+      kinds("Zaaaaaa"),
+      [(PatternKind::Repeat, "aaaaaa".to_string())]
+    );
+    assert_eq!(
+      kinds("asdfgh!"),
+      [(PatternKind::Keyboard, "asdfgh".to_string())]
+    );
+    assert!(kinds("k7#Qp2!x").is_empty());
+  }
+
+  #[test]
+  fn test_score() {
+    assert_eq!(score("password").score(), 0);
+    assert_eq!(score("qwerty123").score(), 0);
+    assert!(score("abcabcabcabc").score() <= 1);
+    assert_eq!(score("k7#Qp2!xLm9@vR4z").score(), 4);
+    assert!(score("").guesses_log2 == 0.0);
+  }
+
+  #[test]
+  fn test_brute_force() {
+    let report = score("k7#Qp2!x");
+    let expected = 8.0 * (26.0f64 + 26.0 + 10.0 + 33.0).log2();
+    assert!((report.guesses_log2 - expected).abs() < 1e-9);
+    assert_eq!(report.attack_estimate().entropy_bits, report.guesses_log2);
+  }
+
+  #[test]
+  fn test_render_strength_bar() {
+    assert_eq!(render_strength_bar(0, 10), "\x1b[31m██░░░░░░░░\x1b[0m");
+    assert_eq!(render_strength_bar(2, 10), "\x1b[33m██████░░░░\x1b[0m");
+    assert_eq!(render_strength_bar(4, 10), "\x1b[32m██████████\x1b[0m");
+    assert_eq!(render_strength_bar(9, 5), render_strength_bar(4, 5));
+    assert_eq!(render_strength_bar(1, 0), "\x1b[31m\x1b[0m");
+  }
+}
@@ -0,0 +1,77 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+/// A low-complexity structure that password filters, such as cracklib-based
+/// PAM modules, reject regardless of length and character classes.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Structure {
+  /// The password repeats a shorter string, e.g. `abcabc` or `aaaaaaaa`.
+  Repetition,
+  /// The password reads the same backwards, e.g. `ab1221ba`.
+  Palindrome,
+  /// Every character is the previous one shifted by the same step, e.g.
+  /// `abcdefgh` or `97531`.
+  Sequence,
+}
+
+impl std::fmt::Display for Structure {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      Structure::Repetition => write!(f, "repetition"),
+      Structure::Palindrome => write!(f, "palindrome"),
+      Structure::Sequence => write!(f, "sequence"),
+    }
+  }
+}
+
+/// Returns the low-complexity structure of `password`, if any. Passwords of
+/// fewer than two characters have none.
+pub fn find_structure(password: &str) -> Option<Structure> {
+  let chars: Vec<char> = password.chars().collect();
+  let n = chars.len();
+  if n < 2 {
+    return None;
+  }
+
+  let repeats = |unit: usize| (unit..n).all(|i| chars[i] == chars[i - unit]);
+  if (1..n).filter(|&unit| n.is_multiple_of(unit)).any(repeats) {
+    return Some(Structure::Repetition);
+  }
+
+  if chars.iter().eq(chars.iter().rev()) {
+    return Some(Structure::Palindrome);
+  }
+
+  let step = |i: usize| chars[i] as i64 - chars[i - 1] as i64;
+  if (2..n).all(|i| step(i) == step(1)) {
+    return Some(Structure::Sequence);
+  }
+
+  None
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_find_structure() {
+    assert_eq!(find_structure("aaaaaaaa"), Some(Structure::Repetition));
+    assert_eq!(find_structure("abcabc"), Some(Structure::Repetition));
+    assert_eq!(find_structure("Xy1Xy1Xy1"), Some(Structure::Repetition));
+    assert_eq!(find_structure("ab1221ba"), Some(Structure::Palindrome));
+    assert_eq!(find_structure("racecar"), Some(Structure::Palindrome));
+    assert_eq!(find_structure("abcdefgh"), Some(Structure::Sequence));
+    assert_eq!(find_structure("97531"), Some(Structure::Sequence));
+    assert_eq!(find_structure("abcabd"), None);
+    assert_eq!(find_structure("k7#Qp2!x"), None);
+    assert_eq!(find_structure("a"), None);
+    assert_eq!(find_structure(""), None);
+  }
+
+  #[test]
+  fn test_structure_display() {
+    assert_eq!(Structure::Palindrome.to_string(), "palindrome");
+  }
+}
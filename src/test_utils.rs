@@ -0,0 +1,94 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+//! Helpers for testing code that generates passwords with pwdg, enabled by
+//! the `test-utils` feature.
+//!
+//! None of the RNGs here are secure. Use them only in tests, through
+//! `PwdGen::gen_with_rng`.
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::Policy;
+
+pub use rand::rngs::mock::StepRng;
+
+/// Returns a mock RNG whose output starts at `initial` and increases by
+/// `increment`, so that every password it is used for is the same.
+pub fn step_rng(initial: u64, increment: u64) -> StepRng {
+  StepRng::new(initial, increment)
+}
+
+/// Returns an RNG seeded with `seed`, which draws realistic but repeatable
+/// passwords.
+pub fn seeded_rng(seed: u64) -> StdRng {
+  StdRng::seed_from_u64(seed)
+}
+
+/// Panics with every reason why `password` is not valid under `policy`. See
+/// `Policy::password_violations`.
+#[track_caller]
+pub fn assert_allowed(policy: &Policy, password: &str) {
+  let violations = policy.password_violations(password);
+  if !violations.is_empty() {
+    let reasons: Vec<String> =
+      violations.iter().map(ToString::to_string).collect();
+    panic!(
+      "password {:?} is not allowed by the policy: {}",
+      password,
+      reasons.join("; ")
+    );
+  }
+}
+
+/// Generates `count` passwords for `policy` from `seeded_rng(seed)` and
+/// asserts that each is allowed by it.
+#[track_caller]
+pub fn assert_generates_allowed(policy: &Policy, seed: u64, count: usize) {
+  let generator = policy.generator().expect("policy is valid");
+  let mut rng = seeded_rng(seed);
+  for _ in 0..count {
+    assert_allowed(policy, &generator.gen_with_rng(&mut rng));
+  }
+}
+
+#[cfg(all(test, feature = "builtin-charsets"))]
+mod tests {
+  use super::*;
+  use crate::PwdGenOptions;
+
+  fn policy() -> Policy {
+    Policy::new(
+      12,
+      PwdGenOptions {
+        min_digit: 3,
+        ..Default::default()
+      },
+    )
+  }
+
+  #[test]
+  fn test_rngs_are_deterministic() {
+    let generator = policy().generator().unwrap();
+    assert_eq!(
+      generator.gen_with_rng(&mut step_rng(0, 1)),
+      generator.gen_with_rng(&mut step_rng(0, 1))
+    );
+    assert_eq!(
+      generator.gen_with_rng(&mut seeded_rng(7)),
+      generator.gen_with_rng(&mut seeded_rng(7))
+    );
+  }
+
+  #[test]
+  fn test_assert_generates_allowed() {
+    assert_generates_allowed(&policy(), 42, 100);
+  }
+
+  #[test]
+  #[should_panic(expected = "password \"abc\" is not allowed by the policy")]
+  fn test_assert_allowed_panics() {
+    assert_allowed(&policy(), "abc");
+  }
+}
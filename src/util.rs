@@ -2,8 +2,10 @@
 Copyright 2024 Owain Davies
 SPDX-License-Identifier: Apache-2.0
 */
+mod exclude;
 mod filter;
 mod uint;
 
-pub use filter::filtered_range;
+pub use exclude::parse_exclusions;
+pub use filter::{filtered_range, unique};
 pub use uint::checked_sum;
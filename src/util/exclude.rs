@@ -0,0 +1,175 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use crate::charset::{builtin_chars, BUILTIN_CLASSES};
+use crate::Error;
+
+/// Expands an exclusion pattern into the characters it denotes.
+///
+/// A pattern is a comma-separated list of items, each of which is a range of
+/// letters or digits (`a-f`), a bracket expression of characters and ranges
+/// (`[0-9_]`), or a built-in class (`class:special`). If no item is any of
+/// these, the whole pattern is taken literally, so plain lists such as `O0Il`
+/// and `+-=` keep working. A pattern mixing both kinds of item is ambiguous
+/// and fails with `Error::InvalidExclusion`, rather than being read in a way
+/// that excludes other characters than intended.
+pub fn parse_exclusions(pattern: &str) -> Result<String, Error> {
+  let items: Vec<&str> = pattern.split(',').collect();
+  match items.iter().find(|item| !is_structured(item)) {
+    Some(_) if !items.iter().any(|item| is_structured(item)) => {
+      return Ok(pattern.to_string());
+    }
+    Some(item) => {
+      return Err(Error::InvalidExclusion(format!(
+        "'{}' is not a range, bracket expression, or class, so the pattern \
+         is ambiguous; write ranges in brackets, e.g. [{}]",
+        item, item
+      )));
+    }
+    None => {}
+  }
+
+  let mut chars = String::new();
+  for item in items {
+    if let Some(name) = item.strip_prefix("class:") {
+      let category = BUILTIN_CLASSES
+        .into_iter()
+        .find(|category| category.to_string() == name)
+        .ok_or_else(|| {
+          Error::InvalidExclusion(format!("unknown class '{}'", name))
+        })?;
+      chars.extend(builtin_chars(&category));
+    } else if let Some(inner) = item
+      .strip_prefix('[')
+      .and_then(|item| item.strip_suffix(']'))
+    {
+      expand_bracket(inner, &mut chars)?;
+    } else {
+      expand_bracket(item, &mut chars)?;
+    }
+  }
+
+  Ok(chars)
+}
+
+fn is_structured(item: &str) -> bool {
+  item.starts_with("class:")
+    || (item.len() >= 3 && item.starts_with('[') && item.ends_with(']'))
+    || is_bare_range(item)
+}
+
+/// Whether `item` is a range outside brackets, such as `a-f`. Its ends must
+/// be both lowercase letters, both uppercase letters, or both digits, so
+/// that lists of symbols such as `+-=` are not read as ranges.
+fn is_bare_range(item: &str) -> bool {
+  let chars: Vec<char> = item.chars().collect();
+  let kind = |c: char| {
+    if c.is_ascii_lowercase() {
+      Some(0)
+    } else if c.is_ascii_uppercase() {
+      Some(1)
+    } else if c.is_ascii_digit() {
+      Some(2)
+    } else {
+      None
+    }
+  };
+  chars.len() == 3
+    && chars[1] == '-'
+    && kind(chars[0]).is_some()
+    && kind(chars[0]) == kind(chars[2])
+}
+
+/// Appends the characters and `x-y` ranges of `inner` to `chars`. A `-` at
+/// either end is literal.
+fn expand_bracket(inner: &str, chars: &mut String) -> Result<(), Error> {
+  let inner: Vec<char> = inner.chars().collect();
+  let mut i = 0;
+  while i < inner.len() {
+    if i + 2 < inner.len() && inner[i + 1] == '-' {
+      let (start, end) = (inner[i], inner[i + 2]);
+      if start > end {
+        return Err(Error::InvalidExclusion(format!(
+          "range '{}-{}' is out of order",
+          start, end
+        )));
+      }
+      chars.extend(start..=end);
+      i += 3;
+    } else {
+      chars.push(inner[i]);
+      i += 1;
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_literal_pattern() {
+    assert_eq!(parse_exclusions("O0Il").unwrap(), "O0Il");
+    assert_eq!(parse_exclusions("#$%&").unwrap(), "#$%&");
+    assert_eq!(parse_exclusions("+-=,.").unwrap(), "+-=,.");
+    // Ranges outside brackets are only of letters or digits.
+    assert_eq!(parse_exclusions("+-=").unwrap(), "+-=");
+    assert_eq!(parse_exclusions("a-F").unwrap(), "a-F");
+  }
+
+  #[test]
+  fn test_ranges() {
+    assert_eq!(parse_exclusions("a-f,A-F").unwrap(), "abcdefABCDEF");
+    assert_eq!(parse_exclusions("[a-f],[A-F]").unwrap(), "abcdefABCDEF");
+    assert_eq!(parse_exclusions("0-9").unwrap(), "0123456789");
+  }
+
+  #[test]
+  fn test_ambiguous_pattern() {
+    assert!(matches!(
+      parse_exclusions("a-f,+-="),
+      Err(Error::InvalidExclusion(_))
+    ));
+    assert!(matches!(
+      parse_exclusions("[0-9],xyz"),
+      Err(Error::InvalidExclusion(_))
+    ));
+  }
+
+  #[test]
+  fn test_bracket_expression() {
+    assert_eq!(parse_exclusions("[0-9]").unwrap(), "0123456789");
+    assert_eq!(parse_exclusions("[a-c_-]").unwrap(), "abc_-");
+  }
+
+  #[test]
+  fn test_out_of_order_range() {
+    assert!(matches!(
+      parse_exclusions("[z-a]"),
+      Err(Error::InvalidExclusion(_))
+    ));
+  }
+
+  #[test]
+  fn test_unknown_class() {
+    assert!(matches!(
+      parse_exclusions("class:foo"),
+      Err(Error::InvalidExclusion(_))
+    ));
+  }
+
+  #[cfg(feature = "builtin-charsets")]
+  #[test]
+  fn test_class() {
+    assert_eq!(parse_exclusions("class:digit").unwrap(), "0123456789");
+    assert_eq!(
+      parse_exclusions("class:special,[x-z]").unwrap(),
+      crate::SPECIAL_CHARS
+        .iter()
+        .chain(&['x', 'y', 'z'])
+        .collect::<String>()
+    );
+  }
+}
@@ -19,6 +19,16 @@ where
   }
 }
 
+/// Collects `iter` into a vector, keeping only the first occurrence of each
+/// item.
+pub fn unique<T>(iter: impl Iterator<Item = T>) -> Vec<T>
+where
+  T: Eq + Hash + Clone,
+{
+  let mut seen = HashSet::new();
+  iter.filter(|item| seen.insert(item.clone())).collect()
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -60,4 +70,16 @@ mod tests {
     let result: Vec<char> = filtered_range(range, &Some(exclusions));
     assert_eq!(result, vec!['a', 'b', 'c']);
   }
+
+  #[test]
+  fn test_unique_preserves_first_occurrence() {
+    let result: Vec<char> = unique("abacbd".chars());
+    assert_eq!(result, vec!['a', 'b', 'c', 'd']);
+  }
+
+  #[test]
+  fn test_unique_empty() {
+    let result: Vec<char> = unique("".chars());
+    assert!(result.is_empty());
+  }
 }
@@ -13,10 +13,7 @@ where
 {
   let mut acc = T::default();
   for item in iter {
-    acc = match acc.checked_add(item) {
-      Some(sum) => sum,
-      None => return None,
-    };
+    acc = acc.checked_add(item)?;
   }
   Some(acc)
 }
@@ -39,11 +36,8 @@ mod tests {
 
   #[test]
   fn test_checked_sum_success() {
-    assert_eq!(checked_sum([1 as u8, 2, 3].iter().cloned()), Some(6));
-    assert_eq!(
-      checked_sum([100 as u16, 200, 300].iter().cloned()),
-      Some(600)
-    );
+    assert_eq!(checked_sum([1_u8, 2, 3].iter().cloned()), Some(6));
+    assert_eq!(checked_sum([100_u16, 200, 300].iter().cloned()), Some(600));
     assert_eq!(
       checked_sum([u32::MAX - 200, 100, 100].iter().cloned()),
       Some(u32::MAX)
@@ -0,0 +1,333 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use sha2::{Digest, Sha256};
+use std::fmt::Display;
+
+use crate::util::unique;
+use crate::{AttackEstimate, Category, Conflict, Error, Fix, Warning};
+
+/// The characters of a class that remain after applying exclusions.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct EffectiveClass {
+  pub category: Category,
+  pub chars: Vec<char>,
+  /// Minimum number of characters required from this class.
+  pub min: usize,
+}
+
+/// The configuration a password generator uses after validation.
+///
+/// Returned by `PwdGenOptions::validate`, so that front-ends can show the
+/// effective character set and entropy of a policy without constructing a
+/// generator.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct EffectiveConfig {
+  pub length: usize,
+  /// Classes in generation order. Classes left empty by exclusions are kept.
+  pub classes: Vec<EffectiveClass>,
+  /// Union of the characters of all classes, without duplicates.
+  pub charset: Vec<char>,
+  /// Characters that those beyond the minimums are drawn from, without
+  /// duplicates, if `PwdGenOptions::fill_classes` restricts them. Otherwise
+  /// they are drawn from `charset`.
+  pub fill: Option<Vec<char>>,
+  /// Characters that the first character is drawn from, if
+  /// `PwdGenOptions::first_char_class` is set.
+  pub first: Option<Vec<char>>,
+  /// Whether passwords with a simple structure are regenerated, as set by
+  /// `PwdGenOptions::reject_structures`.
+  pub reject_structures: bool,
+}
+
+impl EffectiveConfig {
+  pub(crate) fn new(length: usize, classes: Vec<EffectiveClass>) -> Self {
+    let charset =
+      unique(classes.iter().flat_map(|class| class.chars.iter().cloned()));
+    EffectiveConfig {
+      length,
+      classes,
+      charset,
+      fill: None,
+      first: None,
+      reject_structures: false,
+    }
+  }
+
+  /// Estimated entropy of a generated password, in bits.
+  ///
+  /// Computed as `length * log2(charset size)`, which treats every character
+  /// as drawn uniformly from the whole charset. Minimum requirements reduce
+  /// the true entropy slightly.
+  ///
+  /// If `fill` is restricted, the characters required by each minimum count
+  /// `log2(class size)` bits each and the rest `log2(fill size)` bits each.
+  pub fn entropy_bits(&self) -> f64 {
+    let Some(fill) = &self.fill else {
+      return self.length as f64 * (self.charset.len() as f64).log2();
+    };
+    let mut bits = 0.0;
+    let mut remaining = self.length;
+    for class in self.classes.iter().filter(|class| class.min > 0) {
+      bits += class.min as f64 * (class.chars.len() as f64).log2();
+      remaining = remaining.saturating_sub(class.min);
+    }
+    if remaining > 0 {
+      bits += remaining as f64 * (fill.len() as f64).log2();
+    }
+    bits
+  }
+
+  /// Estimates how long attackers would take to guess a password, from
+  /// `entropy_bits`.
+  pub fn attack_estimate(&self) -> AttackEstimate {
+    AttackEstimate::new(self.entropy_bits())
+  }
+
+  /// Returns the SHA-256 hash, in hexadecimal, of the length, of the
+  /// characters and minimum of each class, of any restricted `fill` and
+  /// `first`, and of `reject_structures`, which determine the passwords that
+  /// can be generated. Stable across releases.
+  ///
+  /// Each is hashed after a tag naming it, in that order. Tags and strings
+  /// are hashed as their length in bytes followed by their UTF-8 bytes, and
+  /// lengths and numbers as 64-bit big-endian integers.
+  pub fn fingerprint(&self) -> String {
+    let mut hasher = Sha256::new();
+    let string = |hasher: &mut Sha256, text: &str| {
+      hasher.update((text.len() as u64).to_be_bytes());
+      hasher.update(text);
+    };
+    let chars = |hasher: &mut Sha256, chars: &[char]| {
+      string(hasher, &chars.iter().collect::<String>());
+    };
+
+    string(&mut hasher, "pwdg-policy-v1");
+    string(&mut hasher, "length");
+    hasher.update((self.length as u64).to_be_bytes());
+    for class in &self.classes {
+      string(&mut hasher, "class");
+      string(&mut hasher, &class.category.to_string());
+      hasher.update((class.min as u64).to_be_bytes());
+      chars(&mut hasher, &class.chars);
+    }
+    if let Some(fill) = &self.fill {
+      string(&mut hasher, "fill");
+      chars(&mut hasher, fill);
+    }
+    if let Some(first) = &self.first {
+      string(&mut hasher, "first");
+      chars(&mut hasher, first);
+    }
+    if self.reject_structures {
+      string(&mut hasher, "reject-structures");
+    }
+    hex::encode(hasher.finalize())
+  }
+}
+
+/// A structured description of a password generator, for rendering its
+/// configuration in a front-end.
+///
+/// Returned by `PwdGen::describe`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct GeneratorDescription {
+  pub length: usize,
+  /// Classes in generation order, after exclusions. The size of a class is
+  /// the number of its characters.
+  pub classes: Vec<EffectiveClass>,
+  /// Characters excluded by the options, without duplicates.
+  pub excluded: Vec<char>,
+  /// Number of distinct characters a password may contain.
+  pub charset_size: usize,
+  /// Estimated entropy in bits. See `EffectiveConfig::entropy_bits`.
+  pub entropy_bits: f64,
+  /// Plain-language constraints, e.g. "at least 2 uppercase".
+  pub constraints: Vec<String>,
+  /// Risks of the configuration. See `PwdGen::warnings`.
+  pub warnings: Vec<Warning>,
+}
+
+/// Every constraint violated by a password generator configuration.
+///
+/// Returned by `PwdGen::validate`. An empty report means the configuration
+/// is valid.
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+  errors: Vec<Error>,
+  conflicts: Vec<Conflict>,
+  fixes: Vec<Fix>,
+}
+
+impl ValidationReport {
+  pub(crate) fn new(
+    errors: Vec<Error>,
+    conflicts: Vec<Conflict>,
+    fixes: Vec<Fix>,
+  ) -> Self {
+    ValidationReport {
+      errors,
+      conflicts,
+      fixes,
+    }
+  }
+
+  /// Returns `true` if no constraint is violated.
+  pub fn is_valid(&self) -> bool {
+    self.errors.is_empty()
+  }
+
+  /// Returns the violated constraints, in the order they were checked.
+  pub fn errors(&self) -> &[Error] {
+    &self.errors
+  }
+
+  /// Consumes the report, returning the violated constraints.
+  pub fn into_errors(self) -> Vec<Error> {
+    self.errors
+  }
+
+  /// Returns the contradictions between minimums and exclusions, which
+  /// explain some of the errors and suggest fixes. A valid configuration
+  /// may have conflicts that cause no error.
+  pub fn conflicts(&self) -> &[Conflict] {
+    &self.conflicts
+  }
+
+  /// Returns the minimal changes to a single option that each make the
+  /// configuration valid, e.g. "reduce min_special to 2" or "increase
+  /// length to 14". Empty if the configuration is valid or no single
+  /// change suffices.
+  pub fn fixes(&self) -> &[Fix] {
+    &self.fixes
+  }
+}
+
+impl std::error::Error for ValidationReport {}
+
+impl std::fmt::Display for ValidationReport {
+  /// Writes each violated constraint, then each conflict, on its own line,
+  /// then the fixes, if any, on one line.
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    let errors = self.errors.iter().map(|error| error as &dyn Display);
+    let conflicts = self.conflicts.iter().map(|c| c as &dyn Display);
+    for (i, problem) in errors.chain(conflicts).enumerate() {
+      if i > 0 {
+        writeln!(f)?;
+      }
+      write!(f, "{}", problem)?;
+    }
+    if !self.fixes.is_empty() {
+      let fixes: Vec<String> =
+        self.fixes.iter().map(ToString::to_string).collect();
+      write!(f, "\nTo fix: {}.", fixes.join(" or "))?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_effective_config_charset_and_entropy() {
+    let classes = vec![
+      EffectiveClass {
+        category: Category::Lower,
+        chars: vec!['a', 'b', 'c', 'd'],
+        min: 1,
+      },
+      EffectiveClass {
+        category: Category::Custom("extra".to_string()),
+        chars: vec!['c', 'd', 'e', 'f', 'g', 'h'],
+        min: 0,
+      },
+    ];
+    let config = EffectiveConfig::new(10, classes);
+
+    assert_eq!(config.charset, vec!['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h']);
+    assert_eq!(config.entropy_bits(), 30.0);
+  }
+
+  #[test]
+  fn test_fingerprint() {
+    let config = |min| {
+      EffectiveConfig::new(
+        10,
+        vec![EffectiveClass {
+          category: Category::Lower,
+          chars: vec!['a', 'b', 'c', 'd'],
+          min,
+        }],
+      )
+    };
+
+    assert_eq!(
+      config(1).fingerprint(),
+      "c4d445fa4d925bfe5566dc53e18595f1ac501c55e25f7f47453022af6d88e383"
+    );
+    assert_ne!(config(1).fingerprint(), config(2).fingerprint());
+
+    let mut restricted = config(1);
+    restricted.fill = Some(vec!['a', 'b']);
+    assert_ne!(restricted.fingerprint(), config(1).fingerprint());
+    assert_eq!(restricted.entropy_bits(), 2.0 + 9.0);
+
+    let mut first = config(1);
+    first.first = Some(vec!['a', 'b']);
+    assert_ne!(first.fingerprint(), config(1).fingerprint());
+
+    let mut rejecting = config(1);
+    rejecting.reject_structures = true;
+    assert_ne!(rejecting.fingerprint(), config(1).fingerprint());
+  }
+
+  #[test]
+  fn test_empty_report_is_valid() {
+    let report = ValidationReport::default();
+    assert!(report.is_valid());
+    assert_eq!(format!("{}", report), "");
+  }
+
+  #[test]
+  fn test_report_display_lists_every_error() {
+    let report = ValidationReport::new(
+      vec![Error::Length, Error::MinLimitExceeded],
+      vec![Conflict::RequiredCharsExcluded {
+        category: Category::Custom("symbols".to_string()),
+        excluded: vec!['#'],
+      }],
+      Vec::new(),
+    );
+    let display = format!("{}", report);
+
+    assert!(!report.is_valid());
+    assert_eq!(display.lines().count(), 3);
+    assert!(display.contains("[Error::Length]"));
+    assert!(display.contains("[Error::MinLimitExceeded]"));
+    assert!(display.ends_with("[Conflict::RequiredCharsExcluded]"));
+  }
+
+  #[test]
+  fn test_report_display_lists_fixes() {
+    let report = ValidationReport::new(
+      vec![Error::MinLimitExceeded],
+      Vec::new(),
+      vec![
+        Fix::Length(14),
+        Fix::Minimum {
+          category: Category::Special,
+          min: 2,
+        },
+      ],
+    );
+    assert_eq!(
+      report.to_string(),
+      "Sum of minimum character requirements exceeds password length. \
+       [Error::MinLimitExceeded]\n\
+       To fix: increase length to 14 or reduce min_special to 2."
+    );
+  }
+}
@@ -0,0 +1,165 @@
+/*
+Copyright 2024 Owain Davies
+SPDX-License-Identifier: Apache-2.0
+*/
+use crate::{Category, EffectiveConfig};
+
+/// Estimated entropy, in bits, below which passwords are considered weak.
+pub const LOW_ENTROPY_BITS: f64 = 48.0;
+
+/// Number of distinct characters below which a charset is considered tiny.
+pub const SMALL_CHARSET_SIZE: usize = 10;
+
+/// A risky, but valid, password generator configuration.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Warning {
+  /// Estimated entropy, in bits, is below `LOW_ENTROPY_BITS`.
+  LowEntropy(f64),
+  /// Fewer than `SMALL_CHARSET_SIZE` distinct characters remain after
+  /// exclusions.
+  SmallCharset(usize),
+  /// The minimums add up to the length, so the number of characters of each
+  /// class is fixed and only their order and choice are random.
+  FixedComposition,
+  /// A class has no characters left after exclusions. Not reported for
+  /// options with a charset, which empties classes by design.
+  EmptyClass(Category),
+  /// Excluded characters that no class or charset contains, so that
+  /// excluding them has no effect. Often a typo.
+  UnusedExclusions(Vec<char>),
+}
+
+impl std::fmt::Display for Warning {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      Warning::LowEntropy(bits) => {
+        write!(
+          f,
+          "Estimated entropy of {:.1} bits is below {} bits. \
+           [Warning::LowEntropy]",
+          bits, LOW_ENTROPY_BITS
+        )
+      }
+      Warning::SmallCharset(size) => {
+        write!(
+          f,
+          "Only {} distinct characters remain. [Warning::SmallCharset]",
+          size
+        )
+      }
+      Warning::FixedComposition => {
+        write!(
+          f,
+          concat!(
+            "Minimum requirements fix the number of characters of every ",
+            "class. [Warning::FixedComposition]"
+          )
+        )
+      }
+      Warning::EmptyClass(category) => {
+        write!(
+          f,
+          "No {} characters remain after exclusions. [Warning::EmptyClass]",
+          category
+        )
+      }
+      Warning::UnusedExclusions(chars) => {
+        write!(
+          f,
+          "Excluding characters outside the charset has no effect: {}. \
+           [Warning::UnusedExclusions]",
+          chars.iter().collect::<String>()
+        )
+      }
+    }
+  }
+}
+
+/// Returns every warning about `config`, where `min_total` is the sum of the
+/// minimums, `has_charset` whether the options restrict the charset, and
+/// `unused_exclusions` the excluded characters outside the charset.
+pub(crate) fn check(
+  config: &EffectiveConfig,
+  min_total: usize,
+  has_charset: bool,
+  unused_exclusions: Vec<char>,
+) -> Vec<Warning> {
+  let mut warnings = Vec::new();
+
+  let bits = config.entropy_bits();
+  if bits < LOW_ENTROPY_BITS {
+    warnings.push(Warning::LowEntropy(bits));
+  }
+  if config.charset.len() < SMALL_CHARSET_SIZE {
+    warnings.push(Warning::SmallCharset(config.charset.len()));
+  }
+  if min_total == config.length {
+    warnings.push(Warning::FixedComposition);
+  }
+  if !has_charset {
+    warnings.extend(
+      config
+        .classes
+        .iter()
+        .filter(|class| class.chars.is_empty())
+        .map(|class| Warning::EmptyClass(class.category.clone())),
+    );
+  }
+  if !unused_exclusions.is_empty() {
+    warnings.push(Warning::UnusedExclusions(unused_exclusions));
+  }
+
+  warnings
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::EffectiveClass;
+
+  fn config(length: usize, chars: &str) -> EffectiveConfig {
+    EffectiveConfig::new(
+      length,
+      vec![
+        EffectiveClass {
+          category: Category::Lower,
+          chars: chars.chars().collect(),
+          min: 0,
+        },
+        EffectiveClass {
+          category: Category::Digit,
+          chars: Vec::new(),
+          min: 0,
+        },
+      ],
+    )
+  }
+
+  #[test]
+  fn test_no_warnings() {
+    let config = config(16, "abcdefghijklmnop");
+    assert!(check(&config, 0, true, Vec::new()).is_empty());
+  }
+
+  #[test]
+  fn test_every_warning() {
+    let config = config(8, "abc");
+    let warnings = check(&config, 8, false, vec!['§']);
+    assert_eq!(
+      warnings,
+      vec![
+        Warning::LowEntropy(8.0 * 3f64.log2()),
+        Warning::SmallCharset(3),
+        Warning::FixedComposition,
+        Warning::EmptyClass(Category::Digit),
+        Warning::UnusedExclusions(vec!['§']),
+      ]
+    );
+    assert!(warnings[0].to_string().ends_with("[Warning::LowEntropy]"));
+    assert_eq!(
+      warnings[4].to_string(),
+      "Excluding characters outside the charset has no effect: §. \
+       [Warning::UnusedExclusions]"
+    );
+  }
+}
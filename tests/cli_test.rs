@@ -3,7 +3,8 @@ Copyright 2024 Owain Davies
 SPDX-License-Identifier: Apache-2.0
 */
 use pwdg::SPECIAL_CHARS;
-use std::process::Command;
+use std::io::Write;
+use std::process::{Command, Stdio};
 
 fn run_app(args: &[&str]) -> Result<String, String> {
   let path = if cfg!(debug_assertions) {
@@ -73,6 +74,46 @@ fn test_reject_short_password() {
   }
 }
 
+fn run_app_with_stdin(args: &[&str], stdin: &str) -> Result<String, String> {
+  run_app_with_env(args, stdin, &[])
+}
+
+fn run_app_with_env(
+  args: &[&str],
+  stdin: &str,
+  env: &[(&str, &std::ffi::OsStr)],
+) -> Result<String, String> {
+  let path = if cfg!(debug_assertions) {
+    "./target/debug/pwdg"
+  } else {
+    "./target/release/pwdg"
+  };
+
+  let mut child = Command::new(path)
+    .args(args)
+    .envs(env.iter().cloned())
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .expect("failed to execute process");
+
+  child
+    .stdin
+    .take()
+    .expect("stdin is piped")
+    .write_all(stdin.as_bytes())
+    .expect("failed to write to stdin");
+
+  let output = child.wait_with_output().expect("failed to wait on process");
+
+  if output.status.success() {
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+  } else {
+    Err(String::from_utf8_lossy(&output.stderr).into_owned())
+  }
+}
+
 fn count_chars<F>(input: &str, f: F) -> usize
 where
   F: Fn(&char) -> bool,
@@ -91,10 +132,10 @@ fn test_minimum_character_types() {
     "--min-special=2",
   ]) {
     let password = output.trim();
-    assert!(count_chars(&password, |c| c.is_uppercase()) >= 2);
-    assert!(count_chars(&password, |c| c.is_lowercase()) >= 2);
-    assert!(count_chars(&password, |c| c.is_digit(10)) >= 2);
-    assert!(count_chars(&password, |c| SPECIAL_CHARS.contains(c)) >= 2);
+    assert!(count_chars(password, |c| c.is_uppercase()) >= 2);
+    assert!(count_chars(password, |c| c.is_lowercase()) >= 2);
+    assert!(count_chars(password, |c| c.is_ascii_digit()) >= 2);
+    assert!(count_chars(password, |c| SPECIAL_CHARS.contains(c)) >= 2);
   } else {
     panic!("Password should contain at least 2 characters from each category.");
   }
@@ -200,7 +241,7 @@ fn test_strong_password_option() {
       "Password must contain at least one lowercase letter."
     );
     assert!(
-      password.chars().any(|c| c.is_digit(10)),
+      password.chars().any(|c| c.is_ascii_digit()),
       "Password must contain at least one digit."
     );
     assert!(
@@ -234,3 +275,2007 @@ fn test_combined_options_length_and_exclusion() {
     ));
   }
 }
+
+#[test]
+fn test_rng_selftest_option() {
+  if let Ok(output) = run_app(&["--rng-selftest", "-l", "16"]) {
+    assert_eq!(output.trim().len(), 16);
+  } else {
+    panic!("Password generation with the RNG self-test should succeed.");
+  }
+}
+
+#[test]
+fn test_generation_options_with_subcommands() {
+  let output = run_app(&["--rng-selftest", "pin", "--length", "6"])
+    .expect("Subcommands should run the RNG self-test too.");
+  assert_eq!(output.trim().len(), 6);
+
+  for args in [
+    &["--copy", "pin"][..],
+    &["--extra-entropy", "00ff", "salt"],
+    &["--extra-entropy", "00ff", "--save-profile", "p"],
+    &["--copy", "--options-json", "-"],
+  ] {
+    assert!(run_app(args).is_err(), "{:?}", args);
+  }
+}
+
+#[test]
+fn test_extra_entropy_option() {
+  let output = run_app(&["--extra-entropy", "00ff 10ab", "-l", "16"])
+    .expect("Mixing in hex entropy should succeed.");
+  assert_eq!(output.trim_end_matches('\n').chars().count(), 16);
+
+  let output = run_app_with_stdin(
+    &["--extra-entropy", "-", "-l", "16"],
+    "sdkfjh2398 dfhq",
+  )
+  .expect("Mixing in entropy from standard input should succeed.");
+  assert_eq!(output.trim_end_matches('\n').chars().count(), 16);
+
+  assert!(run_app(&["--extra-entropy", "abc"]).is_err());
+  assert!(run_app_with_stdin(&["--extra-entropy", "-"], "").is_err());
+}
+
+#[test]
+fn test_reports_all_validation_errors() {
+  if let Err(err) = run_app(&["-l", "6", "--min-upper=4", "--min-digit=4"]) {
+    assert!(err.contains("[Error::Length]"));
+    assert!(err.contains("[Error::MinLimitExceeded]"));
+  } else {
+    panic!("Invalid options should be rejected.");
+  }
+}
+
+#[test]
+fn test_explain_option() {
+  if let Ok(output) =
+    run_app(&["--explain", "-l", "16", "--min-upper=2", "--exclude=O0Il"])
+  {
+    assert_eq!(
+      output.trim(),
+      "16+ characters, at least 2 uppercase, excludes: O0Il"
+    );
+  } else {
+    panic!("Explaining a valid policy should succeed.");
+  }
+}
+
+#[test]
+fn test_dry_run_option() {
+  let output = run_app(&["--dry-run", "-l", "8", "--exclude", "class:special"])
+    .expect("A dry run of a valid policy should succeed.");
+  let lines: Vec<&str> = output.lines().collect();
+  assert!(lines[0].starts_with("8+ characters, excludes: !@#"));
+  assert_eq!(lines[2], "lower (min 0): abcdefghijklmnopqrstuvwxyz");
+  assert_eq!(lines[4], "special (min 0): (none)");
+  assert_eq!(lines[5], "charset size: 62");
+  assert!(output.contains("[Warning::EmptyClass]"));
+  assert!(output.contains("[Warning::LowEntropy]"));
+
+  let error = run_app(&["--dry-run", "--min-digit", "2", "--exclude", "[0-9]"])
+    .expect_err("A dry run of an invalid policy should fail.");
+  assert!(error.contains("[Error::InsufficientCharacters]"));
+}
+
+/// Runs the app, returning its output with both standard output and standard
+/// error.
+fn run_app_output(args: &[&str]) -> std::process::Output {
+  let path = if cfg!(debug_assertions) {
+    "./target/debug/pwdg"
+  } else {
+    "./target/release/pwdg"
+  };
+
+  Command::new(path)
+    .args(args)
+    .output()
+    .expect("failed to execute process")
+}
+
+#[test]
+fn test_min_bits() {
+  let output = run_app(&["--min-bits", "80", "-l", "13"])
+    .expect("13 characters should reach 80 bits.");
+  assert_eq!(output.trim_end().len(), 13);
+
+  let error = run_app(&["--min-bits", "80", "-l", "12"])
+    .expect_err("12 characters should not reach 80 bits.");
+  assert!(error.contains("[Error::EntropyTooLow]"));
+}
+
+#[test]
+fn test_weak_configuration_warnings() {
+  let output = run_app_output(&["--min-upper", "4", "--min-digit", "4"]);
+  assert!(output.status.success());
+  let stderr = String::from_utf8_lossy(&output.stderr);
+  assert!(stderr.contains("[Warning::FixedComposition]"));
+
+  let output = run_app_output(&[]);
+  assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn test_stats_option() {
+  let output =
+    run_app_output(&["--stats", "-l", "12", "--exclude", "class:special"]);
+  assert!(output.status.success());
+  assert_eq!(String::from_utf8_lossy(&output.stdout).trim().len(), 12);
+
+  let stderr = String::from_utf8_lossy(&output.stderr);
+  assert!(stderr.contains("charset size: 62"));
+  assert!(stderr.contains("bits per character"));
+  assert!(stderr.contains("digit: "));
+  assert!(!stderr.contains("special: "));
+}
+
+#[test]
+fn test_policy_export_json() {
+  if let Ok(output) = run_app(&[
+    "policy",
+    "export",
+    "--format=json",
+    "-l",
+    "16",
+    "--min-digit=2",
+    "--exclude=0",
+  ]) {
+    assert!(output.contains("\"version\": 1"));
+    assert!(output.contains("\"min\": 16"));
+    assert!(output.contains("\"exclude\": \"0\""));
+  } else {
+    panic!("Exporting a policy as JSON should succeed.");
+  }
+}
+
+#[test]
+fn test_policy_strength() {
+  let output = run_app(&["policy", "strength", "-l", "12"])
+    .expect("Estimating the strength of a policy should succeed.");
+  let lines: Vec<&str> = output.lines().collect();
+  assert_eq!(lines.len(), 6);
+  assert!(lines[0].starts_with("entropy: "));
+  assert!(lines[1].starts_with("online-throttled "));
+
+  let output =
+    run_app(&["policy", "strength", "--format", "json", "-l", "12"]).unwrap();
+  let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+  assert_eq!(json["crack_times"].as_array().unwrap().len(), 5);
+  assert!(json["crack_times"][3]["attacker"]["guesses_per_second"].is_number());
+}
+
+#[test]
+fn test_policy_export_json_schema() {
+  if let Ok(output) =
+    run_app(&["policy", "export", "--format=json-schema", "-l", "12"])
+  {
+    assert!(output.contains("\"minLength\": 12"));
+    assert!(output.contains("\"pattern\""));
+  } else {
+    panic!("Exporting a policy as a JSON Schema should succeed.");
+  }
+}
+
+fn write_temp_file(name: &str, contents: &str) -> std::path::PathBuf {
+  let path = std::env::temp_dir().join(format!(
+    "pwdg_cli_test_{}_{}",
+    std::process::id(),
+    name
+  ));
+  std::fs::write(&path, contents).expect("failed to write temporary file");
+  path
+}
+
+#[test]
+fn test_policy_file_json() {
+  let path = write_temp_file(
+    "policy.json",
+    r#"{
+      "version": 1,
+      "length": { "min": 20, "max": null },
+      "classes": [{ "name": "digit", "chars": "0123456789", "min": 5 }],
+      "exclude": "5"
+    }"#,
+  );
+
+  let result = run_app(&["--policy-file", path.to_str().unwrap()]);
+  std::fs::remove_file(&path).ok();
+
+  if let Ok(output) = result {
+    let password = output.trim();
+    assert_eq!(password.len(), 20);
+    assert!(count_chars(password, |c| c.is_ascii_digit()) >= 5);
+    assert!(!password.contains('5'));
+  } else {
+    panic!("Generating from a JSON policy file should succeed.");
+  }
+}
+
+#[test]
+fn test_policy_file_ad_with_override() {
+  let path = write_temp_file(
+    "policy.txt",
+    "ComplexityEnabled : True\nMinPasswordLength : 14\n",
+  );
+
+  let result = run_app(&["--policy-file", path.to_str().unwrap(), "-l", "18"]);
+  std::fs::remove_file(&path).ok();
+
+  if let Ok(output) = result {
+    let password = output.trim();
+    assert_eq!(password.len(), 18);
+    assert!(password.chars().any(|c| c.is_uppercase()));
+    assert!(password.chars().any(|c| c.is_lowercase()));
+    assert!(password.chars().any(|c| c.is_ascii_digit()));
+  } else {
+    panic!("Generating from an AD-style policy file should succeed.");
+  }
+}
+
+#[test]
+fn test_policy_compare() {
+  let strict = write_temp_file(
+    "strict.txt",
+    "ComplexityEnabled : True\nMinPasswordLength : 16\n",
+  );
+  let lenient = write_temp_file("lenient.txt", "MinPasswordLength : 12\n");
+  let strict_path = strict.to_str().unwrap();
+  let lenient_path = lenient.to_str().unwrap();
+
+  let subset = run_app(&["policy", "compare", strict_path, lenient_path]);
+  let not_subset = run_app(&["policy", "compare", lenient_path, strict_path]);
+  std::fs::remove_file(&strict).ok();
+  std::fs::remove_file(&lenient).ok();
+
+  assert!(subset.is_ok(), "A stricter policy should be a subset.");
+  if let Err(err) = not_subset {
+    assert!(err.contains("is not a subset of"));
+  } else {
+    panic!("A more lenient policy should not be a subset.");
+  }
+}
+
+#[test]
+fn test_policy_show_and_validate() {
+  if let Ok(output) = run_app(&["policy", "show", "-l", "12", "--min-digit=2"])
+  {
+    assert_eq!(output.trim(), "12+ characters, at least 2 digits");
+  } else {
+    panic!("Showing a policy should succeed.");
+  }
+
+  if let Err(err) = run_app(&["policy", "validate", "-l", "6"]) {
+    assert!(err.contains("[Error::Length]"));
+  } else {
+    panic!("Validating an invalid policy should fail.");
+  }
+}
+
+#[test]
+fn test_policy_fingerprint() {
+  let fingerprint = run_app(&["policy", "fingerprint", "-l", "12"]).unwrap();
+  let fingerprint = fingerprint.trim();
+  assert_eq!(fingerprint.len(), 64);
+  assert!(fingerprint.chars().all(|c| c.is_ascii_hexdigit()));
+  let again = run_app(&["policy", "fingerprint", "--length=12"]).unwrap();
+  assert_eq!(again.trim(), fingerprint);
+  let other = run_app(&["policy", "fingerprint", "-l", "13"]).unwrap();
+  assert_ne!(other.trim(), fingerprint);
+}
+
+#[test]
+fn test_charsets() {
+  let output = run_app(&["charsets", "--exclude", "O0Il1", "--min-digit", "9"])
+    .expect("Charsets should be printed even for an invalid policy.");
+  let lines: Vec<&str> = output.lines().collect();
+  assert_eq!(lines[0], "upper: 24 characters, 4.58 bits each");
+  assert_eq!(lines[2], "  removed: IO");
+  assert_eq!(lines[6], "digit: 8 characters, 3.00 bits each");
+  assert_eq!(lines[7], "  23456789");
+  assert_eq!(lines[8], "  removed: 01");
+  assert_eq!(
+    lines.last().unwrap(),
+    &"total: 89 characters, 6.48 bits each"
+  );
+
+  let output = run_app(&["charsets", "--preset", "pin"]).unwrap();
+  assert!(output.contains("upper: 0 characters\n  removed: "));
+  assert!(output.ends_with("total: 10 characters, 3.32 bits each\n"));
+}
+
+#[test]
+fn test_policy_import() {
+  let path = write_temp_file("import.txt", "MinPasswordLength : 15\n");
+  let result = run_app(&["policy", "import", path.to_str().unwrap()]);
+  std::fs::remove_file(&path).ok();
+
+  if let Ok(output) = result {
+    assert!(output.contains("\"min\": 15"));
+  } else {
+    panic!("Importing an AD-style policy should succeed.");
+  }
+}
+
+#[test]
+fn test_exclude_from_stdin() {
+  let exclude: String = ('a'..='z').chain('0'..='9').collect();
+  if let Ok(output) = run_app_with_stdin(
+    &["--exclude", "-", "-l", "32"],
+    &format!("{}\n", exclude),
+  ) {
+    let password = output.trim();
+    assert_eq!(password.len(), 32);
+    assert!(!password.chars().any(|c| exclude.contains(c)));
+  } else {
+    panic!("Reading exclusions from stdin should succeed.");
+  }
+}
+
+#[test]
+fn test_exclude_file() {
+  let path = write_temp_file("exclude.txt", "a\nb\n\nAB\nxy\n");
+  let path = path.to_str().unwrap();
+  for _ in 0..20 {
+    let output =
+      run_app(&["--exclude-file", path, "--exclude", "c", "-l", "64"])
+        .expect("Exclusions from a file should be accepted.");
+    let password = output.trim();
+    assert!(!password.contains(['a', 'b', 'c']));
+    assert!(!password.contains("AB") && !password.contains("xy"));
+  }
+}
+
+#[test]
+fn test_exclusion_ranges_and_classes() {
+  let output = run_app(&["--exclude", "[a-z],[0-9],class:special", "-l", "32"])
+    .expect("Exclusion patterns should be accepted.");
+  assert!(output.trim().chars().all(|c| c.is_ascii_uppercase()));
+
+  // A bare item with a '-' is literal, not the range from '+' to '='.
+  let output =
+    run_app(&["--charset", "+-=,.0123", "-e", "+-=", "-l", "16"]).unwrap();
+  assert!(output.trim().chars().all(|c| ",.0123".contains(c)));
+
+  let output = run_app(&["--exclude", "a-f,A-F", "-l", "64"]).unwrap();
+  assert!(!output
+    .trim()
+    .chars()
+    .any(|c| c.is_ascii_hexdigit() && !c.is_ascii_digit()));
+
+  let error = run_app(&["--exclude", "a-f,xyz"]).unwrap_err();
+  assert!(error.contains("[Error::InvalidExclusion]"));
+  let error = run_app(&["--exclude", "class:nope"]).unwrap_err();
+  assert!(error.contains("[Error::InvalidExclusion]"));
+}
+
+#[test]
+fn test_unused_exclusions_warning() {
+  let output = run_app_output(&["--preset", "hex", "--exclude", "aZ"]);
+  assert!(output.status.success());
+  let stderr = String::from_utf8_lossy(&output.stderr);
+  assert!(stderr.contains("no effect: Z. [Warning::UnusedExclusions]"));
+}
+
+#[test]
+fn test_conflicts() {
+  let error = run_app(&["--min-digit", "2", "--exclude", "[0-8]"]).unwrap_err();
+  let lines: Vec<&str> = error.lines().collect();
+  assert_eq!(lines.len(), 3);
+  assert!(lines[0].ends_with("[Error::InsufficientCharacters]"));
+  assert!(lines[1].contains("Lower the minimum to 1"));
+  assert!(lines[1].ends_with("[Conflict::ClassEmptied]"));
+  assert_eq!(lines[2], "To fix: reduce min_digit to 1.");
+}
+
+#[test]
+fn test_exclude_group() {
+  for _ in 0..20 {
+    let output = run_app(&[
+      "--exclude-group",
+      "quotes,slashes",
+      "--exclude-group=brackets",
+      "--exclude",
+      "x",
+      "-l",
+      "64",
+    ])
+    .expect("Exclusion groups should be accepted.");
+    assert!(!output.trim().contains(|c| "\"'`/\\()[]{}<>x".contains(c)));
+  }
+  assert!(run_app(&["--exclude-group", "nope"]).is_err());
+
+  let spec = write_temp_file(
+    "exclude_group.json",
+    r#"[{"label": "a", "exclude-group": ["nope"]}]"#,
+  );
+  let error = run_app(&["batch", spec.to_str().unwrap()]).unwrap_err();
+  assert!(error.contains("Unknown exclusion group 'nope'."));
+}
+
+#[test]
+fn test_fill_classes() {
+  for _ in 0..20 {
+    let output = run_app(&[
+      "--min-special",
+      "3",
+      "--fill-classes",
+      "upper,lower,digit",
+      "-l",
+      "24",
+    ])
+    .expect("Fill classes should be accepted.");
+    let password = output.trim();
+    assert_eq!(password.chars().count(), 24);
+    assert_eq!(
+      password
+        .chars()
+        .filter(|c| !c.is_ascii_alphanumeric())
+        .count(),
+      3
+    );
+  }
+
+  let error = run_app(&["--fill-classes", "accents"]).unwrap_err();
+  assert!(error.contains("unknown fill class 'accents'"));
+}
+
+#[test]
+fn test_first() {
+  for _ in 0..20 {
+    let output = run_app(&["--first", "alpha", "--min-digit", "6", "-l", "8"])
+      .expect("A first character class should be accepted.");
+    let password = output.trim();
+    assert!(password.starts_with(|c: char| c.is_ascii_alphabetic()));
+    assert!(password.chars().filter(char::is_ascii_digit).count() >= 6);
+  }
+
+  let error =
+    run_app(&["--first", "upper", "--min-digit", "8", "-l", "8"]).unwrap_err();
+  assert!(error.contains("[Error::FirstCharClass]"));
+  assert!(run_app(&["--first", "special"]).is_err());
+}
+
+#[test]
+fn test_reject_structures() {
+  let path = write_temp_file("structures_charset.txt", "01");
+  for _ in 0..20 {
+    let output = run_app(&[
+      "--charset-file",
+      path.to_str().unwrap(),
+      "--reject-structures",
+    ])
+    .expect("Rejecting structures should be accepted.");
+    let password: Vec<char> = output.trim().chars().collect();
+    assert!(!password.iter().eq(password.iter().rev()));
+    assert_ne!(password[..4], password[4..]);
+  }
+}
+
+#[test]
+fn test_pwquality() {
+  let conf = write_temp_file(
+    "pwquality.conf",
+    "# Require every class\nminclass = 4\nmaxrepeat = 1\nmaxsequence = 2\n",
+  );
+  for _ in 0..20 {
+    let output = run_app(&["--pwquality", conf.to_str().unwrap(), "-l", "10"])
+      .expect("A pwquality.conf file should be accepted.");
+    let password: Vec<char> = output.trim().chars().collect();
+    assert!(password.iter().any(char::is_ascii_uppercase));
+    assert!(password.iter().any(char::is_ascii_lowercase));
+    assert!(password.iter().any(char::is_ascii_digit));
+    assert!(password.iter().any(|c| !c.is_ascii_alphanumeric()));
+    assert!(password.windows(2).all(|pair| pair[0] != pair[1]));
+  }
+
+  let conf = write_temp_file("pwquality_strict.conf", "minlen = 64\n");
+  let error =
+    run_app(&["--pwquality", conf.to_str().unwrap(), "-l", "8"]).unwrap_err();
+  assert!(error.contains("libpwquality accepts"));
+
+  let conf = write_temp_file("pwquality_invalid.conf", "minclass = all\n");
+  let error = run_app(&["--pwquality", conf.to_str().unwrap()]).unwrap_err();
+  assert!(error.contains("invalid pwquality setting 'minclass = all'"));
+}
+
+#[test]
+fn test_account_name() {
+  let charset = write_temp_file("account_charset.txt", "abc");
+  for _ in 0..20 {
+    let output = run_app(&[
+      "--charset-file",
+      charset.to_str().unwrap(),
+      "--account-name",
+      "ABC",
+    ])
+    .expect("An account name should be accepted.");
+    assert!(!output.contains("abc"));
+  }
+
+  let output = run_app(&[
+    "--preset",
+    "windows",
+    "--account-name",
+    "jsmith",
+    "--display-name",
+    "Jo Smith",
+  ])
+  .expect("The windows preset should be accepted.");
+  let password = output.trim();
+  assert_eq!(password.chars().count(), 14);
+  assert!(password.chars().any(|c| c.is_ascii_uppercase()));
+  assert!(password.chars().any(|c| c.is_ascii_lowercase()));
+  assert!(password.chars().any(|c| c.is_ascii_digit()));
+
+  assert!(run_app(&["--display-name", "Jo Smith"]).is_err());
+}
+
+#[test]
+fn test_random_case() {
+  let charset = write_temp_file("random_case_charset.txt", "abcdefgh");
+  let args = ["--charset-file", charset.to_str().unwrap(), "--random-case"];
+  let outputs: Vec<String> = (0..20)
+    .map(|_| run_app(&args).expect("Random case should be accepted."))
+    .collect();
+  for output in &outputs {
+    assert!(output
+      .trim()
+      .chars()
+      .all(|c| "abcdefgh".contains(c.to_ascii_lowercase())));
+  }
+  assert!(outputs
+    .iter()
+    .any(|output| output.contains(char::is_uppercase)));
+
+  let path = if cfg!(debug_assertions) {
+    "./target/debug/pwdg"
+  } else {
+    "./target/release/pwdg"
+  };
+  let output = Command::new(path)
+    .args(args)
+    .output()
+    .expect("failed to execute process");
+  assert!(String::from_utf8_lossy(&output.stderr)
+    .contains("random case: +8.0 bits of entropy\n"));
+
+  let error = run_app(&["--random-case", "--min-upper", "1"]).unwrap_err();
+  assert!(error.contains("could break the minimum of the upper class"));
+}
+
+#[test]
+fn test_inject() {
+  let charset = write_temp_file("inject_charset.txt", "abc");
+  for _ in 0..20 {
+    let output = run_app(&[
+      "--charset-file",
+      charset.to_str().unwrap(),
+      "--exclude",
+      "[0-8]",
+      "--inject",
+      "2d1s",
+    ])
+    .expect("An injection spec should be accepted.");
+    let password = output.trim();
+    assert_eq!(password.chars().count(), 11);
+    assert_eq!(password.matches('9').count(), 2);
+    assert_eq!(
+      password
+        .chars()
+        .filter(|c| SPECIAL_CHARS.contains(c))
+        .count(),
+      1
+    );
+  }
+
+  let error = run_app(&["--inject", "2x"]).unwrap_err();
+  assert!(error.contains("invalid injection spec '2x'"));
+  let error =
+    run_app(&["--inject", "1d", "--exclude", "class:digit"]).unwrap_err();
+  assert!(error.contains("--inject has no digit characters left."));
+}
+
+#[test]
+fn test_site_req() {
+  for _ in 0..20 {
+    let output =
+      run_app(&["--site-req", "8-20,no-spaces,symbols:!@#,must:Ul d"])
+        .expect("Site requirements should be accepted.");
+    let password = output.trim();
+    assert_eq!(password.chars().count(), 20);
+    assert!(password.chars().any(|c| c.is_ascii_uppercase()));
+    assert!(password.chars().any(|c| c.is_ascii_lowercase()));
+    assert!(password.chars().any(|c| c.is_ascii_digit()));
+    assert!(password
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || "!@#".contains(c)));
+  }
+
+  let output = run_app(&["--site-req", "8-20", "--length", "12"]).unwrap();
+  assert_eq!(output.trim().chars().count(), 12);
+
+  let error = run_app(&["--site-req", "must:Ux"]).unwrap_err();
+  assert!(error.contains("unknown site requirement 'must:Ux'"));
+  assert!(run_app(&["--site-req", "8-20", "--preset", "hex"]).is_err());
+}
+
+#[test]
+fn test_passphrase() {
+  let wordlist =
+    write_temp_file("wordlist.txt", "11111\tapple\n11112\tberry\ncherry\n");
+  let wordlist = wordlist.to_str().unwrap();
+
+  let output = run_app(&["passphrase", "--wordlist", wordlist]).unwrap();
+  let words: Vec<&str> = output.trim().split('-').collect();
+  assert_eq!(words.len(), 6);
+  assert!(words
+    .iter()
+    .all(|w| ["apple", "berry", "cherry"].contains(w)));
+
+  let output = run_app(&[
+    "passphrase",
+    "--wordlist",
+    wordlist,
+    "--words",
+    "3",
+    "--separator",
+    " ",
+  ])
+  .unwrap();
+  assert_eq!(output.trim().split(' ').count(), 3);
+
+  let error = run_app(&["passphrase", "--wordlist", wordlist, "--words", "0"])
+    .unwrap_err();
+  assert!(error.contains("at least one word"));
+
+  // Without --wordlist, the words come from the EFF long wordlist.
+  let output =
+    run_app(&["passphrase", "--words", "4", "--separator", " "]).unwrap();
+  let words: Vec<&str> = output.trim().split(' ').collect();
+  assert_eq!(words.len(), 4);
+  assert!(words
+    .iter()
+    .all(|w| pwdg::EFF_LARGE_WORDLIST.contains(&format!("\t{}\n", w))));
+}
+
+#[test]
+fn test_record_and_due() {
+  let meta = std::env::temp_dir()
+    .join(format!("pwdg_cli_test_{}_meta", std::process::id()))
+    .join("meta.toml");
+  let _ = std::fs::remove_file(&meta);
+  let env = [("PWDG_META", meta.as_os_str())];
+
+  let output = run_app_with_env(&["--record", "db", "-l", "12"], "", &env)
+    .expect("Recording a credential should succeed.");
+  let password = output.trim();
+  assert_eq!(password.len(), 12);
+  let stored = std::fs::read_to_string(&meta).unwrap();
+  assert!(stored.contains("label = \"db\""));
+  assert!(!stored.contains(password));
+
+  let output = run_app_with_env(&["due"], "", &env).unwrap();
+  assert!(output.is_empty());
+  let output = run_app_with_env(&["due", "--days", "0"], "", &env).unwrap();
+  assert!(output.starts_with("db\t0 days\t"));
+
+  std::fs::remove_dir_all(meta.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn test_receipt() {
+  let key = write_temp_file("receipt.key", "auditor secret");
+  let key = key.to_str().unwrap();
+  let output = run_app_output(&["--receipt", key, "-l", "16"]);
+  assert!(output.status.success());
+  let password = String::from_utf8(output.stdout).unwrap();
+  let password = password.trim();
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  let receipt = stderr
+    .lines()
+    .find_map(|line| line.strip_prefix("receipt: "))
+    .expect("A receipt should be printed.");
+  assert!(receipt.starts_with("pwdg-receipt-v1 "));
+  assert!(!receipt.contains(password));
+
+  let password = format!("--password={}", password);
+  let output = run_app(&["verify-receipt", receipt, "--key", key, &password])
+    .expect("The receipt should verify.");
+  assert!(output.starts_with("Valid receipt"));
+
+  let other = write_temp_file("receipt_other.key", "someone else");
+  let other = other.to_str().unwrap();
+  assert!(run_app(&["verify-receipt", receipt, "--key", other]).is_err());
+  let error = run_app(&[
+    "verify-receipt",
+    receipt,
+    "--key",
+    key,
+    "--password",
+    "not-it",
+  ])
+  .unwrap_err();
+  assert!(error.contains("not for this password"));
+
+  if cfg!(feature = "clipboard") {
+    let args = ["--receipt", key, "--copy", "--quiet"];
+    assert!(run_app(&args).unwrap_err().contains("cannot be used with"));
+  }
+}
+
+#[test]
+fn test_commit() {
+  use sha2::{Digest, Sha256};
+
+  let output = run_app_output(&["--commit", "sha256", "-l", "16"]);
+  assert!(output.status.success());
+  let password = String::from_utf8(output.stdout).unwrap();
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  let field = |name: &str| {
+    stderr
+      .lines()
+      .find_map(|line| line.strip_prefix(name))
+      .expect("The commitment should be printed.")
+      .to_string()
+  };
+  let commitment = field("commitment: ");
+  let salt = field("commitment salt: ");
+
+  let salt: Vec<u8> = (0..salt.len())
+    .step_by(2)
+    .map(|i| u8::from_str_radix(&salt[i..i + 2], 16).unwrap())
+    .collect();
+  let expected: String = Sha256::new()
+    .chain_update(&salt)
+    .chain_update(password.trim())
+    .finalize()
+    .iter()
+    .map(|b| format!("{:02x}", b))
+    .collect();
+  assert_eq!(commitment, expected);
+
+  if cfg!(feature = "clipboard") {
+    let args = ["--commit", "sha256", "--copy", "--quiet"];
+    assert!(run_app(&args).unwrap_err().contains("cannot be used with"));
+  }
+}
+
+#[test]
+fn test_pin() {
+  let output = run_app(&["pin"]).expect("A PIN should be generated.");
+  assert_eq!(output.trim().len(), 6);
+  assert!(output.trim().chars().all(|c| c.is_ascii_digit()));
+
+  for _ in 0..20 {
+    let output = run_app(&["pin", "-l", "4", "--reject-weak"]).unwrap();
+    let pin = output.trim();
+    assert_eq!(pin.len(), 4);
+    assert_eq!(pwdg::pin_weakness(pin), None);
+  }
+
+  let error = run_app(&["pin", "-l", "3"]).unwrap_err();
+  assert!(error.contains("[Error::InvalidPolicy]"));
+}
+
+#[test]
+fn test_split_delivery() {
+  let output = run_app(&["--split-delivery", "-l", "16"])
+    .expect("Splitting a password should succeed.");
+  let shares: Vec<&str> = output.lines().collect();
+  assert_eq!(shares.len(), 2);
+  assert!(shares.iter().all(|share| share.len() == 32));
+
+  let password = run_app(&["combine", shares[1], shares[0]])
+    .expect("The shares should combine.");
+  let password = password.trim();
+  assert_eq!(password.len(), 16);
+  assert!(password.chars().all(|c| c.is_ascii_graphic()));
+
+  assert!(run_app(&["combine", shares[0], "00"]).is_err());
+}
+
+#[test]
+fn test_share_once() {
+  use std::io::{BufRead, BufReader, Read, Write};
+
+  let path = if cfg!(debug_assertions) {
+    "./target/debug/pwdg"
+  } else {
+    "./target/release/pwdg"
+  };
+  let mut child = Command::new(path)
+    .args(["share", "--once", "--ttl", "30s"])
+    .args(["-l", "16"])
+    .stdout(Stdio::piped())
+    .stderr(Stdio::null())
+    .spawn()
+    .expect("failed to start pwdg share");
+  let mut url = String::new();
+  BufReader::new(child.stdout.take().unwrap())
+    .read_line(&mut url)
+    .unwrap();
+  let (addr, path) = url
+    .trim()
+    .strip_prefix("http://")
+    .and_then(|rest| rest.split_once('/'))
+    .expect("A URL should be printed.");
+  assert!(addr.starts_with("127.0.0.1:"));
+
+  let get = |path: &str| {
+    let mut stream = std::net::TcpStream::connect(addr).unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", path, addr).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+  };
+  assert!(get("/guess").starts_with("HTTP/1.1 404"));
+  // A client that never finishes its request does not hold up others, and
+  // one that sends too much is cut off.
+  let _idle = std::net::TcpStream::connect(addr).unwrap();
+  let mut huge = std::net::TcpStream::connect(addr).unwrap();
+  write!(huge, "GET /{} HTTP/1.1\r\n\r\n", "x".repeat(10_000)).unwrap();
+  let mut response = String::new();
+  // The connection may be reset before the response is read.
+  let _ = huge.read_to_string(&mut response);
+  assert!(!response.contains("200 OK"));
+  let response = get(&format!("/{}", path));
+  assert!(response.starts_with("HTTP/1.1 200"));
+  let (_, body) = response.split_once("\r\n\r\n").unwrap();
+  assert_eq!(body.trim_end().len(), 16);
+
+  assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn test_count() {
+  let output = run_app(&["-c", "5", "-l", "12"])
+    .expect("Generating several passwords should succeed.");
+  let passwords: Vec<&str> = output.lines().collect();
+  assert_eq!(passwords.len(), 5);
+  assert!(passwords.iter().all(|password| password.len() == 12));
+
+  let output =
+    run_app(&["--count", "3", "--preset", "pin", "--checksum", "luhn"])
+      .unwrap();
+  assert_eq!(output.lines().count(), 3);
+
+  assert!(run_app(&["--count", "0"]).is_err());
+  assert!(run_app(&["--count", "2", "--copy"]).is_err());
+}
+
+#[test]
+fn test_batch_recipients() {
+  let dir = std::env::temp_dir()
+    .join(format!("pwdg_cli_test_{}_recipients", std::process::id()));
+  let _ = std::fs::remove_dir_all(&dir);
+  let gnupg = dir.join("gnupg");
+  std::fs::create_dir_all(&gnupg).unwrap();
+  #[cfg(unix)]
+  std::fs::set_permissions(
+    &gnupg,
+    std::os::unix::fs::PermissionsExt::from_mode(0o700),
+  )
+  .unwrap();
+  let out = dir.join("out");
+  let specs =
+    write_temp_file("recipients_specs.txt", "db: -l 20\napi: -l 12\n");
+  let recipients = write_temp_file(
+    "recipients.toml",
+    "db = \"gpg:db@example.invalid\"\napi = \"gpg:api@example.invalid\"\n",
+  );
+  let args = [
+    "batch",
+    specs.to_str().unwrap(),
+    "--recipients",
+    recipients.to_str().unwrap(),
+    "--dir",
+    out.to_str().unwrap(),
+  ];
+  let env = [("GNUPGHOME", gnupg.as_os_str())];
+
+  let partial = write_temp_file("recipients_partial.toml", "db = \"age1x\"\n");
+  let mut partial_args = args;
+  partial_args[3] = partial.to_str().unwrap();
+  let error = run_app_with_env(&partial_args, "", &env).unwrap_err();
+  assert!(error.contains("No recipient for 'api'."));
+  assert!(!out.exists());
+
+  for user in ["db@example.invalid", "api@example.invalid"] {
+    let status = Command::new("gpg")
+      .args(["--batch", "--passphrase", "", "--quick-gen-key", user])
+      .env("GNUPGHOME", &gnupg)
+      .stderr(Stdio::null())
+      .status();
+    match status {
+      Ok(status) => assert!(status.success()),
+      // gpg is not installed, so encryption cannot be tested.
+      Err(_) => return,
+    }
+  }
+
+  run_app_with_env(&args, "", &env).expect("Encrypting should succeed.");
+  let manifest: serde_json::Value = serde_json::from_str(
+    &std::fs::read_to_string(out.join("manifest.json")).unwrap(),
+  )
+  .unwrap();
+  assert_eq!(manifest[0]["label"], "db");
+  assert_eq!(manifest[0]["file"], "db.gpg");
+  assert_eq!(manifest[1]["recipient"], "api@example.invalid");
+  assert!(manifest[0].get("password").is_none());
+
+  let decrypted = Command::new("gpg")
+    .args(["--batch", "--decrypt"])
+    .arg(out.join("db.gpg"))
+    .env("GNUPGHOME", &gnupg)
+    .stderr(Stdio::null())
+    .output()
+    .unwrap();
+  assert_eq!(decrypted.stdout.len(), 20);
+
+  std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_charset() {
+  let output = run_app(&["--charset", "[0-9A-F]", "-l", "24"])
+    .expect("A charset of ranges should be accepted.");
+  assert_eq!(output.trim().len(), 24);
+  assert!(output
+    .trim()
+    .chars()
+    .all(|c| c.is_ascii_digit() || ('A'..='F').contains(&c)));
+
+  let output = run_app(&["--charset", "xyz!", "-l", "12"]).unwrap();
+  assert!(output.trim().chars().all(|c| "xyz!".contains(c)));
+
+  let charset = write_temp_file("charset_conflict.txt", "abc");
+  let charset = charset.to_str().unwrap();
+  assert!(run_app(&["--charset", "abc", "--charset-file", charset]).is_err());
+}
+
+#[test]
+fn test_invalid_options_suggest_fixes() {
+  let error = run_app(&["-l", "8", "--min-special", "5", "--min-digit", "5"])
+    .unwrap_err();
+  assert!(error.contains("[Error::MinLimitExceeded]"));
+  assert!(error.contains(
+    "To fix: increase length to 10 or reduce min_digit to 3 or reduce \
+     min_special to 3."
+  ));
+
+  let error = run_app(&["-l", "12", "--min-bits", "100"]).unwrap_err();
+  assert!(error.contains(
+    "To fix: increase length to 16 or reduce min_entropy_bits to 78."
+  ));
+}
+
+#[cfg(feature = "clipboard")]
+#[test]
+fn test_clear_after() {
+  let copied = std::env::temp_dir()
+    .join(format!("pwdg_test_{}_cleared.txt", std::process::id()));
+  let copy = format!("tee {}", copied.display());
+  let paste = format!("cat {}", copied.display());
+  let env = [
+    ("PWDG_COPY_COMMAND", std::ffi::OsStr::new(&copy)),
+    ("PWDG_PASTE_COMMAND", std::ffi::OsStr::new(&paste)),
+  ];
+
+  let args = ["--copy", "--quiet", "--clear-after", "1s", "-l", "16"];
+  run_app_with_env(&args, "", &env).expect("Copying should succeed.");
+  assert_eq!(std::fs::read_to_string(&copied).unwrap().len(), 16);
+  std::thread::sleep(std::time::Duration::from_secs(3));
+  assert_eq!(std::fs::read_to_string(&copied).unwrap(), "");
+
+  // Text copied since is kept.
+  run_app_with_env(&args, "", &env).unwrap();
+  std::fs::write(&copied, "other").unwrap();
+  std::thread::sleep(std::time::Duration::from_secs(3));
+  assert_eq!(std::fs::read_to_string(&copied).unwrap(), "other");
+  std::fs::remove_file(&copied).unwrap();
+
+  assert!(run_app(&["--clear-after", "1s"]).is_err());
+}
+
+#[test]
+fn test_avoid_ambiguous() {
+  for _ in 0..20 {
+    let output = run_app(&["--avoid-ambiguous", "-l", "64"])
+      .expect("Avoiding ambiguous characters should succeed.");
+    assert!(!output.trim().chars().any(|c| "0OIl1|".contains(c)));
+  }
+
+  let output = run_app(&["policy", "export", "--avoid-ambiguous"]).unwrap();
+  assert!(output.contains("\"avoid_ambiguous\": true"));
+}
+
+#[test]
+fn test_audit_distribution() {
+  // Unbiased output is reported as biased by chance, rarely.
+  let output =
+    run_app_output(&["audit", "--distribution", "--samples", "2000"]);
+  let output = String::from_utf8(output.stdout).unwrap();
+  assert!(output.starts_with("passwords: 2000\n"));
+  assert!(output.contains("character anomalies: "));
+  assert!(!output.contains("outside the charset"));
+
+  assert!(run_app(&["audit"]).is_err());
+  let error = run_app_with_stdin(
+    &[
+      "audit",
+      "--distribution",
+      "--input",
+      "-",
+      "--charset",
+      "abc",
+    ],
+    "abcabcab\nabcabcxy\n",
+  )
+  .unwrap_err();
+  assert!(error.contains("anomalies"));
+}
+
+#[test]
+fn test_pattern() {
+  let output = run_app(&["--pattern", r"Ldddd\-ss", "--exclude", "abc"])
+    .expect("A pattern of available classes should be accepted.");
+  let password: Vec<char> = output.trim().chars().collect();
+  assert_eq!(password.len(), 8);
+  assert!(password[0].is_ascii_lowercase() && !"abc".contains(password[0]));
+  assert!(password[1..5].iter().all(char::is_ascii_digit));
+  assert_eq!(password[5], '-');
+
+  assert!(run_app(&["--pattern", "Lx"]).is_err());
+  let output = run_app(&["--pattern", "aaaaaaaa", "--min-digit", "8"]).unwrap();
+  assert!(output.trim().chars().all(|c| c.is_ascii_digit()));
+  let error =
+    run_app(&["--pattern", "Laaaaaaa", "--min-upper", "8"]).unwrap_err();
+  assert!(error.contains("[Error::Infeasible]"));
+  assert!(run_app(&["--pattern", "Ld", "--inject", "1d"]).is_err());
+
+  let error = run_app(&["--pattern", "a"]).unwrap_err();
+  assert!(error.contains("[Error::Length]"));
+  let output =
+    run_app(&["--pattern", "dddddddd", "--reject-structures"]).unwrap();
+  assert!(pwdg::find_structure(output.trim()).is_none());
+  let args = ["--pattern", "aaaaaaaa", "--fill-classes", "lower"];
+  assert!(run_app(&args)
+    .unwrap_err()
+    .contains("[Error::InvalidPolicy]"));
+}
+
+#[test]
+fn test_policy_compare_entropy() {
+  let complex = write_temp_file(
+    "complex.txt",
+    "ComplexityEnabled : True\nMinPasswordLength : 12\n",
+  );
+  let plain = write_temp_file("plain.txt", "MinPasswordLength : 12\n");
+  let complex_path = complex.to_str().unwrap();
+  let plain_path = plain.to_str().unwrap();
+
+  let output = run_app(&[
+    "policy",
+    "compare-entropy",
+    complex_path,
+    plain_path,
+    "-l",
+    "16",
+  ]);
+  std::fs::remove_file(&complex).ok();
+  std::fs::remove_file(&plain).ok();
+
+  let output = output.expect("Comparing two policies should succeed.");
+  let lines: Vec<&str> = output.lines().collect();
+  assert_eq!(lines[0], "At 16 characters:");
+  assert!(lines[1].ends_with(" bits"));
+  assert!(lines[3].contains(" matches "));
+  assert_eq!(lines.len(), 5);
+}
+
+#[test]
+fn test_meter() {
+  let output = run_app_output(&["--meter", "-l", "16"]);
+  assert!(output.status.success());
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert!(stderr.starts_with("strength: \x1b["));
+  assert!(stderr.trim_end().ends_with("/4"));
+  assert_eq!(String::from_utf8(output.stdout).unwrap().trim().len(), 16);
+}
+
+#[test]
+fn test_prompt_new() {
+  let args = ["prompt-new", "--length", "12", "--min-digit", "1"];
+  let output = run_app_with_stdin(&args, "Tr0ub4dor&3xyz\nTr0ub4dor&3xyz\n")
+    .expect("A password that follows the policy should be accepted.");
+  assert!(output.starts_with("The password satisfies the policy.\n"));
+  assert!(output.contains("strength: "));
+  assert!(!output.contains("Tr0ub4dor"), "{}", output);
+
+  let error = run_app_with_stdin(
+    &["prompt-new", "--first", "upper", "-l", "10"],
+    "9xkqzvbwpt\n9xkqzvbwpt\n",
+  )
+  .unwrap_err();
+  assert!(error.contains("Password rejected."));
+
+  let error = run_app_with_stdin(&args, "password\npassword\n").unwrap_err();
+  assert!(error.contains("Password rejected."));
+
+  let error =
+    run_app_with_stdin(&args, "Tr0ub4dor&3xyz\nTr0ub4dor&3xy\n").unwrap_err();
+  assert!(error.contains("Passwords do not match."));
+
+  assert!(run_app_with_stdin(&args, "Tr0ub4dor&3xyz\n").is_err());
+}
+
+#[test]
+fn test_infer_policy() {
+  let text = "Must be 8-64 characters and include one uppercase letter and \
+              two numbers. No symbols.";
+  let output = run_app_with_stdin(&["infer-policy"], text).unwrap();
+  let mut lines = output.lines();
+  assert_eq!(
+    lines.next().unwrap(),
+    "Inferred policy: 64+ characters, at least 1 uppercase, 2 digits, \
+     excludes: !@#$%^&*()_+-={}[]|:;\"'<>,.?/~\\`"
+  );
+  assert!(lines.next().unwrap().starts_with(
+    "Options: --length 64 --min-upper 1 --min-digit 2 --exclude="
+  ));
+
+  let json = run_app_with_stdin(&["infer-policy", "--json"], text).unwrap();
+  let policy = write_temp_file("inferred_policy.json", &json);
+  let password = run_app(&["--policy-file", policy.to_str().unwrap()]).unwrap();
+  let password = password.trim();
+  assert_eq!(password.len(), 64);
+  assert!(password.chars().all(char::is_alphanumeric));
+
+  assert!(run_app_with_stdin(&["infer-policy"], "Be creative.").is_err());
+
+  let text = "At least 12 characters with one special character (!@#).";
+  let json = run_app_with_stdin(&["infer-policy", "--json"], text).unwrap();
+  let policy = write_temp_file("inferred_specials.json", &json);
+  let password = run_app(&["--policy-file", policy.to_str().unwrap()]).unwrap();
+  let specials: Vec<char> = password
+    .trim()
+    .chars()
+    .filter(|c| SPECIAL_CHARS.contains(c))
+    .collect();
+  assert!(!specials.is_empty());
+  assert!(specials.iter().all(|c| "!@#".contains(*c)));
+}
+
+#[test]
+fn test_charset_file() {
+  let path = write_temp_file("charset.txt", "0123456789\nabcdef\nabc\n");
+  let output = run_app(&[
+    "--charset-file",
+    path.to_str().unwrap(),
+    "--exclude",
+    "0",
+    "-l",
+    "32",
+  ])
+  .expect("A charset file should be accepted.");
+  let password = output.trim();
+  assert_eq!(password.len(), 32);
+  assert!(password.chars().all(|c| "123456789abcdef".contains(c)));
+}
+
+#[test]
+fn test_preset() {
+  let output = run_app(&["--preset", "hex"]).expect("hex is a preset.");
+  let password = output.trim();
+  assert_eq!(password.len(), 32);
+  assert!(password.chars().all(|c| c.is_ascii_hexdigit()));
+
+  let output = run_app(&["--preset", "pin", "-l", "10"])
+    .expect("Options should override a preset.");
+  assert!(output.trim().len() == 10);
+  assert!(output.trim().chars().all(|c| c.is_ascii_digit()));
+
+  assert!(run_app(&["--preset", "nope"]).is_err());
+}
+
+#[test]
+fn test_save_profile() {
+  let config = std::env::temp_dir()
+    .join(format!("pwdg_test_{}_config.toml", std::process::id()));
+  let _ = std::fs::remove_file(&config);
+  let env = [("PWDG_CONFIG", config.as_os_str())];
+
+  run_app_with_env(
+    &["--preset", "pin", "-l", "12", "--save-profile", "p"],
+    "",
+    &env,
+  )
+  .expect("Saving a new profile should succeed.");
+  let output = run_app_with_env(&["--profile", "p"], "", &env)
+    .expect("A saved profile should be usable.");
+  assert_eq!(output.trim().len(), 12);
+  assert!(output.trim().chars().all(|c| c.is_ascii_digit()));
+
+  assert!(
+    run_app_with_env(&["-l", "9", "--save-profile", "p"], "n\n", &env).is_err()
+  );
+  run_app_with_env(&["-l", "9", "--save-profile", "p"], "y\n", &env)
+    .expect("Overwriting a confirmed profile should succeed.");
+  let output = run_app_with_env(&["--profile", "p"], "", &env).unwrap();
+  assert_eq!(output.trim().len(), 9);
+
+  assert!(run_app_with_env(&["--profile", "nope"], "", &env).is_err());
+  std::fs::remove_file(&config).unwrap();
+}
+
+#[test]
+fn test_batch() {
+  let lines = write_temp_file(
+    "batch.txt",
+    "# credentials\ndb: --length 20 --min-digit 2\n\napi: --preset hex\n",
+  );
+  let output = run_app(&["batch", lines.to_str().unwrap()])
+    .expect("A batch of line specs should succeed.");
+  let entries: Vec<(&str, &str)> = output
+    .lines()
+    .map(|line| line.split_once(": ").unwrap())
+    .collect();
+  assert_eq!(entries.len(), 2);
+  assert_eq!(entries[0].0, "db");
+  assert_eq!(entries[0].1.len(), 20);
+  assert!(count_chars(entries[0].1, |c| c.is_ascii_digit()) >= 2);
+  assert_eq!(entries[1].0, "api");
+  assert_eq!(entries[1].1.len(), 32);
+
+  let toml = write_temp_file(
+    "batch.toml",
+    "[[spec]]\nlabel = \"db\"\nlength = 12\n\n[[spec]]\nlabel = \"pin\"\npreset = \"pin\"\n",
+  );
+  let output =
+    run_app(&["batch", toml.to_str().unwrap(), "--format", "json"]).unwrap();
+  let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+  assert_eq!(json[0]["label"], "db");
+  assert_eq!(json[0]["password"].as_str().unwrap().len(), 12);
+  assert_eq!(json[1]["label"], "pin");
+  let fingerprint = run_app(&["policy", "fingerprint", "-l", "12"]).unwrap();
+  assert_eq!(json[0]["policy_hash"], fingerprint.trim());
+
+  let invalid = write_temp_file("batch_invalid.txt", "x: -l 3\n");
+  assert!(run_app(&["batch", invalid.to_str().unwrap()]).is_err());
+}
+
+#[test]
+fn test_batch_count_and_vary_length() {
+  let lines = write_temp_file(
+    "batch_count.txt",
+    "admin: --length 16 --count 3\nuser: --length 12\n",
+  );
+  let output = run_app(&[
+    "batch",
+    lines.to_str().unwrap(),
+    "--format",
+    "json",
+    "--vary-length",
+    "4",
+  ])
+  .expect("A batch with counts should succeed.");
+  let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+  let entries = json.as_array().unwrap();
+  let labels: Vec<&str> = entries
+    .iter()
+    .map(|e| e["label"].as_str().unwrap())
+    .collect();
+  assert_eq!(labels, ["admin-1", "admin-2", "admin-3", "user"]);
+  for (entry, base) in entries.iter().zip([16, 16, 16, 12]) {
+    let length = entry["length"].as_u64().unwrap();
+    assert!((base..=base + 4).contains(&length));
+    assert_eq!(entry["password"].as_str().unwrap().len() as u64, length);
+    assert!(entry["entropy_bits"].as_f64().unwrap() > 0.0);
+  }
+
+  let json = write_temp_file(
+    "bundle_count.json",
+    r#"[{"label": "worker", "count": 2, "length": 10}]"#,
+  );
+  let output = run_app(&["bundle", "--spec", json.to_str().unwrap()]).unwrap();
+  let bundle: serde_json::Value = serde_json::from_str(&output).unwrap();
+  assert_eq!(bundle["worker-2"].as_str().unwrap().len(), 10);
+
+  let unsorted = write_temp_file("batch_unsorted.txt", "zeta: -l 8\nalpha:\n");
+  let output = run_app(&[
+    "batch",
+    unsorted.to_str().unwrap(),
+    "--sort",
+    "--template",
+    "{{index}} {{label}}",
+  ])
+  .unwrap();
+  assert_eq!(output, "1 alpha\n0 zeta\n");
+
+  let output =
+    run_app(&["batch", lines.to_str().unwrap(), "--shuffle-output"]).unwrap();
+  let mut labels: Vec<&str> = output
+    .lines()
+    .map(|line| line.split_once(": ").unwrap().0)
+    .collect();
+  labels.sort();
+  assert_eq!(labels, ["admin-1", "admin-2", "admin-3", "user"]);
+  assert!(run_app(&[
+    "batch",
+    lines.to_str().unwrap(),
+    "--sort",
+    "--shuffle-output"
+  ])
+  .is_err());
+
+  let zero = write_temp_file("batch_zero.txt", "x: --count 0\n");
+  assert!(run_app(&["batch", zero.to_str().unwrap()]).is_err());
+}
+
+#[test]
+fn test_from_csv() {
+  let path = write_temp_file(
+    "accounts.csv",
+    "user,length,strong\nalice,,\nbob,20,true\n",
+  );
+  let path = path.to_str().unwrap();
+
+  let output = run_app(&["--from-csv", path, "-l", "10", "--override-columns"])
+    .expect("Generating passwords for a CSV file should succeed.");
+  let mut reader = csv::Reader::from_reader(output.as_bytes());
+  assert_eq!(
+    reader.headers().unwrap(),
+    vec!["user", "length", "strong", "password"]
+  );
+  let rows: Vec<csv::StringRecord> =
+    reader.records().map(Result::unwrap).collect();
+  assert_eq!(&rows[0][0], "alice");
+  assert_eq!(rows[0][3].len(), 10);
+  assert_eq!(&rows[1][0], "bob");
+  assert_eq!(rows[1][3].len(), 20);
+  assert!(rows[1][3].chars().any(|c| SPECIAL_CHARS.contains(&c)));
+
+  let output =
+    run_app(&["--from-csv", path, "--password-column", "length"]).unwrap();
+  let mut reader = csv::Reader::from_reader(output.as_bytes());
+  assert_eq!(reader.headers().unwrap(), vec!["user", "length", "strong"]);
+  let rows: Vec<csv::StringRecord> =
+    reader.records().map(Result::unwrap).collect();
+  assert_eq!(&rows[0][0], "alice");
+  assert_eq!(rows[0][1].len(), 8);
+}
+
+#[test]
+fn test_template() {
+  let output = run_app(&[
+    "--template",
+    "user={{label}} pass={{password}} length={{ length }}",
+    "--label",
+    "alice",
+    "--exclude",
+    " ",
+    "-l",
+    "12",
+  ])
+  .expect("Rendering a template should succeed.");
+  let output = output.trim_end_matches('\n');
+  assert!(output.starts_with("user=alice pass="));
+  assert!(output.ends_with(" length=12"));
+  assert_eq!(output.len(), "user=alice pass= length=12".len() + 12);
+
+  let specs = write_temp_file("template_specs.txt", "db: -l 10\n");
+  let output = run_app(&[
+    "batch",
+    specs.to_str().unwrap(),
+    "--template",
+    "{{label}}={{password}}",
+  ])
+  .unwrap();
+  assert!(output.starts_with("db="));
+  assert_eq!(output.trim_end_matches('\n').len(), "db=".len() + 10);
+
+  let error = run_app(&["--template", "{{nope}}"]).unwrap_err();
+  assert!(error.contains("Unknown template placeholder 'nope'"));
+  assert!(run_app(&["--template", "{{expires_at}}"]).is_err());
+}
+
+#[test]
+fn test_valid_days() {
+  let output = run_app(&[
+    "--template",
+    "{{created_at}} {{expires_at}}",
+    "--valid-days",
+    "2",
+  ])
+  .expect("--valid-days should stamp the template.");
+  let times: Vec<u64> = output
+    .split_whitespace()
+    .map(|time| time.parse().unwrap())
+    .collect();
+  assert_eq!(times[1] - times[0], 2 * 86400);
+
+  let specs =
+    write_temp_file("valid_days_specs.txt", "db: -l 10\napi: -l 12\n");
+  let output = run_app(&[
+    "batch",
+    specs.to_str().unwrap(),
+    "--format",
+    "json",
+    "--valid-days",
+    "90",
+  ])
+  .unwrap();
+  let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+  for entry in json.as_array().unwrap() {
+    let created_at = entry["created_at"].as_u64().unwrap();
+    assert_eq!(
+      entry["expires_at"].as_u64().unwrap(),
+      created_at + 90 * 86400
+    );
+  }
+  assert!(run_app(&["--valid-days", "0"]).is_err());
+}
+
+#[test]
+fn test_rotate() {
+  let env = write_temp_file(
+    "rotate.env",
+    "DB_PASSWORD=old-db\nAPI_KEY=old-api\nDEBUG=1\n",
+  );
+  let output = run_app(&[
+    "rotate",
+    "--file",
+    env.to_str().unwrap(),
+    "--keys",
+    "DB_PASSWORD,API_KEY",
+    "--length",
+    "20",
+    "--exclude",
+    "'",
+  ])
+  .expect("Rotating keys of a .env file should succeed.");
+  assert!(!output.contains("old-"));
+  assert!(output.contains("-DB_PASSWORD=********\n+DB_PASSWORD=********\n"));
+
+  let backup = format!("{}.bak", env.display());
+  assert_eq!(
+    std::fs::read_to_string(&backup).unwrap(),
+    "DB_PASSWORD=old-db\nAPI_KEY=old-api\nDEBUG=1\n"
+  );
+  let rotated = std::fs::read_to_string(&env).unwrap();
+  let lines: Vec<&str> = rotated.lines().collect();
+  assert_eq!(lines.len(), 3);
+  assert_eq!(lines[0].len(), "DB_PASSWORD=''".len() + 20);
+  assert!(lines[1].starts_with("API_KEY='"));
+  assert_eq!(lines[2], "DEBUG=1");
+
+  let error = run_app(&[
+    "rotate",
+    "--file",
+    env.to_str().unwrap(),
+    "--keys",
+    "MISSING",
+  ])
+  .unwrap_err();
+  assert!(error.contains("Key 'MISSING' not found."));
+  assert_eq!(std::fs::read_to_string(&env).unwrap(), rotated);
+  std::fs::remove_file(&env).unwrap();
+  std::fs::remove_file(&backup).unwrap();
+}
+
+#[cfg(feature = "clipboard")]
+#[test]
+fn test_copy_quiet() {
+  let copied = std::env::temp_dir()
+    .join(format!("pwdg_test_{}_copied.txt", std::process::id()));
+  let command = format!("tee {}", copied.display());
+  let env = [("PWDG_COPY_COMMAND", std::ffi::OsStr::new(&command))];
+
+  let output = run_app_with_env(&["--copy", "--quiet", "-l", "16"], "", &env)
+    .expect("Copying quietly should succeed.");
+  assert!(output.is_empty());
+  assert_eq!(std::fs::read_to_string(&copied).unwrap().len(), 16);
+  std::fs::remove_file(&copied).unwrap();
+
+  assert!(run_app(&["--quiet"]).is_err());
+}
+
+#[test]
+fn test_checksum() {
+  for kind in ["luhn", "mod97"] {
+    let output = run_app(&["--checksum", kind, "-l", "12", "-e", "\\"])
+      .expect("Appending a checksum should succeed.");
+    let password = output.trim_end_matches('\n');
+    let length = if kind == "luhn" { 13 } else { 14 };
+    assert_eq!(password.len(), length);
+
+    // Passwords may start with `-`, so they follow `--`.
+    let args = ["verify-checksum", "--checksum", kind, "-e", "\\", "--"];
+    assert!(run_app(&[&args[..], &[password]].concat()).is_ok());
+
+    let mut typo: Vec<char> = password.chars().collect();
+    typo[0] = if typo[0] == 'a' { 'b' } else { 'a' };
+    let typo: String = typo.into_iter().collect();
+    assert!(run_app(&[&args[..], &[&typo]].concat()).is_err());
+  }
+}
+
+#[test]
+fn test_salt() {
+  let output = run_app(&["salt"]).expect("Generating a salt should succeed.");
+  let salt = output.trim_end();
+  assert_eq!(salt.len(), 32);
+  assert!(salt.chars().all(|c| c.is_ascii_hexdigit()));
+
+  let output = run_app(&["salt", "--bytes", "12", "--encode", "base64"])
+    .expect("Generating a salt should succeed.");
+  assert_eq!(output.trim_end().len(), 16);
+
+  assert!(run_app(&["salt", "--bytes", "0"]).is_err());
+}
+
+#[test]
+fn test_bundle() {
+  let spec = write_temp_file(
+    "bundle.toml",
+    "[[spec]]\nlabel = \"db-password\"\nlength = 24\n\n\
+     [[spec]]\nlabel = \"api-token\"\npreset = \"hex\"\n",
+  );
+  let spec = spec.to_str().unwrap();
+
+  let output = run_app(&["bundle", "--spec", spec])
+    .expect("Generating a bundle should succeed.");
+  let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+  assert_eq!(json["db-password"].as_str().unwrap().len(), 24);
+  assert_eq!(json["api-token"].as_str().unwrap().len(), 32);
+
+  let output = run_app(&["bundle", "--spec", spec, "--format", "env"]).unwrap();
+  let lines: Vec<&str> = output.lines().collect();
+  assert_eq!(lines.len(), 2);
+  assert!(lines[0].starts_with("DB_PASSWORD='"));
+  assert!(lines[1].starts_with("API_TOKEN='"));
+
+  let duplicate = write_temp_file("bundle_dup.txt", "a: -l 8\na: -l 9\n");
+  let error =
+    run_app(&["bundle", "--spec", duplicate.to_str().unwrap()]).unwrap_err();
+  assert!(error.contains("Duplicate secret name 'a'"));
+}
+
+#[test]
+fn test_docker_secret_files() {
+  let spec =
+    write_temp_file("docker_specs.txt", "db: -l 20\napi: --preset hex\n");
+  let dir = std::env::temp_dir()
+    .join(format!("pwdg_test_{}_secrets", std::process::id()));
+  let _ = std::fs::remove_dir_all(&dir);
+
+  let output = run_app(&[
+    "batch",
+    spec.to_str().unwrap(),
+    "--format",
+    "docker-secret",
+    "--dir",
+    dir.to_str().unwrap(),
+  ])
+  .expect("Writing secret files should succeed.");
+  assert!(!output.contains(&std::fs::read_to_string(dir.join("db")).unwrap()));
+  assert_eq!(std::fs::read_to_string(dir.join("db")).unwrap().len(), 20);
+  assert_eq!(std::fs::read_to_string(dir.join("api")).unwrap().len(), 32);
+
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = std::fs::metadata(dir.join("db"))
+      .unwrap()
+      .permissions()
+      .mode();
+    assert_eq!(mode & 0o777, 0o600);
+  }
+
+  std::fs::remove_dir_all(&dir).unwrap();
+  assert!(run_app(&[
+    "batch",
+    spec.to_str().unwrap(),
+    "--format",
+    "docker-secret"
+  ])
+  .is_err());
+}
+
+/// Creates a directory holding an executable `name` running `script`, and
+/// returns it with a `PATH` value that finds it first.
+#[cfg(unix)]
+fn fake_command(name: &str, script: &str) -> (std::path::PathBuf, String) {
+  use std::os::unix::fs::PermissionsExt;
+
+  let dir = std::env::temp_dir().join(format!(
+    "pwdg_test_{}_{}",
+    std::process::id(),
+    name
+  ));
+  std::fs::create_dir_all(&dir).unwrap();
+  let command = dir.join(name);
+  std::fs::write(&command, script).unwrap();
+  std::fs::set_permissions(&command, std::fs::Permissions::from_mode(0o755))
+    .unwrap();
+
+  let path = format!("{}:{}", dir.display(), std::env::var("PATH").unwrap());
+  (dir, path)
+}
+
+#[cfg(all(feature = "aws", unix))]
+#[test]
+fn test_store_aws() {
+  let received = std::env::temp_dir()
+    .join(format!("pwdg_test_{}_aws_received", std::process::id()));
+  let (dir, path) = fake_command(
+    "aws",
+    &format!(
+      r#"#!/bin/sh
+cat > {received}
+case "$2" in
+  put-secret-value)
+    echo "An error occurred (ResourceNotFoundException)" >&2; exit 254 ;;
+  create-secret)
+    echo '{{"ARN": "arn:aws:secretsmanager:secret:db", "VersionId": "v1"}}' ;;
+  put-parameter) echo '{{"Version": 3, "Tier": "Standard"}}' ;;
+esac
+"#,
+      received = received.display()
+    ),
+  );
+  let env = [("PATH", std::ffi::OsStr::new(&path))];
+
+  let output = run_app_with_env(
+    &["--store", "aws-sm", "--name", "prod/db", "-l", "16"],
+    "",
+    &env,
+  )
+  .expect("Storing in Secrets Manager should succeed.");
+  assert_eq!(output, "arn:aws:secretsmanager:secret:db v1\n");
+  assert_eq!(std::fs::read_to_string(&received).unwrap().len(), 16);
+
+  let output = run_app_with_env(
+    &["--store", "aws-ssm", "--name", "/prod/db", "-l", "12"],
+    "",
+    &env,
+  )
+  .expect("Storing in Parameter Store should succeed.");
+  assert_eq!(output, "/prod/db 3\n");
+  assert_eq!(std::fs::read_to_string(&received).unwrap().len(), 12);
+
+  for extra in [&["--hash", "argon2id"][..], &["--split-delivery"]] {
+    let args = [&["--store", "aws-sm", "--name", "prod/db"], extra].concat();
+    let error = run_app_with_env(&args, "", &env).unwrap_err();
+    assert!(error.contains("cannot be used with"), "{}", error);
+  }
+
+  std::fs::remove_file(&received).unwrap();
+  std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_format_systemd_cred() {
+  let (dir, path) =
+    fake_command("systemd-creds", "#!/bin/sh\necho \"$@\"\nwc -c\n");
+  let env = [("PATH", std::ffi::OsStr::new(&path))];
+
+  let output = run_app_with_env(
+    &["--format", "systemd-cred", "--name", "db", "-l", "20"],
+    "",
+    &env,
+  )
+  .expect("Encrypting a credential should succeed.");
+  let lines: Vec<&str> = output.lines().map(str::trim).collect();
+  assert_eq!(lines, ["encrypt --name=db - -", "20"]);
+
+  assert!(run_app(&["--format", "systemd-cred"]).is_err());
+  std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_format_pg_scram() {
+  let output = run_app(&["--format", "pg-scram", "-l", "16"])
+    .expect("Computing a SCRAM verifier should succeed.");
+  let lines: Vec<&str> = output.lines().collect();
+  assert_eq!(lines.len(), 2);
+  assert_eq!(lines[0].len(), 16);
+
+  let parts: Vec<&str> = lines[1].split(['$', ':']).collect();
+  assert_eq!(parts[0], "SCRAM-SHA-256");
+  assert_eq!(parts[1], "4096");
+  assert_eq!(parts[2].len(), 24);
+  assert_eq!(parts[3].len(), 44);
+  assert_eq!(parts[4].len(), 44);
+}
+
+#[test]
+fn test_hash_argon2id() {
+  let output = run_app(&[
+    "--hash",
+    "argon2id",
+    "--argon2-memory",
+    "8192",
+    "--argon2-iterations",
+    "3",
+    "-l",
+    "16",
+  ])
+  .expect("Computing an Argon2id hash should succeed.");
+  let lines: Vec<&str> = output.lines().collect();
+  assert_eq!(lines.len(), 2);
+  assert_eq!(lines[0].len(), 16);
+  assert!(lines[1].starts_with("$argon2id$v=19$m=8192,t=3,p=1$"));
+
+  assert!(
+    run_app(&["--hash", "argon2id", "--argon2-parallelism", "0"]).is_err()
+  );
+  assert!(run_app(&["--argon2-memory", "65536"]).is_err());
+}
+
+#[test]
+fn test_hash_ntlm() {
+  let output = run_app(&["--hash", "ntlm", "-l", "16"])
+    .expect("Computing an NT hash should succeed.");
+  let lines: Vec<&str> = output.lines().collect();
+  assert_eq!(lines.len(), 2);
+  assert_eq!(lines[0].len(), 16);
+  assert_eq!(lines[1].len(), 32);
+  assert!(lines[1].chars().all(|c| c.is_ascii_hexdigit()));
+
+  assert!(run_app(&["--hash", "ntlm", "--format", "pg-scram"]).is_err());
+
+  let output = run_app_output(&["--hash", "ntlm"]);
+  assert!(String::from_utf8(output.stderr)
+    .unwrap()
+    .contains("insecure"));
+  if !cfg!(feature = "clipboard") {
+    return;
+  }
+  let path = if cfg!(debug_assertions) {
+    "./target/debug/pwdg"
+  } else {
+    "./target/release/pwdg"
+  };
+  let output = Command::new(path)
+    .args(["--hash", "ntlm", "--copy", "--quiet"])
+    .env("PWDG_COPY_COMMAND", "cat")
+    .output()
+    .unwrap();
+  assert!(output.status.success());
+  assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn test_format_mysql_sha2() {
+  let output = run_app(&["--format", "mysql-sha2", "-l", "16"])
+    .expect("Computing a caching_sha2_password hash should succeed.");
+  let lines: Vec<&str> = output.lines().collect();
+  assert_eq!(lines.len(), 2);
+  assert_eq!(lines[0].len(), 16);
+
+  let hex = lines[1]
+    .strip_prefix("0x")
+    .expect("Expected a hex literal.");
+  let hash: Vec<u8> = (0..hex.len())
+    .step_by(2)
+    .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+    .collect();
+  assert_eq!(hash.len(), 70);
+  assert!(hash.starts_with(b"$A$005$"));
+}
+
+#[test]
+fn test_options_json() {
+  let output = run_app_with_stdin(
+    &["--options-json", "-"],
+    r#"{"length": 20, "min-digit": 2, "count": 2}"#,
+  )
+  .expect("A valid options document should succeed.");
+  let response: serde_json::Value = serde_json::from_str(&output).unwrap();
+  let passwords = response["passwords"].as_array().unwrap();
+  assert_eq!(passwords.len(), 2);
+  assert_eq!(passwords[0].as_str().unwrap().len(), 20);
+  assert_eq!(response["policy_hash"].as_str().unwrap().len(), 64);
+
+  let error = run_app_with_stdin(&["--options-json", "-"], r#"{"length": 4}"#)
+    .expect_err("An invalid policy should fail.");
+  assert!(error.contains("[Error::Length]"));
+  assert!(run_app(&["--options-json", "-", "--length", "12"]).is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_daemon() {
+  use std::io::{BufRead, BufReader};
+  use std::os::unix::fs::PermissionsExt;
+  use std::os::unix::net::UnixStream;
+
+  let path = if cfg!(debug_assertions) {
+    "./target/debug/pwdg"
+  } else {
+    "./target/release/pwdg"
+  };
+  let socket = std::env::temp_dir()
+    .join(format!("pwdg_test_{}_daemon.sock", std::process::id()));
+  let mut daemon = Command::new(path)
+    .args(["daemon", "--socket", socket.to_str().unwrap()])
+    .stderr(Stdio::null())
+    .spawn()
+    .expect("failed to start the daemon");
+
+  let stream = (0..100)
+    .find_map(|_| {
+      std::thread::sleep(std::time::Duration::from_millis(50));
+      UnixStream::connect(&socket).ok()
+    })
+    .expect("the daemon should listen on the socket");
+  let mode = std::fs::metadata(&socket).unwrap().permissions().mode();
+
+  let mut writer = stream.try_clone().unwrap();
+  let mut lines = BufReader::new(stream).lines();
+  let mut request = |line: &str| -> serde_json::Value {
+    writeln!(writer, "{}", line).unwrap();
+    serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap()
+  };
+  let response = request(r#"{"length": 16, "count": 2}"#);
+  let error = request(r#"{"length": 4}"#);
+  let metrics = request(r#"{"metrics": true}"#);
+  let huge = request(r#"{"length": 1000000000000}"#);
+  let long = request(&format!(r#"{{"exclude": "{}"}}"#, "x".repeat(70_000)));
+
+  daemon.kill().unwrap();
+  daemon.wait().unwrap();
+  std::fs::remove_file(&socket).unwrap();
+
+  assert_eq!(mode & 0o777, 0o600);
+  let passwords = response["passwords"].as_array().unwrap();
+  assert_eq!(passwords.len(), 2);
+  assert_eq!(passwords[0].as_str().unwrap().len(), 16);
+  assert!(error["error"].as_str().unwrap().contains("[Error::Length]"));
+  let metrics = metrics["metrics"].as_str().unwrap();
+  assert!(metrics.contains("pwdg_requests_total{outcome=\"ok\"} 1\n"));
+  assert!(metrics.contains("pwdg_requests_total{outcome=\"error\"} 1\n"));
+  assert!(metrics.contains("pwdg_passwords_total 2\n"));
+  assert!(!metrics.contains(passwords[0].as_str().unwrap()));
+  assert_eq!(huge["error"], "length must be at most 1024");
+  assert!(long["error"]
+    .as_str()
+    .unwrap()
+    .contains("at most 65536 bytes"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_daemon_reloads_config() {
+  use std::io::{BufRead, BufReader};
+  use std::os::unix::net::UnixStream;
+
+  let path = if cfg!(debug_assertions) {
+    "./target/debug/pwdg"
+  } else {
+    "./target/release/pwdg"
+  };
+  let temp = std::env::temp_dir();
+  let id = std::process::id();
+  let socket = temp.join(format!("pwdg_test_{}_reload.sock", id));
+  let config = temp.join(format!("pwdg_test_{}_reload.toml", id));
+  std::fs::write(&config, "[profiles.p]\nlength = 16\n").unwrap();
+  let mut daemon = Command::new(path)
+    .args([
+      "daemon",
+      "--socket",
+      socket.to_str().unwrap(),
+      "--profile",
+      "p",
+    ])
+    .env("PWDG_CONFIG", &config)
+    .stderr(Stdio::null())
+    .spawn()
+    .expect("failed to start the daemon");
+
+  let stream = (0..100)
+    .find_map(|_| {
+      std::thread::sleep(std::time::Duration::from_millis(50));
+      UnixStream::connect(&socket).ok()
+    })
+    .expect("the daemon should listen on the socket");
+  let mut writer = stream.try_clone().unwrap();
+  let mut lines = BufReader::new(stream).lines();
+  let mut length = || {
+    writeln!(writer, "{{}}").unwrap();
+    let response: serde_json::Value =
+      serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+    response["passwords"][0].as_str().unwrap().len()
+  };
+  let before = length();
+  std::fs::write(&config, "[profiles.p]\nlength = 20\n").unwrap();
+  let modified =
+    std::time::SystemTime::now() + std::time::Duration::from_secs(1);
+  std::fs::File::options()
+    .write(true)
+    .open(&config)
+    .unwrap()
+    .set_modified(modified)
+    .unwrap();
+  let after = length();
+
+  daemon.kill().unwrap();
+  daemon.wait().unwrap();
+  std::fs::remove_file(&socket).unwrap();
+  std::fs::remove_file(&config).unwrap();
+
+  assert_eq!(before, 16);
+  assert_eq!(after, 20);
+}
+
+#[test]
+fn test_pinentry() {
+  let output = run_app_with_stdin(
+    &["pinentry", "--length", "20", "--min-digit", "2"],
+    "SETDESC New passphrase\nSETREPEAT\nGETPIN\nBYE\n",
+  )
+  .expect("Running the pinentry mode should succeed.");
+  let lines: Vec<&str> = output.lines().collect();
+  assert_eq!(lines[0], "OK Pleased to meet you");
+  assert_eq!(lines[3], "S PIN_REPEATED");
+
+  let passphrase = lines[4]
+    .strip_prefix("D ")
+    .expect("Expected a D line.")
+    .replace("%25", "%");
+  assert_eq!(passphrase.chars().count(), 20);
+  assert!(passphrase.chars().filter(char::is_ascii_digit).count() >= 2);
+  assert_eq!(lines[6], "OK closing connection");
+}
+
+#[cfg(all(feature = "dbus", unix))]
+#[test]
+fn test_serve_dbus() {
+  let available = |program: &str| {
+    Command::new(program)
+      .arg("--help")
+      .stdout(Stdio::null())
+      .stderr(Stdio::null())
+      .status()
+      .is_ok()
+  };
+  if !available("dbus-run-session") || !available("gdbus") {
+    return;
+  }
+
+  let path = if cfg!(debug_assertions) {
+    "./target/debug/pwdg"
+  } else {
+    "./target/release/pwdg"
+  };
+  let script = r#"
+    "$PWDG" serve-dbus 2>/dev/null &
+    for i in $(seq 50); do
+      gdbus call --session -d io.github.OTheDev.pwdg \
+        -o /io/github/OTheDev/pwdg \
+        -m io.github.OTheDev.pwdg.Generator.Generate \
+        "['--preset', 'alnum', '--length', '20']" 2>/dev/null && break
+      sleep 0.1
+    done
+    kill $!
+  "#;
+  let output = Command::new("dbus-run-session")
+    .args(["--", "sh", "-c", script])
+    .env("PWDG", path)
+    .output()
+    .expect("failed to run dbus-run-session");
+
+  // gdbus prints the reply as a tuple, e.g. ('password',), escaping some
+  // special characters, which the preset leaves out.
+  let reply = String::from_utf8_lossy(&output.stdout);
+  let password = reply
+    .trim()
+    .strip_prefix("('")
+    .and_then(|reply| reply.strip_suffix("',)"))
+    .expect("Expected a reply from the D-Bus service.");
+  assert_eq!(password.len(), 20);
+  assert!(password.chars().all(|c| c.is_ascii_alphanumeric()));
+}